@@ -6,11 +6,15 @@
 // - config --edit: Open config file in $EDITOR
 // - config --update: Merge new defaults into existing config (with diff preview)
 // - config --init: Interactive setup wizard
+// - mock-upstream: Fake Anthropic/OpenAI API for offline end-to-end testing
+// - transform --dry-run: Run configured transformers on a saved request and diff the result
 
 use crate::config::{Config, VERSION};
+use crate::proxy::transformation::dry_run::{self, DryRunOptions};
+use crate::proxy::transformation::TransformationPipeline;
 use crate::theme::list_bundled_themes;
 use clap::{Parser, Subcommand};
-use std::io::Write;
+use std::io::{IsTerminal, Write};
 use std::process::Command;
 
 /// Aspy - Observability proxy for Claude Code
@@ -62,6 +66,44 @@ pub enum Commands {
         #[arg(long)]
         reindex: bool,
     },
+
+    /// Run a fake Anthropic/OpenAI upstream with scripted responses
+    MockUpstream {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:9191")]
+        bind: String,
+
+        /// Comma-separated scenarios, played in order and cycled
+        /// (text, tool-use, thinking, error, stream-error, overloaded, rate-limit, slow)
+        #[arg(long, default_value = "text")]
+        scenario: String,
+
+        /// Delay between streamed SSE frames in milliseconds
+        #[arg(long, default_value_t = 20)]
+        chunk_delay_ms: u64,
+    },
+
+    /// Run configured transformers on a request offline and show what changes
+    Transform {
+        /// Request body JSON, a logged Request event, or a JSONL session log ("-" = stdin)
+        input: Option<String>,
+
+        /// Run the pipeline offline and print a diff (nothing is sent upstream)
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Client ID for client_id conditions (defaults to the logged user)
+        #[arg(long)]
+        client: Option<String>,
+
+        /// Turn number for turn_number conditions (defaults to the number of user messages)
+        #[arg(long)]
+        turn: Option<u64>,
+
+        /// Only run the logged request with this ID (session logs)
+        #[arg(long)]
+        request_id: Option<String>,
+    },
 }
 
 /// Handle CLI commands. Returns true if a command was handled (exit after).
@@ -131,10 +173,109 @@ pub fn handle_cli() -> bool {
             }
             true
         }
+        Some(Commands::MockUpstream {
+            bind,
+            scenario,
+            chunk_delay_ms,
+        }) => {
+            handle_mock_upstream(&bind, &scenario, chunk_delay_ms);
+            true
+        }
+        Some(Commands::Transform {
+            input,
+            dry_run,
+            client,
+            turn,
+            request_id,
+        }) => {
+            match input.filter(|_| dry_run) {
+                Some(input) => {
+                    let options = DryRunOptions { client, turn };
+                    handle_transform_dry_run(&input, &options, request_id.as_deref());
+                }
+                None => {
+                    // No input or flag provided, show help
+                    println!("Usage: aspy transform --dry-run <REQUEST> [OPTIONS]");
+                    println!();
+                    println!("Run the configured [transformers] on a request without sending it.");
+                    println!("REQUEST is a request body JSON file, a logged Request event, or a");
+                    println!("JSONL session log from the log directory (\"-\" reads stdin).");
+                    println!();
+                    println!("Options:");
+                    println!("  --client <ID>        Client ID for client_id conditions");
+                    println!("  --turn <N>           Turn number for turn_number conditions");
+                    println!("  --request-id <ID>    Only run this request from a session log");
+                }
+            }
+            true
+        }
         None => false, // No subcommand, run normal proxy
     }
 }
 
+fn handle_mock_upstream(bind: &str, scenario: &str, chunk_delay_ms: u64) {
+    let script = match crate::mock_upstream::parse_script(scenario) {
+        Ok(script) => script,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // handle_cli is synchronous but already runs inside the tokio runtime
+    let result = tokio::task::block_in_place(|| {
+        tokio::runtime::Handle::current().block_on(crate::mock_upstream::run(
+            bind,
+            script,
+            std::time::Duration::from_millis(chunk_delay_ms),
+        ))
+    });
+    if let Err(e) = result {
+        eprintln!("Error: {:#}", e);
+        std::process::exit(1);
+    }
+}
+
+fn handle_transform_dry_run(input: &str, options: &DryRunOptions, request_id: Option<&str>) {
+    let text = if input == "-" {
+        std::io::read_to_string(std::io::stdin())
+    } else {
+        std::fs::read_to_string(input)
+    };
+    let inputs = match text
+        .map_err(anyhow::Error::from)
+        .and_then(|text| dry_run::parse_input(&text, input))
+    {
+        Ok(inputs) => inputs,
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            std::process::exit(1);
+        }
+    };
+    let inputs: Vec<_> = inputs
+        .into_iter()
+        .filter(|i| request_id.is_none_or(|id| i.request_id.as_deref() == Some(id)))
+        .collect();
+    if inputs.is_empty() {
+        eprintln!("Error: no request with ID {}", request_id.unwrap_or("?"));
+        std::process::exit(1);
+    }
+
+    let config = Config::from_env();
+    if !config.transformers.enabled {
+        eprintln!("Note: [transformers] enabled = false, the proxy won't run these transformers");
+    }
+    let pipeline = TransformationPipeline::from_config(&config.transformers);
+    let color = std::io::stdout().is_terminal();
+
+    // External transformers block on their worker threads
+    tokio::task::block_in_place(|| {
+        for input in &inputs {
+            println!("{}", dry_run::run(&pipeline, input, options).render(color));
+        }
+    });
+}
+
 fn handle_config_path() {
     match Config::config_path() {
         Some(path) => println!("{}", path.display()),
@@ -1,6 +1,7 @@
-//! Augmentation configuration
+//! Augmentation and tool guard configuration
 //!
-//! Augmentations modify API responses by injecting additional content.
+//! Augmentations modify API responses by injecting additional content. The
+//! tool guard checks `tool_use` blocks in responses against policy rules.
 
 use serde::Deserialize;
 
@@ -48,3 +49,95 @@ impl Augmentation {
         }
     }
 }
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Tool Guard Configuration
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// What a tool guard rule does to a matching tool call
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GuardAction {
+    /// Let the call through with a warning text block after it
+    Warn,
+    /// Replace the call with a refusal text block
+    #[default]
+    Deny,
+}
+
+impl GuardAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GuardAction::Warn => "warn",
+            GuardAction::Deny => "deny",
+        }
+    }
+}
+
+/// A policy rule checked against `tool_use` blocks in responses
+///
+/// The rule triggers when the tool matches and the checked value matches
+/// `pattern` or falls outside `allow`.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct ToolGuardRule {
+    /// Name shown in events (defaults to the tool pattern)
+    pub name: Option<String>,
+    /// Tool name regex, matched against the whole name
+    pub tool: String,
+    /// Top-level input field to check (default: the whole input as JSON)
+    pub field: Option<String>,
+    /// Regex that triggers the rule when it matches
+    pub pattern: Option<String>,
+    /// Regex the value must match, triggering the rule when it doesn't
+    pub allow: Option<String>,
+    /// What to do when the rule triggers
+    #[serde(default)]
+    pub action: GuardAction,
+    /// Explanation shown to the user and the model
+    pub reason: Option<String>,
+    /// Client id (exact match, unset = every client)
+    pub client: Option<String>,
+}
+
+/// Response-side policy for tool calls
+#[derive(Debug, Clone)]
+pub struct ToolGuard {
+    /// Whether rules are enforced
+    pub enabled: bool,
+    /// Rules in order (the first deny wins, otherwise the first warn)
+    pub rules: Vec<ToolGuardRule>,
+}
+
+impl Default for ToolGuard {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            rules: Vec::new(),
+        }
+    }
+}
+
+/// Tool guard config as loaded from file
+#[derive(Debug, Deserialize, Default)]
+pub struct FileToolGuard {
+    pub enabled: Option<bool>,
+    #[serde(default)]
+    pub rules: Vec<ToolGuardRule>,
+}
+
+impl ToolGuard {
+    /// Create from file config with defaults
+    pub fn from_file(file: Option<FileToolGuard>) -> Self {
+        let file = file.unwrap_or_default();
+
+        Self {
+            enabled: file.enabled.unwrap_or(true),
+            rules: file.rules,
+        }
+    }
+
+    /// Check if the guard has rules to enforce
+    pub fn is_configured(&self) -> bool {
+        self.enabled && !self.rules.is_empty()
+    }
+}
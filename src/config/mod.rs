@@ -29,10 +29,14 @@ mod tests;
 // Re-exports (maintain public API)
 // ─────────────────────────────────────────────────────────────────────────────
 
-pub use augmentation::{Augmentation, FileAugmentation};
+#[allow(unused_imports)]
+pub use augmentation::{
+    Augmentation, FileAugmentation, FileToolGuard, GuardAction, ToolGuard, ToolGuardRule,
+};
 pub use features::{Features, FileFeatures};
 pub use observability::{
-    CortexConfig, CountTokens, EmbeddingsConfig, FileCortexConfig, FileCountTokens,
+    BreakpointRule, Breakpoints, Cassette, CassetteMode, CortexConfig, CountTokens,
+    EmbeddingsConfig, FileBreakpoints, FileCassette, FileCortexConfig, FileCountTokens,
     FileEmbeddingsConfig, FileLogging, FileOtelConfig, FileTranslation, LogRotation, LoggingConfig,
     OtelConfig, Translation,
 };
@@ -40,8 +44,9 @@ pub use observability::{
 // but are accessed through struct fields like ProviderConfig.auth)
 #[allow(unused_imports)]
 pub use routing::{
-    ApiFormat, AuthMethod, ClientConfig, ClientsConfig, CountTokensHandling, ProviderAuth,
-    ProviderConfig,
+    ApiFormat, AuthMethod, ClientConfig, ClientsConfig, CountTokensHandling, Failover,
+    FileFailover, FileRateLimit, FileRetry, PoolKey, PoolStrategy, ProviderAuth, ProviderConfig,
+    RateLimit, RateLimits, Retry,
 };
 pub use transformers::{FileTransformers, Transformers};
 
@@ -115,6 +120,24 @@ pub struct Config {
 
     /// Client and provider configuration for multi-user routing
     pub clients: ClientsConfig,
+
+    /// Provider failover and health tracking settings
+    pub failover: Failover,
+
+    /// Upstream retry policy (429/529 backoff)
+    pub retry: Retry,
+
+    /// Per-client request/token rate limits
+    pub rate_limit: RateLimit,
+
+    /// Record/replay of upstream traffic
+    pub cassette: Cassette,
+
+    /// Request breakpoints for TUI inspection
+    pub breakpoints: Breakpoints,
+
+    /// Policy rules for tool calls in responses
+    pub tool_guard: ToolGuard,
 }
 
 impl Default for Config {
@@ -139,6 +162,12 @@ impl Default for Config {
             count_tokens: CountTokens::default(),
             otel: OtelConfig::default(),
             clients: ClientsConfig::default(),
+            failover: Failover::default(),
+            retry: Retry::default(),
+            rate_limit: RateLimit::default(),
+            cassette: Cassette::default(),
+            breakpoints: Breakpoints::default(),
+            tool_guard: ToolGuard::default(),
         }
     }
 }
@@ -185,6 +214,24 @@ pub(crate) struct FileConfig {
     /// Optional [otel] section (OpenTelemetry export)
     pub otel: Option<FileOtelConfig>,
 
+    /// Optional [failover] section (provider fallback chains)
+    pub failover: Option<FileFailover>,
+
+    /// Optional [retry] section (upstream retry policy)
+    pub retry: Option<FileRetry>,
+
+    /// Optional [rate_limit] section (per-client request/token limits)
+    pub rate_limit: Option<FileRateLimit>,
+
+    /// Optional [cassette] section (record/replay of upstream traffic)
+    pub cassette: Option<FileCassette>,
+
+    /// Optional [breakpoints] section (hold requests for TUI inspection)
+    pub breakpoints: Option<FileBreakpoints>,
+
+    /// Optional [tool_guard] section (policy for tool calls in responses)
+    pub tool_guard: Option<FileToolGuard>,
+
     /// Optional [clients.X] sections for multi-user routing
     #[serde(default)]
     pub clients: HashMap<String, ClientConfig>,
@@ -348,6 +395,21 @@ impl Config {
         let transformers = Transformers::from_file(file.transformers);
         let count_tokens = CountTokens::from_file(file.count_tokens);
         let translation = Translation::from_file(file.translation);
+        let failover = Failover::from_file(file.failover);
+        let retry = Retry::from_file(file.retry);
+        let rate_limit = RateLimit::from_file(file.rate_limit);
+
+        // Cassette: env > file > default (env makes one-off recordings easy)
+        let mut cassette = Cassette::from_file(file.cassette);
+        if let Ok(mode) = std::env::var("ASPY_CASSETTE_MODE") {
+            cassette.mode = CassetteMode::from_str(&mode);
+        }
+        if let Ok(path) = std::env::var("ASPY_CASSETTE_PATH") {
+            cassette.path = PathBuf::from(path);
+        }
+
+        let breakpoints = Breakpoints::from_file(file.breakpoints);
+        let tool_guard = ToolGuard::from_file(file.tool_guard);
 
         // Embeddings: env var for API key takes precedence
         let embeddings_api_key = std::env::var("ASPY_EMBEDDINGS_API_KEY").ok();
@@ -392,6 +454,12 @@ impl Config {
             count_tokens,
             otel,
             clients,
+            failover,
+            retry,
+            rate_limit,
+            cassette,
+            breakpoints,
+            tool_guard,
         }
     }
 }
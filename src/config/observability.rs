@@ -470,3 +470,158 @@ impl Translation {
         }
     }
 }
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Cassette (Record/Replay) Configuration
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Cassette mode for upstream traffic
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum CassetteMode {
+    /// Normal proxying (default)
+    #[default]
+    Off,
+    /// Forward upstream and append each exchange to the cassette
+    Record,
+    /// Serve responses from the cassette without calling the provider
+    Replay,
+}
+
+impl CassetteMode {
+    /// Parse mode string from config/env
+    pub fn from_str(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "record" => Self::Record,
+            "replay" => Self::Replay,
+            _ => Self::Off, // Unknown values fall back to normal proxying
+        }
+    }
+
+    /// Convert to string for TOML serialization
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::Record => "record",
+            Self::Replay => "replay",
+        }
+    }
+}
+
+/// Record/replay of upstream traffic for offline reproduction
+///
+/// Record mode writes each upstream exchange (request, status, headers and
+/// raw response chunks with timing) to a JSON Lines cassette. Replay mode
+/// serves matching requests from that file instead of calling the provider.
+#[derive(Debug, Clone)]
+pub struct Cassette {
+    /// Off, record or replay
+    pub mode: CassetteMode,
+    /// Cassette file (JSON Lines, one exchange per line)
+    pub path: PathBuf,
+    /// Replay chunks with their recorded timing (false = as fast as possible)
+    pub realtime: bool,
+}
+
+impl Default for Cassette {
+    fn default() -> Self {
+        Self {
+            mode: CassetteMode::Off,
+            path: PathBuf::from("./cassettes/aspy.jsonl"),
+            realtime: false,
+        }
+    }
+}
+
+/// Cassette config as loaded from file
+#[derive(Debug, Deserialize, Default)]
+pub struct FileCassette {
+    pub mode: Option<String>,
+    pub path: Option<String>,
+    pub realtime: Option<bool>,
+}
+
+impl Cassette {
+    /// Create from file config with defaults
+    pub fn from_file(file: Option<FileCassette>) -> Self {
+        let file = file.unwrap_or_default();
+        let defaults = Self::default();
+
+        Self {
+            mode: file
+                .mode
+                .as_deref()
+                .map(CassetteMode::from_str)
+                .unwrap_or(defaults.mode),
+            path: file.path.map(PathBuf::from).unwrap_or(defaults.path),
+            realtime: file.realtime.unwrap_or(defaults.realtime),
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Breakpoints Configuration
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// A condition that pauses matching requests for inspection in the TUI
+///
+/// All fields that are set must match; unset fields match anything.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct BreakpointRule {
+    /// Client id (exact match)
+    pub client: Option<String>,
+    /// Model pattern (case-insensitive regex)
+    pub model: Option<String>,
+    /// Tool name answered by a tool_result in the last user message
+    pub tool: Option<String>,
+    /// Pattern matched against the last user prompt (regex)
+    pub prompt: Option<String>,
+}
+
+/// Request breakpoints (debugging aid, requires the TUI)
+#[derive(Debug, Clone)]
+pub struct Breakpoints {
+    /// Whether breakpoints are active at startup (toggle with `b` in the TUI)
+    pub armed: bool,
+    /// Seconds to hold a request before letting it through (0 = wait forever)
+    pub timeout_secs: u64,
+    /// Rules that trigger a breakpoint (any rule matching holds the request)
+    pub rules: Vec<BreakpointRule>,
+}
+
+impl Default for Breakpoints {
+    fn default() -> Self {
+        Self {
+            armed: true,
+            timeout_secs: 300,
+            rules: Vec::new(),
+        }
+    }
+}
+
+/// Breakpoints config as loaded from file
+#[derive(Debug, Deserialize, Default)]
+pub struct FileBreakpoints {
+    pub armed: Option<bool>,
+    pub timeout_secs: Option<u64>,
+    #[serde(default)]
+    pub rules: Vec<BreakpointRule>,
+}
+
+impl Breakpoints {
+    /// Create from file config with defaults
+    pub fn from_file(file: Option<FileBreakpoints>) -> Self {
+        let file = file.unwrap_or_default();
+        let defaults = Self::default();
+
+        Self {
+            armed: file.armed.unwrap_or(defaults.armed),
+            timeout_secs: file.timeout_secs.unwrap_or(defaults.timeout_secs),
+            rules: file.rules,
+        }
+    }
+
+    /// Check if any breakpoint rules are defined
+    pub fn is_configured(&self) -> bool {
+        !self.rules.is_empty()
+    }
+}
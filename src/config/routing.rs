@@ -132,6 +132,73 @@ pub struct ProviderAuth {
     /// Whether to strip incoming auth headers before forwarding
     /// Default: true for bearer/x-api-key/basic/header, false for passthrough
     pub strip_incoming: Option<bool>,
+
+    /// Pool of keys to balance requests across (takes precedence over `key`/`key_env`)
+    ///
+    /// Only honored on `[providers.X.auth]`; each request picks one key using
+    /// `pool_strategy`, and keys that hit their rate limit are parked until reset.
+    #[serde(default)]
+    pub pool: Vec<PoolKey>,
+
+    /// How to choose a key from `pool`
+    #[serde(default)]
+    pub pool_strategy: PoolStrategy,
+}
+
+/// A single key in a provider's key pool
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PoolKey {
+    /// Label shown in stats (defaults to key_env, then "key-N")
+    pub name: Option<String>,
+
+    /// API key value (direct, less secure - prefer key_env)
+    pub key: Option<String>,
+
+    /// Environment variable name to read key from (preferred)
+    pub key_env: Option<String>,
+}
+
+impl PoolKey {
+    /// Label for stats and logs (never the key itself)
+    pub fn label(&self, index: usize) -> String {
+        self.name
+            .clone()
+            .or_else(|| self.key_env.clone())
+            .unwrap_or_else(|| format!("key-{}", index + 1))
+    }
+
+    /// Resolve the key from env var or direct value (env var wins)
+    pub fn resolve(&self) -> Option<String> {
+        if let Some(env_name) = &self.key_env {
+            if let Ok(value) = std::env::var(env_name) {
+                if !value.is_empty() {
+                    return Some(value);
+                }
+            }
+        }
+        self.key.clone()
+    }
+}
+
+/// Key selection strategy for provider key pools
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PoolStrategy {
+    /// Rotate through available keys in order
+    #[default]
+    RoundRobin,
+    /// Pick the key with the most remaining quota (from rate limit headers)
+    MostRemaining,
+}
+
+impl PoolStrategy {
+    /// Convert to string for TOML serialization
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::RoundRobin => "round_robin",
+            Self::MostRemaining => "most_remaining",
+        }
+    }
 }
 
 impl ProviderAuth {
@@ -175,8 +242,12 @@ impl ProviderAuth {
     /// Build the authentication header (name, value) for this config
     /// Returns None if passthrough or no key available
     pub fn build_header(&self) -> Option<(String, String)> {
-        let key = self.resolve_key()?;
+        self.build_header_for_key(self.resolve_key()?)
+    }
 
+    /// Build the authentication header for a specific key (e.g., from a pool)
+    /// Returns None if passthrough
+    pub fn build_header_for_key(&self, key: String) -> Option<(String, String)> {
         match &self.method {
             AuthMethod::Passthrough => None,
             AuthMethod::Bearer => Some(("authorization".to_string(), format!("Bearer {}", key))),
@@ -257,6 +328,22 @@ pub struct ProviderConfig {
     /// ```
     #[serde(default)]
     pub model_mapping: HashMap<String, String>,
+
+    /// Ordered fallback providers (references [providers.X])
+    ///
+    /// When this provider fails with a retryable error (see `[failover]`)
+    /// or is cooling down after repeated failures, the request is re-routed
+    /// to the next healthy provider in this list. Requests are re-translated
+    /// when the fallback uses a different `api_format`.
+    ///
+    /// Example:
+    /// ```toml
+    /// [providers.anthropic]
+    /// base_url = "https://api.anthropic.com"
+    /// fallback = ["openrouter"]
+    /// ```
+    #[serde(default)]
+    pub fallback: Vec<String>,
 }
 
 impl ProviderConfig {
@@ -321,6 +408,222 @@ pub struct ClientConfig {
     /// Use this for multi-tenant scenarios where clients need different credentials
     #[serde(default)]
     pub auth: Option<ProviderAuth>,
+
+    /// Optional fallback chain override (takes precedence over provider's fallback)
+    #[serde(default)]
+    pub fallback: Vec<String>,
+
+    /// Optional shadow provider to mirror requests to (references [providers.X])
+    ///
+    /// Responses from the shadow are never returned to the client; they are
+    /// only recorded for comparison with the primary.
+    #[serde(default)]
+    pub shadow: Option<String>,
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Failover Configuration
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Provider failover and health tracking settings
+///
+/// Each provider tracks consecutive failures. Once `failure_threshold` is
+/// reached, the provider is skipped for `cooldown_secs` and requests go
+/// straight to the next provider in the fallback chain.
+#[derive(Debug, Clone)]
+pub struct Failover {
+    /// Consecutive failures before a provider enters cooldown
+    pub failure_threshold: u32,
+    /// How long an unhealthy provider is skipped (seconds)
+    pub cooldown_secs: u64,
+    /// Upstream status codes that trigger failover (connection errors always do)
+    pub status_codes: Vec<u16>,
+}
+
+impl Default for Failover {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 3,
+            cooldown_secs: 30,
+            status_codes: vec![500, 502, 503, 504, 529],
+        }
+    }
+}
+
+/// Failover config as loaded from file
+#[derive(Debug, Deserialize, Default)]
+pub struct FileFailover {
+    pub failure_threshold: Option<u32>,
+    pub cooldown_secs: Option<u64>,
+    pub status_codes: Option<Vec<u16>>,
+}
+
+impl Failover {
+    /// Create from file config with defaults
+    pub fn from_file(file: Option<FileFailover>) -> Self {
+        let file = file.unwrap_or_default();
+        let defaults = Self::default();
+
+        Self {
+            failure_threshold: file
+                .failure_threshold
+                .unwrap_or(defaults.failure_threshold)
+                .max(1),
+            cooldown_secs: file.cooldown_secs.unwrap_or(defaults.cooldown_secs),
+            status_codes: file.status_codes.unwrap_or(defaults.status_codes),
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Retry Configuration
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Upstream retry policy for rate-limit and overload responses
+///
+/// Retries happen before any response bytes reach the client. The delay
+/// honors `retry-after` and `anthropic-ratelimit-*-reset` headers when the
+/// server provides them, otherwise uses exponential backoff with jitter.
+#[derive(Debug, Clone)]
+pub struct Retry {
+    /// Enable automatic retries (opt-in)
+    pub enabled: bool,
+    /// Total attempts per request, including the first
+    pub max_attempts: u32,
+    /// Backoff before the first retry (doubles each retry)
+    pub initial_backoff_ms: u64,
+    /// Upper bound on any single wait; server hints beyond this give up instead
+    pub max_backoff_ms: u64,
+    /// Upstream status codes that are retried
+    pub status_codes: Vec<u16>,
+}
+
+impl Default for Retry {
+    fn default() -> Self {
+        Self {
+            enabled: false, // Opt-in: holds requests open while waiting
+            max_attempts: 3,
+            initial_backoff_ms: 1000,
+            max_backoff_ms: 30_000,
+            status_codes: vec![429, 529],
+        }
+    }
+}
+
+/// Retry config as loaded from file
+#[derive(Debug, Deserialize, Default)]
+pub struct FileRetry {
+    pub enabled: Option<bool>,
+    pub max_attempts: Option<u32>,
+    pub initial_backoff_ms: Option<u64>,
+    pub max_backoff_ms: Option<u64>,
+    pub status_codes: Option<Vec<u16>>,
+}
+
+impl Retry {
+    /// Create from file config with defaults
+    pub fn from_file(file: Option<FileRetry>) -> Self {
+        let file = file.unwrap_or_default();
+        let defaults = Self::default();
+
+        Self {
+            enabled: file.enabled.unwrap_or(defaults.enabled),
+            max_attempts: file.max_attempts.unwrap_or(defaults.max_attempts).max(1),
+            initial_backoff_ms: file
+                .initial_backoff_ms
+                .unwrap_or(defaults.initial_backoff_ms),
+            max_backoff_ms: file.max_backoff_ms.unwrap_or(defaults.max_backoff_ms),
+            status_codes: file.status_codes.unwrap_or(defaults.status_codes),
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Rate Limit Configuration
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Limits applied to a single client/user identity
+///
+/// Each field is independent; `None` means unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+pub struct RateLimits {
+    /// Requests admitted per rolling minute
+    pub requests_per_minute: Option<u32>,
+    /// Estimated input tokens admitted per rolling minute
+    pub input_tokens_per_minute: Option<u64>,
+    /// Responses in flight at once (streams count until they finish)
+    pub concurrent_streams: Option<u32>,
+}
+
+impl RateLimits {
+    /// True if no limit is set
+    pub fn is_unlimited(&self) -> bool {
+        self.requests_per_minute.is_none()
+            && self.input_tokens_per_minute.is_none()
+            && self.concurrent_streams.is_none()
+    }
+
+    /// Fill unset fields from `defaults`
+    fn or(self, defaults: RateLimits) -> RateLimits {
+        RateLimits {
+            requests_per_minute: self.requests_per_minute.or(defaults.requests_per_minute),
+            input_tokens_per_minute: self
+                .input_tokens_per_minute
+                .or(defaults.input_tokens_per_minute),
+            concurrent_streams: self.concurrent_streams.or(defaults.concurrent_streams),
+        }
+    }
+}
+
+/// Per-client/per-user request rate limiting at the proxy
+///
+/// Identities are the same as session tracking: the client id when the
+/// request is routed (`/dev-1/v1/messages`), otherwise the API key hash.
+#[derive(Debug, Clone, Default)]
+pub struct RateLimit {
+    /// Enable rate limiting (opt-in)
+    pub enabled: bool,
+    /// Limits for every identity without an override
+    pub limits: RateLimits,
+    /// Overrides by client id (unset fields fall back to `limits`)
+    pub clients: HashMap<String, RateLimits>,
+    /// Overrides by user id / API key hash (unset fields fall back to `limits`)
+    pub users: HashMap<String, RateLimits>,
+}
+
+/// Rate limit config as loaded from file
+#[derive(Debug, Deserialize, Default)]
+pub struct FileRateLimit {
+    pub enabled: Option<bool>,
+    #[serde(flatten)]
+    pub limits: RateLimits,
+    #[serde(default)]
+    pub clients: HashMap<String, RateLimits>,
+    #[serde(default)]
+    pub users: HashMap<String, RateLimits>,
+}
+
+impl RateLimit {
+    /// Create from file config with defaults
+    pub fn from_file(file: Option<FileRateLimit>) -> Self {
+        let file = file.unwrap_or_default();
+
+        Self {
+            enabled: file.enabled.unwrap_or(false),
+            limits: file.limits,
+            clients: file.clients,
+            users: file.users,
+        }
+    }
+
+    /// Effective limits for an identity (client overrides win over user overrides)
+    pub fn limits_for(&self, identity: &str) -> RateLimits {
+        self.clients
+            .get(identity)
+            .or_else(|| self.users.get(identity))
+            .map(|o| o.or(self.limits))
+            .unwrap_or(self.limits)
+    }
 }
 
 // ─────────────────────────────────────────────────────────────────────────────
@@ -372,23 +675,6 @@ impl ClientsConfig {
         !self.clients.is_empty()
     }
 
-    /// Get the API format expected by a client's provider
-    ///
-    /// Returns the provider's api_format setting, or None if client not found.
-    /// Default is Anthropic format (no translation needed for Claude Code).
-    pub fn get_client_api_format(&self, client_id: &str) -> Option<&ApiFormat> {
-        self.get_client_provider(client_id).map(|p| &p.api_format)
-    }
-
-    /// Get the effective API path for a client's provider
-    ///
-    /// Returns the provider's custom api_path if set, otherwise the default
-    /// path for the provider's api_format. Returns None if client not found.
-    pub fn get_client_api_path(&self, client_id: &str) -> Option<&str> {
-        self.get_client_provider(client_id)
-            .map(|p| p.effective_api_path())
-    }
-
     /// Get the effective count_tokens handling for a client's provider
     ///
     /// Returns the provider's count_tokens setting (or its default based on api_format).
@@ -418,13 +704,85 @@ impl ClientsConfig {
             .and_then(|p| p.auth.as_ref())
     }
 
-    /// Get the model mapping for a client's provider
+    /// Get the ordered provider chain for a client (primary first)
     ///
-    /// Returns the provider's model_mapping if configured and non-empty,
-    /// otherwise None (caller should fall back to global mapping).
-    pub fn get_client_model_mapping(&self, client_id: &str) -> Option<&HashMap<String, String>> {
-        self.get_client_provider(client_id)
-            .map(|p| &p.model_mapping)
-            .filter(|m| !m.is_empty())
+    /// The client's own `fallback` list overrides the primary provider's.
+    /// Unknown provider IDs and duplicates are skipped. Returns an empty
+    /// chain if the client or its primary provider is not configured.
+    pub fn get_client_provider_chain(&self, client_id: &str) -> Vec<(&str, &ProviderConfig)> {
+        let Some(client) = self.get_client(client_id) else {
+            return Vec::new();
+        };
+        let Some(primary) = self.providers.get(&client.provider) else {
+            return Vec::new();
+        };
+
+        let fallback = if client.fallback.is_empty() {
+            &primary.fallback
+        } else {
+            &client.fallback
+        };
+        self.build_provider_chain(&client.provider, fallback)
+    }
+
+    /// Get a provider followed by its own fallback chain
+    ///
+    /// Used when a request is routed to a provider directly (e.g., by the
+    /// model router) rather than through its client's provider.
+    pub fn get_provider_chain(&self, provider_id: &str) -> Vec<(&str, &ProviderConfig)> {
+        let Some(provider) = self.providers.get(provider_id) else {
+            return Vec::new();
+        };
+        self.build_provider_chain(provider_id, &provider.fallback)
+    }
+
+    /// Primary provider plus known, de-duplicated fallbacks
+    fn build_provider_chain(
+        &self,
+        primary_id: &str,
+        fallback: &[String],
+    ) -> Vec<(&str, &ProviderConfig)> {
+        let Some((primary_id, primary)) = self.providers.get_key_value(primary_id) else {
+            return Vec::new();
+        };
+
+        let mut chain = vec![(primary_id.as_str(), primary)];
+        for id in fallback {
+            if chain.iter().any(|(existing, _)| existing == id) {
+                continue;
+            }
+            if let Some((id, provider)) = self.providers.get_key_value(id) {
+                chain.push((id.as_str(), provider));
+            }
+        }
+        chain
+    }
+
+    /// Get the authentication config to use when sending a client's request
+    /// to a specific provider in its chain
+    ///
+    /// The client's auth override only applies to its primary provider;
+    /// fallback providers always use their own auth config.
+    pub fn get_provider_auth(&self, client_id: &str, provider_id: &str) -> Option<&ProviderAuth> {
+        let is_primary = self
+            .get_client(client_id)
+            .is_some_and(|c| c.provider == provider_id);
+        if is_primary {
+            return self.get_effective_auth(client_id);
+        }
+        self.providers
+            .get(provider_id)
+            .and_then(|p| p.auth.as_ref())
+    }
+
+    /// Get the shadow provider a client's requests are mirrored to
+    ///
+    /// Returns None if the client has no shadow configured or it references
+    /// an unknown provider.
+    pub fn get_client_shadow(&self, client_id: &str) -> Option<(&str, &ProviderConfig)> {
+        let shadow = self.get_client(client_id)?.shadow.as_deref()?;
+        self.providers
+            .get_key_value(shadow)
+            .map(|(id, provider)| (id.as_str(), provider))
     }
 }
@@ -2,7 +2,7 @@
 //!
 //! Single source of truth for config file format.
 
-use super::{ApiFormat, Config, CountTokensHandling};
+use super::{ApiFormat, Config, CountTokensHandling, PoolStrategy, RateLimits};
 
 impl Config {
     /// Serialize clients HashMap to TOML sections
@@ -13,6 +13,7 @@ impl Config {
 # [clients.dev-1]
 # name = "Dev Laptop"
 # provider = "anthropic"       # References [providers.anthropic] below
+# shadow = "openrouter"        # Mirror requests here for comparison (never returned)
 "#
             .to_string();
         }
@@ -30,6 +31,12 @@ impl Config {
             if !client.tags.is_empty() {
                 output.push_str(&format!("tags = {:?}\n", client.tags));
             }
+            if !client.fallback.is_empty() {
+                output.push_str(&format!("fallback = {:?}\n", client.fallback));
+            }
+            if let Some(shadow) = &client.shadow {
+                output.push_str(&format!("shadow = \"{}\"\n", shadow));
+            }
             output.push('\n');
         }
         output
@@ -43,6 +50,17 @@ impl Config {
 # [providers.anthropic]
 # base_url = "https://api.anthropic.com"
 # # count_tokens defaults to "passthrough" for anthropic api_format
+# fallback = ["openrouter"]  # Re-route here on 5xx/529/connection errors
+# # Optional key pool: balance requests across several keys
+# [providers.anthropic.auth]
+# method = "x_api_key"
+# pool_strategy = "most_remaining"  # or "round_robin" (default)
+# [[providers.anthropic.auth.pool]]
+# name = "team-a"
+# key_env = "ANTHROPIC_KEY_A"
+# [[providers.anthropic.auth.pool]]
+# name = "team-b"
+# key_env = "ANTHROPIC_KEY_B"
 #
 # # Provider with OpenAI-compatible API (e.g., OpenRouter)
 # [providers.openrouter]
@@ -109,6 +127,11 @@ impl Config {
                 }
             }
 
+            // Serialize fallback chain if set
+            if !provider.fallback.is_empty() {
+                output.push_str(&format!("fallback = {:?}\n", provider.fallback));
+            }
+
             // Serialize auth config if present
             if let Some(auth) = &provider.auth {
                 output.push_str(&format!("\n[providers.{}.auth]\n", provider_id));
@@ -125,6 +148,24 @@ impl Config {
                 if let Some(strip) = auth.strip_incoming {
                     output.push_str(&format!("strip_incoming = {}\n", strip));
                 }
+                if auth.pool_strategy != PoolStrategy::default() {
+                    output.push_str(&format!(
+                        "pool_strategy = \"{}\"\n",
+                        auth.pool_strategy.as_str()
+                    ));
+                }
+                for pool_key in &auth.pool {
+                    output.push_str(&format!("\n[[providers.{}.auth.pool]]\n", provider_id));
+                    if let Some(name) = &pool_key.name {
+                        output.push_str(&format!("name = \"{}\"\n", name));
+                    }
+                    if let Some(key_env) = &pool_key.key_env {
+                        output.push_str(&format!("key_env = \"{}\"\n", key_env));
+                    }
+                    if let Some(key) = &pool_key.key {
+                        output.push_str(&format!("key = \"{}\"\n", key));
+                    }
+                }
             }
 
             // Serialize model_mapping if non-empty
@@ -144,12 +185,273 @@ impl Config {
         output
     }
 
+    /// Serialize the [rate_limit] section (unset limits are written as comments)
+    pub(super) fn rate_limit_to_toml(&self) -> String {
+        fn push_limits(output: &mut String, limits: &RateLimits, commented: bool) {
+            for (key, value) in [
+                (
+                    "requests_per_minute",
+                    limits.requests_per_minute.map(u64::from),
+                ),
+                ("input_tokens_per_minute", limits.input_tokens_per_minute),
+                (
+                    "concurrent_streams",
+                    limits.concurrent_streams.map(u64::from),
+                ),
+            ] {
+                match value {
+                    Some(v) => output.push_str(&format!("{} = {}\n", key, v)),
+                    None if commented => output.push_str(&format!("# {} = 0\n", key)),
+                    None => {}
+                }
+            }
+        }
+
+        let mut output = format!("[rate_limit]\nenabled = {}\n", self.rate_limit.enabled);
+        push_limits(&mut output, &self.rate_limit.limits, true);
+
+        for (scope, overrides) in [
+            ("clients", &self.rate_limit.clients),
+            ("users", &self.rate_limit.users),
+        ] {
+            // Sort keys for deterministic output
+            let mut ids: Vec<_> = overrides.keys().collect();
+            ids.sort();
+            for id in ids {
+                output.push_str(&format!("\n[rate_limit.{}.\"{}\"]\n", scope, id));
+                push_limits(&mut output, &overrides[id], false);
+            }
+        }
+
+        if self.rate_limit.clients.is_empty() && self.rate_limit.users.is_empty() {
+            output.push_str(
+                r#"
+# Per-client overrides (unset fields fall back to the limits above):
+# [rate_limit.clients."ci-agent"]
+# requests_per_minute = 10
+# concurrent_streams = 1
+"#,
+            );
+        }
+        output
+    }
+
+    /// Serialize the [breakpoints] section and its rules
+    pub(super) fn breakpoints_to_toml(&self) -> String {
+        let mut output = format!(
+            "[breakpoints]\narmed = {}\ntimeout_secs = {}  # 0 = wait forever\n",
+            self.breakpoints.armed, self.breakpoints.timeout_secs
+        );
+
+        if self.breakpoints.rules.is_empty() {
+            output.push_str(
+                r#"
+# Rules (all set fields must match; any matching rule holds the request):
+# [[breakpoints.rules]]
+# client = "dev-1"        # Client id
+# model = "opus"          # Case-insensitive regex
+# tool = "Bash"           # Tool answered by a tool_result in the last message
+# prompt = "(?i)deploy"   # Regex on the last user prompt
+"#,
+            );
+        }
+
+        for rule in &self.breakpoints.rules {
+            output.push_str("\n[[breakpoints.rules]]\n");
+            for (key, value) in [
+                ("client", &rule.client),
+                ("model", &rule.model),
+                ("tool", &rule.tool),
+                ("prompt", &rule.prompt),
+            ] {
+                if let Some(value) = value {
+                    // Debug formatting escapes quotes and backslashes in patterns
+                    output.push_str(&format!("{} = {:?}\n", key, value));
+                }
+            }
+        }
+        output
+    }
+
+    /// Serialize the [tool_guard] section and its rules
+    pub(super) fn tool_guard_to_toml(&self) -> String {
+        let mut output = format!("[tool_guard]\nenabled = {}\n", self.tool_guard.enabled);
+
+        if self.tool_guard.rules.is_empty() {
+            output.push_str(
+                r#"
+# Rules (the first matching deny wins, otherwise the first matching warn):
+# [[tool_guard.rules]]
+# name = "destructive-shell"
+# tool = "Bash"                   # Tool name regex (whole name)
+# field = "command"               # Input field to check (default: whole input)
+# pattern = "rm\\s+-rf\\s+/(\\s|$)|git\\s+push\\s+.*--force"
+# action = "deny"                 # "deny" (replace the call) or "warn" (annotate)
+# reason = "Destructive command"
+#
+# [[tool_guard.rules]]
+# tool = "Edit|Write"
+# field = "file_path"
+# allow = "^/home/me/project/"    # Triggers when the value does NOT match
+# client = "review-bot"           # Client id (default: every client)
+"#,
+            );
+        }
+
+        for rule in &self.tool_guard.rules {
+            output.push_str("\n[[tool_guard.rules]]\n");
+            output.push_str(&format!("tool = {:?}\n", rule.tool));
+            for (key, value) in [
+                ("name", &rule.name),
+                ("field", &rule.field),
+                ("pattern", &rule.pattern),
+                ("allow", &rule.allow),
+                ("reason", &rule.reason),
+                ("client", &rule.client),
+            ] {
+                if let Some(value) = value {
+                    // Debug formatting escapes quotes and backslashes in patterns
+                    output.push_str(&format!("{} = {:?}\n", key, value));
+                }
+            }
+            output.push_str(&format!("action = \"{}\"\n", rule.action.as_str()));
+        }
+        output
+    }
+
     /// Serialize transformers config to TOML (returns empty string if not configured)
     pub(super) fn transformers_to_toml(&self) -> String {
-        use crate::proxy::transformation::{PositionConfig, RuleConfig};
+        use crate::proxy::transformation::ToolRuleConfig;
 
         let mut output = String::new();
 
+        // Serialize budget if configured
+        if let Some(ref budget) = self.transformers.budget {
+            if budget.enabled {
+                output.push_str(&format!(
+                    r#"
+# ─────────────────────────────────────────────────────────────────────────────
+# SPENDING BUDGETS
+# ─────────────────────────────────────────────────────────────────────────────
+# USD limits per client id / user id. Requests are blocked once a limit is hit.
+
+[transformers.budget]
+enabled = true
+warn_percent = {}
+"#,
+                    budget.warn_percent
+                ));
+
+                for (scope, limits) in [("clients", &budget.clients), ("users", &budget.users)] {
+                    // Sort keys for deterministic output
+                    let mut ids: Vec<_> = limits.keys().collect();
+                    ids.sort();
+                    for id in ids {
+                        let limit = &limits[id];
+                        output.push_str(&format!("\n[transformers.budget.{}.\"{}\"]\n", scope, id));
+                        for (key, value) in [
+                            ("daily_usd", limit.daily_usd),
+                            ("weekly_usd", limit.weekly_usd),
+                            ("monthly_usd", limit.monthly_usd),
+                        ] {
+                            if let Some(usd) = value {
+                                // Debug formatting keeps the decimal point (5.0, not 5)
+                                output.push_str(&format!("{} = {:?}\n", key, usd));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        // Serialize model-router if configured
+        if let Some(ref router) = self.transformers.model_router {
+            if router.enabled && !router.rules.is_empty() {
+                output.push_str(
+                    r#"
+# ─────────────────────────────────────────────────────────────────────────────
+# MODEL ROUTER
+# ─────────────────────────────────────────────────────────────────────────────
+# Picks the provider (and optionally rewrites the model) per request.
+# Rules are evaluated in order; the first match wins.
+
+[transformers.model-router]
+enabled = true
+"#,
+                );
+
+                for rule in &router.rules {
+                    output.push_str("\n[[transformers.model-router.rules]]\n");
+                    if let Some(ref name) = rule.name {
+                        output.push_str(&format!("name = \"{}\"\n", name));
+                    }
+                    if let Some(ref model) = rule.model {
+                        output.push_str(&format!("model = \"{}\"\n", model));
+                    }
+                    if let Some(ref tokens) = rule.context_tokens {
+                        output.push_str(&format!("context_tokens = \"{}\"\n", tokens));
+                    }
+                    if let Some(ref provider) = rule.provider {
+                        output.push_str(&format!("provider = \"{}\"\n", provider));
+                    }
+                    if !rule.model_mapping.is_empty() {
+                        let mut mappings: Vec<_> = rule.model_mapping.iter().collect();
+                        mappings.sort();
+                        let entries: Vec<String> = mappings
+                            .iter()
+                            .map(|(from, to)| format!("\"{}\" = \"{}\"", from, to))
+                            .collect();
+                        output.push_str(&format!("model_mapping = {{ {} }}\n", entries.join(", ")));
+                    }
+                    if let Some(ref cond) = rule.when {
+                        cond.write_toml(&mut output);
+                    }
+                }
+            }
+        }
+
+        // Serialize projects if configured
+        if let Some(ref projects) = self.transformers.projects {
+            if projects.enabled && !projects.registry.is_empty() {
+                output.push_str(
+                    r#"
+# ─────────────────────────────────────────────────────────────────────────────
+# PROJECTS
+# ─────────────────────────────────────────────────────────────────────────────
+# Per-repository instructions, rules and budgets, matched on the working
+# directory in Claude Code's system prompt. The first match wins.
+
+[transformers.projects]
+enabled = true
+"#,
+                );
+
+                for project in &projects.registry {
+                    output.push_str("\n[[transformers.projects.registry]]\n");
+                    output.push_str(&format!("name = {:?}\n", project.name));
+                    output.push_str(&format!("path = {:?}\n", project.path));
+                    if let Some(ref instructions) = project.instructions {
+                        output.push_str(&format!("instructions = {:?}\n", instructions));
+                    }
+                    if let Some(ref limits) = project.budget {
+                        for (key, value) in [
+                            ("daily_usd", limits.daily_usd),
+                            ("weekly_usd", limits.weekly_usd),
+                            ("monthly_usd", limits.monthly_usd),
+                        ] {
+                            if let Some(usd) = value {
+                                output.push_str(&format!("budget.{} = {:?}\n", key, usd));
+                            }
+                        }
+                    }
+                    for rule in &project.rules {
+                        output.push_str("\n[[transformers.projects.registry.rules]]\n");
+                        write_tag_rule(&mut output, rule);
+                    }
+                }
+            }
+        }
+
         // Serialize tag-editor if configured
         if let Some(ref editor) = self.transformers.tag_editor {
             if editor.enabled && !editor.rules.is_empty() {
@@ -157,70 +459,7 @@ impl Config {
 
                 for rule in &editor.rules {
                     output.push_str("\n[[transformers.tag-editor.rules]]\n");
-                    match rule {
-                        RuleConfig::Inject {
-                            tag,
-                            content,
-                            position,
-                            when,
-                        } => {
-                            output.push_str("type = \"inject\"\n");
-                            output.push_str(&format!("tag = \"{}\"\n", tag));
-                            // Escape content for TOML multiline if needed
-                            if content.contains('\n') {
-                                output
-                                    .push_str(&format!("content = \"\"\"\n{}\n\"\"\"\n", content));
-                            } else {
-                                output.push_str(&format!("content = \"{}\"\n", content));
-                            }
-                            match position {
-                                PositionConfig::Start => {
-                                    output.push_str("position = \"start\"\n");
-                                }
-                                PositionConfig::End => {
-                                    // end is default, can omit
-                                }
-                                PositionConfig::Before { pattern } => {
-                                    output.push_str(&format!(
-                                        "position.before.pattern = \"{}\"\n",
-                                        pattern
-                                    ));
-                                }
-                                PositionConfig::After { pattern } => {
-                                    output.push_str(&format!(
-                                        "position.after.pattern = \"{}\"\n",
-                                        pattern
-                                    ));
-                                }
-                            }
-                            // Output when condition using dotted keys (valid TOML for array elements)
-                            if let Some(cond) = when {
-                                cond.write_toml(&mut output);
-                            }
-                        }
-                        RuleConfig::Remove { tag, pattern, when } => {
-                            output.push_str("type = \"remove\"\n");
-                            output.push_str(&format!("tag = \"{}\"\n", tag));
-                            output.push_str(&format!("pattern = \"{}\"\n", pattern));
-                            if let Some(cond) = when {
-                                cond.write_toml(&mut output);
-                            }
-                        }
-                        RuleConfig::Replace {
-                            tag,
-                            pattern,
-                            replacement,
-                            when,
-                        } => {
-                            output.push_str("type = \"replace\"\n");
-                            output.push_str(&format!("tag = \"{}\"\n", tag));
-                            output.push_str(&format!("pattern = \"{}\"\n", pattern));
-                            output.push_str(&format!("replacement = \"{}\"\n", replacement));
-                            if let Some(cond) = when {
-                                cond.write_toml(&mut output);
-                            }
-                        }
-                    }
+                    write_tag_rule(&mut output, rule);
                 }
             }
         }
@@ -245,6 +484,7 @@ enabled = true
                     match rule {
                         crate::proxy::transformation::system_editor::RuleConfig::Append {
                             content,
+                            when,
                         } => {
                             output.push_str("type = \"append\"\n");
                             if content.contains('\n') {
@@ -253,9 +493,13 @@ enabled = true
                             } else {
                                 output.push_str(&format!("content = \"{}\"\n", content));
                             }
+                            if let Some(cond) = when {
+                                cond.write_toml(&mut output);
+                            }
                         }
                         crate::proxy::transformation::system_editor::RuleConfig::Prepend {
                             content,
+                            when,
                         } => {
                             output.push_str("type = \"prepend\"\n");
                             if content.contains('\n') {
@@ -264,15 +508,69 @@ enabled = true
                             } else {
                                 output.push_str(&format!("content = \"{}\"\n", content));
                             }
+                            if let Some(cond) = when {
+                                cond.write_toml(&mut output);
+                            }
                         }
                         crate::proxy::transformation::system_editor::RuleConfig::Replace {
                             pattern,
                             replacement,
+                            when,
                         } => {
                             output.push_str("type = \"replace\"\n");
                             output.push_str(&format!("pattern = \"{}\"\n", pattern));
                             output.push_str(&format!("replacement = \"{}\"\n", replacement));
+                            if let Some(cond) = when {
+                                cond.write_toml(&mut output);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        // Serialize tool-filter if configured
+        if let Some(ref filter) = self.transformers.tool_filter {
+            if filter.enabled && !filter.rules.is_empty() {
+                output.push_str(
+                    r#"
+# ─────────────────────────────────────────────────────────────────────────────
+# TOOL FILTER
+# ─────────────────────────────────────────────────────────────────────────────
+# Removes, renames or re-describes tool definitions, optionally per client.
+
+[transformers.tool-filter]
+enabled = true
+"#,
+                );
+
+                for rule in &filter.rules {
+                    output.push_str("\n[[transformers.tool-filter.rules]]\n");
+                    let when = match rule {
+                        ToolRuleConfig::Remove { tool, when } => {
+                            output.push_str("type = \"remove\"\n");
+                            output.push_str(&format!("tool = {:?}\n", tool));
+                            when
                         }
+                        ToolRuleConfig::Rename { tool, to, when } => {
+                            output.push_str("type = \"rename\"\n");
+                            output.push_str(&format!("tool = {:?}\n", tool));
+                            output.push_str(&format!("to = {:?}\n", to));
+                            when
+                        }
+                        ToolRuleConfig::Describe {
+                            tool,
+                            description,
+                            when,
+                        } => {
+                            output.push_str("type = \"describe\"\n");
+                            output.push_str(&format!("tool = {:?}\n", tool));
+                            output.push_str(&format!("description = {:?}\n", description));
+                            when
+                        }
+                    };
+                    if let Some(cond) = when {
+                        cond.write_toml(&mut output);
                     }
                 }
             }
@@ -296,6 +594,159 @@ enabled = true
             }
         }
 
+        // Serialize context-enricher if configured
+        if let Some(ref enricher) = self.transformers.context_enricher {
+            if enricher.enabled {
+                output.push_str(&format!(
+                    r#"
+# ─────────────────────────────────────────────────────────────────────────────
+# CONTEXT ENRICHER
+# ─────────────────────────────────────────────────────────────────────────────
+# Injects relevant history from cortex into the prompt as <aspy-context>.
+
+[transformers.context-enricher]
+enabled = true
+max_tokens = {}
+max_results = {}
+min_relevance = {:?}
+min_prompt_chars = {}
+"#,
+                    enricher.max_tokens,
+                    enricher.max_results,
+                    enricher.min_relevance,
+                    enricher.min_prompt_chars
+                ));
+                if let Some(ref when) = enricher.when {
+                    when.write_toml(&mut output);
+                }
+            }
+        }
+
+        // Serialize tool-governor if configured
+        if let Some(ref governor) = self.transformers.tool_governor {
+            if governor.enabled {
+                output.push_str(&format!(
+                    r#"
+# ─────────────────────────────────────────────────────────────────────────────
+# TOOL GOVERNOR
+# ─────────────────────────────────────────────────────────────────────────────
+# Truncates oversized tool results from earlier turns (originals kept in cortex).
+
+[transformers.tool-governor]
+enabled = true
+max_tokens = {}
+keep_recent_turns = {}
+"#,
+                    governor.max_tokens, governor.keep_recent_turns
+                ));
+                if !governor.tools.is_empty() {
+                    output.push_str("\n[transformers.tool-governor.tools]\n");
+                    for (tool, limit) in &governor.tools {
+                        output.push_str(&format!("{:?} = {}\n", tool, limit));
+                    }
+                }
+            }
+        }
+
+        // Serialize scripts if configured
+        if let Some(ref scripts) = self.transformers.scripts {
+            if scripts.enabled {
+                output.push_str(&format!(
+                    r#"
+# ─────────────────────────────────────────────────────────────────────────────
+# SCRIPTS
+# ─────────────────────────────────────────────────────────────────────────────
+# Runs transform(body, ctx) from each .rhai file in dir (hot-reloaded).
+
+[transformers.scripts]
+enabled = true
+dir = {:?}
+timeout_ms = {}
+max_operations = {}
+"#,
+                    scripts.dir.display().to_string(),
+                    scripts.timeout_ms,
+                    scripts.max_operations
+                ));
+            }
+        }
+
+        // Serialize external if configured
+        if let Some(ref external) = self.transformers.external {
+            if external.enabled {
+                output.push_str(
+                    r#"
+# ─────────────────────────────────────────────────────────────────────────────
+# EXTERNAL TRANSFORMER
+# ─────────────────────────────────────────────────────────────────────────────
+# Sends each request to an HTTP endpoint or child process and applies its answer.
+
+[transformers.external]
+enabled = true
+"#,
+                );
+                if let Some(ref url) = external.url {
+                    output.push_str(&format!("url = {:?}\n", url));
+                }
+                if !external.command.is_empty() {
+                    output.push_str(&format!("command = {:?}\n", external.command));
+                }
+                output.push_str(&format!("timeout_ms = {}\n", external.timeout_ms));
+                output.push_str(&format!(
+                    "failure_mode = \"{}\"\n",
+                    external.failure_mode.as_str()
+                ));
+            }
+        }
+
+        // Serialize redactor if configured
+        if let Some(ref redactor) = self.transformers.redactor {
+            if redactor.enabled {
+                output.push_str(
+                    r#"
+# ─────────────────────────────────────────────────────────────────────────────
+# REDACTOR
+# ─────────────────────────────────────────────────────────────────────────────
+# Replaces secrets in tool results with placeholders before forwarding.
+
+[transformers.redactor]
+enabled = true
+"#,
+                );
+                if !redactor.builtin {
+                    output.push_str("builtin = false\n");
+                }
+                if !redactor.exclude.is_empty() {
+                    output.push_str(&format!("exclude = {:?}\n", redactor.exclude));
+                }
+                for pattern in &redactor.patterns {
+                    output.push_str("\n[[transformers.redactor.patterns]]\n");
+                    output.push_str(&format!("name = {:?}\n", pattern.name));
+                    // Debug formatting escapes quotes and backslashes in patterns
+                    output.push_str(&format!("pattern = {:?}\n", pattern.pattern));
+                }
+            }
+        }
+
+        // Serialize cache-optimizer if configured
+        if let Some(ref cache) = self.transformers.cache_optimizer {
+            if cache.enabled {
+                output.push_str(&format!(
+                    r#"
+# ─────────────────────────────────────────────────────────────────────────────
+# CACHE OPTIMIZER
+# ─────────────────────────────────────────────────────────────────────────────
+# Places prompt-cache breakpoints on tools, system and stable message prefixes.
+
+[transformers.cache-optimizer]
+enabled = true
+min_prefix_tokens = {}
+"#,
+                    cache.min_prefix_tokens
+                ));
+            }
+        }
+
         output
     }
 
@@ -397,6 +848,51 @@ auto_detect = {translation_auto_detect}
 [transformers]
 enabled = {transformers_enabled}
 
+# Budget - daily/weekly/monthly USD limits per client id or user id (API key hash)
+# Spend is priced from usage and persisted in cortex. Past the limit, requests
+# get a 402 billing_error; at warn_percent a one-time warning is appended.
+#
+# [transformers.budget]
+# enabled = true
+# warn_percent = 80
+# [transformers.budget.clients."dev-1"]
+# daily_usd = 5.0
+# monthly_usd = 100.0
+
+# Model Router - pick the provider per request by model, context size, turn or client
+# Providers come from [providers]; their fallback chains still apply.
+# Conditions: model (regex), context_tokens (">100000"), when.turn_number, when.client_id
+#
+# Example: Send Haiku utility calls to a cheap backend, keep Opus on Anthropic
+# [transformers.model-router]
+# enabled = true
+# [[transformers.model-router.rules]]
+# name = "haiku-cheap"
+# model = "haiku"
+# provider = "openrouter"
+# model_mapping = {{ "haiku" = "openai/gpt-4o-mini" }}
+#
+# [[transformers.model-router.rules]]
+# name = "opus-direct"
+# model = "opus"
+# provider = "anthropic"
+
+# Projects - per-repository instructions, tag rules and budgets
+# Matched on the working directory in the system prompt (globs, `|` for several).
+# The project is stored with the session, so cortex stats group by repository.
+#
+# [transformers.projects]
+# enabled = true
+# [[transformers.projects.registry]]
+# name = "aspy"
+# path = "~/work/aspy|~/work/aspy-*"
+# instructions = "~/notes/aspy.md"   # Appended to the system prompt
+# budget.daily_usd = 10.0
+# [[transformers.projects.registry.rules]]
+# type = "inject"
+# tag = "aspy-context"
+# content = "Run cargo clippy before committing."
+
 # System Reminder Editor - modify <system-reminder> tags in user messages
 # Rules are applied in order. Rule types:
 #   inject  - Add new <system-reminder> content (position: start, end, before, after)
@@ -441,6 +937,59 @@ enabled = {transformers_enabled}
 # Compaction Enhancer - inject continuity guidance when Claude Code runs /compact
 # [transformers.compact-enhancer]
 # enabled = true
+#
+# Context Enricher - inject relevant cortex history as <aspy-context> (needs cortex)
+# [transformers.context-enricher]
+# enabled = true
+# max_tokens = 1000         # Budget for the injected block
+# max_results = 5
+# min_relevance = 0.3       # 0.0 - 1.0, lower injects weaker matches
+# when.turn_number = ">1"   # Optional gating, same as tag-editor rules
+#
+# Cache Optimizer - add prompt-cache breakpoints for clients that don't set them
+# [transformers.cache-optimizer]
+# enabled = true
+# min_prefix_tokens = 1024  # Skip prefixes shorter than this
+#
+# Tool Filter - remove, rename or re-describe tools per client (history follows)
+# [transformers.tool-filter]
+# enabled = true
+# [[transformers.tool-filter.rules]]
+# type = "remove"           # Also: "rename" (to = "..."), "describe" (description = "...")
+# tool = "Bash|Write"       # Full-name regex
+# when.client_id = "review-bot"
+#
+# Tool Governor - cap old tool results (head + tail kept, originals in cortex)
+# [transformers.tool-governor]
+# enabled = true
+# max_tokens = 4000         # Default cap per result
+# keep_recent_turns = 2     # Recent turns are never truncated
+# [transformers.tool-governor.tools]
+# Read = 8000               # Per-tool caps (0 = never truncate)
+# Bash = 2000
+#
+# Scripts - custom transformers in Rhai, hot-reloaded from ~/.config/aspy/scripts
+# Each *.rhai file defines fn transform(body, ctx) returning (), modified(body) or block(reason)
+# [transformers.scripts]
+# enabled = true
+# dir = "scripts"           # Relative to ~/.config/aspy
+# timeout_ms = 50           # Per-call limits
+# max_operations = 1000000
+#
+# External - hand each request to your own service (JSON protocol, see docs/transformers.md)
+# [transformers.external]
+# enabled = true
+# url = "http://127.0.0.1:9000/transform"  # Or: command = ["python3", "policy.py"]
+# timeout_ms = 200
+# failure_mode = "open"     # "open" forwards unchanged on failure, "closed" rejects with 503
+#
+# Redactor - replace secrets in tool results with ⟦SECRET_N⟧ placeholders
+# [transformers.redactor]
+# enabled = true
+# exclude = ["email"]  # Built-in detectors to skip
+# [[transformers.redactor.patterns]]
+# name = "internal-host"
+# pattern = "\\b[a-z0-9-]+\\.corp\\.example\\.com\\b"
 {transformers_section}
 # ─────────────────────────────────────────────────────────────────────────────
 # OPENTELEMETRY EXPORT (Optional)
@@ -466,7 +1015,71 @@ service_version = "{otel_service_version}"
 # ─────────────────────────────────────────────────────────────────────────────
 # Define where to forward API requests. Clients reference these by name.
 {providers_section}
-"#,
+# ─────────────────────────────────────────────────────────────────────────────
+# PROVIDER FAILOVER
+# ─────────────────────────────────────────────────────────────────────────────
+# Providers with a `fallback` list re-route failed requests to the next healthy
+# provider. After `failure_threshold` consecutive failures a provider is skipped
+# for `cooldown_secs`. Connection errors always count as failures.
+
+[failover]
+failure_threshold = {failover_threshold}
+cooldown_secs = {failover_cooldown}
+status_codes = {failover_status_codes:?}
+
+# ─────────────────────────────────────────────────────────────────────────────
+# UPSTREAM RETRY (Optional)
+# ─────────────────────────────────────────────────────────────────────────────
+# Retry rate-limited (429) and overloaded (529) responses before anything is
+# streamed to the client. Waits honor retry-after and anthropic-ratelimit-*-reset
+# headers; otherwise exponential backoff with jitter. A server-requested wait
+# longer than max_backoff_ms is not retried.
+
+[retry]
+enabled = {retry_enabled}
+max_attempts = {retry_max_attempts}
+initial_backoff_ms = {retry_initial_backoff}
+max_backoff_ms = {retry_max_backoff}
+status_codes = {retry_status_codes:?}
+
+# ─────────────────────────────────────────────────────────────────────────────
+# CLIENT RATE LIMITS (Optional)
+# ─────────────────────────────────────────────────────────────────────────────
+# Cap each client (or API key, when not routed) so one runaway agent loop can't
+# starve everyone sharing the upstream org. Limits use a rolling 60s window;
+# input tokens are estimated locally. Over-limit requests get an Anthropic-style
+# 429 with retry-after. count_tokens calls are not limited here.
+
+{rate_limit_section}
+# ─────────────────────────────────────────────────────────────────────────────
+# CASSETTE (Record/Replay)
+# ─────────────────────────────────────────────────────────────────────────────
+# Record upstream exchanges (request + raw response chunks with timing) to a
+# JSON Lines file, then replay them offline to reproduce SSE/translation bugs.
+# mode: "off", "record" or "replay". Env overrides: ASPY_CASSETTE_MODE,
+# ASPY_CASSETTE_PATH. Recordings contain prompts and responses - keep them private.
+
+[cassette]
+mode = "{cassette_mode}"
+path = "{cassette_path}"
+realtime = {cassette_realtime}  # Replay with recorded chunk timing
+
+# ─────────────────────────────────────────────────────────────────────────────
+# REQUEST BREAKPOINTS (TUI only)
+# ─────────────────────────────────────────────────────────────────────────────
+# Hold matching requests before they are forwarded so you can inspect, edit,
+# continue or reject them from the TUI. Press `b` in the TUI to arm/disarm.
+# Held requests continue unchanged after timeout_secs.
+
+{breakpoints_section}
+# ─────────────────────────────────────────────────────────────────────────────
+# TOOL GUARD
+# ─────────────────────────────────────────────────────────────────────────────
+# Check tool calls in responses before they reach the client. Denied calls are
+# replaced with a refusal text block; warned calls get a warning block after
+# them. Every decision is logged as a ToolPolicyDecision event.
+
+{tool_guard_section}"#,
             theme = self.theme,
             use_bg = self.use_theme_background,
             preset = self.preset,
@@ -549,6 +1162,20 @@ service_version = "{otel_service_version}"
             otel_service_version = self.otel.service_version,
             clients_section = self.clients_to_toml(),
             providers_section = self.providers_to_toml(),
+            failover_threshold = self.failover.failure_threshold,
+            failover_cooldown = self.failover.cooldown_secs,
+            failover_status_codes = self.failover.status_codes,
+            retry_enabled = self.retry.enabled,
+            retry_max_attempts = self.retry.max_attempts,
+            retry_initial_backoff = self.retry.initial_backoff_ms,
+            retry_max_backoff = self.retry.max_backoff_ms,
+            retry_status_codes = self.retry.status_codes,
+            rate_limit_section = self.rate_limit_to_toml(),
+            cassette_mode = self.cassette.mode.as_str(),
+            cassette_path = self.cassette.path.display(),
+            cassette_realtime = self.cassette.realtime,
+            breakpoints_section = self.breakpoints_to_toml(),
+            tool_guard_section = self.tool_guard_to_toml(),
         )
     }
 
@@ -569,3 +1196,66 @@ service_version = "{otel_service_version}"
         std::fs::write(&path, self.to_toml())
     }
 }
+
+/// Write one tag-editor rule (the `[[...rules]]` header is written by the caller)
+fn write_tag_rule(output: &mut String, rule: &crate::proxy::transformation::RuleConfig) {
+    use crate::proxy::transformation::{PositionConfig, RuleConfig};
+
+    match rule {
+        RuleConfig::Inject {
+            tag,
+            content,
+            position,
+            when,
+        } => {
+            output.push_str("type = \"inject\"\n");
+            output.push_str(&format!("tag = \"{}\"\n", tag));
+            // Escape content for TOML multiline if needed
+            if content.contains('\n') {
+                output.push_str(&format!("content = \"\"\"\n{}\n\"\"\"\n", content));
+            } else {
+                output.push_str(&format!("content = \"{}\"\n", content));
+            }
+            match position {
+                PositionConfig::Start => {
+                    output.push_str("position = \"start\"\n");
+                }
+                PositionConfig::End => {
+                    // end is default, can omit
+                }
+                PositionConfig::Before { pattern } => {
+                    output.push_str(&format!("position.before.pattern = \"{}\"\n", pattern));
+                }
+                PositionConfig::After { pattern } => {
+                    output.push_str(&format!("position.after.pattern = \"{}\"\n", pattern));
+                }
+            }
+            // Output when condition using dotted keys (valid TOML for array elements)
+            if let Some(cond) = when {
+                cond.write_toml(output);
+            }
+        }
+        RuleConfig::Remove { tag, pattern, when } => {
+            output.push_str("type = \"remove\"\n");
+            output.push_str(&format!("tag = \"{}\"\n", tag));
+            output.push_str(&format!("pattern = \"{}\"\n", pattern));
+            if let Some(cond) = when {
+                cond.write_toml(output);
+            }
+        }
+        RuleConfig::Replace {
+            tag,
+            pattern,
+            replacement,
+            when,
+        } => {
+            output.push_str("type = \"replace\"\n");
+            output.push_str(&format!("tag = \"{}\"\n", tag));
+            output.push_str(&format!("pattern = \"{}\"\n", pattern));
+            output.push_str(&format!("replacement = \"{}\"\n", replacement));
+            if let Some(cond) = when {
+                cond.write_toml(output);
+            }
+        }
+    }
+}
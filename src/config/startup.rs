@@ -3,7 +3,7 @@
 //! This is the SINGLE SOURCE OF TRUTH for what features exist.
 //! Adding a new feature? Add it here, and it shows up in startup automatically.
 
-use super::Config;
+use super::{CassetteMode, Config};
 
 impl Config {
     /// Get all feature definitions based on current configuration.
@@ -106,6 +106,54 @@ impl Config {
             "API translation (experimental)",
         ));
 
+        // Budget: optional (per client/user spending limits)
+        let budget_active = self.transformers.enabled
+            && self
+                .transformers
+                .budget
+                .as_ref()
+                .map(|c| c.enabled)
+                .unwrap_or(false);
+        features.push(FeatureDefinition::optional(
+            "budget",
+            "budget",
+            FeatureCategory::Pipeline,
+            budget_active,
+            "Spending budgets",
+        ));
+
+        // Model router: optional (per-request provider/model selection)
+        let model_router_active = self.transformers.enabled
+            && self
+                .transformers
+                .model_router
+                .as_ref()
+                .map(|c| c.enabled && !c.rules.is_empty())
+                .unwrap_or(false);
+        features.push(FeatureDefinition::optional(
+            "model-router",
+            "router",
+            FeatureCategory::Routing,
+            model_router_active,
+            "Model-based routing",
+        ));
+
+        // Projects: optional (per-repository instructions, rules and budgets)
+        let projects_active = self.transformers.enabled
+            && self
+                .transformers
+                .projects
+                .as_ref()
+                .map(|c| c.enabled && !c.registry.is_empty())
+                .unwrap_or(false);
+        features.push(FeatureDefinition::optional(
+            "projects",
+            "projects",
+            FeatureCategory::Pipeline,
+            projects_active,
+            "Per-project rules",
+        ));
+
         // Transformation: optional (request modification before forwarding)
         // Shows as active when enabled=true AND has configured rules
         let tag_editor_active = self.transformers.enabled
@@ -142,6 +190,22 @@ impl Config {
             .highlight_when_missing("[transformers.system-editor]\nenabled = true"),
         );
 
+        // Tool filter: optional (removes, renames or re-describes tools)
+        let tool_filter_active = self.transformers.enabled
+            && self
+                .transformers
+                .tool_filter
+                .as_ref()
+                .map(|c| c.enabled && !c.rules.is_empty())
+                .unwrap_or(false);
+        features.push(FeatureDefinition::optional(
+            "tool-filter",
+            "tools",
+            FeatureCategory::Pipeline,
+            tool_filter_active,
+            "Tool definition filtering",
+        ));
+
         // Compact enhancer: optional (enhances compaction prompts)
         let compact_enhancer_active = self.transformers.enabled
             && self
@@ -161,6 +225,102 @@ impl Config {
             .highlight_when_missing("[transformers.compact-enhancer]\nenabled = true"),
         );
 
+        // Context enricher: optional (injects relevant cortex history)
+        let context_enricher_active = self.transformers.enabled
+            && self
+                .transformers
+                .context_enricher
+                .as_ref()
+                .map(|c| c.enabled)
+                .unwrap_or(false);
+        features.push(FeatureDefinition::optional(
+            "context-enricher",
+            "enrich",
+            FeatureCategory::Pipeline,
+            context_enricher_active,
+            "Cortex context injection",
+        ));
+
+        // Tool governor: optional (truncates oversized old tool results)
+        let tool_governor_active = self.transformers.enabled
+            && self
+                .transformers
+                .tool_governor
+                .as_ref()
+                .map(|c| c.enabled)
+                .unwrap_or(false);
+        features.push(FeatureDefinition::optional(
+            "tool-governor",
+            "governor",
+            FeatureCategory::Pipeline,
+            tool_governor_active,
+            "Tool result caps",
+        ));
+
+        // Scripts: optional (user-defined Rhai transformers)
+        let scripts_active = self.transformers.enabled
+            && self
+                .transformers
+                .scripts
+                .as_ref()
+                .map(|c| c.enabled)
+                .unwrap_or(false);
+        features.push(FeatureDefinition::optional(
+            "scripts",
+            "scripts",
+            FeatureCategory::Pipeline,
+            scripts_active,
+            "Scripted transformers",
+        ));
+
+        // External: optional (delegates to an HTTP endpoint or child process)
+        let external_active = self.transformers.enabled
+            && self
+                .transformers
+                .external
+                .as_ref()
+                .map(|c| c.enabled)
+                .unwrap_or(false);
+        features.push(FeatureDefinition::optional(
+            "external",
+            "external",
+            FeatureCategory::Pipeline,
+            external_active,
+            "External transformer",
+        ));
+
+        // Redactor: optional (replaces secrets in tool results)
+        let redactor_active = self.transformers.enabled
+            && self
+                .transformers
+                .redactor
+                .as_ref()
+                .map(|c| c.enabled)
+                .unwrap_or(false);
+        features.push(FeatureDefinition::optional(
+            "redactor",
+            "redact",
+            FeatureCategory::Pipeline,
+            redactor_active,
+            "Secret redaction",
+        ));
+
+        // Cache optimizer: optional (places prompt-cache breakpoints)
+        let cache_optimizer_active = self.transformers.enabled
+            && self
+                .transformers
+                .cache_optimizer
+                .as_ref()
+                .map(|c| c.enabled)
+                .unwrap_or(false);
+        features.push(FeatureDefinition::optional(
+            "cache-optimizer",
+            "cache-opt",
+            FeatureCategory::Pipeline,
+            cache_optimizer_active,
+            "Prompt-cache breakpoints",
+        ));
+
         // OpenTelemetry: configurable (requires connection string)
         let otel_def = if self.otel.is_configured() {
             FeatureDefinition::configurable(
@@ -191,6 +351,49 @@ impl Config {
             "Multi-client",
         ));
 
+        features.push(FeatureDefinition::optional(
+            "retry",
+            "retry",
+            FeatureCategory::Routing,
+            self.retry.enabled,
+            "429/529 retry",
+        ));
+
+        features.push(FeatureDefinition::optional(
+            "rate-limit",
+            "rate-limit",
+            FeatureCategory::Routing,
+            self.rate_limit.enabled,
+            "Client rate limits",
+        ));
+
+        // Cassette: optional (record/replay upstream traffic)
+        features.push(FeatureDefinition::optional(
+            "cassette",
+            "cassette",
+            FeatureCategory::Routing,
+            self.cassette.mode != CassetteMode::Off,
+            "Record/replay",
+        ));
+
+        // Tool guard: configurable (needs rules)
+        features.push(FeatureDefinition::configurable(
+            "tool-guard",
+            "guard",
+            FeatureCategory::Pipeline,
+            self.tool_guard.is_configured(),
+            "Tool call policy",
+        ));
+
+        // Breakpoints: configurable (needs rules, and the TUI to answer them)
+        features.push(FeatureDefinition::configurable(
+            "breakpoints",
+            "breakpoints",
+            FeatureCategory::Routing,
+            self.breakpoints.is_configured() && self.enable_tui,
+            "Request breakpoints",
+        ));
+
         features
     }
 }
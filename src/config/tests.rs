@@ -47,6 +47,7 @@ fn test_config_roundtrip_with_transformers() {
                     turn_number: Some(">2".to_string()),
                     has_tool_results: None,
                     client_id: None,
+                    ..Default::default()
                 }),
             },
             RuleConfig::Inject {
@@ -57,6 +58,7 @@ fn test_config_roundtrip_with_transformers() {
                     turn_number: Some("every:3".to_string()),
                     has_tool_results: Some("=0".to_string()),
                     client_id: Some("dev-1|foundry".to_string()),
+                    ..Default::default()
                 }),
             },
         ],
@@ -102,7 +104,11 @@ fn test_config_roundtrip_with_transformers() {
 fn test_all_transformers_have_toml_serialization() {
     use crate::proxy::transformation::system_editor::RuleConfig as SystemRuleConfig;
     use crate::proxy::transformation::{
-        CompactEnhancerConfig, PositionConfig, RuleConfig, SystemEditorConfig, TagEditorConfig,
+        BudgetConfig, BudgetLimits, CacheOptimizerConfig, CompactEnhancerConfig,
+        ContextEnricherConfig, ExternalConfig, FailureMode, ModelRouterConfig, PatternConfig,
+        PositionConfig, ProjectConfig, ProjectsConfig, RedactorConfig, RouteRuleConfig, RuleConfig,
+        ScriptsConfig, SystemEditorConfig, TagEditorConfig, ToolFilterConfig, ToolGovernorConfig,
+        ToolRuleConfig, WhenCondition,
     };
 
     // ─────────────────────────────────────────────────────────────────────
@@ -128,12 +134,130 @@ fn test_all_transformers_have_toml_serialization() {
         enabled: true,
         rules: vec![SystemRuleConfig::Append {
             content: "test".to_string(),
+            when: Some(WhenCondition {
+                context_usage: Some(">80".to_string()),
+                any: vec![WhenCondition {
+                    model: Some("opus".to_string()),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }),
+        }],
+    });
+
+    // Tool filter with a per-client removal
+    config.transformers.tool_filter = Some(ToolFilterConfig {
+        enabled: true,
+        rules: vec![ToolRuleConfig::Remove {
+            tool: "Bash|Write".to_string(),
+            when: Some(WhenCondition {
+                client_id: Some("review-bot".to_string()),
+                ..Default::default()
+            }),
+        }],
+    });
+
+    // Projects with instructions, a budget and a project-scoped rule
+    config.transformers.projects = Some(ProjectsConfig {
+        enabled: true,
+        registry: vec![ProjectConfig {
+            name: "aspy".to_string(),
+            path: "~/work/aspy|/srv/aspy-*".to_string(),
+            instructions: Some("~/notes/aspy.md".to_string()),
+            budget: Some(BudgetLimits {
+                daily_usd: Some(10.0),
+                ..Default::default()
+            }),
+            rules: vec![RuleConfig::Inject {
+                tag: "aspy-context".to_string(),
+                content: "Run clippy".to_string(),
+                position: PositionConfig::End,
+                when: None,
+            }],
         }],
     });
 
     // Compact enhancer with minimal valid config
     config.transformers.compact_enhancer = Some(CompactEnhancerConfig { enabled: true });
 
+    // Context enricher with a when condition
+    config.transformers.context_enricher = Some(ContextEnricherConfig {
+        enabled: true,
+        max_tokens: 800,
+        min_relevance: 0.5,
+        when: Some(WhenCondition {
+            turn_number: Some(">1".to_string()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    });
+
+    // Tool governor with a per-tool limit
+    config.transformers.tool_governor = Some(ToolGovernorConfig {
+        enabled: true,
+        max_tokens: 3000,
+        tools: [("Bash".to_string(), 1500)].into(),
+        keep_recent_turns: 1,
+    });
+
+    // Scripts with a custom directory
+    config.transformers.scripts = Some(ScriptsConfig {
+        enabled: true,
+        dir: "my-scripts".into(),
+        timeout_ms: 25,
+        ..Default::default()
+    });
+
+    // External transformer with a child process
+    config.transformers.external = Some(ExternalConfig {
+        enabled: true,
+        command: vec!["python3".to_string(), "policy.py".to_string()],
+        timeout_ms: 150,
+        failure_mode: FailureMode::Closed,
+        ..Default::default()
+    });
+
+    // Redactor with a custom pattern that needs escaping
+    config.transformers.redactor = Some(RedactorConfig {
+        enabled: true,
+        exclude: vec!["email".to_string()],
+        patterns: vec![PatternConfig {
+            name: "ticket".to_string(),
+            pattern: r#"\bTICKET-\d+\b"#.to_string(),
+        }],
+        ..Default::default()
+    });
+
+    // Cache optimizer with a non-default threshold
+    config.transformers.cache_optimizer = Some(CacheOptimizerConfig {
+        enabled: true,
+        min_prefix_tokens: 2048,
+    });
+
+    // Model router with minimal valid config
+    config.transformers.model_router = Some(ModelRouterConfig {
+        enabled: true,
+        rules: vec![RouteRuleConfig {
+            model: Some("haiku".to_string()),
+            provider: Some("cheap".to_string()),
+            ..Default::default()
+        }],
+    });
+
+    // Budget with one client limit
+    config.transformers.budget = Some(BudgetConfig {
+        enabled: true,
+        clients: [(
+            "dev-1".to_string(),
+            BudgetLimits {
+                daily_usd: Some(5.0),
+                ..Default::default()
+            },
+        )]
+        .into(),
+        ..Default::default()
+    });
+
     // ─────────────────────────────────────────────────────────────────────
     // STEP 2: Generate TOML output
     // ─────────────────────────────────────────────────────────────────────
@@ -168,6 +292,86 @@ fn test_all_transformers_have_toml_serialization() {
         toml_str
     );
 
+    assert!(
+        toml_str.contains("[transformers.cache-optimizer]"),
+        "cache-optimizer missing from TOML output!\n\
+         Did you forget to serialize it in transformers_to_toml()?\n\
+         TOML output:\n{}",
+        toml_str
+    );
+
+    assert!(
+        toml_str.contains("[transformers.context-enricher]"),
+        "context-enricher missing from TOML output!\n\
+         Did you forget to serialize it in transformers_to_toml()?\n\
+         TOML output:\n{}",
+        toml_str
+    );
+
+    assert!(
+        toml_str.contains("[transformers.projects]"),
+        "projects missing from TOML output!\n\
+         Did you forget to serialize it in transformers_to_toml()?\n\
+         TOML output:\n{}",
+        toml_str
+    );
+
+    assert!(
+        toml_str.contains("[transformers.tool-filter]"),
+        "tool-filter missing from TOML output!\n\
+         Did you forget to serialize it in transformers_to_toml()?\n\
+         TOML output:\n{}",
+        toml_str
+    );
+
+    assert!(
+        toml_str.contains("[transformers.tool-governor]"),
+        "tool-governor missing from TOML output!\n\
+         Did you forget to serialize it in transformers_to_toml()?\n\
+         TOML output:\n{}",
+        toml_str
+    );
+
+    assert!(
+        toml_str.contains("[transformers.scripts]"),
+        "scripts missing from TOML output!\n\
+         Did you forget to serialize it in transformers_to_toml()?\n\
+         TOML output:\n{}",
+        toml_str
+    );
+
+    assert!(
+        toml_str.contains("[transformers.external]"),
+        "external missing from TOML output!\n\
+         Did you forget to serialize it in transformers_to_toml()?\n\
+         TOML output:\n{}",
+        toml_str
+    );
+
+    assert!(
+        toml_str.contains("[transformers.redactor]"),
+        "redactor missing from TOML output!\n\
+         Did you forget to serialize it in transformers_to_toml()?\n\
+         TOML output:\n{}",
+        toml_str
+    );
+
+    assert!(
+        toml_str.contains("[transformers.model-router]"),
+        "model-router missing from TOML output!\n\
+         Did you forget to serialize it in transformers_to_toml()?\n\
+         TOML output:\n{}",
+        toml_str
+    );
+
+    assert!(
+        toml_str.contains("[transformers.budget]"),
+        "budget missing from TOML output!\n\
+         Did you forget to serialize it in transformers_to_toml()?\n\
+         TOML output:\n{}",
+        toml_str
+    );
+
     // ─────────────────────────────────────────────────────────────────────
     // STEP 4: Verify round-trip works (catches TOML syntax errors)
     // ─────────────────────────────────────────────────────────────────────
@@ -212,6 +416,98 @@ fn test_all_transformers_have_toml_serialization() {
         .compact_enhancer
         .expect("compact_enhancer should be present");
     assert!(compact.enabled, "compact_enhancer.enabled should be true");
+
+    // Verify cache-optimizer
+    let cache = transformers
+        .cache_optimizer
+        .expect("cache_optimizer should be present");
+    assert!(cache.enabled, "cache_optimizer.enabled should be true");
+    assert_eq!(cache.min_prefix_tokens, 2048);
+
+    // Verify context-enricher
+    let enricher = transformers
+        .context_enricher
+        .expect("context_enricher should be present");
+    assert!(enricher.enabled, "context_enricher.enabled should be true");
+    assert_eq!(enricher.max_tokens, 800);
+    assert_eq!(enricher.min_relevance, 0.5);
+    assert_eq!(
+        enricher.when.and_then(|w| w.turn_number).as_deref(),
+        Some(">1")
+    );
+
+    // Verify projects
+    let projects = transformers.projects.expect("projects should be present");
+    assert!(projects.enabled, "projects.enabled should be true");
+    let project = &projects.registry[0];
+    assert_eq!(project.name, "aspy");
+    assert_eq!(project.path, "~/work/aspy|/srv/aspy-*");
+    assert_eq!(project.instructions.as_deref(), Some("~/notes/aspy.md"));
+    assert_eq!(project.budget.and_then(|b| b.daily_usd), Some(10.0));
+    assert!(
+        matches!(&project.rules[..], [RuleConfig::Inject { tag, .. }] if tag == "aspy-context"),
+        "unexpected project rules: {:?}",
+        project.rules
+    );
+
+    // Verify tool-filter
+    let filter = transformers
+        .tool_filter
+        .expect("tool_filter should be present");
+    assert!(filter.enabled, "tool_filter.enabled should be true");
+    match &filter.rules[..] {
+        [ToolRuleConfig::Remove { tool, when }] => {
+            assert_eq!(tool, "Bash|Write");
+            assert_eq!(
+                when.as_ref().and_then(|w| w.client_id.as_deref()),
+                Some("review-bot")
+            );
+        }
+        other => panic!("unexpected tool-filter rules: {:?}", other),
+    }
+
+    // Verify tool-governor
+    let governor = transformers
+        .tool_governor
+        .expect("tool_governor should be present");
+    assert!(governor.enabled, "tool_governor.enabled should be true");
+    assert_eq!(governor.max_tokens, 3000);
+    assert_eq!(governor.keep_recent_turns, 1);
+    assert_eq!(governor.tools["Bash"], 1500);
+
+    // Verify scripts
+    let scripts = transformers.scripts.expect("scripts should be present");
+    assert!(scripts.enabled, "scripts.enabled should be true");
+    assert_eq!(scripts.dir, std::path::PathBuf::from("my-scripts"));
+    assert_eq!(scripts.timeout_ms, 25);
+
+    // Verify external
+    let external = transformers.external.expect("external should be present");
+    assert!(external.enabled, "external.enabled should be true");
+    assert_eq!(external.command, vec!["python3", "policy.py"]);
+    assert_eq!(external.timeout_ms, 150);
+    assert_eq!(external.failure_mode, FailureMode::Closed);
+
+    // Verify redactor
+    let redactor = transformers.redactor.expect("redactor should be present");
+    assert!(redactor.enabled, "redactor.enabled should be true");
+    assert!(redactor.builtin, "redactor.builtin should default to true");
+    assert_eq!(redactor.exclude, vec!["email"]);
+    assert_eq!(redactor.patterns[0].pattern, r#"\bTICKET-\d+\b"#);
+
+    // Verify model-router
+    let router = transformers
+        .model_router
+        .expect("model_router should be present");
+    assert!(router.enabled, "model_router.enabled should be true");
+    assert_eq!(router.rules.len(), 1, "model_router should have 1 rule");
+    assert_eq!(router.rules[0].provider.as_deref(), Some("cheap"));
+
+    // Verify budget
+    let budget = transformers.budget.expect("budget should be present");
+    assert!(budget.enabled, "budget.enabled should be true");
+    assert_eq!(budget.warn_percent, 80);
+    assert_eq!(budget.clients["dev-1"].daily_usd, Some(5.0));
 }
 
 /// Ensures the DEFAULT template includes commented examples for all transformers.
@@ -244,6 +540,74 @@ fn test_default_template_documents_all_transformers() {
         "compact-enhancer not documented in default template!\n\
          Add a commented example so users can discover this feature."
     );
+
+    assert!(
+        toml_str.contains("transformers.cache-optimizer")
+            || toml_str.contains("# [transformers.cache-optimizer]"),
+        "cache-optimizer not documented in default template!\n\
+         Add a commented example so users can discover this feature."
+    );
+
+    assert!(
+        toml_str.contains("transformers.context-enricher")
+            || toml_str.contains("# [transformers.context-enricher]"),
+        "context-enricher not documented in default template!\n\
+         Add a commented example so users can discover this feature."
+    );
+
+    assert!(
+        toml_str.contains("transformers.projects")
+            || toml_str.contains("# [transformers.projects]"),
+        "projects not documented in default template!\n\
+         Add a commented example so users can discover this feature."
+    );
+
+    assert!(
+        toml_str.contains("transformers.tool-filter")
+            || toml_str.contains("# [transformers.tool-filter]"),
+        "tool-filter not documented in default template!\n\
+         Add a commented example so users can discover this feature."
+    );
+
+    assert!(
+        toml_str.contains("transformers.tool-governor")
+            || toml_str.contains("# [transformers.tool-governor]"),
+        "tool-governor not documented in default template!\n\
+         Add a commented example so users can discover this feature."
+    );
+
+    assert!(
+        toml_str.contains("transformers.scripts") || toml_str.contains("# [transformers.scripts]"),
+        "scripts not documented in default template!\n\
+         Add a commented example so users can discover this feature."
+    );
+
+    assert!(
+        toml_str.contains("transformers.external")
+            || toml_str.contains("# [transformers.external]"),
+        "external not documented in default template!\n\
+         Add a commented example so users can discover this feature."
+    );
+
+    assert!(
+        toml_str.contains("transformers.redactor")
+            || toml_str.contains("# [transformers.redactor]"),
+        "redactor not documented in default template!\n\
+         Add a commented example so users can discover this feature."
+    );
+
+    assert!(
+        toml_str.contains("transformers.model-router")
+            || toml_str.contains("# [transformers.model-router]"),
+        "model-router not documented in default template!\n\
+         Add a commented example so users can discover this feature."
+    );
+
+    assert!(
+        toml_str.contains("transformers.budget") || toml_str.contains("# [transformers.budget]"),
+        "budget not documented in default template!\n\
+         Add a commented example so users can discover this feature."
+    );
 }
 
 /// EXHAUSTIVE TEST: Ensures every transformer has a feature_definitions entry.
@@ -257,7 +621,11 @@ fn test_default_template_documents_all_transformers() {
 fn test_all_transformers_have_feature_definitions() {
     use crate::proxy::transformation::system_editor::RuleConfig as SystemRuleConfig;
     use crate::proxy::transformation::{
-        CompactEnhancerConfig, PositionConfig, RuleConfig, SystemEditorConfig, TagEditorConfig,
+        BudgetConfig, BudgetLimits, CacheOptimizerConfig, CompactEnhancerConfig,
+        ContextEnricherConfig, ExternalConfig, FailureMode, ModelRouterConfig, PatternConfig,
+        PositionConfig, ProjectConfig, ProjectsConfig, RedactorConfig, RouteRuleConfig, RuleConfig,
+        ScriptsConfig, SystemEditorConfig, TagEditorConfig, ToolFilterConfig, ToolGovernorConfig,
+        ToolRuleConfig, WhenCondition,
     };
 
     // ─────────────────────────────────────────────────────────────────────
@@ -281,11 +649,129 @@ fn test_all_transformers_have_feature_definitions() {
         enabled: true,
         rules: vec![SystemRuleConfig::Append {
             content: "test".to_string(),
+            when: Some(WhenCondition {
+                context_usage: Some(">80".to_string()),
+                any: vec![WhenCondition {
+                    model: Some("opus".to_string()),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }),
+        }],
+    });
+
+    // Tool filter with a per-client removal
+    config.transformers.tool_filter = Some(ToolFilterConfig {
+        enabled: true,
+        rules: vec![ToolRuleConfig::Remove {
+            tool: "Bash|Write".to_string(),
+            when: Some(WhenCondition {
+                client_id: Some("review-bot".to_string()),
+                ..Default::default()
+            }),
+        }],
+    });
+
+    // Projects with instructions, a budget and a project-scoped rule
+    config.transformers.projects = Some(ProjectsConfig {
+        enabled: true,
+        registry: vec![ProjectConfig {
+            name: "aspy".to_string(),
+            path: "~/work/aspy|/srv/aspy-*".to_string(),
+            instructions: Some("~/notes/aspy.md".to_string()),
+            budget: Some(BudgetLimits {
+                daily_usd: Some(10.0),
+                ..Default::default()
+            }),
+            rules: vec![RuleConfig::Inject {
+                tag: "aspy-context".to_string(),
+                content: "Run clippy".to_string(),
+                position: PositionConfig::End,
+                when: None,
+            }],
         }],
     });
 
     config.transformers.compact_enhancer = Some(CompactEnhancerConfig { enabled: true });
 
+    // Context enricher with a when condition
+    config.transformers.context_enricher = Some(ContextEnricherConfig {
+        enabled: true,
+        max_tokens: 800,
+        min_relevance: 0.5,
+        when: Some(WhenCondition {
+            turn_number: Some(">1".to_string()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    });
+
+    // Tool governor with a per-tool limit
+    config.transformers.tool_governor = Some(ToolGovernorConfig {
+        enabled: true,
+        max_tokens: 3000,
+        tools: [("Bash".to_string(), 1500)].into(),
+        keep_recent_turns: 1,
+    });
+
+    // Scripts with a custom directory
+    config.transformers.scripts = Some(ScriptsConfig {
+        enabled: true,
+        dir: "my-scripts".into(),
+        timeout_ms: 25,
+        ..Default::default()
+    });
+
+    // External transformer with a child process
+    config.transformers.external = Some(ExternalConfig {
+        enabled: true,
+        command: vec!["python3".to_string(), "policy.py".to_string()],
+        timeout_ms: 150,
+        failure_mode: FailureMode::Closed,
+        ..Default::default()
+    });
+
+    // Redactor with a custom pattern that needs escaping
+    config.transformers.redactor = Some(RedactorConfig {
+        enabled: true,
+        exclude: vec!["email".to_string()],
+        patterns: vec![PatternConfig {
+            name: "ticket".to_string(),
+            pattern: r#"\bTICKET-\d+\b"#.to_string(),
+        }],
+        ..Default::default()
+    });
+
+    // Cache optimizer with a non-default threshold
+    config.transformers.cache_optimizer = Some(CacheOptimizerConfig {
+        enabled: true,
+        min_prefix_tokens: 2048,
+    });
+
+    // Model router with minimal valid config
+    config.transformers.model_router = Some(ModelRouterConfig {
+        enabled: true,
+        rules: vec![RouteRuleConfig {
+            model: Some("haiku".to_string()),
+            provider: Some("cheap".to_string()),
+            ..Default::default()
+        }],
+    });
+
+    // Budget with one client limit
+    config.transformers.budget = Some(BudgetConfig {
+        enabled: true,
+        clients: [(
+            "dev-1".to_string(),
+            BudgetLimits {
+                daily_usd: Some(5.0),
+                ..Default::default()
+            },
+        )]
+        .into(),
+        ..Default::default()
+    });
+
     // ─────────────────────────────────────────────────────────────────────
     // STEP 2: Get feature definitions
     // ─────────────────────────────────────────────────────────────────────
@@ -321,11 +807,105 @@ fn test_all_transformers_have_feature_definitions() {
         feature_ids
     );
 
+    assert!(
+        feature_ids.contains(&"cache-optimizer"),
+        "cache-optimizer missing from feature_definitions()!\n\
+         Add it to Config::feature_definitions() so it shows in startup logs.\n\
+         Features found: {:?}",
+        feature_ids
+    );
+
+    assert!(
+        feature_ids.contains(&"context-enricher"),
+        "context-enricher missing from feature_definitions()!\n\
+         Add it to Config::feature_definitions() so it shows in startup logs.\n\
+         Features found: {:?}",
+        feature_ids
+    );
+
+    assert!(
+        feature_ids.contains(&"projects"),
+        "projects missing from feature_definitions()!\n\
+         Add it to Config::feature_definitions() so it shows in startup logs.\n\
+         Features found: {:?}",
+        feature_ids
+    );
+
+    assert!(
+        feature_ids.contains(&"tool-filter"),
+        "tool-filter missing from feature_definitions()!\n\
+         Add it to Config::feature_definitions() so it shows in startup logs.\n\
+         Features found: {:?}",
+        feature_ids
+    );
+
+    assert!(
+        feature_ids.contains(&"tool-governor"),
+        "tool-governor missing from feature_definitions()!\n\
+         Add it to Config::feature_definitions() so it shows in startup logs.\n\
+         Features found: {:?}",
+        feature_ids
+    );
+
+    assert!(
+        feature_ids.contains(&"scripts"),
+        "scripts missing from feature_definitions()!\n\
+         Add it to Config::feature_definitions() so it shows in startup logs.\n\
+         Features found: {:?}",
+        feature_ids
+    );
+
+    assert!(
+        feature_ids.contains(&"external"),
+        "external missing from feature_definitions()!\n\
+         Add it to Config::feature_definitions() so it shows in startup logs.\n\
+         Features found: {:?}",
+        feature_ids
+    );
+
+    assert!(
+        feature_ids.contains(&"redactor"),
+        "redactor missing from feature_definitions()!\n\
+         Add it to Config::feature_definitions() so it shows in startup logs.\n\
+         Features found: {:?}",
+        feature_ids
+    );
+
+    assert!(
+        feature_ids.contains(&"model-router"),
+        "model-router missing from feature_definitions()!\n\
+         Add it to Config::feature_definitions() so it shows in startup logs.\n\
+         Features found: {:?}",
+        feature_ids
+    );
+
+    assert!(
+        feature_ids.contains(&"budget"),
+        "budget missing from feature_definitions()!\n\
+         Add it to Config::feature_definitions() so it shows in startup logs.\n\
+         Features found: {:?}",
+        feature_ids
+    );
+
     // ─────────────────────────────────────────────────────────────────────
     // STEP 4: Verify they show as ACTIVE when enabled
     // ─────────────────────────────────────────────────────────────────────
     use crate::startup::FeatureStatus;
-    for id in ["tag-editor", "system-editor", "compact-enhancer"] {
+    for id in [
+        "tag-editor",
+        "system-editor",
+        "compact-enhancer",
+        "cache-optimizer",
+        "context-enricher",
+        "tool-filter",
+        "tool-governor",
+        "scripts",
+        "external",
+        "redactor",
+        "model-router",
+        "budget",
+        "projects",
+    ] {
         let feature = features.iter().find(|f| f.id == id).unwrap();
         assert!(
             matches!(feature.status, FeatureStatus::Active),
@@ -494,6 +1074,7 @@ fn test_provider_effective_api_path_default_anthropic() {
         auth: None,
         count_tokens: None,
         model_mapping: HashMap::new(),
+        fallback: vec![],
     };
     assert_eq!(provider.effective_api_path(), "/v1/messages");
 }
@@ -508,6 +1089,7 @@ fn test_provider_effective_api_path_default_openai() {
         auth: None,
         count_tokens: None,
         model_mapping: HashMap::new(),
+        fallback: vec![],
     };
     assert_eq!(provider.effective_api_path(), "/v1/chat/completions");
 }
@@ -523,6 +1105,7 @@ fn test_provider_effective_api_path_custom_overrides_default() {
         auth: None,
         model_mapping: HashMap::new(),
         count_tokens: None,
+        fallback: vec![],
     };
     assert_eq!(provider.effective_api_path(), "/chat/completions");
 }
@@ -538,6 +1121,7 @@ fn test_provider_effective_api_path_custom_with_anthropic_format() {
         auth: None,
         count_tokens: None,
         model_mapping: HashMap::new(),
+        fallback: vec![],
     };
     assert_eq!(provider.effective_api_path(), "/messages");
 }
@@ -552,6 +1136,8 @@ fn test_clients_config_get_client_api_path() {
             provider: "zai".to_string(),
             tags: vec![],
             auth: None,
+            fallback: vec![],
+            shadow: None,
         },
     );
 
@@ -566,16 +1152,22 @@ fn test_clients_config_get_client_api_path() {
             auth: None,
             model_mapping: HashMap::new(),
             count_tokens: None,
+            fallback: vec![],
         },
     );
 
     let config = ClientsConfig { clients, providers };
 
     // Client with custom api_path
-    assert_eq!(config.get_client_api_path("zai"), Some("/chat/completions"));
+    assert_eq!(
+        config
+            .get_client_provider("zai")
+            .map(|p| p.effective_api_path()),
+        Some("/chat/completions")
+    );
 
     // Unknown client returns None
-    assert_eq!(config.get_client_api_path("unknown"), None);
+    assert!(config.get_client_provider("unknown").is_none());
 }
 
 #[test]
@@ -593,6 +1185,7 @@ fn test_provider_api_path_serialization() {
             auth: None,
             model_mapping: HashMap::new(),
             count_tokens: None,
+            fallback: vec![],
         },
     );
 
@@ -631,6 +1224,7 @@ fn test_provider_effective_count_tokens_default_anthropic() {
         auth: None,
         count_tokens: None,
         model_mapping: HashMap::new(),
+        fallback: vec![],
     };
     // Anthropic defaults to Passthrough
     assert_eq!(
@@ -649,6 +1243,7 @@ fn test_provider_effective_count_tokens_default_openai() {
         auth: None,
         count_tokens: None,
         model_mapping: HashMap::new(),
+        fallback: vec![],
     };
     // OpenAI defaults to Synthetic
     assert_eq!(
@@ -668,6 +1263,7 @@ fn test_provider_effective_count_tokens_explicit_override() {
         auth: None,
         count_tokens: Some(CountTokensHandling::Dedupe),
         model_mapping: HashMap::new(),
+        fallback: vec![],
     };
     assert_eq!(
         provider.effective_count_tokens(),
@@ -685,6 +1281,8 @@ fn test_clients_config_get_client_count_tokens() {
             provider: "anthropic".to_string(),
             tags: vec![],
             auth: None,
+            fallback: vec![],
+            shadow: None,
         },
     );
     clients.insert(
@@ -694,6 +1292,8 @@ fn test_clients_config_get_client_count_tokens() {
             provider: "openai".to_string(),
             tags: vec![],
             auth: None,
+            fallback: vec![],
+            shadow: None,
         },
     );
 
@@ -708,6 +1308,7 @@ fn test_clients_config_get_client_count_tokens() {
             auth: None,
             count_tokens: None,
             model_mapping: HashMap::new(),
+            fallback: vec![],
         },
     );
     providers.insert(
@@ -720,6 +1321,7 @@ fn test_clients_config_get_client_count_tokens() {
             auth: None,
             count_tokens: None,
             model_mapping: HashMap::new(),
+            fallback: vec![],
         },
     );
 
@@ -763,6 +1365,7 @@ fn test_provider_count_tokens_toml_only_serializes_non_default() {
             auth: None,
             count_tokens: None, // Default for Anthropic is Passthrough
             model_mapping: HashMap::new(),
+            fallback: vec![],
         },
     );
 
@@ -777,6 +1380,7 @@ fn test_provider_count_tokens_toml_only_serializes_non_default() {
             auth: None,
             count_tokens: Some(CountTokensHandling::Dedupe), // Non-default
             model_mapping: HashMap::new(),
+            fallback: vec![],
         },
     );
 
@@ -803,3 +1407,404 @@ fn test_provider_count_tokens_toml_only_serializes_non_default() {
         );
     }
 }
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Provider failover tests
+// ─────────────────────────────────────────────────────────────────────────────
+
+fn failover_clients() -> ClientsConfig {
+    let file: FileConfig = toml::from_str(
+        r#"
+        [clients.dev-1]
+        name = "Dev"
+        provider = "anthropic"
+
+        [clients.ci]
+        name = "CI"
+        provider = "anthropic"
+        fallback = ["zai", "missing", "anthropic"]
+        [clients.ci.auth]
+        method = "x_api_key"
+        key = "ci-key"
+
+        [providers.anthropic]
+        base_url = "https://api.anthropic.com"
+        fallback = ["openrouter", "zai", "openrouter"]
+
+        [providers.openrouter]
+        base_url = "https://openrouter.ai/api/v1"
+        api_format = "openai"
+        [providers.openrouter.auth]
+        method = "bearer"
+        key = "or-key"
+
+        [providers.zai]
+        base_url = "https://api.z.ai/api/coding/paas/v4"
+        api_format = "openai"
+        "#,
+    )
+    .expect("failover config should parse");
+
+    ClientsConfig {
+        clients: file.clients,
+        providers: file.providers,
+    }
+}
+
+#[test]
+fn test_provider_chain_uses_provider_fallback() {
+    let clients = failover_clients();
+    let chain: Vec<&str> = clients
+        .get_client_provider_chain("dev-1")
+        .into_iter()
+        .map(|(id, _)| id)
+        .collect();
+
+    // Primary first, duplicates skipped
+    assert_eq!(chain, vec!["anthropic", "openrouter", "zai"]);
+}
+
+#[test]
+fn test_provider_chain_client_override() {
+    let clients = failover_clients();
+    let chain: Vec<&str> = clients
+        .get_client_provider_chain("ci")
+        .into_iter()
+        .map(|(id, _)| id)
+        .collect();
+
+    // Client list replaces provider list; unknown and primary entries skipped
+    assert_eq!(chain, vec!["anthropic", "zai"]);
+    assert!(clients.get_client_provider_chain("unknown").is_empty());
+}
+
+#[test]
+fn test_provider_auth_client_override_only_for_primary() {
+    let clients = failover_clients();
+
+    let primary = clients.get_provider_auth("ci", "anthropic").unwrap();
+    assert_eq!(primary.key.as_deref(), Some("ci-key"));
+
+    // Fallback providers use their own auth, never the client's override
+    let fallback = clients.get_provider_auth("ci", "openrouter").unwrap();
+    assert_eq!(fallback.key.as_deref(), Some("or-key"));
+    assert!(clients.get_provider_auth("ci", "zai").is_none());
+}
+
+#[test]
+fn test_failover_from_file() {
+    let defaults = Failover::from_file(None);
+    assert_eq!(defaults.failure_threshold, 3);
+    assert_eq!(defaults.cooldown_secs, 30);
+    assert!(defaults.status_codes.contains(&529));
+
+    let custom = Failover::from_file(Some(FileFailover {
+        failure_threshold: Some(0),
+        cooldown_secs: Some(5),
+        status_codes: Some(vec![503]),
+    }));
+    // Threshold is clamped so a single failure can still trigger cooldown
+    assert_eq!(custom.failure_threshold, 1);
+    assert_eq!(custom.cooldown_secs, 5);
+    assert_eq!(custom.status_codes, vec![503]);
+}
+
+#[test]
+fn test_failover_serialization_roundtrip() {
+    let config = Config {
+        clients: failover_clients(),
+        failover: Failover {
+            cooldown_secs: 90,
+            ..Failover::default()
+        },
+        ..Config::default()
+    };
+
+    let toml_str = config.to_toml();
+    assert!(
+        toml_str.contains(r#"fallback = ["openrouter", "zai", "openrouter"]"#),
+        "Provider fallback should be serialized.\nTOML:\n{}",
+        toml_str
+    );
+
+    let file_config: FileConfig = toml::from_str(&toml_str).expect("Config should round-trip");
+    let failover = Failover::from_file(file_config.failover);
+    assert_eq!(failover.cooldown_secs, 90);
+    assert_eq!(
+        file_config.clients["ci"].fallback,
+        vec!["zai", "missing", "anthropic"]
+    );
+}
+
+#[test]
+fn test_client_shadow_parse_and_roundtrip() {
+    let file: FileConfig = toml::from_str(
+        r#"
+        [clients.dev-1]
+        name = "Dev"
+        provider = "anthropic"
+        shadow = "zai"
+
+        [clients.ci]
+        name = "CI"
+        provider = "anthropic"
+        shadow = "missing"
+
+        [providers.anthropic]
+        base_url = "https://api.anthropic.com"
+
+        [providers.zai]
+        base_url = "https://api.z.ai/api/coding/paas/v4"
+        api_format = "openai"
+        "#,
+    )
+    .expect("shadow config should parse");
+    let config = Config {
+        clients: ClientsConfig {
+            clients: file.clients,
+            providers: file.providers,
+        },
+        ..Config::default()
+    };
+
+    let (id, provider) = config.clients.get_client_shadow("dev-1").unwrap();
+    assert_eq!(id, "zai");
+    assert_eq!(provider.api_format, ApiFormat::Openai);
+    assert!(
+        config.clients.get_client_shadow("ci").is_none(),
+        "unknown shadow providers are ignored"
+    );
+
+    let file_config: FileConfig =
+        toml::from_str(&config.to_toml()).expect("Config should round-trip");
+    assert_eq!(file_config.clients["dev-1"].shadow.as_deref(), Some("zai"));
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Retry policy tests
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[test]
+fn test_retry_defaults_and_roundtrip() {
+    let defaults = Retry::from_file(None);
+    assert!(!defaults.enabled, "Retry should be opt-in");
+    assert_eq!(defaults.max_attempts, 3);
+    assert_eq!(defaults.status_codes, vec![429, 529]);
+
+    let config = Config {
+        retry: Retry {
+            enabled: true,
+            max_attempts: 5,
+            ..Retry::default()
+        },
+        ..Config::default()
+    };
+    let toml_str = config.to_toml();
+    let file_config: FileConfig = toml::from_str(&toml_str).expect("Config should round-trip");
+    let retry = Retry::from_file(file_config.retry);
+    assert!(retry.enabled);
+    assert_eq!(retry.max_attempts, 5);
+    assert_eq!(retry.max_backoff_ms, 30_000);
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Rate limit tests
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[test]
+fn test_rate_limit_parse_and_roundtrip() {
+    assert!(
+        !RateLimit::from_file(None).enabled,
+        "Rate limits should be opt-in"
+    );
+
+    let file: FileConfig = toml::from_str(
+        r#"
+        [rate_limit]
+        enabled = true
+        requests_per_minute = 30
+        input_tokens_per_minute = 200000
+        [rate_limit.clients."ci-agent"]
+        concurrent_streams = 1
+        "#,
+    )
+    .expect("Rate limit config should parse");
+    let config = Config {
+        rate_limit: RateLimit::from_file(file.rate_limit),
+        ..Config::default()
+    };
+    let ci = config.rate_limit.limits_for("ci-agent");
+    assert_eq!(ci.concurrent_streams, Some(1));
+    assert_eq!(ci.requests_per_minute, Some(30), "falls back to defaults");
+    assert_eq!(
+        config.rate_limit.limits_for("dev-1").concurrent_streams,
+        None
+    );
+
+    let toml_str = config.to_toml();
+    let reparsed: FileConfig = toml::from_str(&toml_str).expect("Config should round-trip");
+    let rate_limit = RateLimit::from_file(reparsed.rate_limit);
+    assert!(rate_limit.enabled);
+    assert_eq!(rate_limit.limits.input_tokens_per_minute, Some(200_000));
+    assert_eq!(
+        rate_limit.clients["ci-agent"],
+        RateLimits {
+            concurrent_streams: Some(1),
+            ..RateLimits::default()
+        }
+    );
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Breakpoint tests
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[test]
+fn test_breakpoints_parse_and_roundtrip() {
+    let defaults = Breakpoints::from_file(None);
+    assert!(!defaults.is_configured());
+    assert_eq!(defaults.timeout_secs, 300);
+
+    let file: FileConfig = toml::from_str(
+        r#"
+        [breakpoints]
+        armed = false
+        [[breakpoints.rules]]
+        client = "dev-1"
+        prompt = "(?i)\\bdeploy\\b"
+        [[breakpoints.rules]]
+        tool = "Bash"
+        "#,
+    )
+    .expect("Breakpoint config should parse");
+    let config = Config {
+        breakpoints: Breakpoints::from_file(file.breakpoints),
+        ..Config::default()
+    };
+    assert!(!config.breakpoints.armed);
+    assert_eq!(config.breakpoints.rules.len(), 2);
+    assert_eq!(
+        config.breakpoints.rules[0].prompt.as_deref(),
+        Some("(?i)\\bdeploy\\b")
+    );
+
+    let toml_str = config.to_toml();
+    let reparsed: FileConfig = toml::from_str(&toml_str).expect("Config should round-trip");
+    let breakpoints = Breakpoints::from_file(reparsed.breakpoints);
+    assert!(!breakpoints.armed);
+    assert_eq!(breakpoints.rules, config.breakpoints.rules);
+}
+
+#[test]
+fn test_tool_guard_parse_and_roundtrip() {
+    assert!(!ToolGuard::from_file(None).is_configured());
+
+    let file: FileConfig = toml::from_str(
+        r#"
+        [tool_guard]
+        [[tool_guard.rules]]
+        name = "force-push"
+        tool = "Bash"
+        field = "command"
+        pattern = "git push .*--force"
+        [[tool_guard.rules]]
+        tool = "Edit|Write"
+        field = "file_path"
+        allow = "^/work/"
+        action = "warn"
+        reason = "edit outside the project"
+        client = "dev-1"
+        "#,
+    )
+    .expect("Tool guard config should parse");
+    let config = Config {
+        tool_guard: ToolGuard::from_file(file.tool_guard),
+        ..Config::default()
+    };
+    assert!(config.tool_guard.is_configured());
+    assert_eq!(config.tool_guard.rules[0].action, GuardAction::Deny);
+    assert_eq!(config.tool_guard.rules[1].action, GuardAction::Warn);
+    assert_eq!(config.tool_guard.rules[1].allow.as_deref(), Some("^/work/"));
+
+    let toml_str = config.to_toml();
+    let reparsed: FileConfig = toml::from_str(&toml_str).expect("Config should round-trip");
+    assert_eq!(
+        ToolGuard::from_file(reparsed.tool_guard).rules,
+        config.tool_guard.rules
+    );
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Key pool tests
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[test]
+fn test_key_pool_parse_and_roundtrip() {
+    let file: FileConfig = toml::from_str(
+        r#"
+        [providers.anthropic]
+        base_url = "https://api.anthropic.com"
+        [providers.anthropic.auth]
+        method = "x_api_key"
+        pool_strategy = "most_remaining"
+        [[providers.anthropic.auth.pool]]
+        name = "team-a"
+        key_env = "ANTHROPIC_KEY_A"
+        [[providers.anthropic.auth.pool]]
+        key = "sk-direct"
+        "#,
+    )
+    .expect("Key pool config should parse");
+
+    let config = Config {
+        clients: ClientsConfig {
+            clients: file.clients,
+            providers: file.providers,
+        },
+        ..Config::default()
+    };
+    let auth = config.clients.providers["anthropic"].auth.as_ref().unwrap();
+    assert_eq!(auth.pool_strategy, PoolStrategy::MostRemaining);
+    assert_eq!(auth.pool[0].label(0), "team-a");
+    assert_eq!(auth.pool[1].label(1), "key-2");
+    assert_eq!(auth.pool[1].resolve().as_deref(), Some("sk-direct"));
+
+    let toml_str = config.to_toml();
+    let reparsed: FileConfig = toml::from_str(&toml_str).expect("Config should round-trip");
+    let auth = reparsed.providers["anthropic"].auth.clone().unwrap();
+    assert_eq!(auth.pool.len(), 2);
+    assert_eq!(auth.pool_strategy, PoolStrategy::MostRemaining);
+    assert_eq!(auth.pool[0].key_env.as_deref(), Some("ANTHROPIC_KEY_A"));
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Cassette tests
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[test]
+fn test_cassette_defaults_and_roundtrip() {
+    let defaults = Cassette::from_file(None);
+    assert_eq!(
+        defaults.mode,
+        CassetteMode::Off,
+        "Cassettes should be opt-in"
+    );
+    assert!(!defaults.realtime);
+    assert_eq!(CassetteMode::from_str("REPLAY"), CassetteMode::Replay);
+    assert_eq!(CassetteMode::from_str("bogus"), CassetteMode::Off);
+
+    let config = Config {
+        cassette: Cassette {
+            mode: CassetteMode::Record,
+            path: PathBuf::from("./fixtures/session.jsonl"),
+            realtime: true,
+        },
+        ..Config::default()
+    };
+    let toml_str = config.to_toml();
+    let file_config: FileConfig = toml::from_str(&toml_str).expect("Config should round-trip");
+    let cassette = Cassette::from_file(file_config.cassette);
+    assert_eq!(cassette.mode, CassetteMode::Record);
+    assert_eq!(cassette.path, PathBuf::from("./fixtures/session.jsonl"));
+    assert!(cassette.realtime);
+}
@@ -16,26 +16,71 @@ pub struct Transformers {
     /// Set to true to enable transformation pipeline.
     pub enabled: bool,
 
+    /// Budget configuration (per client/user spending limits)
+    pub budget: Option<crate::proxy::transformation::BudgetConfig>,
+
+    /// Model router configuration (picks provider/model per request)
+    pub model_router: Option<crate::proxy::transformation::ModelRouterConfig>,
+
+    /// Projects configuration (per-repository instructions, rules and budgets)
+    pub projects: Option<crate::proxy::transformation::ProjectsConfig>,
+
     /// Tag editor configuration (operates on configurable XML-style tags)
     pub tag_editor: Option<crate::proxy::transformation::TagEditorConfig>,
 
     /// System editor configuration (modifies system prompts)
     pub system_editor: Option<crate::proxy::transformation::SystemEditorConfig>,
 
+    /// Tool filter configuration (removes, renames or re-describes tools per client)
+    pub tool_filter: Option<crate::proxy::transformation::ToolFilterConfig>,
+
     /// Compact enhancer configuration (enhances compaction prompts with session context)
     pub compact_enhancer: Option<crate::proxy::transformation::CompactEnhancerConfig>,
+
+    /// Context enricher configuration (injects relevant cortex history)
+    pub context_enricher: Option<crate::proxy::transformation::ContextEnricherConfig>,
+
+    /// Tool governor configuration (truncates oversized old tool results)
+    pub tool_governor: Option<crate::proxy::transformation::ToolGovernorConfig>,
+
+    /// Scripts configuration (user-defined Rhai transformers)
+    pub scripts: Option<crate::proxy::transformation::ScriptsConfig>,
+
+    /// External transformer configuration (HTTP endpoint or child process)
+    pub external: Option<crate::proxy::transformation::ExternalConfig>,
+
+    /// Redactor configuration (replaces secrets in tool results)
+    pub redactor: Option<crate::proxy::transformation::RedactorConfig>,
+
+    /// Cache optimizer configuration (places prompt-cache breakpoints)
+    pub cache_optimizer: Option<crate::proxy::transformation::CacheOptimizerConfig>,
 }
 
 /// Transformers config as loaded from file
 #[derive(Debug, Deserialize, Default)]
 pub struct FileTransformers {
     pub enabled: Option<bool>,
+    pub budget: Option<crate::proxy::transformation::BudgetConfig>,
+    #[serde(rename = "model-router")]
+    pub model_router: Option<crate::proxy::transformation::ModelRouterConfig>,
+    pub projects: Option<crate::proxy::transformation::ProjectsConfig>,
     #[serde(rename = "tag-editor")]
     pub tag_editor: Option<crate::proxy::transformation::TagEditorConfig>,
     #[serde(rename = "system-editor")]
     pub system_editor: Option<crate::proxy::transformation::SystemEditorConfig>,
+    #[serde(rename = "tool-filter")]
+    pub tool_filter: Option<crate::proxy::transformation::ToolFilterConfig>,
     #[serde(rename = "compact-enhancer")]
     pub compact_enhancer: Option<crate::proxy::transformation::CompactEnhancerConfig>,
+    #[serde(rename = "context-enricher")]
+    pub context_enricher: Option<crate::proxy::transformation::ContextEnricherConfig>,
+    #[serde(rename = "tool-governor")]
+    pub tool_governor: Option<crate::proxy::transformation::ToolGovernorConfig>,
+    pub scripts: Option<crate::proxy::transformation::ScriptsConfig>,
+    pub external: Option<crate::proxy::transformation::ExternalConfig>,
+    pub redactor: Option<crate::proxy::transformation::RedactorConfig>,
+    #[serde(rename = "cache-optimizer")]
+    pub cache_optimizer: Option<crate::proxy::transformation::CacheOptimizerConfig>,
 }
 
 impl Transformers {
@@ -45,9 +90,19 @@ impl Transformers {
 
         Self {
             enabled: file.enabled.unwrap_or(false),
+            budget: file.budget,
+            model_router: file.model_router,
+            projects: file.projects,
             tag_editor: file.tag_editor,
             system_editor: file.system_editor,
+            tool_filter: file.tool_filter,
             compact_enhancer: file.compact_enhancer,
+            context_enricher: file.context_enricher,
+            tool_governor: file.tool_governor,
+            scripts: file.scripts,
+            external: file.external,
+            redactor: file.redactor,
+            cache_optimizer: file.cache_optimizer,
         }
     }
 }
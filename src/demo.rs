@@ -172,6 +172,7 @@ fn generate_demo_sequence() -> Vec<(ProxyEvent, u64)> {
             output_tokens: 285,
             cache_creation_tokens: 8000,
             cache_read_tokens: 22000,
+            cache_optimized: false,
         },
         400,
     ));
@@ -211,6 +212,7 @@ fn generate_demo_sequence() -> Vec<(ProxyEvent, u64)> {
             output_tokens: 174,
             cache_creation_tokens: 5000,
             cache_read_tokens: 30000,
+            cache_optimized: false,
         },
         600,
     ));
@@ -265,6 +267,7 @@ fn generate_demo_sequence() -> Vec<(ProxyEvent, u64)> {
             output_tokens: 156,
             cache_creation_tokens: 0,
             cache_read_tokens: 0,
+            cache_optimized: false,
         },
         300,
     ));
@@ -312,6 +315,7 @@ fn generate_demo_sequence() -> Vec<(ProxyEvent, u64)> {
             output_tokens: 891,
             cache_creation_tokens: 8000,
             cache_read_tokens: 44000,
+            cache_optimized: false,
         },
         800,
     ));
@@ -366,6 +370,7 @@ fn generate_demo_sequence() -> Vec<(ProxyEvent, u64)> {
             output_tokens: 89,
             cache_creation_tokens: 0,
             cache_read_tokens: 0,
+            cache_optimized: false,
         },
         200,
     ));
@@ -405,6 +410,7 @@ fn generate_demo_sequence() -> Vec<(ProxyEvent, u64)> {
             output_tokens: 567,
             cache_creation_tokens: 0,
             cache_read_tokens: 60000,
+            cache_optimized: false,
         },
         400,
     ));
@@ -456,6 +462,7 @@ fn generate_demo_sequence() -> Vec<(ProxyEvent, u64)> {
             output_tokens: 234,
             cache_creation_tokens: 0,
             cache_read_tokens: 75000,
+            cache_optimized: false,
         },
         400,
     ));
@@ -498,6 +505,7 @@ fn generate_demo_sequence() -> Vec<(ProxyEvent, u64)> {
             output_tokens: 156,
             cache_creation_tokens: 0,
             cache_read_tokens: 90000,
+            cache_optimized: false,
         },
         600,
     ));
@@ -600,6 +608,7 @@ fn generate_demo_sequence() -> Vec<(ProxyEvent, u64)> {
             output_tokens: 234,
             cache_creation_tokens: 0,
             cache_read_tokens: 0,
+            cache_optimized: false,
         },
         500,
     ));
@@ -625,6 +634,7 @@ fn generate_demo_sequence() -> Vec<(ProxyEvent, u64)> {
             output_tokens: 1823,
             cache_creation_tokens: 0,
             cache_read_tokens: 102000,
+            cache_optimized: false,
         },
         1000,
     ));
@@ -664,6 +674,7 @@ fn generate_demo_sequence() -> Vec<(ProxyEvent, u64)> {
             output_tokens: 456,
             cache_creation_tokens: 0,
             cache_read_tokens: 112000,
+            cache_optimized: false,
         },
         2000,
     ));
@@ -92,6 +92,9 @@ pub enum ProxyEvent {
         output_tokens: u32,
         cache_creation_tokens: u32,
         cache_read_tokens: u32,
+        /// The cache optimizer placed breakpoints in this request
+        #[serde(default)]
+        cache_optimized: bool,
     },
 
     /// Claude's thinking/reasoning block (extended thinking feature)
@@ -141,6 +144,22 @@ pub enum ProxyEvent {
         modifications: Vec<String>,
     },
 
+    /// An oversized tool result was truncated for the first time
+    ///
+    /// Emitted once per result by the tool governor; carries the original
+    /// content so cortex can archive it.
+    ToolResultTruncated {
+        timestamp: DateTime<Utc>,
+        tool_use_id: String,
+        tool_name: String,
+        /// Estimated tokens of the original content
+        tokens_before: u32,
+        /// Estimated tokens after truncation
+        tokens_after: u32,
+        /// The tool_result content as the client sent it
+        original: serde_json::Value,
+    },
+
     /// Response was augmented (tokens injected)
     ResponseAugmented {
         timestamp: DateTime<Utc>,
@@ -150,6 +169,91 @@ pub enum ProxyEvent {
         tokens_injected: u32,
     },
 
+    /// Model router matched a rule for a request
+    ///
+    /// Emitted once upstream responds, with the provider that served the
+    /// request and the model the rule selected.
+    ModelRouted {
+        timestamp: DateTime<Utc>,
+        request_id: String,
+        /// Name of the rule that matched
+        rule: String,
+        /// Provider that served the request (after cooldown and failover)
+        provider: String,
+        /// Model requested by the client
+        model: Option<String>,
+        /// Model sent upstream (differs from `model` when the rule rewrote it)
+        routed_model: Option<String>,
+    },
+
+    /// Request was re-routed to a fallback provider
+    ///
+    /// Emitted when a provider fails with a retryable error (connection
+    /// failure, 5xx, 529 overloaded) or is skipped while cooling down.
+    ProviderFailover {
+        timestamp: DateTime<Utc>,
+        request_id: String,
+        /// Provider that failed or was skipped
+        from_provider: String,
+        /// Provider the request was re-routed to
+        to_provider: String,
+        /// Why the switch happened (e.g., "HTTP 529", "Connection error: ...")
+        reason: String,
+        /// Upstream status code that triggered the switch (None for errors/cooldown)
+        status: Option<u16>,
+    },
+
+    /// Upstream returned a retryable status (429/529) and the request will be re-sent
+    ///
+    /// Emitted before waiting, so `delay_ms` is the wait about to happen.
+    UpstreamRetry {
+        timestamp: DateTime<Utc>,
+        request_id: String,
+        /// Attempt that failed (1-based)
+        attempt: u32,
+        /// Maximum attempts allowed by the retry policy
+        max_attempts: u32,
+        /// Upstream status that triggered the retry
+        status: u16,
+        /// Wait before the next attempt in milliseconds
+        delay_ms: u64,
+        /// Where the delay came from: "retry-after", "ratelimit-reset", or "backoff"
+        delay_source: String,
+    },
+
+    /// The tool guard flagged a tool call in a response
+    ///
+    /// `action` is "deny" (the call was replaced with a refusal) or "warn"
+    /// (the call went through with a warning).
+    ToolPolicyDecision {
+        timestamp: DateTime<Utc>,
+        request_id: String,
+        tool_use_id: String,
+        tool_name: String,
+        /// "deny" or "warn"
+        action: String,
+        /// Name of the rule that triggered
+        rule: String,
+        reason: String,
+        /// The call's input as the model produced it
+        input: serde_json::Value,
+    },
+
+    /// A request mirrored to a shadow provider finished on both sides
+    ///
+    /// The shadow response never reaches the client; this event records both
+    /// outcomes side by side for later diffing.
+    ShadowComparison {
+        timestamp: DateTime<Utc>,
+        request_id: String,
+        /// Provider the request was mirrored to
+        shadow_provider: String,
+        /// What the client actually received
+        primary: ResponseSample,
+        /// What the shadow provider returned
+        shadow: ResponseSample,
+    },
+
     /// PreCompact hook was triggered (before context compaction)
     ///
     /// Fired by Claude Code's PreCompact hook before /compact runs.
@@ -199,6 +303,61 @@ pub enum ProxyEvent {
         /// Estimated context tokens from last known API usage
         estimated_tokens: u64,
     },
+
+    /// A subagent (Task tool) conversation finished
+    ///
+    /// Synthesized when the Task tool result reaches the parent. Usage is
+    /// rolled up: it includes the subagent's own nested subagents.
+    SubagentCompleted {
+        timestamp: DateTime<Utc>,
+        /// Task tool_use id that spawned the subagent
+        agent_id: String,
+        /// Spawning subagent (None when spawned by the main conversation)
+        parent_id: Option<String>,
+        /// Short task description from the Task call
+        description: String,
+        /// Subagent type ("general-purpose", "Explore", ...)
+        subagent_type: Option<String>,
+        started: DateTime<Utc>,
+        duration: Duration,
+        /// API calls made by the subagent
+        requests: u32,
+        input_tokens: u64,
+        output_tokens: u64,
+        cache_read_tokens: u64,
+        cache_creation_tokens: u64,
+        cost_usd: f64,
+        /// False if the Task tool result was an error
+        success: bool,
+    },
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Response Sample (shadow traffic comparison)
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Comparable summary of one upstream response
+///
+/// Stop reasons are normalized to Anthropic names so responses from
+/// OpenAI-format providers can be compared directly.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ResponseSample {
+    /// HTTP status (None if the request failed before a response)
+    pub status: Option<u16>,
+    /// Model reported by the provider
+    pub model: Option<String>,
+    /// Time to response headers in milliseconds
+    pub ttfb_ms: u64,
+    /// Time to the complete response in milliseconds
+    pub latency_ms: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Why the response ended ("end_turn", "tool_use", "max_tokens", ...)
+    pub stop_reason: Option<String>,
+    /// Names of the tools the model chose to call, in order
+    pub tool_uses: Vec<String>,
+    /// Transport or parse failure (None on success)
+    pub error: Option<String>,
 }
 
 // ─────────────────────────────────────────────────────────────────────────────
@@ -223,6 +382,11 @@ pub struct TrackedEvent {
     /// Used for display purposes (copy to /resume), not for filtering
     pub session_id: Option<String>,
 
+    /// Subagent conversation the event belongs to (Task tool_use id)
+    /// None for the main conversation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent_id: Option<String>,
+
     /// When the event was tracked (may differ slightly from inner event timestamp)
     pub tracked_at: DateTime<Utc>,
 
@@ -237,11 +401,18 @@ impl TrackedEvent {
         Self {
             user_id,
             session_id,
+            agent_id: None,
             tracked_at: Utc::now(),
             event,
         }
     }
 
+    /// Attribute the event to a subagent conversation
+    pub fn with_agent(mut self, agent_id: Option<String>) -> Self {
+        self.agent_id = agent_id;
+        self
+    }
+
     /// Create a tracked event with no user context (anonymous/unknown)
     ///
     /// Used for events where user routing context isn't available:
@@ -276,20 +447,35 @@ impl TrackedEvent {
             | ProxyEvent::UserPrompt { timestamp, .. }
             | ProxyEvent::AssistantResponse { timestamp, .. }
             | ProxyEvent::RequestTransformed { timestamp, .. }
+            | ProxyEvent::ToolResultTruncated { timestamp, .. }
             | ProxyEvent::ResponseAugmented { timestamp, .. }
+            | ProxyEvent::ModelRouted { timestamp, .. }
+            | ProxyEvent::ProviderFailover { timestamp, .. }
+            | ProxyEvent::UpstreamRetry { timestamp, .. }
+            | ProxyEvent::ToolPolicyDecision { timestamp, .. }
+            | ProxyEvent::ShadowComparison { timestamp, .. }
             | ProxyEvent::PreCompactHook { timestamp, .. }
             | ProxyEvent::ContextRecovery { timestamp, .. }
             | ProxyEvent::TodoSnapshot { timestamp, .. }
-            | ProxyEvent::ContextEstimate { timestamp, .. } => *timestamp,
+            | ProxyEvent::ContextEstimate { timestamp, .. }
+            | ProxyEvent::SubagentCompleted { timestamp, .. } => *timestamp,
         }
     }
 }
 
 /// Summary statistics for the status bar
-#[derive(Debug, Clone)]
+///
+/// Serializable so sessions survive proxy restarts; the sparkline histories
+/// are runtime-only and start empty after a restore.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Stats {
     pub total_requests: usize,
     pub failed_requests: usize,
+    /// Upstream retries after 429/529 responses
+    pub retry_count: usize,
+    /// Total time spent waiting between retries (milliseconds)
+    pub retry_delay_ms: u64,
     pub total_tool_calls: usize,
     pub failed_tool_calls: usize,
     /// Accumulated TTFB (time to first byte) for averaging
@@ -330,15 +516,19 @@ pub struct Stats {
 
     // === Historical data for trend visualization (Sparklines) ===
     /// Token usage snapshots (last 30 data points)
+    #[serde(skip)]
     pub token_history: VecDeque<TokenSnapshot>,
 
     /// Tool call frequency over time (last 30 data points)
+    #[serde(skip)]
     pub tool_call_history: VecDeque<u32>,
 
     /// Cache hit rate history (last 30 data points)
+    #[serde(skip)]
     pub cache_rate_history: VecDeque<f64>,
 
     /// Thinking token progression (last 30 data points)
+    #[serde(skip)]
     pub thinking_token_history: VecDeque<u64>,
 
     // === Aspy token modification tracking ===
@@ -349,7 +539,7 @@ pub struct Stats {
 }
 
 /// Per-model token tracking for Statistics view
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ModelTokens {
     pub input: u64,
     pub output: u64,
@@ -380,6 +570,12 @@ impl Stats {
         }
     }
 
+    /// Record an upstream retry and the wait it cost
+    pub fn record_retry(&mut self, delay_ms: u64) {
+        self.retry_count += 1;
+        self.retry_delay_ms += delay_ms;
+    }
+
     /// Average time to first byte across all API responses
     pub fn avg_ttfb(&self) -> Duration {
         if self.response_count == 0 {
@@ -532,12 +728,18 @@ impl Stats {
                 output_tokens,
                 cache_creation_tokens,
                 cache_read_tokens,
+                cache_optimized,
                 ..
             } => {
                 self.total_input_tokens += *input_tokens as u64;
                 self.total_output_tokens += *output_tokens as u64;
                 self.total_cache_creation_tokens += *cache_creation_tokens as u64;
                 self.total_cache_read_tokens += *cache_read_tokens as u64;
+                self.transform_stats.record_cache_usage(
+                    *cache_optimized,
+                    *input_tokens,
+                    *cache_read_tokens,
+                );
 
                 // Track per-model stats
                 let model_stats = self.model_tokens.entry(model.clone()).or_default();
@@ -582,6 +784,9 @@ impl Stats {
             ProxyEvent::ContextCompact { .. } => {
                 self.compact_count += 1;
             }
+            ProxyEvent::UpstreamRetry { delay_ms, .. } => {
+                self.record_retry(*delay_ms);
+            }
             ProxyEvent::RequestTransformed {
                 transformer,
                 tokens_before,
@@ -608,6 +813,8 @@ impl Stats {
     pub fn merge(&mut self, other: &Stats) {
         self.total_requests += other.total_requests;
         self.failed_requests += other.failed_requests;
+        self.retry_count += other.retry_count;
+        self.retry_delay_ms += other.retry_delay_ms;
         self.total_tool_calls += other.total_tool_calls;
         self.failed_tool_calls += other.failed_tool_calls;
         self.total_ttfb += other.total_ttfb;
@@ -656,6 +863,13 @@ impl Stats {
             entry.1 += rem;
         }
 
+        self.transform_stats
+            .cache_optimized
+            .merge(&other.transform_stats.cache_optimized);
+        self.transform_stats
+            .cache_baseline
+            .merge(&other.transform_stats.cache_baseline);
+
         self.augment_stats.tokens_injected += other.augment_stats.tokens_injected;
         for (name, count) in &other.augment_stats.by_augmenter {
             *self
@@ -672,6 +886,8 @@ impl Default for Stats {
         Self {
             total_requests: 0,
             failed_requests: 0,
+            retry_count: 0,
+            retry_delay_ms: 0,
             total_tool_calls: 0,
             failed_tool_calls: 0,
             total_ttfb: Duration::default(),
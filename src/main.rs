@@ -15,6 +15,7 @@ mod config;
 mod demo;
 mod events;
 mod logging;
+mod mock_upstream;
 mod parser;
 mod pipeline;
 mod pricing;
@@ -395,6 +396,18 @@ async fn main() -> Result<()> {
         config.context_limit,
     )));
 
+    // Create request breakpoints (TUI only - someone has to answer them)
+    let breakpoints = if config.breakpoints.is_configured() && config.enable_tui {
+        Some(Arc::new(proxy::breakpoints::BreakpointHub::from_config(
+            &config.breakpoints,
+        )?))
+    } else {
+        if config.breakpoints.is_configured() {
+            tracing::warn!("Breakpoints need the TUI; ignoring them in headless mode");
+        }
+        None
+    };
+
     // Spawn the storage task (if enabled)
     // This runs in the background, writing events to disk
     let storage_handle = if config.features.json_logging {
@@ -434,7 +447,7 @@ async fn main() -> Result<()> {
         })
     } else {
         // Initialize event processing pipeline and query interface
-        let (pipeline, cortex_query, embedding_indexer) = if config.cortex.enabled {
+        let (pipeline, cortex_query, embedding_indexer, session_state) = if config.cortex.enabled {
             use pipeline::{
                 cortex::CortexProcessor,
                 cortex_query::CortexQuery,
@@ -459,6 +472,7 @@ async fn main() -> Result<()> {
 
             match CortexProcessor::new(cortex_config) {
                 Ok(processor) => {
+                    let session_state = processor.session_state_writer();
                     pipeline.register(processor);
 
                     // Initialize OpenTelemetry exporter if configured
@@ -569,24 +583,25 @@ async fn main() -> Result<()> {
                                 Some(std::sync::Arc::new(pipeline)),
                                 Some(std::sync::Arc::new(query)),
                                 indexer,
+                                Some(session_state),
                             )
                         }
                         Err(e) => {
                             registry.fail("cortex", e.to_string());
                             tracing::error!("⚠ Failed to initialize cortex query interface: {}", e);
-                            (Some(std::sync::Arc::new(pipeline)), None, None)
+                            (Some(std::sync::Arc::new(pipeline)), None, None, None)
                         }
                     }
                 }
                 Err(e) => {
                     registry.fail("cortex", e.to_string());
                     tracing::error!("⚠ Failed to initialize cortex processor: {}", e);
-                    (None, None, None)
+                    (None, None, None, None)
                 }
             }
         } else {
             tracing::warn!("⚠ cortex processor disabled in config");
-            (None, None, None)
+            (None, None, None, None)
         };
 
         // Bundle channels and shared state for the proxy
@@ -613,6 +628,8 @@ async fn main() -> Result<()> {
             pipeline,
             cortex_query,
             embedding_indexer: indexer_handle,
+            breakpoints: breakpoints.clone(),
+            session_state,
         };
         tokio::spawn(async move {
             proxy::start_proxy(proxy_config, channels, shutdown_rx, shared)
@@ -636,6 +653,7 @@ async fn main() -> Result<()> {
             streaming_thinking,
             shared_stats,
             shared_events,
+            breakpoints,
         )
         .await
         {
@@ -0,0 +1,798 @@
+// Mock upstream: a fake Anthropic/OpenAI API for exercising the proxy offline
+//
+// Serves scripted responses so the full proxy → parser → cortex path can be
+// driven end-to-end without API keys:
+// - POST /v1/messages               (SSE when "stream": true, JSON otherwise)
+// - POST /v1/messages/count_tokens  (estimated from the request body)
+// - POST /v1/chat/completions       (OpenAI format, SSE or JSON)
+//
+// Scenarios are played in order and cycle, so `--scenario overloaded,text`
+// answers the first request with a 529 and the retry with a normal reply.
+// A request can pick its own scenario with the `x-mock-scenario` header;
+// count_tokens only honors the header and never advances the script.
+//
+// Run with: aspy mock-upstream --scenario tool-use
+// Then point the proxy at it: ANTHROPIC_API_URL=http://127.0.0.1:9191
+
+use anyhow::{Context, Result};
+use axum::body::Body;
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use bytes::Bytes;
+use futures::StreamExt;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Delay between SSE frames for the `slow` scenario
+const SLOW_CHUNK_DELAY: Duration = Duration::from_millis(750);
+
+/// Header that overrides the scripted scenario for one request
+const SCENARIO_HEADER: &str = "x-mock-scenario";
+
+/// A scripted upstream behavior
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scenario {
+    /// Plain text reply
+    Text,
+    /// Text followed by a tool_use block (stop_reason = tool_use)
+    ToolUse,
+    /// Thinking block followed by text
+    Thinking,
+    /// 400 invalid_request_error
+    Error,
+    /// Stream starts normally, then emits an SSE error event
+    StreamError,
+    /// 529 overloaded_error
+    Overloaded,
+    /// 429 rate_limit_error with retry-after
+    RateLimit,
+    /// Text reply with long pauses between frames
+    Slow,
+}
+
+impl Scenario {
+    pub const ALL: [Scenario; 8] = [
+        Self::Text,
+        Self::ToolUse,
+        Self::Thinking,
+        Self::Error,
+        Self::StreamError,
+        Self::Overloaded,
+        Self::RateLimit,
+        Self::Slow,
+    ];
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().replace('_', "-").as_str() {
+            "text" => Some(Self::Text),
+            "tool-use" | "tool" => Some(Self::ToolUse),
+            "thinking" => Some(Self::Thinking),
+            "error" | "400" => Some(Self::Error),
+            "stream-error" => Some(Self::StreamError),
+            "overloaded" | "529" => Some(Self::Overloaded),
+            "rate-limit" | "429" => Some(Self::RateLimit),
+            "slow" => Some(Self::Slow),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Text => "text",
+            Self::ToolUse => "tool-use",
+            Self::Thinking => "thinking",
+            Self::Error => "error",
+            Self::StreamError => "stream-error",
+            Self::Overloaded => "overloaded",
+            Self::RateLimit => "rate-limit",
+            Self::Slow => "slow",
+        }
+    }
+
+    /// Error status and Anthropic error type, for scenarios that fail up front
+    fn failure(&self) -> Option<(StatusCode, &'static str, &'static str)> {
+        match self {
+            Self::Error => Some((
+                StatusCode::BAD_REQUEST,
+                "invalid_request_error",
+                "Mock invalid request",
+            )),
+            Self::Overloaded => Some((
+                StatusCode::from_u16(529).unwrap_or(StatusCode::SERVICE_UNAVAILABLE),
+                "overloaded_error",
+                "Overloaded",
+            )),
+            Self::RateLimit => Some((
+                StatusCode::TOO_MANY_REQUESTS,
+                "rate_limit_error",
+                "Mock rate limit exceeded",
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// Parse a comma-separated scenario script
+pub fn parse_script(script: &str) -> Result<Vec<Scenario>> {
+    let scenarios = script
+        .split(',')
+        .filter(|s| !s.trim().is_empty())
+        .map(|s| {
+            Scenario::from_str(s).with_context(|| {
+                let names: Vec<_> = Scenario::ALL.iter().map(|s| s.as_str()).collect();
+                format!(
+                    "Unknown scenario '{}' (expected: {})",
+                    s.trim(),
+                    names.join(", ")
+                )
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    anyhow::ensure!(!scenarios.is_empty(), "Scenario script is empty");
+    Ok(scenarios)
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Scripted replies
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// A content block in a mock reply
+#[derive(Debug, Clone)]
+enum Block {
+    Thinking(String),
+    Text(String),
+    ToolUse {
+        id: String,
+        name: String,
+        input: Value,
+    },
+}
+
+/// A complete mock reply, rendered into whichever wire format was requested
+#[derive(Debug, Clone)]
+struct Reply {
+    id: String,
+    model: String,
+    blocks: Vec<Block>,
+    input_tokens: u32,
+    output_tokens: u32,
+}
+
+impl Reply {
+    fn new(scenario: Scenario, seq: usize, model: &str, input_tokens: u32) -> Self {
+        let mut blocks = Vec::new();
+        match scenario {
+            Scenario::Thinking => {
+                blocks.push(Block::Thinking(
+                    "The user wants a quick answer. Nothing here needs a tool, \
+                     so I'll reply directly."
+                        .to_string(),
+                ));
+                blocks.push(Block::Text("Here's the answer after some thought.".into()));
+            }
+            Scenario::ToolUse => {
+                blocks.push(Block::Text("Let me look at that file.".into()));
+                blocks.push(Block::ToolUse {
+                    id: format!("toolu_mock_{:06}", seq),
+                    name: "Read".to_string(),
+                    input: json!({ "file_path": "/tmp/mock/README.md" }),
+                });
+            }
+            Scenario::Slow => blocks.push(Block::Text(
+                "This reply is streamed slowly, one word at a time.".into(),
+            )),
+            _ => blocks.push(Block::Text(
+                "Hello from aspy mock-upstream! This is a scripted reply.".into(),
+            )),
+        }
+
+        let output_tokens = blocks
+            .iter()
+            .map(|b| match b {
+                Block::Thinking(t) | Block::Text(t) => crate::tokens::estimate_tokens(t),
+                Block::ToolUse { input, .. } => crate::tokens::estimate_json_tokens(input),
+            })
+            .sum();
+
+        Self {
+            id: format!("msg_mock_{:06}", seq),
+            model: model.to_string(),
+            blocks,
+            input_tokens,
+            output_tokens,
+        }
+    }
+
+    fn stop_reason(&self) -> &'static str {
+        if self
+            .blocks
+            .iter()
+            .any(|b| matches!(b, Block::ToolUse { .. }))
+        {
+            "tool_use"
+        } else {
+            "end_turn"
+        }
+    }
+
+    /// Anthropic non-streaming message
+    fn anthropic_message(&self) -> Value {
+        let content: Vec<Value> = self
+            .blocks
+            .iter()
+            .map(|b| match b {
+                Block::Thinking(t) => {
+                    json!({ "type": "thinking", "thinking": t, "signature": "mock-signature" })
+                }
+                Block::Text(t) => json!({ "type": "text", "text": t }),
+                Block::ToolUse { id, name, input } => {
+                    json!({ "type": "tool_use", "id": id, "name": name, "input": input })
+                }
+            })
+            .collect();
+
+        json!({
+            "id": self.id,
+            "type": "message",
+            "role": "assistant",
+            "model": self.model,
+            "content": content,
+            "stop_reason": self.stop_reason(),
+            "stop_sequence": null,
+            "usage": {
+                "input_tokens": self.input_tokens,
+                "output_tokens": self.output_tokens,
+                "cache_creation_input_tokens": 0,
+                "cache_read_input_tokens": 0
+            }
+        })
+    }
+
+    /// Anthropic SSE frames (`event: …\ndata: …\n\n`)
+    ///
+    /// Text is split on words and tool input on JSON fragments so clients see
+    /// realistic multi-delta blocks.
+    fn anthropic_frames(&self, scenario: Scenario) -> Vec<String> {
+        let mut events = vec![(
+            "message_start",
+            json!({
+                "type": "message_start",
+                "message": {
+                    "id": self.id,
+                    "type": "message",
+                    "role": "assistant",
+                    "model": self.model,
+                    "content": [],
+                    "stop_reason": null,
+                    "stop_sequence": null,
+                    "usage": {
+                        "input_tokens": self.input_tokens,
+                        "output_tokens": 1,
+                        "cache_creation_input_tokens": 0,
+                        "cache_read_input_tokens": 0
+                    }
+                }
+            }),
+        )];
+        events.push(("ping", json!({ "type": "ping" })));
+
+        for (index, block) in self.blocks.iter().enumerate() {
+            let (start, deltas) = match block {
+                Block::Thinking(t) => (
+                    json!({ "type": "thinking", "thinking": "" }),
+                    split_words(t)
+                        .into_iter()
+                        .map(|w| json!({ "type": "thinking_delta", "thinking": w }))
+                        .chain(std::iter::once(
+                            json!({ "type": "signature_delta", "signature": "mock-signature" }),
+                        ))
+                        .collect::<Vec<_>>(),
+                ),
+                Block::Text(t) => (
+                    json!({ "type": "text", "text": "" }),
+                    split_words(t)
+                        .into_iter()
+                        .map(|w| json!({ "type": "text_delta", "text": w }))
+                        .collect(),
+                ),
+                Block::ToolUse { id, name, input } => (
+                    json!({ "type": "tool_use", "id": id, "name": name, "input": {} }),
+                    split_json(&input.to_string())
+                        .into_iter()
+                        .map(|p| json!({ "type": "input_json_delta", "partial_json": p }))
+                        .collect(),
+                ),
+            };
+            events.push((
+                "content_block_start",
+                json!({ "type": "content_block_start", "index": index, "content_block": start }),
+            ));
+            for delta in deltas {
+                events.push((
+                    "content_block_delta",
+                    json!({ "type": "content_block_delta", "index": index, "delta": delta }),
+                ));
+            }
+            events.push((
+                "content_block_stop",
+                json!({ "type": "content_block_stop", "index": index }),
+            ));
+
+            if scenario == Scenario::StreamError {
+                events.push((
+                    "error",
+                    json!({
+                        "type": "error",
+                        "error": { "type": "overloaded_error", "message": "Overloaded" }
+                    }),
+                ));
+                return render_sse(events);
+            }
+        }
+
+        events.push((
+            "message_delta",
+            json!({
+                "type": "message_delta",
+                "delta": { "stop_reason": self.stop_reason(), "stop_sequence": null },
+                "usage": { "output_tokens": self.output_tokens }
+            }),
+        ));
+        events.push(("message_stop", json!({ "type": "message_stop" })));
+        render_sse(events)
+    }
+
+    /// OpenAI non-streaming chat completion
+    fn openai_completion(&self) -> Value {
+        let text: String = self
+            .blocks
+            .iter()
+            .filter_map(|b| match b {
+                Block::Text(t) => Some(t.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let tool_calls: Vec<Value> = self
+            .blocks
+            .iter()
+            .filter_map(|b| match b {
+                Block::ToolUse { id, name, input } => Some(json!({
+                    "id": id,
+                    "type": "function",
+                    "function": { "name": name, "arguments": input.to_string() }
+                })),
+                _ => None,
+            })
+            .collect();
+
+        let mut message = json!({ "role": "assistant", "content": text });
+        if !tool_calls.is_empty() {
+            message["tool_calls"] = Value::Array(tool_calls);
+        }
+
+        json!({
+            "id": format!("chatcmpl-{}", self.id),
+            "object": "chat.completion",
+            "created": chrono::Utc::now().timestamp(),
+            "model": self.model,
+            "choices": [{
+                "index": 0,
+                "message": message,
+                "finish_reason": self.openai_finish_reason()
+            }],
+            "usage": self.openai_usage()
+        })
+    }
+
+    /// OpenAI SSE frames (`data: …\n\n`, terminated by `[DONE]`)
+    fn openai_frames(&self) -> Vec<String> {
+        let id = format!("chatcmpl-{}", self.id);
+        let created = chrono::Utc::now().timestamp();
+        let chunk = |delta: Value, finish: Option<&str>| {
+            json!({
+                "id": id,
+                "object": "chat.completion.chunk",
+                "created": created,
+                "model": self.model,
+                "choices": [{ "index": 0, "delta": delta, "finish_reason": finish }]
+            })
+        };
+
+        let mut chunks = vec![chunk(json!({ "role": "assistant", "content": "" }), None)];
+        let mut tool_index = 0;
+        for block in &self.blocks {
+            match block {
+                Block::Thinking(_) => {}
+                Block::Text(t) => {
+                    for word in split_words(t) {
+                        chunks.push(chunk(json!({ "content": word }), None));
+                    }
+                }
+                Block::ToolUse { id, name, input } => {
+                    chunks.push(chunk(
+                        json!({ "tool_calls": [{
+                            "index": tool_index,
+                            "id": id,
+                            "type": "function",
+                            "function": { "name": name, "arguments": "" }
+                        }] }),
+                        None,
+                    ));
+                    for part in split_json(&input.to_string()) {
+                        chunks.push(chunk(
+                            json!({ "tool_calls": [{
+                                "index": tool_index,
+                                "function": { "arguments": part }
+                            }] }),
+                            None,
+                        ));
+                    }
+                    tool_index += 1;
+                }
+            }
+        }
+        chunks.push(chunk(json!({}), Some(self.openai_finish_reason())));
+
+        let mut usage = chunk(json!({}), None);
+        usage["choices"] = json!([]);
+        usage["usage"] = self.openai_usage();
+        chunks.push(usage);
+
+        chunks
+            .into_iter()
+            .map(|c| format!("data: {}\n\n", c))
+            .chain(std::iter::once("data: [DONE]\n\n".to_string()))
+            .collect()
+    }
+
+    fn openai_finish_reason(&self) -> &'static str {
+        match self.stop_reason() {
+            "tool_use" => "tool_calls",
+            _ => "stop",
+        }
+    }
+
+    fn openai_usage(&self) -> Value {
+        json!({
+            "prompt_tokens": self.input_tokens,
+            "completion_tokens": self.output_tokens,
+            "total_tokens": self.input_tokens + self.output_tokens
+        })
+    }
+}
+
+fn render_sse(events: Vec<(&str, Value)>) -> Vec<String> {
+    events
+        .into_iter()
+        .map(|(name, data)| format!("event: {}\ndata: {}\n\n", name, data))
+        .collect()
+}
+
+/// Split text into words, keeping the separating whitespace on each piece
+fn split_words(text: &str) -> Vec<String> {
+    text.split_inclusive(' ').map(str::to_string).collect()
+}
+
+/// Split serialized JSON into small fragments (like upstream partial_json)
+fn split_json(json: &str) -> Vec<String> {
+    let chars: Vec<char> = json.chars().collect();
+    chars.chunks(12).map(|c| c.iter().collect()).collect()
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Server
+// ─────────────────────────────────────────────────────────────────────────────
+
+struct MockState {
+    script: Vec<Scenario>,
+    cursor: AtomicUsize,
+    chunk_delay: Duration,
+}
+
+impl MockState {
+    /// Pick the scenario for a request: header override, else next in script
+    fn next(&self, headers: &HeaderMap) -> (Scenario, usize) {
+        let seq = self.cursor.fetch_add(1, Ordering::Relaxed);
+        let scenario = header_scenario(headers).unwrap_or(self.script[seq % self.script.len()]);
+        (scenario, seq + 1)
+    }
+
+    fn delay_for(&self, scenario: Scenario) -> Duration {
+        if scenario == Scenario::Slow {
+            SLOW_CHUNK_DELAY
+        } else {
+            self.chunk_delay
+        }
+    }
+}
+
+/// Scenario requested via the override header, if any
+fn header_scenario(headers: &HeaderMap) -> Option<Scenario> {
+    headers
+        .get(SCENARIO_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(Scenario::from_str)
+}
+
+/// Run the mock upstream until Ctrl+C
+pub async fn run(bind: &str, script: Vec<Scenario>, chunk_delay: Duration) -> Result<()> {
+    let names: Vec<_> = script.iter().map(|s| s.as_str()).collect();
+    let state = Arc::new(MockState {
+        script,
+        cursor: AtomicUsize::new(0),
+        chunk_delay,
+    });
+
+    let app = Router::new()
+        .route("/v1/messages", post(messages))
+        .route("/v1/messages/count_tokens", post(count_tokens))
+        .route("/v1/chat/completions", post(chat_completions))
+        .with_state(state);
+
+    let listener = tokio::net::TcpListener::bind(bind)
+        .await
+        .with_context(|| format!("Failed to bind to {}", bind))?;
+
+    println!("Mock upstream listening on http://{}", bind);
+    println!("Scenario script: {}", names.join(" → "));
+    println!(
+        "Point the proxy at it with: ANTHROPIC_API_URL=http://{} aspy",
+        bind
+    );
+    println!("Press Ctrl+C to stop.");
+
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async {
+            tokio::signal::ctrl_c().await.ok();
+        })
+        .await
+        .context("Mock upstream server error")
+}
+
+async fn messages(
+    State(state): State<Arc<MockState>>,
+    headers: HeaderMap,
+    Json(body): Json<Value>,
+) -> Response {
+    let (scenario, seq) = state.next(&headers);
+    let model = body["model"].as_str().unwrap_or("claude-mock");
+    let stream = body["stream"].as_bool().unwrap_or(false);
+    println!(
+        "#{} POST /v1/messages model={} stream={} → {}",
+        seq,
+        model,
+        stream,
+        scenario.as_str()
+    );
+
+    if let Some((status, error_type, message)) = scenario.failure() {
+        return anthropic_error(status, error_type, message);
+    }
+
+    let reply = Reply::new(scenario, seq, model, estimate_input(&body));
+    if stream {
+        sse_response(reply.anthropic_frames(scenario), state.delay_for(scenario))
+    } else {
+        Json(reply.anthropic_message()).into_response()
+    }
+}
+
+async fn count_tokens(headers: HeaderMap, Json(body): Json<Value>) -> Response {
+    // Clients call this between turns, so it doesn't advance the script;
+    // only the override header can make it fail
+    let scenario = header_scenario(&headers).unwrap_or(Scenario::Text);
+    println!("POST /v1/messages/count_tokens → {}", scenario.as_str());
+
+    match scenario.failure() {
+        Some((status, error_type, message)) => anthropic_error(status, error_type, message),
+        None => Json(json!({ "input_tokens": estimate_input(&body) })).into_response(),
+    }
+}
+
+async fn chat_completions(
+    State(state): State<Arc<MockState>>,
+    headers: HeaderMap,
+    Json(body): Json<Value>,
+) -> Response {
+    let (scenario, seq) = state.next(&headers);
+    let model = body["model"].as_str().unwrap_or("gpt-mock");
+    let stream = body["stream"].as_bool().unwrap_or(false);
+    println!(
+        "#{} POST /v1/chat/completions model={} stream={} → {}",
+        seq,
+        model,
+        stream,
+        scenario.as_str()
+    );
+
+    if let Some((status, error_type, message)) = scenario.failure() {
+        let mut response = (
+            status,
+            Json(json!({
+                "error": { "message": message, "type": error_type, "code": null }
+            })),
+        )
+            .into_response();
+        if scenario == Scenario::RateLimit {
+            response
+                .headers_mut()
+                .insert("retry-after", axum::http::HeaderValue::from_static("1"));
+        }
+        return response;
+    }
+
+    let reply = Reply::new(scenario, seq, model, estimate_input(&body));
+    if stream {
+        sse_response(reply.openai_frames(), state.delay_for(scenario))
+    } else {
+        Json(reply.openai_completion()).into_response()
+    }
+}
+
+/// Input tokens for a request (messages, system and tools)
+fn estimate_input(body: &Value) -> u32 {
+    ["system", "messages", "tools"]
+        .iter()
+        .filter_map(|key| body.get(*key))
+        .map(crate::tokens::estimate_json_tokens)
+        .sum::<u32>()
+        .max(1)
+}
+
+fn anthropic_error(status: StatusCode, error_type: &str, message: &str) -> Response {
+    let mut response = (
+        status,
+        Json(json!({
+            "type": "error",
+            "error": { "type": error_type, "message": message }
+        })),
+    )
+        .into_response();
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        let headers = response.headers_mut();
+        headers.insert("retry-after", axum::http::HeaderValue::from_static("1"));
+        headers.insert(
+            "anthropic-ratelimit-requests-remaining",
+            axum::http::HeaderValue::from_static("0"),
+        );
+    }
+    response
+}
+
+fn sse_response(frames: Vec<String>, delay: Duration) -> Response {
+    let stream = futures::stream::iter(frames.into_iter().enumerate()).then(
+        move |(idx, frame)| async move {
+            if idx > 0 && !delay.is_zero() {
+                tokio::time::sleep(delay).await;
+            }
+            Ok::<_, std::convert::Infallible>(Bytes::from(frame))
+        },
+    );
+
+    Response::builder()
+        .header("content-type", "text/event-stream")
+        .header("cache-control", "no-cache")
+        .body(Body::from_stream(stream))
+        .unwrap_or_else(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse rendered Anthropic frames back into (event, data) pairs
+    fn parse_frames(frames: &[String]) -> Vec<(String, Value)> {
+        frames
+            .iter()
+            .map(|f| {
+                let mut lines = f.lines();
+                let event = lines.next().unwrap().trim_start_matches("event: ");
+                let data = lines.next().unwrap().trim_start_matches("data: ");
+                (event.to_string(), serde_json::from_str(data).unwrap())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_script() {
+        let script = parse_script("overloaded, 429,tool_use,text").unwrap();
+        assert_eq!(
+            script,
+            vec![
+                Scenario::Overloaded,
+                Scenario::RateLimit,
+                Scenario::ToolUse,
+                Scenario::Text
+            ]
+        );
+        assert!(parse_script("text,bogus").is_err());
+        assert!(parse_script(" , ").is_err());
+        for scenario in Scenario::ALL {
+            assert_eq!(Scenario::from_str(scenario.as_str()), Some(scenario));
+        }
+    }
+
+    #[test]
+    fn test_script_cycles_and_header_overrides() {
+        let state = MockState {
+            script: vec![Scenario::Overloaded, Scenario::Text],
+            cursor: AtomicUsize::new(0),
+            chunk_delay: Duration::ZERO,
+        };
+        let empty = HeaderMap::new();
+        assert_eq!(state.next(&empty), (Scenario::Overloaded, 1));
+        assert_eq!(state.next(&empty), (Scenario::Text, 2));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(SCENARIO_HEADER, "thinking".parse().unwrap());
+        assert_eq!(state.next(&headers), (Scenario::Thinking, 3));
+        assert_eq!(state.next(&empty), (Scenario::Text, 4));
+        assert_eq!(state.delay_for(Scenario::Slow), SLOW_CHUNK_DELAY);
+    }
+
+    #[test]
+    fn test_anthropic_tool_use_stream_reassembles() {
+        let reply = Reply::new(Scenario::ToolUse, 7, "claude-sonnet-4", 100);
+        let events = parse_frames(&reply.anthropic_frames(Scenario::ToolUse));
+
+        assert_eq!(events.first().unwrap().0, "message_start");
+        assert_eq!(events.last().unwrap().0, "message_stop");
+
+        let partial: String = events
+            .iter()
+            .filter(|(_, d)| d["delta"]["type"] == "input_json_delta")
+            .map(|(_, d)| d["delta"]["partial_json"].as_str().unwrap())
+            .collect();
+        let input: Value = serde_json::from_str(&partial).unwrap();
+        assert_eq!(input["file_path"], "/tmp/mock/README.md");
+
+        let message_delta = events.iter().find(|(e, _)| e == "message_delta").unwrap();
+        assert_eq!(message_delta.1["delta"]["stop_reason"], "tool_use");
+        assert_eq!(
+            reply.anthropic_message()["content"][1]["id"],
+            "toolu_mock_000007"
+        );
+    }
+
+    #[test]
+    fn test_stream_error_stops_after_first_block() {
+        let reply = Reply::new(Scenario::StreamError, 1, "claude-mock", 10);
+        let events = parse_frames(&reply.anthropic_frames(Scenario::StreamError));
+        let last = events.last().unwrap();
+        assert_eq!(last.0, "error");
+        assert_eq!(last.1["error"]["type"], "overloaded_error");
+        assert!(!events.iter().any(|(e, _)| e == "message_stop"));
+    }
+
+    #[test]
+    fn test_openai_stream_and_completion() {
+        let reply = Reply::new(Scenario::ToolUse, 2, "gpt-4o", 50);
+        let frames = reply.openai_frames();
+        assert_eq!(frames.last().unwrap(), "data: [DONE]\n\n");
+
+        let text: String = frames
+            .iter()
+            .filter_map(|f| serde_json::from_str::<Value>(f.trim_start_matches("data: ")).ok())
+            .filter_map(|c| {
+                c["choices"][0]["delta"]["content"]
+                    .as_str()
+                    .map(String::from)
+            })
+            .collect();
+        assert_eq!(text, "Let me look at that file.");
+
+        let completion = reply.openai_completion();
+        assert_eq!(completion["choices"][0]["finish_reason"], "tool_calls");
+        assert_eq!(
+            completion["choices"][0]["message"]["tool_calls"][0]["function"]["name"],
+            "Read"
+        );
+        assert_eq!(completion["usage"]["prompt_tokens"], 50);
+    }
+}
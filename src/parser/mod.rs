@@ -351,6 +351,7 @@ impl Parser {
                 output_tokens: usage.output_tokens,
                 cache_creation_tokens: cache_creation,
                 cache_read_tokens: cache_read,
+                cache_optimized: false,
             });
         }
 
@@ -662,6 +663,7 @@ impl Parser {
                     output_tokens,
                     cache_creation_tokens,
                     cache_read_tokens,
+                    cache_optimized: false,
                 });
             }
         }
@@ -107,11 +107,7 @@ impl CortexMetrics {
             avg_write_latency_us: {
                 let total = self.write_latency_us.load(Ordering::Relaxed);
                 let count = self.flush_count.load(Ordering::Relaxed);
-                if count > 0 {
-                    total / count
-                } else {
-                    0
-                }
+                total.checked_div(count).unwrap_or(0)
             },
         }
     }
@@ -130,9 +126,47 @@ pub struct MetricsSnapshot {
 /// Commands sent to the writer thread
 enum WriterCommand {
     Store(Box<ProxyEvent>, ProcessContext),
+    SaveSessions(SessionStateBatch),
     Shutdown,
 }
 
+/// Snapshot of one live session for the `session_state` table
+#[derive(Debug, Clone)]
+pub struct SessionStateRow {
+    /// Session key (display form, `~` prefix for implicit sessions)
+    pub session_key: String,
+    pub user_id: String,
+    /// Serialized `SessionSnapshot`
+    pub state_json: String,
+}
+
+/// Changed and removed sessions since the last save
+#[derive(Debug, Clone, Default)]
+pub struct SessionStateBatch {
+    pub upserts: Vec<SessionStateRow>,
+    /// Keys of sessions that ended or were superseded
+    pub removed: Vec<String>,
+}
+
+impl SessionStateBatch {
+    pub fn is_empty(&self) -> bool {
+        self.upserts.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Handle for saving session snapshots through the cortex writer thread
+#[derive(Clone)]
+pub struct SessionStateWriter {
+    tx: SyncSender<WriterCommand>,
+}
+
+impl SessionStateWriter {
+    /// Queue a batch for writing (false if the writer is busy or gone)
+    pub fn save(&self, batch: SessionStateBatch) -> bool {
+        self.tx.try_send(WriterCommand::SaveSessions(batch)).is_ok()
+    }
+}
+
 /// Lifetime statistics processor
 ///
 /// Writes events to SQLite using a dedicated thread.
@@ -195,6 +229,13 @@ impl CortexProcessor {
         })
     }
 
+    /// Handle for persisting session manager state
+    pub fn session_state_writer(&self) -> SessionStateWriter {
+        SessionStateWriter {
+            tx: self.tx.clone(),
+        }
+    }
+
     /// Get current metrics snapshot
     #[allow(dead_code)] // Phase 2: Used by /api/cortex/health endpoint
     pub fn metrics(&self) -> MetricsSnapshot {
@@ -257,6 +298,13 @@ impl CortexProcessor {
                         last_flush = Instant::now();
                     }
                 }
+                Ok(WriterCommand::SaveSessions(sessions)) => {
+                    // Written immediately: snapshots are already coalesced by the caller
+                    if let Err(e) = Self::save_session_state(&conn, &sessions) {
+                        let _ = conn.execute("ROLLBACK", []);
+                        tracing::warn!("Failed to save session state: {}", e);
+                    }
+                }
                 Ok(WriterCommand::Shutdown) => {
                     // Final flush before exit
                     if !batch.is_empty() {
@@ -369,6 +417,30 @@ impl CortexProcessor {
         Ok(())
     }
 
+    /// Upsert and delete session snapshots in one transaction
+    fn save_session_state(conn: &Connection, batch: &SessionStateBatch) -> anyhow::Result<()> {
+        conn.execute("BEGIN TRANSACTION", [])?;
+        for row in &batch.upserts {
+            conn.execute(
+                "INSERT INTO session_state (session_key, user_id, updated_at, state_json)
+                 VALUES (?1, ?2, datetime('now'), ?3)
+                 ON CONFLICT(session_key) DO UPDATE SET
+                     user_id = excluded.user_id,
+                     updated_at = excluded.updated_at,
+                     state_json = excluded.state_json",
+                params![row.session_key, row.user_id, row.state_json],
+            )?;
+        }
+        for key in &batch.removed {
+            conn.execute(
+                "DELETE FROM session_state WHERE session_key = ?1",
+                params![key],
+            )?;
+        }
+        conn.execute("COMMIT", [])?;
+        Ok(())
+    }
+
     /// Initialize database schema with WAL mode and run migrations
     fn init_schema(conn: &Connection) -> anyhow::Result<()> {
         // Performance settings (always applied)
@@ -417,6 +489,30 @@ impl CortexProcessor {
         if current_version < 7 {
             Self::migrate_v6_to_v7(conn)?;
         }
+        if current_version < 8 {
+            Self::migrate_v7_to_v8(conn)?;
+        }
+        if current_version < 9 {
+            Self::migrate_v8_to_v9(conn)?;
+        }
+        if current_version < 10 {
+            Self::migrate_v9_to_v10(conn)?;
+        }
+        if current_version < 11 {
+            Self::migrate_v10_to_v11(conn)?;
+        }
+        if current_version < 12 {
+            Self::migrate_v11_to_v12(conn)?;
+        }
+        if current_version < 13 {
+            Self::migrate_v12_to_v13(conn)?;
+        }
+        if current_version < 14 {
+            Self::migrate_v13_to_v14(conn)?;
+        }
+        if current_version < 15 {
+            Self::migrate_v14_to_v15(conn)?;
+        }
 
         Ok(())
     }
@@ -848,6 +944,262 @@ impl CortexProcessor {
         Ok(())
     }
 
+    /// Migration v7 → v8: Add provider_failovers table
+    fn migrate_v7_to_v8(conn: &Connection) -> anyhow::Result<()> {
+        conn.execute_batch(
+            r#"
+            -- Provider failover history (one row per re-route)
+            CREATE TABLE IF NOT EXISTS provider_failovers (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                session_id TEXT,
+                timestamp TEXT NOT NULL,
+                request_id TEXT NOT NULL,
+                from_provider TEXT NOT NULL,
+                to_provider TEXT NOT NULL,
+                reason TEXT NOT NULL,
+                status INTEGER,
+
+                FOREIGN KEY (session_id) REFERENCES sessions(id)
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_provider_failovers_timestamp
+                ON provider_failovers(timestamp DESC);
+            CREATE INDEX IF NOT EXISTS idx_provider_failovers_from
+                ON provider_failovers(from_provider);
+            "#,
+        )?;
+
+        conn.execute(
+            "UPDATE metadata SET value = '8' WHERE key = 'schema_version'",
+            [],
+        )?;
+
+        tracing::info!("Migrated Cortex database from v7 to v8 (provider failovers)");
+        Ok(())
+    }
+
+    /// Migration v8 → v9: Record the user on each api_usage row
+    ///
+    /// Spending budgets sum cost per user over a time window; keeping the
+    /// user on the row avoids depending on session attribution.
+    fn migrate_v8_to_v9(conn: &Connection) -> anyhow::Result<()> {
+        conn.execute_batch(
+            r#"
+            ALTER TABLE api_usage ADD COLUMN user_id TEXT;
+            CREATE INDEX IF NOT EXISTS idx_usage_user_timestamp
+                ON api_usage(user_id, timestamp);
+            "#,
+        )?;
+
+        conn.execute(
+            "UPDATE metadata SET value = '9' WHERE key = 'schema_version'",
+            [],
+        )?;
+
+        tracing::info!("Migrated Cortex database from v8 to v9 (api_usage user_id)");
+        Ok(())
+    }
+
+    /// Migration v9 → v10: Add shadow_comparisons table
+    fn migrate_v9_to_v10(conn: &Connection) -> anyhow::Result<()> {
+        conn.execute_batch(
+            r#"
+            -- Shadow traffic results (one row per mirrored request, primary vs shadow)
+            CREATE TABLE IF NOT EXISTS shadow_comparisons (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                session_id TEXT,
+                timestamp TEXT NOT NULL,
+                request_id TEXT NOT NULL,
+                user_id TEXT,
+                shadow_provider TEXT NOT NULL,
+
+                primary_status INTEGER,
+                primary_model TEXT,
+                primary_ttfb_ms INTEGER NOT NULL,
+                primary_latency_ms INTEGER NOT NULL,
+                primary_input_tokens INTEGER NOT NULL,
+                primary_output_tokens INTEGER NOT NULL,
+                primary_stop_reason TEXT,
+                primary_tool_uses TEXT NOT NULL,  -- JSON array of tool names
+                primary_error TEXT,
+
+                shadow_status INTEGER,
+                shadow_model TEXT,
+                shadow_ttfb_ms INTEGER NOT NULL,
+                shadow_latency_ms INTEGER NOT NULL,
+                shadow_input_tokens INTEGER NOT NULL,
+                shadow_output_tokens INTEGER NOT NULL,
+                shadow_stop_reason TEXT,
+                shadow_tool_uses TEXT NOT NULL,   -- JSON array of tool names
+                shadow_error TEXT,
+
+                FOREIGN KEY (session_id) REFERENCES sessions(id)
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_shadow_comparisons_timestamp
+                ON shadow_comparisons(timestamp DESC);
+            CREATE INDEX IF NOT EXISTS idx_shadow_comparisons_provider
+                ON shadow_comparisons(shadow_provider);
+            "#,
+        )?;
+
+        conn.execute(
+            "UPDATE metadata SET value = '10' WHERE key = 'schema_version'",
+            [],
+        )?;
+
+        tracing::info!("Migrated Cortex database from v9 to v10 (shadow comparisons)");
+        Ok(())
+    }
+
+    /// Migration v10 → v11: Add tool_result_archive table
+    fn migrate_v10_to_v11(conn: &Connection) -> anyhow::Result<()> {
+        conn.execute_batch(
+            r#"
+            -- Original content of tool results the tool governor truncated
+            CREATE TABLE IF NOT EXISTS tool_result_archive (
+                call_id TEXT PRIMARY KEY,
+                session_id TEXT,
+                timestamp TEXT NOT NULL,
+                user_id TEXT,
+                tool_name TEXT NOT NULL,
+                tokens_before INTEGER NOT NULL,
+                tokens_after INTEGER NOT NULL,
+                content_json TEXT NOT NULL,
+
+                FOREIGN KEY (session_id) REFERENCES sessions(id)
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_tool_result_archive_timestamp
+                ON tool_result_archive(timestamp DESC);
+            "#,
+        )?;
+
+        conn.execute(
+            "UPDATE metadata SET value = '11' WHERE key = 'schema_version'",
+            [],
+        )?;
+
+        tracing::info!("Migrated Cortex database from v10 to v11 (tool result archive)");
+        Ok(())
+    }
+
+    /// v11 → v12: Add project column to sessions table
+    ///
+    /// Set from the projects transformer's registry (matched on the working
+    /// directory in the system prompt) so stats can be grouped per repository.
+    fn migrate_v11_to_v12(conn: &Connection) -> anyhow::Result<()> {
+        // Check if column already exists (idempotent)
+        let has_column: bool = conn
+            .query_row(
+                "SELECT COUNT(*) > 0 FROM pragma_table_info('sessions') WHERE name = 'project'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap_or(false);
+
+        if !has_column {
+            conn.execute("ALTER TABLE sessions ADD COLUMN project TEXT", [])?;
+        }
+
+        conn.execute(
+            "UPDATE metadata SET value = '12' WHERE key = 'schema_version'",
+            [],
+        )?;
+
+        tracing::info!("Migrated Cortex database from v11 to v12 (added project to sessions)");
+        Ok(())
+    }
+
+    /// Migration v12 → v13: Add session_state table
+    ///
+    /// Holds the latest snapshot of each live session (stats, context, todos)
+    /// so the session manager can be restored after a proxy restart.
+    fn migrate_v12_to_v13(conn: &Connection) -> anyhow::Result<()> {
+        conn.execute_batch(
+            r#"
+            CREATE TABLE IF NOT EXISTS session_state (
+                session_key TEXT PRIMARY KEY,
+                user_id TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                state_json TEXT NOT NULL
+            );
+            "#,
+        )?;
+
+        conn.execute(
+            "UPDATE metadata SET value = '13' WHERE key = 'schema_version'",
+            [],
+        )?;
+
+        tracing::info!("Migrated Cortex database from v12 to v13 (session state)");
+        Ok(())
+    }
+
+    /// Migration v13 → v14: Add subagents table
+    ///
+    /// One row per finished subagent (Task tool) conversation. `parent_id`
+    /// links nested subagents; usage columns include descendants.
+    fn migrate_v13_to_v14(conn: &Connection) -> anyhow::Result<()> {
+        conn.execute_batch(
+            r#"
+            CREATE TABLE IF NOT EXISTS subagents (
+                id TEXT PRIMARY KEY,
+                session_id TEXT,
+                parent_id TEXT,
+                description TEXT NOT NULL,
+                subagent_type TEXT,
+                started_at TEXT NOT NULL,
+                ended_at TEXT NOT NULL,
+                duration_ms INTEGER NOT NULL,
+                requests INTEGER NOT NULL,
+                input_tokens INTEGER NOT NULL,
+                output_tokens INTEGER NOT NULL,
+                cache_read_tokens INTEGER NOT NULL,
+                cache_creation_tokens INTEGER NOT NULL,
+                cost_usd REAL NOT NULL,
+                success INTEGER NOT NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_subagents_session ON subagents(session_id);
+            CREATE INDEX IF NOT EXISTS idx_subagents_parent ON subagents(parent_id);
+            "#,
+        )?;
+
+        conn.execute(
+            "UPDATE metadata SET value = '14' WHERE key = 'schema_version'",
+            [],
+        )?;
+
+        tracing::info!("Migrated Cortex database from v13 to v14 (subagents)");
+        Ok(())
+    }
+
+    /// Migration v14 → v15: Record the project on each api_usage row
+    ///
+    /// Project budgets charge each request to the project detected in it,
+    /// which can differ from the session's (latest) project. Existing rows
+    /// are backfilled from their session.
+    fn migrate_v14_to_v15(conn: &Connection) -> anyhow::Result<()> {
+        conn.execute_batch(
+            r#"
+            ALTER TABLE api_usage ADD COLUMN project TEXT;
+            UPDATE api_usage SET project =
+                (SELECT project FROM sessions WHERE sessions.id = api_usage.session_id);
+            CREATE INDEX IF NOT EXISTS idx_usage_project_timestamp
+                ON api_usage(project, timestamp);
+            "#,
+        )?;
+
+        conn.execute(
+            "UPDATE metadata SET value = '15' WHERE key = 'schema_version'",
+            [],
+        )?;
+
+        tracing::info!("Migrated Cortex database from v14 to v15 (api_usage project)");
+        Ok(())
+    }
+
     /// Retention cleanup - deletes old data and syncs FTS indexes
     ///
     /// # FTS External Content Sync Contract
@@ -1007,6 +1359,26 @@ impl CortexProcessor {
             params![cutoff_str],
         )? as u64;
 
+        deleted += conn.execute(
+            "DELETE FROM provider_failovers WHERE timestamp < ?1",
+            params![cutoff_str],
+        )? as u64;
+
+        deleted += conn.execute(
+            "DELETE FROM shadow_comparisons WHERE timestamp < ?1",
+            params![cutoff_str],
+        )? as u64;
+
+        deleted += conn.execute(
+            "DELETE FROM tool_result_archive WHERE timestamp < ?1",
+            params![cutoff_str],
+        )? as u64;
+
+        deleted += conn.execute(
+            "DELETE FROM subagents WHERE ended_at < ?1",
+            params![cutoff_str],
+        )? as u64;
+
         // 6. Clean up orphaned sessions (no recent activity)
         deleted += conn.execute(
             "DELETE FROM sessions WHERE started_at < ?1 AND ended_at IS NOT NULL",
@@ -1037,11 +1409,17 @@ impl CortexProcessor {
         // Use UPSERT to handle transcript_path updates (may arrive after session creation)
         if let Some(sid) = session_id {
             conn.execute(
-                "INSERT INTO sessions (id, user_id, started_at, source, transcript_path)
-                 VALUES (?1, ?2, datetime('now'), 'first_seen', ?3)
+                "INSERT INTO sessions (id, user_id, started_at, source, transcript_path, project)
+                 VALUES (?1, ?2, datetime('now'), 'first_seen', ?3, ?4)
                  ON CONFLICT(id) DO UPDATE SET
-                     transcript_path = COALESCE(excluded.transcript_path, sessions.transcript_path)",
-                params![sid, ctx.user_id.as_deref(), ctx.transcript_path.as_deref()],
+                     transcript_path = COALESCE(excluded.transcript_path, sessions.transcript_path),
+                     project = COALESCE(excluded.project, sessions.project)",
+                params![
+                    sid,
+                    ctx.user_id.as_deref(),
+                    ctx.transcript_path.as_deref(),
+                    ctx.project.as_deref()
+                ],
             )?;
         }
 
@@ -1134,6 +1512,7 @@ impl CortexProcessor {
                 output_tokens,
                 cache_read_tokens,
                 cache_creation_tokens,
+                ..
             } => {
                 // Calculate cost using pricing module
                 let cost_usd = crate::pricing::calculate_cost(
@@ -1145,8 +1524,8 @@ impl CortexProcessor {
                 );
 
                 conn.execute(
-                    "INSERT INTO api_usage (session_id, timestamp, model, input_tokens, output_tokens, cache_read_tokens, cache_creation_tokens, cost_usd)
-                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                    "INSERT INTO api_usage (session_id, timestamp, model, input_tokens, output_tokens, cache_read_tokens, cache_creation_tokens, cost_usd, user_id, project)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
                     params![
                         session_id,
                         timestamp.to_rfc3339(),
@@ -1155,7 +1534,9 @@ impl CortexProcessor {
                         output_tokens,
                         cache_read_tokens,
                         cache_creation_tokens,
-                        cost_usd
+                        cost_usd,
+                        ctx.user_id.as_deref(),
+                        ctx.project.as_deref()
                     ],
                 )?;
             }
@@ -1220,6 +1601,144 @@ impl CortexProcessor {
                 )?;
             }
 
+            ProxyEvent::ProviderFailover {
+                timestamp,
+                request_id,
+                from_provider,
+                to_provider,
+                reason,
+                status,
+            } => {
+                conn.execute(
+                    "INSERT INTO provider_failovers (session_id, timestamp, request_id, from_provider, to_provider, reason, status)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                    params![
+                        session_id,
+                        timestamp.to_rfc3339(),
+                        request_id,
+                        from_provider,
+                        to_provider,
+                        reason,
+                        status
+                    ],
+                )?;
+            }
+
+            ProxyEvent::ShadowComparison {
+                timestamp,
+                request_id,
+                shadow_provider,
+                primary,
+                shadow,
+            } => {
+                conn.execute(
+                    "INSERT INTO shadow_comparisons (
+                         session_id, timestamp, request_id, user_id, shadow_provider,
+                         primary_status, primary_model, primary_ttfb_ms, primary_latency_ms,
+                         primary_input_tokens, primary_output_tokens, primary_stop_reason,
+                         primary_tool_uses, primary_error,
+                         shadow_status, shadow_model, shadow_ttfb_ms, shadow_latency_ms,
+                         shadow_input_tokens, shadow_output_tokens, shadow_stop_reason,
+                         shadow_tool_uses, shadow_error)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14,
+                             ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23)",
+                    params![
+                        session_id,
+                        timestamp.to_rfc3339(),
+                        request_id,
+                        ctx.user_id.as_deref(),
+                        shadow_provider,
+                        primary.status,
+                        primary.model,
+                        primary.ttfb_ms as i64,
+                        primary.latency_ms as i64,
+                        primary.input_tokens as i64,
+                        primary.output_tokens as i64,
+                        primary.stop_reason,
+                        serde_json::to_string(&primary.tool_uses)?,
+                        primary.error,
+                        shadow.status,
+                        shadow.model,
+                        shadow.ttfb_ms as i64,
+                        shadow.latency_ms as i64,
+                        shadow.input_tokens as i64,
+                        shadow.output_tokens as i64,
+                        shadow.stop_reason,
+                        serde_json::to_string(&shadow.tool_uses)?,
+                        shadow.error,
+                    ],
+                )?;
+            }
+
+            // Originals are tool output, so they follow store_tool_io
+            ProxyEvent::ToolResultTruncated {
+                timestamp,
+                tool_use_id,
+                tool_name,
+                tokens_before,
+                tokens_after,
+                original,
+            } if config.store_tool_io => {
+                conn.execute(
+                    "INSERT OR IGNORE INTO tool_result_archive
+                         (call_id, session_id, timestamp, user_id, tool_name,
+                          tokens_before, tokens_after, content_json)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                    params![
+                        tool_use_id,
+                        session_id,
+                        timestamp.to_rfc3339(),
+                        ctx.user_id.as_deref(),
+                        tool_name,
+                        *tokens_before as i64,
+                        *tokens_after as i64,
+                        original.to_string(),
+                    ],
+                )?;
+            }
+
+            ProxyEvent::SubagentCompleted {
+                timestamp,
+                agent_id,
+                parent_id,
+                description,
+                subagent_type,
+                started,
+                duration,
+                requests,
+                input_tokens,
+                output_tokens,
+                cache_read_tokens,
+                cache_creation_tokens,
+                cost_usd,
+                success,
+            } => {
+                conn.execute(
+                    "INSERT OR REPLACE INTO subagents
+                         (id, session_id, parent_id, description, subagent_type, started_at,
+                          ended_at, duration_ms, requests, input_tokens, output_tokens,
+                          cache_read_tokens, cache_creation_tokens, cost_usd, success)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+                    params![
+                        agent_id,
+                        session_id,
+                        parent_id,
+                        description,
+                        subagent_type,
+                        started.to_rfc3339(),
+                        timestamp.to_rfc3339(),
+                        duration.as_millis() as i64,
+                        requests,
+                        *input_tokens as i64,
+                        *output_tokens as i64,
+                        *cache_read_tokens as i64,
+                        *cache_creation_tokens as i64,
+                        cost_usd,
+                        success
+                    ],
+                )?;
+            }
+
             _ => {
                 // Other events not stored in cortex
             }
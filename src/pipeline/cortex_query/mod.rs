@@ -31,19 +31,21 @@
 //! - `semantic` - Vector similarity search using embeddings
 //! - `hybrid` - Reciprocal Rank Fusion combining FTS + vector search
 //! - `sessions` - Session history and lookup queries
+//! - `tools` - Archived tool results
 
 mod fts;
 mod hybrid;
 mod semantic;
 mod sessions;
 mod stats;
+mod tools;
 mod types;
 
 // Re-export all public types for HTTP API serialization
 #[allow(unused_imports)] // Used by REST API JSON serialization, not direct Rust imports
 pub use types::{
-    ContextMatch, EmbeddingStats, LifetimeStats, MatchType, ModelStats, PromptMatch, ResponseMatch,
-    SearchMode, ThinkingMatch, TodoMatch, ToolStats,
+    ArchivedToolResult, ContextMatch, EmbeddingStats, LifetimeStats, MatchType, ModelStats,
+    ProjectStats, PromptMatch, ResponseMatch, SearchMode, ThinkingMatch, TodoMatch, ToolStats,
 };
 
 use r2d2::{Pool, PooledConnection};
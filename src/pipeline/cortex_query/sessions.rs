@@ -5,9 +5,46 @@
 
 use super::CortexQuery;
 use crate::proxy::api::{SessionHistoryItem, SessionStatsSummary};
+use crate::proxy::sessions::SessionSnapshot;
 use rusqlite::{params, OptionalExtension};
 
 impl CortexQuery {
+    /// Load session snapshots saved before the last shutdown
+    ///
+    /// Most recently updated first. Rows that no longer parse (schema drift)
+    /// are skipped with a warning. Returns nothing if the table doesn't
+    /// exist yet (writer still migrating an older database).
+    pub fn get_session_snapshots(&self) -> anyhow::Result<Vec<SessionSnapshot>> {
+        let conn = self.conn()?;
+
+        let has_table: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'session_state')",
+            [],
+            |row| row.get(0),
+        )?;
+        if !has_table {
+            return Ok(Vec::new());
+        }
+
+        let mut stmt = conn.prepare(
+            "SELECT session_key, state_json FROM session_state ORDER BY updated_at DESC",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+
+        let mut snapshots = Vec::new();
+        for row in rows {
+            let (key, state_json) = row?;
+            match serde_json::from_str::<SessionSnapshot>(&state_json) {
+                Ok(snapshot) => snapshots.push(snapshot),
+                Err(e) => tracing::warn!("Skipping unreadable session state {}: {}", key, e),
+            }
+        }
+
+        Ok(snapshots)
+    }
+
     /// Get session history for a specific user
     ///
     /// Returns a list of sessions from the database for the specified user,
@@ -3,9 +3,9 @@
 //! Contains methods for aggregating statistics across sessions,
 //! including token counts, costs, and breakdowns by model/tool.
 
-use super::types::{LifetimeStats, ModelStats, ToolStats};
+use super::types::{LifetimeStats, ModelStats, ProjectStats, ToolStats};
 use super::CortexQuery;
-use rusqlite::params;
+use rusqlite::{params, Connection};
 
 impl CortexQuery {
     // =========================================================================
@@ -201,9 +201,61 @@ impl CortexQuery {
             last_session,
             by_model,
             by_tool,
+            by_project: project_stats(&conn, Some(user_id))?,
         })
     }
 
+    /// Get total spend per user since a timestamp
+    ///
+    /// Used to seed spending budgets at startup. Rows recorded before the
+    /// `api_usage.user_id` column existed fall back to their session's user.
+    ///
+    /// # Arguments
+    /// * `since` - RFC 3339 timestamp (inclusive)
+    ///
+    /// # Returns
+    /// `(user_id, cost_usd)` pairs for users with any spend in the window.
+    pub fn get_spend_since(&self, since: &str) -> anyhow::Result<Vec<(String, f64)>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT COALESCE(a.user_id, s.user_id) AS uid, SUM(a.cost_usd)
+            FROM api_usage a
+            LEFT JOIN sessions s ON a.session_id = s.id
+            WHERE a.timestamp >= ?1
+            GROUP BY uid
+            HAVING uid IS NOT NULL
+            "#,
+        )?;
+        let rows = stmt.query_map(params![since], |row| {
+            Ok((row.get(0)?, row.get::<_, Option<f64>>(1)?.unwrap_or(0.0)))
+        })?;
+        Ok(rows.collect::<Result<Vec<_>, _>>()?)
+    }
+
+    /// Total spend per project since a point in time (for project budgets)
+    ///
+    /// # Arguments
+    /// * `since` - RFC 3339 timestamp (inclusive)
+    ///
+    /// # Returns
+    /// `(project, cost_usd)` pairs for projects with any spend in the window.
+    pub fn get_project_spend_since(&self, since: &str) -> anyhow::Result<Vec<(String, f64)>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT project, SUM(cost_usd)
+            FROM api_usage
+            WHERE timestamp >= ?1 AND project IS NOT NULL
+            GROUP BY project
+            "#,
+        )?;
+        let rows = stmt.query_map(params![since], |row| {
+            Ok((row.get(0)?, row.get::<_, Option<f64>>(1)?.unwrap_or(0.0)))
+        })?;
+        Ok(rows.collect::<Result<Vec<_>, _>>()?)
+    }
+
     // =========================================================================
     // Global Statistics
     // =========================================================================
@@ -362,6 +414,37 @@ impl CortexQuery {
             last_session,
             by_model,
             by_tool,
+            by_project: project_stats(&conn, None)?,
         })
     }
 }
+
+/// Usage grouped by session project, optionally for one user
+fn project_stats(conn: &Connection, user_id: Option<&str>) -> anyhow::Result<Vec<ProjectStats>> {
+    let mut stmt = conn.prepare(
+        r#"
+        SELECT
+            s.project,
+            COUNT(DISTINCT s.id) as sessions,
+            COALESCE(SUM(a.input_tokens + a.output_tokens
+                + a.cache_read_tokens + a.cache_creation_tokens), 0) as tokens,
+            COALESCE(SUM(a.cost_usd), 0) as cost,
+            COUNT(a.session_id) as calls
+        FROM sessions s
+        LEFT JOIN api_usage a ON a.session_id = s.id
+        WHERE s.project IS NOT NULL AND (?1 IS NULL OR s.user_id = ?1)
+        GROUP BY s.project
+        ORDER BY cost DESC
+        "#,
+    )?;
+    let rows = stmt.query_map(params![user_id], |row| {
+        Ok(ProjectStats {
+            project: row.get(0)?,
+            sessions: row.get(1)?,
+            tokens: row.get(2)?,
+            cost_usd: row.get(3)?,
+            calls: row.get(4)?,
+        })
+    })?;
+    Ok(rows.collect::<Result<Vec<_>, _>>()?)
+}
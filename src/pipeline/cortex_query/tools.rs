@@ -0,0 +1,58 @@
+//! Tool result queries
+//!
+//! Retrieves original tool results that the tool governor truncated.
+
+use super::{ArchivedToolResult, CortexQuery};
+use rusqlite::{params, OptionalExtension};
+
+impl CortexQuery {
+    /// Get the archived original of a truncated tool result
+    ///
+    /// # Arguments
+    /// * `call_id` - The tool_use id the result belongs to
+    ///
+    /// # Returns
+    /// `Some(result)` if the result was archived, `None` otherwise
+    pub fn get_archived_tool_result(
+        &self,
+        call_id: &str,
+    ) -> anyhow::Result<Option<ArchivedToolResult>> {
+        let conn = self.conn()?;
+
+        let row = conn
+            .query_row(
+                r#"
+                SELECT call_id, session_id, timestamp, tool_name,
+                       tokens_before, tokens_after, content_json
+                FROM tool_result_archive
+                WHERE call_id = ?1
+                "#,
+                params![call_id],
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, Option<String>>(1)?,
+                        row.get::<_, String>(2)?,
+                        row.get::<_, String>(3)?,
+                        row.get::<_, u32>(4)?,
+                        row.get::<_, u32>(5)?,
+                        row.get::<_, String>(6)?,
+                    ))
+                },
+            )
+            .optional()?;
+
+        let Some((call_id, session_id, timestamp, tool_name, before, after, content)) = row else {
+            return Ok(None);
+        };
+        Ok(Some(ArchivedToolResult {
+            call_id,
+            session_id,
+            timestamp,
+            tool_name,
+            tokens_before: before,
+            tokens_after: after,
+            content: serde_json::from_str(&content)?,
+        }))
+    }
+}
@@ -160,6 +160,19 @@ pub struct ResponseMatch {
     pub rank: f64,
 }
 
+/// Original content of a truncated tool result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedToolResult {
+    pub call_id: String,
+    pub session_id: Option<String>,
+    pub timestamp: String,
+    pub tool_name: String,
+    pub tokens_before: u32,
+    pub tokens_after: u32,
+    /// The tool_result content as the client originally sent it
+    pub content: serde_json::Value,
+}
+
 /// Query result for todo searches
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TodoMatch {
@@ -219,6 +232,7 @@ pub struct LifetimeStats {
     pub last_session: Option<String>,
     pub by_model: Vec<ModelStats>,
     pub by_tool: Vec<ToolStats>,
+    pub by_project: Vec<ProjectStats>,
 }
 
 /// Statistics breakdown by model
@@ -235,6 +249,16 @@ pub struct ModelStats {
     pub calls: i64,
 }
 
+/// Statistics breakdown by project (sessions tagged by the projects transformer)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectStats {
+    pub project: String,
+    pub sessions: i64,
+    pub tokens: i64, // = input + output + cache_read + cache_creation
+    pub cost_usd: f64,
+    pub calls: i64,
+}
+
 /// Statistics breakdown by tool
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolStats {
@@ -39,11 +39,18 @@ impl EventProcessor for LoggingProcessor {
             ProxyEvent::UserPrompt { .. } => "UserPrompt",
             ProxyEvent::AssistantResponse { .. } => "AssistantResponse",
             ProxyEvent::RequestTransformed { .. } => "RequestTransformed",
+            ProxyEvent::ToolResultTruncated { .. } => "ToolResultTruncated",
             ProxyEvent::ResponseAugmented { .. } => "ResponseAugmented",
+            ProxyEvent::ModelRouted { .. } => "ModelRouted",
+            ProxyEvent::ProviderFailover { .. } => "ProviderFailover",
+            ProxyEvent::UpstreamRetry { .. } => "UpstreamRetry",
+            ProxyEvent::ToolPolicyDecision { .. } => "ToolPolicyDecision",
+            ProxyEvent::ShadowComparison { .. } => "ShadowComparison",
             ProxyEvent::PreCompactHook { .. } => "PreCompactHook",
             ProxyEvent::ContextRecovery { .. } => "ContextRecovery",
             ProxyEvent::TodoSnapshot { .. } => "TodoSnapshot",
             ProxyEvent::ContextEstimate { .. } => "ContextEstimate",
+            ProxyEvent::SubagentCompleted { .. } => "SubagentCompleted",
         };
 
         // Log event type with context
@@ -118,6 +118,8 @@ pub struct ProcessContext {
     /// Maps user_id → session → transcript for cross-session recovery.
     /// Example: ~/.claude/projects/.../abc123.jsonl
     pub transcript_path: Option<Arc<str>>,
+    /// Project the session belongs to (from the projects transformer's registry)
+    pub project: Option<Arc<str>>,
     /// Whether this is a demo/test event
     #[allow(dead_code)] // Phase 2: Demo event filtering
     pub is_demo: bool,
@@ -134,9 +136,16 @@ impl ProcessContext {
             session_id: session_id.map(Arc::from),
             user_id: user_id.map(Arc::from),
             transcript_path: transcript_path.map(Arc::from),
+            project: None,
             is_demo,
         }
     }
+
+    /// Attach the session's project
+    pub fn with_project(mut self, project: Option<&str>) -> Self {
+        self.project = project.map(Arc::from);
+        self
+    }
 }
 
 /// Trait for event processors
@@ -422,6 +422,147 @@ impl OtelProcessor {
                 span.end();
             }
 
+            ProxyEvent::ModelRouted {
+                rule,
+                provider,
+                model,
+                routed_model,
+                ..
+            } => {
+                // Internal: Aspy's model router choosing the provider
+                let mut span = tracer
+                    .span_builder("model.route")
+                    .with_kind(SpanKind::Internal)
+                    .start(tracer);
+
+                span.set_attribute(KeyValue::new("route.rule", rule.clone()));
+                span.set_attribute(KeyValue::new("provider.to", provider.clone()));
+                if let Some(m) = model {
+                    span.set_attribute(KeyValue::new("model.requested", m.clone()));
+                }
+                if let Some(m) = routed_model {
+                    span.set_attribute(KeyValue::new("model.routed", m.clone()));
+                }
+
+                if let Some(session) = &ctx.session_id {
+                    span.set_attribute(KeyValue::new("session.id", session.to_string()));
+                }
+
+                span.end();
+            }
+
+            ProxyEvent::ProviderFailover {
+                from_provider,
+                to_provider,
+                reason,
+                status,
+                ..
+            } => {
+                // Internal: Aspy re-routing the request to a fallback provider
+                let mut span = tracer
+                    .span_builder("provider.failover")
+                    .with_kind(SpanKind::Internal)
+                    .start(tracer);
+
+                span.set_attribute(KeyValue::new("provider.from", from_provider.clone()));
+                span.set_attribute(KeyValue::new("provider.to", to_provider.clone()));
+                span.set_attribute(KeyValue::new("failover.reason", reason.clone()));
+                if let Some(code) = status {
+                    span.set_attribute(KeyValue::new("http.status_code", *code as i64));
+                }
+
+                if let Some(session) = &ctx.session_id {
+                    span.set_attribute(KeyValue::new("session.id", session.to_string()));
+                }
+
+                span.end();
+            }
+
+            ProxyEvent::UpstreamRetry {
+                attempt,
+                status,
+                delay_ms,
+                delay_source,
+                ..
+            } => {
+                // Internal: Aspy holding a rate-limited request before re-sending
+                let mut span = tracer
+                    .span_builder("upstream.retry")
+                    .with_kind(SpanKind::Internal)
+                    .start(tracer);
+
+                span.set_attribute(KeyValue::new("retry.attempt", *attempt as i64));
+                span.set_attribute(KeyValue::new("http.status_code", *status as i64));
+                span.set_attribute(KeyValue::new("retry.delay_ms", *delay_ms as i64));
+                span.set_attribute(KeyValue::new("retry.delay_source", delay_source.clone()));
+
+                if let Some(session) = &ctx.session_id {
+                    span.set_attribute(KeyValue::new("session.id", session.to_string()));
+                }
+
+                span.end();
+            }
+
+            ProxyEvent::ToolPolicyDecision {
+                tool_name,
+                action,
+                rule,
+                ..
+            } => {
+                // Internal: tool guard flagged a tool call in a response
+                let mut span = tracer
+                    .span_builder("tool.policy")
+                    .with_kind(SpanKind::Internal)
+                    .start(tracer);
+
+                span.set_attribute(KeyValue::new("tool.name", tool_name.clone()));
+                span.set_attribute(KeyValue::new("policy.action", action.clone()));
+                span.set_attribute(KeyValue::new("policy.rule", rule.clone()));
+
+                if let Some(session) = &ctx.session_id {
+                    span.set_attribute(KeyValue::new("session.id", session.to_string()));
+                }
+
+                span.end();
+            }
+
+            ProxyEvent::ShadowComparison {
+                shadow_provider,
+                primary,
+                shadow,
+                ..
+            } => {
+                // Internal: request mirrored to a shadow provider for comparison
+                let mut span = tracer
+                    .span_builder("shadow.comparison")
+                    .with_kind(SpanKind::Internal)
+                    .start(tracer);
+
+                span.set_attribute(KeyValue::new("shadow.provider", shadow_provider.clone()));
+                span.set_attribute(KeyValue::new(
+                    "primary.latency_ms",
+                    primary.latency_ms as i64,
+                ));
+                span.set_attribute(KeyValue::new("shadow.latency_ms", shadow.latency_ms as i64));
+                span.set_attribute(KeyValue::new(
+                    "shadow.stop_reason_match",
+                    primary.stop_reason == shadow.stop_reason,
+                ));
+                span.set_attribute(KeyValue::new(
+                    "shadow.tool_uses_match",
+                    primary.tool_uses == shadow.tool_uses,
+                ));
+                if let Some(error) = &shadow.error {
+                    span.set_attribute(KeyValue::new("shadow.error", error.clone()));
+                }
+
+                if let Some(session) = &ctx.session_id {
+                    span.set_attribute(KeyValue::new("session.id", session.to_string()));
+                }
+
+                span.end();
+            }
+
             // Events we don't export (too verbose or not useful for telemetry)
             ProxyEvent::Thinking { .. }
             | ProxyEvent::ThinkingStarted { .. }
@@ -429,10 +570,12 @@ impl OtelProcessor {
             | ProxyEvent::AssistantResponse { .. }
             | ProxyEvent::HeadersCaptured { .. }
             | ProxyEvent::RateLimitUpdate { .. }
+            | ProxyEvent::ToolResultTruncated { .. }
             | ProxyEvent::PreCompactHook { .. }
             | ProxyEvent::ContextRecovery { .. }
             | ProxyEvent::TodoSnapshot { .. }
-            | ProxyEvent::ContextEstimate { .. } => {
+            | ProxyEvent::ContextEstimate { .. }
+            | ProxyEvent::SubagentCompleted { .. } => {
                 // Skip - these are either too verbose or internal to Aspy
             }
         }
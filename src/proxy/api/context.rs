@@ -199,7 +199,7 @@ pub async fn get_context_snapshot(
             ("text", snap.text_chars),
             ("system", snap.system_chars),
         ];
-        categories.sort_by(|a, b| b.1.cmp(&a.1));
+        categories.sort_by_key(|c| std::cmp::Reverse(c.1));
 
         let summary_parts: Vec<String> = categories
             .iter()
@@ -2,7 +2,8 @@
 
 use super::ApiError;
 use crate::pipeline::cortex_query::{
-    ContextMatch, LifetimeStats, PromptMatch, ResponseMatch, SearchMode, ThinkingMatch, TodoMatch,
+    ArchivedToolResult, ContextMatch, LifetimeStats, PromptMatch, ResponseMatch, SearchMode,
+    ThinkingMatch, TodoMatch,
 };
 use axum::{
     extract::{Path, Query, State},
@@ -282,6 +283,29 @@ pub async fn cortex_todos(
     }))
 }
 
+// ============================================================================
+// Tool Result Archive Endpoint
+// ============================================================================
+
+/// GET /api/cortex/tool-result/:call_id - Original of a truncated tool result
+///
+/// Returns the full content the tool governor elided from later requests.
+pub async fn cortex_tool_result(
+    State(state): State<crate::proxy::ProxyState>,
+    Path(call_id): Path<String>,
+) -> Result<Json<ArchivedToolResult>, ApiError> {
+    let query_interface = state
+        .cortex_query
+        .as_ref()
+        .ok_or_else(|| ApiError::NotFound("Cortex query interface not available".to_string()))?;
+
+    query_interface
+        .get_archived_tool_result(&call_id)
+        .map_err(|e| ApiError::Internal(format!("Tool result lookup failed: {}", e)))?
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("No archived tool result for {}", call_id)))
+}
+
 // ============================================================================
 // Context Recovery Endpoint
 // ============================================================================
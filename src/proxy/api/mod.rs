@@ -29,7 +29,7 @@ pub use cortex::{
     cortex_cleanup, cortex_context, cortex_context_user, cortex_health, cortex_search_prompts,
     cortex_search_responses, cortex_search_thinking, cortex_search_user_prompts,
     cortex_search_user_responses, cortex_search_user_thinking, cortex_stats, cortex_stats_user,
-    cortex_todos,
+    cortex_todos, cortex_tool_result,
 };
 pub use embeddings::{
     cortex_context_hybrid_user, cortex_embedding_poll, cortex_embedding_reindex,
@@ -121,11 +121,18 @@ pub(crate) fn event_type_name(event: &ProxyEvent) -> &'static str {
         ProxyEvent::UserPrompt { .. } => "UserPrompt",
         ProxyEvent::AssistantResponse { .. } => "AssistantResponse",
         ProxyEvent::RequestTransformed { .. } => "RequestTransformed",
+        ProxyEvent::ToolResultTruncated { .. } => "ToolResultTruncated",
         ProxyEvent::ResponseAugmented { .. } => "ResponseAugmented",
+        ProxyEvent::ModelRouted { .. } => "ModelRouted",
+        ProxyEvent::ProviderFailover { .. } => "ProviderFailover",
+        ProxyEvent::UpstreamRetry { .. } => "UpstreamRetry",
+        ProxyEvent::ToolPolicyDecision { .. } => "ToolPolicyDecision",
+        ProxyEvent::ShadowComparison { .. } => "ShadowComparison",
         ProxyEvent::PreCompactHook { .. } => "PreCompactHook",
         ProxyEvent::ContextRecovery { .. } => "ContextRecovery",
         ProxyEvent::TodoSnapshot { .. } => "TodoSnapshot",
         ProxyEvent::ContextEstimate { .. } => "ContextEstimate",
+        ProxyEvent::SubagentCompleted { .. } => "SubagentCompleted",
     }
 }
 
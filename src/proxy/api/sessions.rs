@@ -156,6 +156,7 @@ pub async fn session_start(
         let tracked = TrackedEvent {
             user_id: Some(event_user_id),
             session_id: Some(request.session_id.clone()),
+            agent_id: None,
             tracked_at: Utc::now(),
             event,
         };
@@ -371,6 +372,9 @@ pub struct SessionListItem {
     /// Path to Claude Code's transcript file (from SessionStart hook)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub transcript_path: Option<String>,
+    /// Project from the projects registry (if detected)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
     /// How session was detected
     pub source: String,
     /// When session started
@@ -428,6 +432,7 @@ pub async fn get_sessions(
                 user_id: s.user_id.to_string(),
                 claude_session_id: s.claude_session_id.clone(),
                 transcript_path: s.transcript_path.clone(),
+                project: s.project.clone(),
                 source: s.source.to_string(),
                 started: s.started,
                 status: status.to_string(),
@@ -17,6 +17,12 @@ pub struct SessionStatsResponse {
     pub requests: RequestInfo,
     pub tools: ToolInfo,
     pub thinking: ThinkingInfo,
+    /// Per-key usage for provider key pools (proxy-wide, not filtered by user)
+    #[serde(default)]
+    pub key_pools: Vec<crate::proxy::key_pool::KeyUsage>,
+    /// External transformer calls and latency (proxy-wide, absent when off)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external_transformer: Option<crate::proxy::transformation::ExternalStats>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,6 +44,10 @@ pub struct TokenInfo {
     pub cache_created: u64,
     /// Cache hit percentage (0-100)
     pub cache_ratio_pct: u64,
+    /// Cache hit rate of requests the cache optimizer modified, minus the
+    /// rest (percentage points; absent until both have usage)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_optimizer_delta_pct: Option<f64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -59,6 +69,10 @@ pub struct RequestInfo {
     pub success_rate_pct: f64,
     /// Average time to first byte in milliseconds
     pub avg_ttfb_ms: u64,
+    /// Upstream retries after 429/529 responses
+    pub retries: usize,
+    /// Total time spent waiting between retries in milliseconds
+    pub retry_delay_ms: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -138,6 +152,7 @@ pub async fn get_stats(
             cached: stats.total_cache_read_tokens,
             cache_created: stats.total_cache_creation_tokens,
             cache_ratio_pct: stats.cache_hit_rate() as u64,
+            cache_optimizer_delta_pct: stats.transform_stats.cache_hit_delta(),
         },
         cost: CostInfo {
             total_usd: stats.total_cost(),
@@ -149,6 +164,8 @@ pub async fn get_stats(
             failed: stats.failed_requests,
             success_rate_pct: stats.success_rate(),
             avg_ttfb_ms: stats.avg_ttfb().as_millis() as u64,
+            retries: stats.retry_count,
+            retry_delay_ms: stats.retry_delay_ms,
         },
         tools: ToolInfo {
             total_calls: stats.total_tool_calls,
@@ -159,6 +176,8 @@ pub async fn get_stats(
             blocks: stats.thinking_blocks,
             total_tokens: stats.thinking_tokens,
         },
+        key_pools: state.key_pools.usage(),
+        external_transformer: state.transformation.external().map(|e| e.stats()),
     };
 
     Ok(Json(response))
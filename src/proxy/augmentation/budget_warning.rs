@@ -0,0 +1,154 @@
+// Budget Warning Augmenter
+//
+// Injects a one-time notice into API responses when a client/user crosses
+// the soft threshold (`warn_percent`) of a spending budget, before the
+// budget transformer starts blocking requests at the hard cap.
+//
+// Spend and the "already warned" state live in the shared BudgetTracker,
+// so each budget window warns at most once.
+//
+// # Filtering
+//
+// Same as context-warning: only end_turn responses from non-Haiku models,
+// so utility calls and intermediate tool_use steps are left untouched.
+
+use super::{AugmentationContext, AugmentedContent, Augmenter, StopReason};
+use crate::proxy::transformation::{BudgetStatus, BudgetTracker};
+use std::sync::Arc;
+
+/// Augmenter that warns when a spending budget is nearly used up
+pub struct BudgetWarningAugmenter {
+    tracker: Arc<BudgetTracker>,
+}
+
+impl BudgetWarningAugmenter {
+    pub fn new(tracker: Arc<BudgetTracker>) -> Self {
+        Self { tracker }
+    }
+
+    /// Build the annotation text with styled borders
+    fn format_annotation(status: &BudgetStatus) -> String {
+        format!(
+            "\n\n`★ aspy (budget-warning augmentation) ─────────────────`\n\
+             {} budget at {:.0}% (${:.2} of ${:.2}).\n\
+             Requests will be blocked at the limit. Resets {}.\n\
+             `───────────────────────────────────────────────────────`",
+            capitalize(status.period.as_str()),
+            status.percent(),
+            status.spent_usd,
+            status.limit_usd,
+            status.resets_at.format("%Y-%m-%d %H:%M UTC")
+        )
+    }
+}
+
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+impl Augmenter for BudgetWarningAugmenter {
+    fn name(&self) -> &'static str {
+        "budget-warning"
+    }
+
+    fn should_apply(&self, ctx: &AugmentationContext) -> bool {
+        ctx.stop_reason == StopReason::EndTurn
+            && !ctx.model.to_lowercase().contains("haiku")
+            && ctx.user_id.is_some_and(|id| self.tracker.is_tracked(id))
+    }
+
+    fn generate(&self, ctx: &AugmentationContext) -> Option<AugmentedContent> {
+        let status = self
+            .tracker
+            .take_warning(ctx.user_id?, chrono::Utc::now())?;
+
+        tracing::info!(
+            identity = %status.identity,
+            period = status.period.as_str(),
+            "Budget warning: {:.0}% (${:.2}/${:.2}) at block #{}",
+            status.percent(),
+            status.spent_usd,
+            status.limit_usd,
+            ctx.next_block_index
+        );
+
+        let annotation = Self::format_annotation(&status);
+        Some(AugmentedContent::text_block(
+            ctx.next_block_index,
+            &annotation,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::transformation::{BudgetConfig, BudgetLimits};
+    use crate::SharedContextState;
+
+    fn augmenter() -> BudgetWarningAugmenter {
+        let mut config = BudgetConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        config.clients.insert(
+            "dev-1".to_string(),
+            BudgetLimits {
+                daily_usd: Some(10.0),
+                ..Default::default()
+            },
+        );
+        BudgetWarningAugmenter::new(Arc::new(BudgetTracker::new(config)))
+    }
+
+    #[test]
+    fn test_warns_once_past_threshold() {
+        let augmenter = augmenter();
+        let context_state = SharedContextState::default();
+        let ctx = AugmentationContext {
+            model: "claude-sonnet-4",
+            stop_reason: StopReason::EndTurn,
+            next_block_index: 2,
+            context_state: &context_state,
+            user_id: Some("dev-1"),
+        };
+        assert!(augmenter.should_apply(&ctx));
+
+        augmenter.tracker.record("dev-1", 5.0, chrono::Utc::now());
+        assert!(augmenter.generate(&ctx).is_none());
+
+        augmenter.tracker.record("dev-1", 3.5, chrono::Utc::now());
+        let content = augmenter.generate(&ctx).expect("should warn at 85%");
+        let sse = String::from_utf8(content.sse_bytes).unwrap();
+        assert!(sse.contains("\"index\":2"));
+        assert!(sse.contains("Daily budget at 85%"));
+
+        assert!(augmenter.generate(&ctx).is_none(), "warns once per window");
+    }
+
+    #[test]
+    fn test_skips_untracked_and_utility_responses() {
+        let augmenter = augmenter();
+        let context_state = SharedContextState::default();
+        let mut ctx = AugmentationContext {
+            model: "claude-haiku-4",
+            stop_reason: StopReason::EndTurn,
+            next_block_index: 0,
+            context_state: &context_state,
+            user_id: Some("dev-1"),
+        };
+        assert!(!augmenter.should_apply(&ctx));
+
+        ctx.model = "claude-opus-4";
+        ctx.stop_reason = StopReason::ToolUse;
+        assert!(!augmenter.should_apply(&ctx));
+
+        ctx.stop_reason = StopReason::EndTurn;
+        ctx.user_id = Some("dev-2");
+        assert!(!augmenter.should_apply(&ctx));
+    }
+}
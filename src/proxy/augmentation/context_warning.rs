@@ -87,27 +87,6 @@ impl ContextWarningAugmenter {
             message
         )
     }
-
-    /// Generate SSE events for a text content block injection
-    fn generate_sse_block(&self, index: u32, text: &str) -> Vec<u8> {
-        // Escape text for JSON
-        let escaped_text = serde_json::to_string(text).unwrap_or_default();
-
-        // Build SSE events for content block
-        // IMPORTANT: SSE format requires "data:" at column 0, no leading whitespace
-        let sse = format!(
-            "event: content_block_start\n\
-             data: {{\"type\":\"content_block_start\",\"index\":{idx},\"content_block\":{{\"type\":\"text\",\"text\":\"\"}}}}\n\n\
-             event: content_block_delta\n\
-             data: {{\"type\":\"content_block_delta\",\"index\":{idx},\"delta\":{{\"type\":\"text_delta\",\"text\":{text}}}}}\n\n\
-             event: content_block_stop\n\
-             data: {{\"type\":\"content_block_stop\",\"index\":{idx}}}\n\n",
-            idx = index,
-            text = escaped_text
-        );
-
-        sse.into_bytes()
-    }
 }
 
 impl Default for ContextWarningAugmenter {
@@ -186,7 +165,9 @@ impl Augmenter for ContextWarningAugmenter {
             ctx.next_block_index
         );
 
-        let sse_bytes = self.generate_sse_block(ctx.next_block_index, &annotation);
-        Some(AugmentedContent::from_text(sse_bytes, &annotation))
+        Some(AugmentedContent::text_block(
+            ctx.next_block_index,
+            &annotation,
+        ))
     }
 }
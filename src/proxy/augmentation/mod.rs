@@ -1,19 +1,23 @@
 // Augmentation module - extensible response modification
 //
 // This module provides a trait-based system for modifying API responses.
-// Augmenters can inject content into SSE streams (e.g., context warnings,
-// annotations, debug info) without touching core proxy logic.
+// Augmenters can inject content into SSE streams and buffered JSON responses
+// (e.g., context warnings, annotations, debug info) without touching core
+// proxy logic.
 //
 // # Architecture
 //
 // ```
-// Response Stream → AugmentationPipeline → [Augmenter1, Augmenter2, ...] → Injections
+// Response Stream → AugmentationPipeline::process          → SSE injection
+// JSON Response   → AugmentationPipeline::process_buffered → appended content blocks
 // ```
 //
 // Each augmenter implements the `Augmenter` trait:
-// - `name()`: Human-readable identifier for logging
+// - `name()`: Human-readable identifier for logging and stats
 // - `should_apply()`: Determines if augmenter should run for this response
-// - `generate_sse()`: Produces SSE bytes to inject
+// - `generate()`: Produces the SSE bytes and content blocks to inject
+//
+// Augmenters built on `AugmentedContent::text_block` work on both paths.
 //
 // # Adding New Augmenters
 //
@@ -21,8 +25,10 @@
 // 2. Implement the `Augmenter` trait
 // 3. Register in `AugmentationPipeline::default()` or via config
 
+mod budget_warning;
 mod context_warning;
 
+pub use budget_warning::BudgetWarningAugmenter;
 pub use context_warning::ContextWarningAugmenter;
 
 use crate::SharedContextState;
@@ -34,26 +40,25 @@ use crate::SharedContextState;
 /// Result of an augmentation including the SSE bytes and token estimate
 #[derive(Debug)]
 pub struct AugmentedContent {
-    /// The SSE bytes to inject
+    /// The SSE bytes to inject (streaming responses)
     pub sse_bytes: Vec<u8>,
+    /// Anthropic content blocks to append (buffered responses)
+    ///
+    /// Empty for stream-only augmentations.
+    pub blocks: Vec<serde_json::Value>,
     /// Estimated tokens in the injected content
     pub tokens_injected: u32,
 }
 
 impl AugmentedContent {
-    /// Create new augmented content with token estimate
-    pub fn new(sse_bytes: Vec<u8>, tokens_injected: u32) -> Self {
+    /// Create a text block injection usable by both streaming and buffered responses
+    pub fn text_block(index: u32, text: &str) -> Self {
         Self {
-            sse_bytes,
-            tokens_injected,
+            sse_bytes: text_block_sse(index, text),
+            blocks: vec![serde_json::json!({ "type": "text", "text": text })],
+            tokens_injected: crate::tokens::estimate_tokens(text),
         }
     }
-
-    /// Create augmented content, auto-estimating tokens from text content
-    pub fn from_text(sse_bytes: Vec<u8>, text_content: &str) -> Self {
-        let tokens = crate::tokens::estimate_tokens(text_content);
-        Self::new(sse_bytes, tokens)
-    }
 }
 
 // ============================================================================
@@ -73,6 +78,9 @@ pub struct AugmentationContext<'a> {
 
     /// Shared context state (token counts, warning thresholds)
     pub context_state: &'a SharedContextState,
+
+    /// User/client identity the response belongs to (if known)
+    pub user_id: Option<&'a str>,
 }
 
 /// Parsed stop reason for cleaner pattern matching
@@ -85,6 +93,16 @@ pub enum StopReason {
 }
 
 impl StopReason {
+    /// Parse a stop_reason value (e.g., from a buffered JSON response)
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "end_turn" => StopReason::EndTurn,
+            "tool_use" => StopReason::ToolUse,
+            "max_tokens" => StopReason::MaxTokens,
+            _ => StopReason::Other,
+        }
+    }
+
     /// Parse stop_reason from the SSE chunk text
     pub fn from_chunk(chunk: &str) -> Option<Self> {
         if chunk.contains("\"stop_reason\":\"end_turn\"")
@@ -107,15 +125,37 @@ impl StopReason {
     }
 }
 
+/// Build SSE events for an injected text content block
+pub(crate) fn text_block_sse(index: u32, text: &str) -> Vec<u8> {
+    // Escape text for JSON
+    let escaped_text = serde_json::to_string(text).unwrap_or_default();
+
+    // Build SSE events for content block
+    // IMPORTANT: SSE format requires "data:" at column 0, no leading whitespace
+    let sse = format!(
+        "event: content_block_start\n\
+         data: {{\"type\":\"content_block_start\",\"index\":{idx},\"content_block\":{{\"type\":\"text\",\"text\":\"\"}}}}\n\n\
+         event: content_block_delta\n\
+         data: {{\"type\":\"content_block_delta\",\"index\":{idx},\"delta\":{{\"type\":\"text_delta\",\"text\":{text}}}}}\n\n\
+         event: content_block_stop\n\
+         data: {{\"type\":\"content_block_stop\",\"index\":{idx}}}\n\n",
+        idx = index,
+        text = escaped_text
+    );
+
+    sse.into_bytes()
+}
+
 // ============================================================================
 // Augmenter Trait
 // ============================================================================
 
 /// Trait for response augmenters (plugins that modify API responses)
 ///
-/// Augmenters are called at the end of SSE streams to optionally inject
-/// additional content blocks. They should be stateless where possible,
-/// with shared state accessed through `AugmentationContext`.
+/// Augmenters are called at the end of SSE streams and buffered JSON
+/// responses to optionally inject additional content blocks. They should be
+/// stateless where possible, with shared state accessed through
+/// `AugmentationContext`.
 ///
 /// # Example
 ///
@@ -130,9 +170,7 @@ impl StopReason {
 ///     }
 ///
 ///     fn generate(&self, ctx: &AugmentationContext) -> Option<AugmentedContent> {
-///         let text = "injected content";
-///         let sse = b"event: ...\ndata: ...\n\n".to_vec();
-///         Some(AugmentedContent::from_text(sse, text))
+///         Some(AugmentedContent::text_block(ctx.next_block_index, "injected content"))
 ///     }
 /// }
 /// ```
@@ -152,6 +190,16 @@ pub trait Augmenter: Send + Sync {
     /// Return `None` if no injection is needed (e.g., threshold not met).
     /// Return `Some(AugmentedContent)` with valid SSE bytes and token estimate.
     fn generate(&self, ctx: &AugmentationContext) -> Option<AugmentedContent>;
+
+    /// Generate content blocks to append to a buffered JSON response
+    ///
+    /// Defaults to `generate()`, which covers augmenters built on
+    /// `AugmentedContent::text_block`. Override for augmenters whose
+    /// buffered output differs from their stream injection. Return content
+    /// with empty `blocks` to skip buffered responses.
+    fn generate_buffered(&self, ctx: &AugmentationContext) -> Option<AugmentedContent> {
+        self.generate(ctx)
+    }
 }
 
 // ============================================================================
@@ -208,10 +256,11 @@ impl AugmentationPipeline {
     /// Process a response context and return any injections
     ///
     /// Iterates through all augmenters, calling `should_apply()` and `generate()`.
-    /// Returns the first successful injection (augmenters are mutually exclusive for now).
+    /// Returns the first successful injection with the name of the augmenter
+    /// that produced it (augmenters are mutually exclusive for now).
     ///
     /// Future: Could return Vec<AugmentedContent> to allow multiple injections.
-    pub fn process(&self, ctx: &AugmentationContext) -> Option<AugmentedContent> {
+    pub fn process(&self, ctx: &AugmentationContext) -> Option<(&'static str, AugmentedContent)> {
         for augmenter in &self.augmenters {
             if augmenter.should_apply(ctx) {
                 if let Some(content) = augmenter.generate(ctx) {
@@ -221,13 +270,50 @@ impl AugmentationPipeline {
                         content.sse_bytes.len(),
                         content.tokens_injected
                     );
-                    return Some(content);
+                    return Some((augmenter.name(), content));
                 }
             }
         }
         None
     }
 
+    /// Augment a buffered Anthropic JSON response in place
+    ///
+    /// Same selection rules as `process()`, but the injected blocks are
+    /// appended to the response's `content` array. Returns the augmenter
+    /// name and tokens injected, or None if nothing was added.
+    pub fn process_buffered(
+        &self,
+        ctx: &AugmentationContext,
+        response: &mut serde_json::Value,
+    ) -> Option<(&'static str, u32)> {
+        let content = response.get_mut("content")?.as_array_mut()?;
+        for augmenter in &self.augmenters {
+            if !augmenter.should_apply(ctx) {
+                continue;
+            }
+            let Some(augmented) = augmenter.generate_buffered(ctx) else {
+                continue;
+            };
+            if augmented.blocks.is_empty() {
+                tracing::debug!(
+                    "Augmenter '{}' has no buffered output, skipping",
+                    augmenter.name()
+                );
+                continue;
+            }
+            tracing::debug!(
+                "Augmenter '{}' appended {} block(s) (~{} tokens)",
+                augmenter.name(),
+                augmented.blocks.len(),
+                augmented.tokens_injected
+            );
+            content.extend(augmented.blocks);
+            return Some((augmenter.name(), augmented.tokens_injected));
+        }
+        None
+    }
+
     /// Get names of registered augmenters (for logging/debug)
     pub fn augmenter_names(&self) -> Vec<&'static str> {
         self.augmenters.iter().map(|a| a.name()).collect()
@@ -242,3 +328,110 @@ impl Default for AugmentationPipeline {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test augmenter with fixed output
+    struct Fixed {
+        name: &'static str,
+        stream_only: bool,
+    }
+
+    impl Augmenter for Fixed {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        fn should_apply(&self, ctx: &AugmentationContext) -> bool {
+            ctx.stop_reason == StopReason::EndTurn
+        }
+
+        fn generate(&self, ctx: &AugmentationContext) -> Option<AugmentedContent> {
+            let mut content = AugmentedContent::text_block(ctx.next_block_index, self.name);
+            if self.stream_only {
+                content.blocks.clear();
+            }
+            Some(content)
+        }
+    }
+
+    fn response() -> serde_json::Value {
+        serde_json::json!({
+            "id": "msg_1",
+            "type": "message",
+            "model": "claude-sonnet-4",
+            "content": [{"type": "text", "text": "Hello"}],
+            "stop_reason": "end_turn",
+            "usage": {"input_tokens": 10, "output_tokens": 2}
+        })
+    }
+
+    #[test]
+    fn test_process_buffered_appends_blocks() {
+        let mut pipeline = AugmentationPipeline::new();
+        pipeline.register(Fixed {
+            name: "stream-only",
+            stream_only: true,
+        });
+        pipeline.register(Fixed {
+            name: "both",
+            stream_only: false,
+        });
+        let context_state = SharedContextState::default();
+        let ctx = AugmentationContext {
+            model: "claude-sonnet-4",
+            stop_reason: StopReason::EndTurn,
+            next_block_index: 1,
+            context_state: &context_state,
+            user_id: None,
+        };
+
+        let mut json = response();
+        let (name, tokens) = pipeline.process_buffered(&ctx, &mut json).unwrap();
+        assert_eq!(name, "both", "stream-only augmenters are skipped");
+        assert!(tokens > 0);
+
+        let content = json["content"].as_array().unwrap();
+        assert_eq!(content.len(), 2);
+        assert_eq!(
+            content[1],
+            serde_json::json!({"type": "text", "text": "both"})
+        );
+        assert_eq!(json["usage"]["input_tokens"], 10, "other fields preserved");
+
+        // Streaming picks the first augmenter and reports its name
+        let (name, content) = pipeline.process(&ctx).unwrap();
+        assert_eq!(name, "stream-only");
+        assert!(String::from_utf8(content.sse_bytes)
+            .unwrap()
+            .contains("\"index\":1"));
+    }
+
+    #[test]
+    fn test_process_buffered_skips_non_messages() {
+        let mut pipeline = AugmentationPipeline::new();
+        pipeline.register(Fixed {
+            name: "both",
+            stream_only: false,
+        });
+        let context_state = SharedContextState::default();
+        let mut ctx = AugmentationContext {
+            model: "claude-sonnet-4",
+            stop_reason: StopReason::parse("tool_use"),
+            next_block_index: 1,
+            context_state: &context_state,
+            user_id: None,
+        };
+
+        let mut json = response();
+        assert!(pipeline.process_buffered(&ctx, &mut json).is_none());
+        assert_eq!(json, response());
+
+        // count_tokens-style bodies have no content array
+        ctx.stop_reason = StopReason::parse("end_turn");
+        let mut count = serde_json::json!({"input_tokens": 42});
+        assert!(pipeline.process_buffered(&ctx, &mut count).is_none());
+    }
+}
@@ -0,0 +1,452 @@
+//! Request breakpoints - hold matching requests until the TUI decides
+//!
+//! Rules from `[[breakpoints.rules]]` are checked against every messages
+//! request before transformation. A match parks the request here; the TUI
+//! picks it up, shows the body in a modal, and sends back a decision.
+//!
+//! ```text
+//! proxy_handler ──hold()──► pending queue ──next_pending()──► TUI modal
+//!       ▲                                                       │
+//!       └──────────── oneshot ◄──────resolve(id, decision)──────┘
+//! ```
+//!
+//! If nobody answers within `timeout_secs` (or the TUI goes away) the
+//! request continues unchanged, so a forgotten breakpoint never wedges
+//! a client forever. A client that disconnects while held takes its
+//! breakpoint with it.
+
+use crate::config::{BreakpointRule, Breakpoints};
+use chrono::{DateTime, Utc};
+use regex::{Regex, RegexBuilder};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+/// What to do with a held request
+#[derive(Debug, Clone, PartialEq)]
+pub enum BreakpointDecision {
+    /// Forward the request, optionally with an edited body
+    Continue { body: Option<Vec<u8>> },
+    /// Answer the client with an error instead of forwarding
+    Reject { status: u16, message: String },
+}
+
+impl BreakpointDecision {
+    /// Parse a reject message typed in the TUI
+    ///
+    /// A leading HTTP status ("529 overloaded for testing") sets the status,
+    /// otherwise the request is rejected with 400.
+    pub fn reject(input: &str) -> Self {
+        let input = input.trim();
+        let (status, message) = match input.split_once(' ') {
+            Some((code, rest)) => match code.parse::<u16>() {
+                Ok(status) if (400..600).contains(&status) => (status, rest.trim()),
+                _ => (400, input),
+            },
+            None => (400, input),
+        };
+        let message = if message.is_empty() {
+            "Request rejected at aspy breakpoint"
+        } else {
+            message
+        };
+        Self::Reject {
+            status,
+            message: message.to_string(),
+        }
+    }
+}
+
+/// A request waiting at a breakpoint (what the TUI shows)
+#[derive(Debug, Clone)]
+pub struct HeldRequest {
+    /// Breakpoint hold id (for resolve)
+    pub id: u64,
+    /// Proxy request id
+    pub request_id: String,
+    /// Client or user identity
+    pub client: Option<String>,
+    /// Requested model
+    pub model: Option<String>,
+    /// API path
+    pub path: String,
+    /// Which rule matched
+    pub reason: String,
+    /// Request body, pretty-printed JSON
+    pub body: String,
+    /// When the request was held
+    pub held_at: DateTime<Utc>,
+}
+
+/// A compiled breakpoint rule
+struct CompiledRule {
+    description: String,
+    client: Option<String>,
+    model: Option<Regex>,
+    tool: Option<String>,
+    prompt: Option<Regex>,
+}
+
+impl CompiledRule {
+    fn compile(rule: &BreakpointRule) -> anyhow::Result<Self> {
+        let model = rule
+            .model
+            .as_deref()
+            .map(|p| RegexBuilder::new(p).case_insensitive(true).build())
+            .transpose()?;
+        let prompt = rule.prompt.as_deref().map(Regex::new).transpose()?;
+
+        let description = [
+            ("client", &rule.client),
+            ("model", &rule.model),
+            ("tool", &rule.tool),
+            ("prompt", &rule.prompt),
+        ]
+        .iter()
+        .filter_map(|(key, value)| value.as_ref().map(|v| format!("{}={}", key, v)))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+        Ok(Self {
+            description: if description.is_empty() {
+                "any request".to_string()
+            } else {
+                description
+            },
+            client: rule.client.clone(),
+            model,
+            tool: rule.tool.clone(),
+            prompt,
+        })
+    }
+
+    fn matches(&self, body: &Value, client: Option<&str>) -> bool {
+        if let Some(ref expected) = self.client {
+            if client != Some(expected.as_str()) {
+                return false;
+            }
+        }
+        if let Some(ref pattern) = self.model {
+            let model = body.get("model").and_then(|m| m.as_str());
+            if !model.is_some_and(|m| pattern.is_match(m)) {
+                return false;
+            }
+        }
+        if let Some(ref tool) = self.tool {
+            if !super::helpers::last_tool_result_names(body)
+                .iter()
+                .any(|name| name == tool)
+            {
+                return false;
+            }
+        }
+        if let Some(ref pattern) = self.prompt {
+            let prompt = super::helpers::extract_user_prompt(body);
+            if !prompt.is_some_and(|p| pattern.is_match(&p)) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// A held request and the channel its decision goes back on
+struct Pending {
+    request: HeldRequest,
+    reply: oneshot::Sender<BreakpointDecision>,
+}
+
+/// Shared breakpoint state between the proxy and the TUI
+pub struct BreakpointHub {
+    rules: Vec<CompiledRule>,
+    armed: AtomicBool,
+    timeout: Option<Duration>,
+    next_id: AtomicU64,
+    pending: Mutex<VecDeque<Pending>>,
+}
+
+impl BreakpointHub {
+    /// Create from configuration (fails on invalid patterns)
+    pub fn from_config(config: &Breakpoints) -> anyhow::Result<Self> {
+        let rules = config
+            .rules
+            .iter()
+            .map(CompiledRule::compile)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(Self {
+            rules,
+            armed: AtomicBool::new(config.armed),
+            timeout: (config.timeout_secs > 0).then(|| Duration::from_secs(config.timeout_secs)),
+            next_id: AtomicU64::new(1),
+            pending: Mutex::new(VecDeque::new()),
+        })
+    }
+
+    pub fn is_armed(&self) -> bool {
+        self.armed.load(Ordering::Relaxed)
+    }
+
+    /// Flip armed state, returning the new state
+    pub fn toggle_armed(&self) -> bool {
+        !self.armed.fetch_xor(true, Ordering::Relaxed)
+    }
+
+    /// Description of the first rule matching a request (None = no breakpoint)
+    pub fn matching_rule(&self, body: &Value, client: Option<&str>) -> Option<&str> {
+        if !self.is_armed() {
+            return None;
+        }
+        self.rules
+            .iter()
+            .find(|r| r.matches(body, client))
+            .map(|r| r.description.as_str())
+    }
+
+    /// Park a request until its decision arrives on the returned hold
+    pub fn hold(&self, mut request: HeldRequest) -> Hold<'_> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        request.id = id;
+        let (reply, rx) = oneshot::channel();
+        self.pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push_back(Pending { request, reply });
+        Hold { hub: self, id, rx }
+    }
+
+    /// Oldest request waiting for a decision
+    pub fn next_pending(&self) -> Option<HeldRequest> {
+        self.pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .front()
+            .map(|p| p.request.clone())
+    }
+
+    /// Whether a hold is still waiting (false after timeout or resolve)
+    pub fn is_pending(&self, id: u64) -> bool {
+        self.pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .any(|p| p.request.id == id)
+    }
+
+    /// Send the decision for a held request (false if it already timed out)
+    pub fn resolve(&self, id: u64, decision: BreakpointDecision) -> bool {
+        self.take(id)
+            .is_some_and(|pending| pending.reply.send(decision).is_ok())
+    }
+
+    fn take(&self, id: u64) -> Option<Pending> {
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        let idx = pending.iter().position(|p| p.request.id == id)?;
+        pending.remove(idx)
+    }
+}
+
+/// A parked request
+///
+/// Dropping it (the decision arrived, it timed out, or the client went away
+/// and the handler was cancelled) removes the request from the hub, so the
+/// TUI never shows a hold nobody is waiting on.
+pub struct Hold<'a> {
+    hub: &'a BreakpointHub,
+    id: u64,
+    rx: oneshot::Receiver<BreakpointDecision>,
+}
+
+impl Hold<'_> {
+    /// Breakpoint hold id
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Wait for the TUI's decision (continues unchanged on timeout)
+    pub async fn wait(mut self) -> BreakpointDecision {
+        let decision = match self.hub.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, &mut self.rx).await.ok(),
+            None => Some((&mut self.rx).await),
+        };
+        match decision {
+            Some(Ok(decision)) => decision,
+            Some(Err(_)) => BreakpointDecision::Continue { body: None },
+            None => {
+                tracing::warn!("Breakpoint #{} timed out, continuing unchanged", self.id);
+                BreakpointDecision::Continue { body: None }
+            }
+        }
+    }
+}
+
+impl Drop for Hold<'_> {
+    fn drop(&mut self) {
+        self.hub.take(self.id);
+    }
+}
+
+/// Shared handle type used by the proxy and TUI
+pub type SharedBreakpoints = Arc<BreakpointHub>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn hub(rules: Vec<BreakpointRule>) -> BreakpointHub {
+        BreakpointHub::from_config(&Breakpoints {
+            rules,
+            ..Breakpoints::default()
+        })
+        .unwrap()
+    }
+
+    fn held(path: &str) -> HeldRequest {
+        HeldRequest {
+            id: 0,
+            request_id: "req".to_string(),
+            client: None,
+            model: None,
+            path: path.to_string(),
+            reason: "any request".to_string(),
+            body: "{}".to_string(),
+            held_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_rule_matching() {
+        let hub = hub(vec![BreakpointRule {
+            client: Some("dev-1".to_string()),
+            model: Some("opus".to_string()),
+            prompt: Some("(?i)deploy".to_string()),
+            ..Default::default()
+        }]);
+        let body = json!({
+            "model": "claude-Opus-4",
+            "messages": [{"role": "user", "content": "Please DEPLOY to staging"}]
+        });
+
+        assert_eq!(
+            hub.matching_rule(&body, Some("dev-1")),
+            Some("client=dev-1 model=opus prompt=(?i)deploy")
+        );
+        assert!(hub.matching_rule(&body, Some("dev-2")).is_none());
+
+        let other = json!({"model": "claude-haiku", "messages": []});
+        assert!(hub.matching_rule(&other, Some("dev-1")).is_none());
+
+        hub.toggle_armed();
+        assert!(
+            hub.matching_rule(&body, Some("dev-1")).is_none(),
+            "disarmed"
+        );
+    }
+
+    #[test]
+    fn test_tool_rule_uses_last_tool_result() {
+        let hub = hub(vec![BreakpointRule {
+            tool: Some("Bash".to_string()),
+            ..Default::default()
+        }]);
+        let body = json!({
+            "messages": [
+                {"role": "user", "content": "list files"},
+                {"role": "assistant", "content": [
+                    {"type": "tool_use", "id": "toolu_1", "name": "Bash", "input": {}}
+                ]},
+                {"role": "user", "content": [
+                    {"type": "tool_result", "tool_use_id": "toolu_1", "content": "a.txt"}
+                ]}
+            ]
+        });
+        assert!(hub.matching_rule(&body, None).is_some());
+
+        let mut later = body.clone();
+        later["messages"]
+            .as_array_mut()
+            .unwrap()
+            .push(json!({"role": "user", "content": "thanks"}));
+        assert!(hub.matching_rule(&later, None).is_none());
+    }
+
+    #[test]
+    fn test_invalid_pattern_fails() {
+        let result = BreakpointHub::from_config(&Breakpoints {
+            rules: vec![BreakpointRule {
+                prompt: Some("(unclosed".to_string()),
+                ..Default::default()
+            }],
+            ..Breakpoints::default()
+        });
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_hold_and_resolve() {
+        let hub = hub(Vec::new());
+        let hold = hub.hold(held("/v1/messages"));
+        let id = hold.id();
+        assert_eq!(hub.next_pending().map(|r| r.id), Some(id));
+
+        let decision = BreakpointDecision::Continue {
+            body: Some(b"{}".to_vec()),
+        };
+        assert!(hub.resolve(id, decision.clone()));
+        assert_eq!(hold.wait().await, decision);
+        assert!(!hub.is_pending(id));
+        assert!(!hub.resolve(id, decision), "already resolved");
+    }
+
+    #[tokio::test]
+    async fn test_timeout_continues_unchanged() {
+        let hub = BreakpointHub::from_config(&Breakpoints::default()).unwrap();
+        let hub = BreakpointHub {
+            timeout: Some(Duration::from_millis(10)),
+            ..hub
+        };
+        let hold = hub.hold(held("/v1/messages"));
+        assert_eq!(
+            hold.wait().await,
+            BreakpointDecision::Continue { body: None }
+        );
+        assert!(hub.next_pending().is_none(), "timed out hold is dropped");
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_wait_releases_hold() {
+        let hub = hub(Vec::new());
+        let hold = hub.hold(held("/v1/messages"));
+        let id = hold.id();
+
+        // Client disconnects: the handler future (and its wait) is dropped
+        let wait = hold.wait();
+        let _ = tokio::time::timeout(Duration::from_millis(10), wait).await;
+
+        assert!(!hub.is_pending(id));
+        assert!(hub.next_pending().is_none());
+        assert!(!hub.resolve(id, BreakpointDecision::Continue { body: None }));
+    }
+
+    #[test]
+    fn test_reject_parses_status() {
+        assert_eq!(
+            BreakpointDecision::reject("529 Overloaded for testing"),
+            BreakpointDecision::Reject {
+                status: 529,
+                message: "Overloaded for testing".to_string()
+            }
+        );
+        assert_eq!(
+            BreakpointDecision::reject("not allowed"),
+            BreakpointDecision::Reject {
+                status: 400,
+                message: "not allowed".to_string()
+            }
+        );
+    }
+}
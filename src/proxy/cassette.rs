@@ -0,0 +1,470 @@
+//! Record/replay cassettes for upstream traffic
+//!
+//! Record mode tees every upstream response into a JSON Lines cassette while
+//! it streams to the client. Replay mode answers requests from the cassette
+//! instead of calling the provider, so SSE parsing, translation and
+//! augmentation bugs can be reproduced offline.
+//!
+//! # Cassette Format
+//!
+//! One exchange per line:
+//!
+//! ```text
+//! {"method":"POST","path":"/v1/messages","body_hash":"…","request":{…},
+//!  "status":200,"headers":[["content-type","text/event-stream"]],
+//!  "chunks":[{"ms":412,"text":"event: message_start\n…"},{"ms":415,"hex":"f09f"}]}
+//! ```
+//!
+//! Chunks keep the upstream's original boundaries; `ms` is the offset from
+//! the response headers. Chunks that aren't valid UTF-8 on their own (a
+//! multi-byte character split across reads) are stored as hex.
+//!
+//! # Matching
+//!
+//! Requests are matched on method, path and a hash of the body the proxy
+//! would send (after transformers, before translation). Each recorded
+//! exchange is served once, in order; when no exact match is left the next
+//! unused exchange for the same endpoint is used, then exact matches repeat.
+
+use crate::config::{Cassette as CassetteConfig, CassetteMode};
+use anyhow::Context;
+use bytes::Bytes;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Response headers that no longer describe the recorded (decoded) bytes
+const SKIPPED_HEADERS: &[&str] = &["content-length", "content-encoding", "transfer-encoding"];
+
+/// Identifies a request for recording and matching
+#[derive(Debug, Clone)]
+pub struct RequestKey {
+    pub method: String,
+    pub path: String,
+    pub body_hash: String,
+}
+
+impl RequestKey {
+    pub fn new(method: &str, path: &str, body: &[u8]) -> Self {
+        let hash = Sha256::digest(body);
+        Self {
+            method: method.to_string(),
+            path: path.to_string(),
+            body_hash: hash[..16].iter().map(|b| format!("{:02x}", b)).collect(),
+        }
+    }
+}
+
+/// A response chunk with its arrival offset
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Chunk {
+    /// Milliseconds after the response headers arrived
+    pub ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hex: Option<String>,
+}
+
+impl Chunk {
+    fn new(ms: u64, bytes: &[u8]) -> Self {
+        match std::str::from_utf8(bytes) {
+            Ok(text) => Self {
+                ms,
+                text: Some(text.to_string()),
+                hex: None,
+            },
+            Err(_) => Self {
+                ms,
+                text: None,
+                hex: Some(bytes.iter().map(|b| format!("{:02x}", b)).collect()),
+            },
+        }
+    }
+
+    fn bytes(&self) -> Bytes {
+        if let Some(ref text) = self.text {
+            return Bytes::from(text.clone());
+        }
+        let hex = self.hex.as_deref().unwrap_or_default();
+        let decoded: Vec<u8> = (0..hex.len() / 2)
+            .filter_map(|i| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok())
+            .collect();
+        Bytes::from(decoded)
+    }
+}
+
+/// One recorded upstream exchange
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+    pub method: String,
+    pub path: String,
+    pub body_hash: String,
+    /// Request body (JSON when parseable, for reading cassettes by hand)
+    pub request: serde_json::Value,
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub chunks: Vec<Chunk>,
+}
+
+/// Replay bookkeeping
+#[derive(Default)]
+struct ReplayState {
+    interactions: Vec<Interaction>,
+    used: Vec<bool>,
+}
+
+/// Cassette recorder/player shared across requests
+pub struct Cassette {
+    mode: CassetteMode,
+    path: PathBuf,
+    realtime: bool,
+    replay: Mutex<ReplayState>,
+    /// Serializes appends so concurrent exchanges don't interleave lines
+    write_lock: Mutex<()>,
+}
+
+impl Cassette {
+    /// Create from config; returns None when cassettes are off
+    ///
+    /// Replay mode loads the whole cassette up front and fails if it can't
+    /// be read, since silently proxying to the real provider would defeat
+    /// the point of replaying.
+    pub fn from_config(config: &CassetteConfig) -> anyhow::Result<Option<Arc<Self>>> {
+        let replay = match config.mode {
+            CassetteMode::Off => return Ok(None),
+            CassetteMode::Record => {
+                if let Some(parent) = config.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                    std::fs::create_dir_all(parent).with_context(|| {
+                        format!("Failed to create cassette directory {}", parent.display())
+                    })?;
+                }
+                tracing::info!("Recording upstream traffic to {}", config.path.display());
+                ReplayState::default()
+            }
+            CassetteMode::Replay => {
+                let content = std::fs::read_to_string(&config.path).with_context(|| {
+                    format!("Failed to read cassette {}", config.path.display())
+                })?;
+                let interactions = parse_cassette(&content)?;
+                tracing::info!(
+                    "Replaying {} exchange(s) from {}",
+                    interactions.len(),
+                    config.path.display()
+                );
+                ReplayState {
+                    used: vec![false; interactions.len()],
+                    interactions,
+                }
+            }
+        };
+
+        Ok(Some(Arc::new(Self {
+            mode: config.mode,
+            path: config.path.clone(),
+            realtime: config.realtime,
+            replay: Mutex::new(replay),
+            write_lock: Mutex::new(()),
+        })))
+    }
+
+    pub fn is_replay(&self) -> bool {
+        self.mode == CassetteMode::Replay
+    }
+
+    pub fn is_record(&self) -> bool {
+        self.mode == CassetteMode::Record
+    }
+
+    /// Build a response for `key` from the cassette (None = no match)
+    pub fn replay(&self, key: &RequestKey) -> Option<reqwest::Response> {
+        let interaction = self.next_match(key)?;
+        let realtime = self.realtime;
+
+        let mut builder = axum::http::Response::builder().status(interaction.status);
+        for (name, value) in &interaction.headers {
+            builder = builder.header(name, value);
+        }
+
+        let chunks = interaction.chunks;
+        let stream = futures::stream::iter(chunks.into_iter().scan(0u64, |last_ms, chunk| {
+            let wait = chunk.ms.saturating_sub(*last_ms);
+            *last_ms = chunk.ms;
+            Some((wait, chunk.bytes()))
+        }))
+        .then(move |(wait, bytes)| async move {
+            if realtime && wait > 0 {
+                tokio::time::sleep(Duration::from_millis(wait)).await;
+            }
+            Ok::<_, std::io::Error>(bytes)
+        });
+
+        builder
+            .body(reqwest::Body::wrap_stream(stream))
+            .ok()
+            .map(reqwest::Response::from)
+    }
+
+    fn next_match(&self, key: &RequestKey) -> Option<Interaction> {
+        let mut state = self.replay.lock().ok()?;
+        let same_endpoint = |i: &Interaction| i.method == key.method && i.path == key.path;
+        let exact = |i: &Interaction| same_endpoint(i) && i.body_hash == key.body_hash;
+
+        let unused = |pred: &dyn Fn(&Interaction) -> bool| {
+            state
+                .interactions
+                .iter()
+                .zip(&state.used)
+                .position(|(i, used)| !used && pred(i))
+        };
+
+        let idx = unused(&exact)
+            .or_else(|| unused(&same_endpoint))
+            .or_else(|| state.interactions.iter().position(exact))?;
+
+        state.used[idx] = true;
+        Some(state.interactions[idx].clone())
+    }
+
+    /// Tee an upstream response into the cassette as it streams
+    ///
+    /// The exchange is appended once the body has been fully read (or the
+    /// client goes away), so partial streams are still captured.
+    pub fn record(
+        self: &Arc<Self>,
+        key: RequestKey,
+        request_body: &[u8],
+        response: reqwest::Response,
+    ) -> reqwest::Response {
+        let started = Instant::now();
+        let status = response.status();
+        let headers = response.headers().clone();
+
+        let recording = Recording {
+            cassette: Arc::clone(self),
+            interaction: Some(Interaction {
+                method: key.method,
+                path: key.path,
+                body_hash: key.body_hash,
+                request: serde_json::from_slice(request_body).unwrap_or_else(|_| {
+                    serde_json::Value::String(String::from_utf8_lossy(request_body).into_owned())
+                }),
+                status: status.as_u16(),
+                headers: headers
+                    .iter()
+                    .filter(|(name, _)| !SKIPPED_HEADERS.contains(&name.as_str()))
+                    .filter_map(|(name, value)| {
+                        Some((name.to_string(), value.to_str().ok()?.to_string()))
+                    })
+                    .collect(),
+                chunks: Vec::new(),
+            }),
+        };
+
+        let stream = response
+            .bytes_stream()
+            .scan(recording, move |recording, chunk| {
+                if let (Ok(bytes), Some(interaction)) = (&chunk, recording.interaction.as_mut()) {
+                    let ms = started.elapsed().as_millis() as u64;
+                    interaction.chunks.push(Chunk::new(ms, bytes));
+                }
+                futures::future::ready(Some(chunk))
+            });
+
+        let mut builder = axum::http::Response::builder().status(status);
+        for (name, value) in headers.iter() {
+            if !SKIPPED_HEADERS.contains(&name.as_str()) {
+                builder = builder.header(name, value);
+            }
+        }
+        match builder.body(reqwest::Body::wrap_stream(stream)) {
+            Ok(response) => reqwest::Response::from(response),
+            Err(e) => {
+                // Headers came from a valid response, so this shouldn't happen
+                tracing::warn!("Failed to rebuild response for recording: {}", e);
+                reqwest::Response::from(axum::http::Response::new(reqwest::Body::from(Vec::new())))
+            }
+        }
+    }
+
+    fn append(&self, interaction: &Interaction) {
+        let _guard = self.write_lock.lock();
+        let result = serde_json::to_string(interaction)
+            .map_err(anyhow::Error::from)
+            .and_then(|line| {
+                let mut file = std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&self.path)?;
+                writeln!(file, "{}", line)?;
+                Ok(())
+            });
+        match result {
+            Ok(()) => tracing::debug!(
+                "Recorded {} {} ({} chunks) to cassette",
+                interaction.method,
+                interaction.path,
+                interaction.chunks.len()
+            ),
+            Err(e) => tracing::warn!("Failed to write cassette {}: {}", self.path.display(), e),
+        }
+    }
+}
+
+/// In-progress recording, written to the cassette when the body stream drops
+struct Recording {
+    cassette: Arc<Cassette>,
+    interaction: Option<Interaction>,
+}
+
+impl Drop for Recording {
+    fn drop(&mut self) {
+        if let Some(interaction) = self.interaction.take() {
+            self.cassette.append(&interaction);
+        }
+    }
+}
+
+/// Parse a JSON Lines cassette (blank lines ignored)
+fn parse_cassette(content: &str) -> anyhow::Result<Vec<Interaction>> {
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(idx, line)| {
+            serde_json::from_str(line)
+                .with_context(|| format!("Invalid cassette entry on line {}", idx + 1))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn interaction(path: &str, body: &[u8], text: &str) -> Interaction {
+        let key = RequestKey::new("POST", path, body);
+        Interaction {
+            method: key.method,
+            path: key.path,
+            body_hash: key.body_hash,
+            request: serde_json::Value::Null,
+            status: 200,
+            headers: vec![("content-type".to_string(), "text/event-stream".to_string())],
+            chunks: vec![Chunk::new(0, text.as_bytes())],
+        }
+    }
+
+    fn player(interactions: Vec<Interaction>) -> Cassette {
+        Cassette {
+            mode: CassetteMode::Replay,
+            path: PathBuf::new(),
+            realtime: false,
+            replay: Mutex::new(ReplayState {
+                used: vec![false; interactions.len()],
+                interactions,
+            }),
+            write_lock: Mutex::new(()),
+        }
+    }
+
+    #[test]
+    fn test_chunk_roundtrip_text_and_hex() {
+        let text = Chunk::new(5, b"data: {}\n\n");
+        assert_eq!(text.text.as_deref(), Some("data: {}\n\n"));
+        assert_eq!(text.bytes(), Bytes::from_static(b"data: {}\n\n"));
+
+        // Half of a 4-byte emoji is not valid UTF-8 on its own
+        let split = Chunk::new(6, &[0xf0, 0x9f]);
+        assert_eq!(split.hex.as_deref(), Some("f09f"));
+        assert_eq!(split.bytes(), Bytes::from_static(&[0xf0, 0x9f]));
+    }
+
+    #[test]
+    fn test_match_prefers_exact_body_then_endpoint_order() {
+        let cassette = player(vec![
+            interaction("/v1/messages", b"first", "one"),
+            interaction("/v1/messages", b"second", "two"),
+        ]);
+
+        // Exact body match, out of order
+        let m = cassette.next_match(&RequestKey::new("POST", "/v1/messages", b"second"));
+        assert_eq!(m.unwrap().chunks[0].text.as_deref(), Some("two"));
+
+        // Unknown body: next unused exchange for the endpoint
+        let m = cassette.next_match(&RequestKey::new("POST", "/v1/messages", b"other"));
+        assert_eq!(m.unwrap().chunks[0].text.as_deref(), Some("one"));
+
+        // Everything used: exact matches repeat, others miss
+        let m = cassette.next_match(&RequestKey::new("POST", "/v1/messages", b"first"));
+        assert_eq!(m.unwrap().chunks[0].text.as_deref(), Some("one"));
+        assert!(cassette
+            .next_match(&RequestKey::new("POST", "/v1/messages", b"other"))
+            .is_none());
+        assert!(cassette
+            .next_match(&RequestKey::new("POST", "/v1/chat/completions", b"first"))
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_replay_builds_response() {
+        let cassette = player(vec![interaction("/v1/messages", b"{}", "event: ping\n\n")]);
+        let response = cassette
+            .replay(&RequestKey::new("POST", "/v1/messages", b"{}"))
+            .unwrap();
+
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.headers()["content-type"], "text/event-stream");
+        assert_eq!(response.text().await.unwrap(), "event: ping\n\n");
+    }
+
+    #[tokio::test]
+    async fn test_record_then_replay_file() {
+        let path =
+            std::env::temp_dir().join(format!("aspy-cassette-test-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let config = CassetteConfig {
+            mode: CassetteMode::Record,
+            path: path.clone(),
+            realtime: false,
+        };
+        let recorder = Cassette::from_config(&config).unwrap().unwrap();
+
+        let upstream = axum::http::Response::builder()
+            .status(200)
+            .header("content-type", "application/json")
+            .header("content-length", "11")
+            .body(reqwest::Body::from("{\"ok\":true}"))
+            .unwrap();
+        let key = RequestKey::new("POST", "/v1/messages", b"{\"a\":1}");
+        let response = recorder.record(key, b"{\"a\":1}", reqwest::Response::from(upstream));
+        assert_eq!(response.text().await.unwrap(), "{\"ok\":true}");
+
+        let player = Cassette::from_config(&CassetteConfig {
+            mode: CassetteMode::Replay,
+            ..config
+        })
+        .unwrap()
+        .unwrap();
+        let replayed = player
+            .replay(&RequestKey::new("POST", "/v1/messages", b"{\"a\":1}"))
+            .unwrap();
+        assert!(replayed.headers().get("content-length").is_none());
+        assert_eq!(replayed.text().await.unwrap(), "{\"ok\":true}");
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_parse_cassette_reports_bad_line() {
+        let good = serde_json::to_string(&interaction("/v1/messages", b"", "x")).unwrap();
+        let content = format!("{}\n\n{}\nnot json\n", good, good);
+        let err = parse_cassette(&content).unwrap_err();
+        assert!(err.to_string().contains("line 4"));
+        assert_eq!(parse_cassette(&format!("{}\n", good)).unwrap().len(), 1);
+    }
+}
@@ -272,13 +272,13 @@ pub fn is_count_tokens_path(path: &str) -> bool {
 
 /// Generate a synthetic count_tokens response
 ///
-/// Returns a minimal valid response for providers that don't support count_tokens
-/// (e.g., OpenAI-compatible APIs). The response format matches Anthropic's schema:
-/// `{"input_tokens": 0}`
+/// Used for providers that don't support count_tokens (e.g., OpenAI-compatible
+/// APIs). The count comes from the local token counter; the response format
+/// matches Anthropic's schema: `{"input_tokens": N}`
 ///
 /// This is used when a provider's `count_tokens` handling is set to `Synthetic`.
-pub fn synthetic_response() -> Bytes {
-    Bytes::from_static(b"{\"input_tokens\":0}")
+pub fn synthetic_response(input_tokens: u64) -> Bytes {
+    Bytes::from(serde_json::json!({ "input_tokens": input_tokens }).to_string())
 }
 
 #[cfg(test)]
@@ -387,11 +387,11 @@ mod tests {
 
     #[test]
     fn test_synthetic_response_is_valid_json() {
-        let response = super::synthetic_response();
+        let response = super::synthetic_response(1234);
         let json: serde_json::Value =
             serde_json::from_slice(&response).expect("synthetic response should be valid JSON");
 
         // Verify it has the expected structure
-        assert_eq!(json["input_tokens"], 0);
+        assert_eq!(json["input_tokens"], 1234);
     }
 }
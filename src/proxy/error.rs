@@ -12,6 +12,16 @@ pub(crate) enum ProxyError {
     BodyRead(String),
     Upstream(String),
     ResponseBuild(String),
+    /// Rejected by aspy itself (budget, policy) - sent as an Anthropic-format error
+    Rejected {
+        status: StatusCode,
+        message: String,
+    },
+    /// Over a per-client rate limit - sent as an Anthropic-format 429
+    RateLimited {
+        message: String,
+        retry_after_secs: u64,
+    },
 }
 
 impl IntoResponse for ProxyError {
@@ -20,6 +30,22 @@ impl IntoResponse for ProxyError {
             ProxyError::BodyRead(msg) => (StatusCode::BAD_REQUEST, msg),
             ProxyError::Upstream(msg) => (StatusCode::BAD_GATEWAY, msg),
             ProxyError::ResponseBuild(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
+            ProxyError::Rejected { status, message } => {
+                tracing::warn!("Request rejected: {} - {}", status, message);
+                return anthropic_error_response(status, &message);
+            }
+            ProxyError::RateLimited {
+                message,
+                retry_after_secs,
+            } => {
+                tracing::info!("Request rate limited: {}", message);
+                let mut response =
+                    anthropic_error_response(StatusCode::TOO_MANY_REQUESTS, &message);
+                response
+                    .headers_mut()
+                    .insert("retry-after", retry_after_secs.into());
+                return response;
+            }
         };
 
         tracing::error!("Proxy error: {} - {}", status, message);
@@ -30,3 +56,77 @@ impl IntoResponse for ProxyError {
             .unwrap_or_else(|_| Response::new(Body::from("Internal error building error response")))
     }
 }
+
+/// Anthropic error `type` for an HTTP status
+fn anthropic_error_type(status: StatusCode) -> &'static str {
+    match status.as_u16() {
+        400 => "invalid_request_error",
+        401 => "authentication_error",
+        402 => "billing_error",
+        403 => "permission_error",
+        404 => "not_found_error",
+        413 => "request_too_large",
+        429 => "rate_limit_error",
+        529 => "overloaded_error",
+        _ => "api_error",
+    }
+}
+
+/// Build an error response in Anthropic's format, which clients like
+/// Claude Code know how to display
+pub(crate) fn anthropic_error_response(status: StatusCode, message: &str) -> Response<Body> {
+    let body = serde_json::json!({
+        "type": "error",
+        "error": {
+            "type": anthropic_error_type(status),
+            "message": message,
+        }
+    });
+
+    Response::builder()
+        .status(status)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap_or_else(|_| Response::new(Body::from("Internal error building error response")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_rejected_is_anthropic_error() {
+        let response = ProxyError::Rejected {
+            status: StatusCode::PAYMENT_REQUIRED,
+            message: "budget exhausted".to_string(),
+        }
+        .into_response();
+        assert_eq!(response.status(), StatusCode::PAYMENT_REQUIRED);
+        assert_eq!(response.headers()["content-type"], "application/json");
+
+        let bytes = axum::body::to_bytes(response.into_body(), 1024)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["type"], "error");
+        assert_eq!(body["error"]["type"], "billing_error");
+        assert_eq!(body["error"]["message"], "budget exhausted");
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_sets_retry_after() {
+        let response = ProxyError::RateLimited {
+            message: "too many requests".to_string(),
+            retry_after_secs: 12,
+        }
+        .into_response();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()["retry-after"], "12");
+
+        let bytes = axum::body::to_bytes(response.into_body(), 1024)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["error"]["type"], "rate_limit_error");
+    }
+}
@@ -0,0 +1,186 @@
+//! Provider health tracking for failover chains
+//!
+//! Providers can declare an ordered `fallback` list. When the provider
+//! serving a request fails (connection error or a configured status code
+//! like 529 overloaded), the proxy re-routes the request to the next
+//! healthy provider in the chain.
+//!
+//! # Health Model
+//!
+//! ```text
+//! success            → consecutive_failures = 0, cooldown cleared
+//! failure            → consecutive_failures += 1
+//! failures ≥ threshold → cooldown for cooldown_secs (provider skipped)
+//! cooldown expires   → one trial request; a failure re-enters cooldown
+//! ```
+//!
+//! The trial-after-cooldown behaviour is a simple half-open circuit breaker:
+//! a provider that is still broken costs one failed request per cooldown
+//! window rather than one per request.
+
+use crate::config::Failover;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Health state for a single provider
+#[derive(Debug, Default)]
+struct HealthEntry {
+    /// Failures since the last success
+    consecutive_failures: u32,
+    /// Provider is skipped until this instant
+    cooldown_until: Option<Instant>,
+}
+
+/// Shared health tracker for all providers
+pub struct ProviderHealth {
+    config: Failover,
+    entries: Mutex<HashMap<String, HealthEntry>>,
+}
+
+impl ProviderHealth {
+    /// Create a new health tracker
+    pub fn new(config: Failover) -> Self {
+        Self {
+            config,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Create a shared instance wrapped in Arc
+    pub fn new_shared(config: Failover) -> Arc<Self> {
+        Arc::new(Self::new(config))
+    }
+
+    /// Whether an upstream status code should trigger failover
+    pub fn is_failover_status(&self, status: u16) -> bool {
+        self.config.status_codes.contains(&status)
+    }
+
+    /// Check if a provider is currently accepting requests (not in cooldown)
+    pub fn is_available(&self, provider_id: &str) -> bool {
+        let Ok(entries) = self.entries.lock() else {
+            // Poisoned lock - fail open rather than blocking all traffic
+            return true;
+        };
+
+        entries
+            .get(provider_id)
+            .and_then(|e| e.cooldown_until)
+            .is_none_or(|until| Instant::now() >= until)
+    }
+
+    /// Find the first available provider in `chain` starting at `start`
+    pub fn next_available(&self, chain: &[&str], start: usize) -> Option<usize> {
+        (start..chain.len()).find(|&idx| self.is_available(chain[idx]))
+    }
+
+    /// Record a successful response from a provider
+    pub fn record_success(&self, provider_id: &str) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.remove(provider_id);
+        }
+    }
+
+    /// Record a failed attempt against a provider
+    ///
+    /// Returns the cooldown duration if this failure put the provider
+    /// into cooldown.
+    pub fn record_failure(&self, provider_id: &str) -> Option<Duration> {
+        let Ok(mut entries) = self.entries.lock() else {
+            return None;
+        };
+
+        let entry = entries.entry(provider_id.to_string()).or_default();
+        entry.consecutive_failures = entry.consecutive_failures.saturating_add(1);
+
+        if entry.consecutive_failures >= self.config.failure_threshold {
+            let cooldown = Duration::from_secs(self.config.cooldown_secs);
+            entry.cooldown_until = Some(Instant::now() + cooldown);
+            tracing::warn!(
+                provider = provider_id,
+                failures = entry.consecutive_failures,
+                cooldown_secs = self.config.cooldown_secs,
+                "Provider marked unhealthy"
+            );
+            Some(cooldown)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn health(threshold: u32, cooldown_secs: u64) -> ProviderHealth {
+        ProviderHealth::new(Failover {
+            failure_threshold: threshold,
+            cooldown_secs,
+            status_codes: vec![503, 529],
+        })
+    }
+
+    #[test]
+    fn test_failover_status_codes() {
+        let health = health(3, 30);
+        assert!(health.is_failover_status(529));
+        assert!(health.is_failover_status(503));
+        assert!(!health.is_failover_status(400));
+        assert!(!health.is_failover_status(200));
+    }
+
+    #[test]
+    fn test_cooldown_after_threshold() {
+        let health = health(2, 60);
+
+        assert!(health.record_failure("primary").is_none());
+        assert!(health.is_available("primary"));
+
+        assert_eq!(
+            health.record_failure("primary"),
+            Some(Duration::from_secs(60))
+        );
+        assert!(!health.is_available("primary"));
+
+        // Other providers are unaffected
+        assert!(health.is_available("backup"));
+    }
+
+    #[test]
+    fn test_success_resets_failures() {
+        let health = health(2, 60);
+
+        health.record_failure("primary");
+        health.record_success("primary");
+        assert!(health.record_failure("primary").is_none());
+        assert!(health.is_available("primary"));
+    }
+
+    #[test]
+    fn test_expired_cooldown_allows_trial() {
+        let health = health(1, 0);
+
+        assert!(health.record_failure("primary").is_some());
+        // Zero-length cooldown expires immediately
+        assert!(health.is_available("primary"));
+        // Still over threshold: the trial failure re-enters cooldown
+        assert!(health.record_failure("primary").is_some());
+    }
+
+    #[test]
+    fn test_next_available_skips_cooling_providers() {
+        let health = health(1, 60);
+        let chain = ["primary", "secondary", "tertiary"];
+
+        assert_eq!(health.next_available(&chain, 0), Some(0));
+
+        health.record_failure("primary");
+        assert_eq!(health.next_available(&chain, 0), Some(1));
+
+        health.record_failure("secondary");
+        assert_eq!(health.next_available(&chain, 0), Some(2));
+        assert_eq!(health.next_available(&chain, 3), None);
+    }
+}
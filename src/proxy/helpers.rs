@@ -45,6 +45,70 @@ pub(crate) fn extract_user_prompt(body: &serde_json::Value) -> Option<String> {
     }
 }
 
+/// Text of the first user message (the prompt that opened the conversation)
+///
+/// Text blocks are joined with newlines; tool_result and other blocks are
+/// ignored.
+pub(crate) fn first_user_text(body: &serde_json::Value) -> Option<String> {
+    let first_user = body
+        .get("messages")?
+        .as_array()?
+        .iter()
+        .find(|msg| msg.get("role").and_then(|r| r.as_str()) == Some("user"))?;
+
+    match first_user.get("content")? {
+        serde_json::Value::String(s) => Some(s.clone()),
+        serde_json::Value::Array(parts) => {
+            let text: Vec<&str> = parts
+                .iter()
+                .filter(|p| p.get("type").and_then(|t| t.as_str()) == Some("text"))
+                .filter_map(|p| p.get("text")?.as_str())
+                .collect();
+            (!text.is_empty()).then(|| text.join("\n"))
+        }
+        _ => None,
+    }
+}
+
+/// Names of the tools answered by tool_result blocks in the last user message
+///
+/// tool_result blocks only carry a `tool_use_id`, so names are looked up
+/// from the tool_use blocks in earlier assistant messages.
+pub(crate) fn last_tool_result_names(body: &serde_json::Value) -> Vec<String> {
+    let Some(messages) = body.get("messages").and_then(|m| m.as_array()) else {
+        return Vec::new();
+    };
+    let blocks = |msg: &serde_json::Value| -> Vec<serde_json::Value> {
+        msg.get("content")
+            .and_then(|c| c.as_array())
+            .cloned()
+            .unwrap_or_default()
+    };
+
+    let Some(last_user) = messages
+        .iter()
+        .rev()
+        .find(|m| m.get("role").and_then(|r| r.as_str()) == Some("user"))
+    else {
+        return Vec::new();
+    };
+
+    blocks(last_user)
+        .iter()
+        .filter(|b| b.get("type").and_then(|t| t.as_str()) == Some("tool_result"))
+        .filter_map(|b| b.get("tool_use_id").and_then(|id| id.as_str()))
+        .filter_map(|id| {
+            messages.iter().flat_map(blocks).find_map(|b| {
+                let is_match = b.get("type").and_then(|t| t.as_str()) == Some("tool_use")
+                    && b.get("id").and_then(|i| i.as_str()) == Some(id);
+                is_match
+                    .then(|| b.get("name").and_then(|n| n.as_str()).map(str::to_string))
+                    .flatten()
+            })
+        })
+        .collect()
+}
+
 /// Check if a header is an authentication header that should be stripped
 /// when transforming auth for a different provider
 pub(crate) fn is_auth_header(name: &str) -> bool {
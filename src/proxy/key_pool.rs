@@ -0,0 +1,436 @@
+//! API key pools with rate-limit-aware selection
+//!
+//! A provider's auth config can list several keys under `pool`. Each
+//! forwarded request picks one key, and every response's rate limit headers
+//! are recorded against the key that sent it.
+//!
+//! # Selection
+//!
+//! ```text
+//! round_robin     → next key in rotation that isn't parked
+//! most_remaining  → key with the most remaining tokens (then requests);
+//!                   keys with no data yet count as full
+//! ```
+//!
+//! # Parking
+//!
+//! A key is parked when a response shows one of its buckets exhausted
+//! (`remaining = 0`) or the key gets a 429. It stays out of rotation until
+//! the reset time from the headers (or `retry-after`). If every key is
+//! parked, the one that resets first is used anyway.
+
+use super::retry;
+use crate::config::{ClientsConfig, PoolKey, PoolStrategy};
+use chrono::{DateTime, Utc};
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How long to park a key after a 429 that carried no reset information
+const DEFAULT_PARK: Duration = Duration::from_secs(60);
+
+/// Runtime state for one pooled key
+#[derive(Debug, Default)]
+struct KeyState {
+    /// Requests sent with this key
+    requests: u64,
+    /// 429 responses received with this key
+    rate_limited: u64,
+    /// Last seen `anthropic-ratelimit-requests-remaining`
+    requests_remaining: Option<u32>,
+    /// Last seen `anthropic-ratelimit-tokens-remaining`
+    tokens_remaining: Option<u32>,
+    /// Key is skipped until this time
+    parked_until: Option<DateTime<Utc>>,
+}
+
+impl KeyState {
+    fn is_parked(&self, now: DateTime<Utc>) -> bool {
+        self.parked_until.is_some_and(|until| until > now)
+    }
+
+    /// Remaining quota for `most_remaining` (unknown = untouched = full)
+    fn remaining_score(&self) -> (u32, u32) {
+        (
+            self.tokens_remaining.unwrap_or(u32::MAX),
+            self.requests_remaining.unwrap_or(u32::MAX),
+        )
+    }
+}
+
+/// Keys for a single provider
+struct KeyPool {
+    strategy: PoolStrategy,
+    keys: Vec<PoolKey>,
+    state: Mutex<Vec<KeyState>>,
+    /// Rotation cursor (round robin order and most_remaining tie-breaks)
+    cursor: AtomicUsize,
+}
+
+impl KeyPool {
+    /// Update a key's quota and parking state from a response
+    fn record(
+        &self,
+        provider_id: &str,
+        index: usize,
+        status: u16,
+        headers: &HeaderMap,
+        captured: &crate::parser::models::CapturedHeaders,
+        now: DateTime<Utc>,
+    ) {
+        let Ok(mut states) = self.state.lock() else {
+            return;
+        };
+        let Some(state) = states.get_mut(index) else {
+            return;
+        };
+
+        if captured.requests_remaining.is_some() {
+            state.requests_remaining = captured.requests_remaining;
+        }
+        if captured.tokens_remaining.is_some() {
+            state.tokens_remaining = captured.tokens_remaining;
+        }
+
+        let rate_limited = status == 429;
+        if rate_limited {
+            state.rate_limited += 1;
+        }
+
+        let exhausted =
+            captured.requests_remaining == Some(0) || captured.tokens_remaining == Some(0);
+        if rate_limited || exhausted {
+            let delay = match retry::server_delay(headers, captured, now) {
+                Some((delay, _)) => delay,
+                None if rate_limited => DEFAULT_PARK,
+                None => return,
+            };
+            let until = now + chrono::Duration::from_std(delay).unwrap_or_default();
+            state.parked_until = Some(until);
+            tracing::info!(
+                provider = provider_id,
+                key = %self.keys[index].label(index),
+                until = %until.to_rfc3339(),
+                "API key parked until rate limit reset"
+            );
+        } else if status < 400 {
+            state.parked_until = None;
+        }
+    }
+}
+
+/// A key chosen for one request
+#[derive(Debug, Clone)]
+pub struct SelectedKey {
+    /// Index into the provider's pool (pass back to `record_response`)
+    pub index: usize,
+    /// Label for logs (never the key itself)
+    pub label: String,
+    /// Resolved key value
+    pub key: String,
+}
+
+/// Per-key usage reported by `/api/stats`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyUsage {
+    pub provider: String,
+    pub key: String,
+    pub requests: u64,
+    pub rate_limited: u64,
+    pub requests_remaining: Option<u32>,
+    pub tokens_remaining: Option<u32>,
+    /// When the key returns to rotation (ISO 8601), if parked
+    pub parked_until: Option<String>,
+}
+
+/// Key pools for all providers, keyed by provider ID
+pub struct KeyPools {
+    pools: HashMap<String, KeyPool>,
+}
+
+impl KeyPools {
+    /// Build pools from every provider whose auth config has a `pool`
+    pub fn from_config(clients: &ClientsConfig) -> Self {
+        let pools = clients
+            .providers
+            .iter()
+            .filter_map(|(id, provider)| {
+                let auth = provider.auth.as_ref().filter(|a| !a.pool.is_empty())?;
+                tracing::info!(
+                    provider = %id,
+                    keys = auth.pool.len(),
+                    strategy = auth.pool_strategy.as_str(),
+                    "Loaded API key pool"
+                );
+                Some((
+                    id.clone(),
+                    KeyPool {
+                        strategy: auth.pool_strategy,
+                        keys: auth.pool.clone(),
+                        state: Mutex::new(auth.pool.iter().map(|_| KeyState::default()).collect()),
+                        cursor: AtomicUsize::new(0),
+                    },
+                ))
+            })
+            .collect();
+
+        Self { pools }
+    }
+
+    /// Create a shared instance wrapped in Arc
+    pub fn new_shared(clients: &ClientsConfig) -> Arc<Self> {
+        Arc::new(Self::from_config(clients))
+    }
+
+    /// Pick a key for a request to `provider_id`
+    ///
+    /// Returns None if the provider has no pool or none of its keys resolve.
+    pub fn select(&self, provider_id: &str) -> Option<SelectedKey> {
+        let pool = self.pools.get(provider_id)?;
+        let mut states = pool.state.lock().ok()?;
+        let now = Utc::now();
+
+        let resolved: Vec<(usize, String)> = pool
+            .keys
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, k)| k.resolve().map(|key| (idx, key)))
+            .collect();
+        if resolved.is_empty() {
+            tracing::warn!(
+                provider = provider_id,
+                "No key in pool resolved - check key_env is set"
+            );
+            return None;
+        }
+
+        // Rotation order starting at the cursor
+        let start = pool.cursor.fetch_add(1, Ordering::Relaxed) % resolved.len();
+        let rotation = resolved.iter().cycle().skip(start).take(resolved.len());
+
+        let chosen = match pool.strategy {
+            PoolStrategy::RoundRobin => rotation
+                .clone()
+                .find(|(idx, _)| !states[*idx].is_parked(now)),
+            PoolStrategy::MostRemaining => rotation
+                .clone()
+                .filter(|(idx, _)| !states[*idx].is_parked(now))
+                .fold(
+                    None,
+                    |best: Option<&(usize, String)>, candidate| match best {
+                        Some(b)
+                            if states[b.0].remaining_score()
+                                >= states[candidate.0].remaining_score() =>
+                        {
+                            Some(b)
+                        }
+                        _ => Some(candidate),
+                    },
+                ),
+        }
+        .or_else(|| {
+            // Everything is parked: use whichever key resets first
+            tracing::debug!(provider = provider_id, "All pooled keys parked");
+            rotation.min_by_key(|(idx, _)| states[*idx].parked_until)
+        })?;
+
+        let (index, key) = chosen.clone();
+        states[index].requests += 1;
+
+        Some(SelectedKey {
+            index,
+            label: pool.keys[index].label(index),
+            key,
+        })
+    }
+
+    /// Whether the provider has a pooled key available right now
+    pub fn has_available(&self, provider_id: &str) -> bool {
+        let Some(pool) = self.pools.get(provider_id) else {
+            return false;
+        };
+        let Ok(states) = pool.state.lock() else {
+            return false;
+        };
+        let now = Utc::now();
+        pool.keys
+            .iter()
+            .zip(states.iter())
+            .any(|(key, state)| !state.is_parked(now) && key.resolve().is_some())
+    }
+
+    /// Record an upstream response against the key that sent it
+    pub fn record_response(
+        &self,
+        provider_id: &str,
+        index: usize,
+        status: u16,
+        headers: &HeaderMap,
+    ) {
+        let Some(pool) = self.pools.get(provider_id) else {
+            return;
+        };
+        let captured = super::helpers::extract_response_headers(headers);
+        pool.record(provider_id, index, status, headers, &captured, Utc::now());
+    }
+
+    /// Per-key usage for all pools (sorted by provider, then pool order)
+    pub fn usage(&self) -> Vec<KeyUsage> {
+        let mut providers: Vec<_> = self.pools.keys().collect();
+        providers.sort();
+
+        let now = Utc::now();
+        let mut usage = Vec::new();
+        for provider in providers {
+            let pool = &self.pools[provider];
+            let Ok(states) = pool.state.lock() else {
+                continue;
+            };
+            for (idx, (key, state)) in pool.keys.iter().zip(states.iter()).enumerate() {
+                usage.push(KeyUsage {
+                    provider: provider.clone(),
+                    key: key.label(idx),
+                    requests: state.requests,
+                    rate_limited: state.rate_limited,
+                    requests_remaining: state.requests_remaining,
+                    tokens_remaining: state.tokens_remaining,
+                    parked_until: state
+                        .parked_until
+                        .filter(|_| state.is_parked(now))
+                        .map(|t| t.to_rfc3339()),
+                });
+            }
+        }
+        usage
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{AuthMethod, ProviderAuth, ProviderConfig};
+    use crate::parser::models::CapturedHeaders;
+    use reqwest::header::HeaderValue;
+
+    fn pools(strategy: PoolStrategy) -> KeyPools {
+        let mut provider: ProviderConfig =
+            toml::from_str(r#"base_url = "https://api.anthropic.com""#).unwrap();
+        provider.auth = Some(ProviderAuth {
+            method: AuthMethod::XApiKey,
+            pool: ["a", "b", "c"]
+                .iter()
+                .map(|name| PoolKey {
+                    name: Some(name.to_string()),
+                    key: Some(format!("sk-{}", name)),
+                    key_env: None,
+                })
+                .collect(),
+            pool_strategy: strategy,
+            ..Default::default()
+        });
+        let mut clients = ClientsConfig::default();
+        clients.providers.insert("anthropic".to_string(), provider);
+        KeyPools::from_config(&clients)
+    }
+
+    fn record(pools: &KeyPools, index: usize, status: u16, captured: CapturedHeaders) {
+        pools.pools["anthropic"].record(
+            "anthropic",
+            index,
+            status,
+            &HeaderMap::new(),
+            &captured,
+            Utc::now(),
+        );
+    }
+
+    #[test]
+    fn test_round_robin_rotates() {
+        let pools = pools(PoolStrategy::RoundRobin);
+        let picked: Vec<_> = (0..4)
+            .map(|_| pools.select("anthropic").unwrap().label)
+            .collect();
+        assert_eq!(picked, vec!["a", "b", "c", "a"]);
+        assert!(pools.select("unknown").is_none());
+    }
+
+    #[test]
+    fn test_most_remaining_prefers_quota() {
+        let pools = pools(PoolStrategy::MostRemaining);
+        for (idx, tokens) in [(0, 1000), (1, 50_000), (2, 20_000)] {
+            record(
+                &pools,
+                idx,
+                200,
+                CapturedHeaders {
+                    tokens_remaining: Some(tokens),
+                    ..Default::default()
+                },
+            );
+        }
+        for _ in 0..3 {
+            assert_eq!(pools.select("anthropic").unwrap().key, "sk-b");
+        }
+    }
+
+    #[test]
+    fn test_exhausted_key_parked_until_reset() {
+        let pools = pools(PoolStrategy::RoundRobin);
+        let reset = (Utc::now() + chrono::Duration::seconds(30)).to_rfc3339();
+        record(
+            &pools,
+            0,
+            200,
+            CapturedHeaders {
+                requests_remaining: Some(0),
+                requests_reset: Some(reset),
+                ..Default::default()
+            },
+        );
+
+        let picked: Vec<_> = (0..4)
+            .map(|_| pools.select("anthropic").unwrap().label)
+            .collect();
+        assert!(!picked.contains(&"a".to_string()));
+
+        let usage = pools.usage();
+        assert!(usage[0].parked_until.is_some());
+        assert!(usage[1].parked_until.is_none());
+    }
+
+    #[test]
+    fn test_rate_limited_key_parked_and_counted() {
+        let pools = pools(PoolStrategy::RoundRobin);
+        let mut headers = HeaderMap::new();
+        headers.insert("retry-after", HeaderValue::from_static("20"));
+        pools.record_response("anthropic", 1, 429, &headers);
+
+        let usage = pools.usage();
+        assert_eq!(usage[1].rate_limited, 1);
+        assert!(usage[1].parked_until.is_some());
+        assert!(pools.has_available("anthropic"));
+    }
+
+    #[test]
+    fn test_all_parked_uses_earliest_reset() {
+        let pools = pools(PoolStrategy::RoundRobin);
+        for (idx, secs) in [(0, 60), (1, 10), (2, 30)] {
+            let reset = (Utc::now() + chrono::Duration::seconds(secs)).to_rfc3339();
+            record(
+                &pools,
+                idx,
+                200,
+                CapturedHeaders {
+                    tokens_remaining: Some(0),
+                    tokens_reset: Some(reset),
+                    ..Default::default()
+                },
+            );
+        }
+        assert!(!pools.has_available("anthropic"));
+        assert_eq!(pools.select("anthropic").unwrap().label, "b");
+    }
+}
@@ -8,23 +8,33 @@
 // while accumulating a copy for parsing. This ensures low latency for
 // Claude Code while maintaining full observability.
 
+mod cassette;
 mod error;
+mod failover;
 mod helpers;
+mod key_pool;
+mod rate_limit;
+mod retry;
 mod server;
+mod shadow;
 mod state;
+mod token_counter;
+mod tool_guard;
 
 pub mod api;
 pub mod augmentation;
+pub mod breakpoints;
 pub mod count_tokens;
 pub mod sessions;
 pub mod sse;
+pub mod subagents;
 pub mod transformation;
 pub mod translation;
 
 use error::ProxyError;
 use helpers::{
     extract_request_headers, extract_response_headers, extract_user_id, extract_user_prompt,
-    is_anthropic_header, is_auth_header,
+    is_anthropic_header, is_auth_header, last_tool_result_names,
 };
 use state::ResponseContext;
 
@@ -33,7 +43,7 @@ pub use state::{EventChannels, ProxyState, SharedState};
 
 use std::error::Error as StdError;
 
-use crate::config::{ClientsConfig, CountTokensHandling};
+use crate::config::{ClientsConfig, CountTokensHandling, ProviderAuth, ProviderConfig};
 use crate::events::{generate_id, ProxyEvent, TrackedEvent};
 use crate::parser::models::CapturedHeaders;
 use anyhow::Result;
@@ -41,7 +51,7 @@ use augmentation::{AugmentationContext, StopReason};
 use axum::{
     body::Body,
     extract::State,
-    http::{Request, Response},
+    http::{HeaderMap, Method, Request, Response, StatusCode, Uri},
 };
 use bytes::Bytes;
 use chrono::Utc;
@@ -131,6 +141,458 @@ fn extract_client_routing(
     }
 }
 
+/// A provider candidate for forwarding a request
+struct UpstreamTarget<'a> {
+    /// Provider ID (None for unrouted requests to the default api_url)
+    provider_id: Option<&'a str>,
+    /// Base URL to forward to
+    base_url: &'a str,
+    /// Provider config (None for unrouted requests)
+    provider: Option<&'a ProviderConfig>,
+    /// Auth config to apply when forwarding to this provider
+    auth: Option<&'a ProviderAuth>,
+    /// The client's credentials were meant for another provider, so its auth
+    /// headers are never forwarded here
+    strip_client_auth: bool,
+}
+
+/// Resolve the ordered list of upstream targets for a request
+///
+/// Routed clients get their provider chain (primary first, then fallbacks).
+/// A `routed_provider` chosen by the model router replaces the client's
+/// primary with that provider and its own fallbacks. Unrouted requests have
+/// a single target: the default api_url.
+fn resolve_upstream_chain<'a>(
+    routing: &ClientRouting,
+    clients: &'a ClientsConfig,
+    default_api_url: &'a str,
+    routed_provider: Option<&str>,
+) -> Vec<UpstreamTarget<'a>> {
+    let client_id = routing.client_id.as_deref();
+    // Provider the client's credentials were issued for (None = default api_url)
+    let home_provider = client_id
+        .and_then(|cid| clients.get_client(cid))
+        .map(|client| client.provider.as_str());
+
+    // Routing to the client's own provider keeps the client's fallback list
+    let routed_provider = routed_provider.filter(|pid| {
+        client_id
+            .and_then(|cid| clients.get_client(cid))
+            .is_none_or(|client| client.provider != *pid)
+    });
+
+    // Unknown routed providers fall back to normal client routing
+    let providers = routed_provider
+        .map(|pid| clients.get_provider_chain(pid))
+        .filter(|chain| !chain.is_empty())
+        .or_else(|| client_id.map(|cid| clients.get_client_provider_chain(cid)))
+        .unwrap_or_default();
+
+    let chain: Vec<_> = providers
+        .into_iter()
+        .map(|(provider_id, provider)| UpstreamTarget {
+            provider_id: Some(provider_id),
+            base_url: &provider.base_url,
+            provider: Some(provider),
+            auth: match client_id {
+                Some(cid) => clients.get_provider_auth(cid, provider_id),
+                None => provider.auth.as_ref(),
+            },
+            strip_client_auth: home_provider != Some(provider_id),
+        })
+        .collect();
+
+    if chain.is_empty() {
+        vec![UpstreamTarget {
+            provider_id: None,
+            base_url: default_api_url,
+            provider: None,
+            auth: None,
+            strip_client_auth: false,
+        }]
+    } else {
+        chain
+    }
+}
+
+/// The client's request after transformation, before target-specific preparation
+struct OutgoingRequest<'a> {
+    method: &'a Method,
+    uri: &'a Uri,
+    headers: &'a HeaderMap,
+    /// API path with client prefix stripped
+    api_path: &'a str,
+    /// Request body (already transformed, not yet translated)
+    body: &'a [u8],
+    body_was_transformed: bool,
+}
+
+/// A request ready to send to a specific upstream target
+struct UpstreamRequest {
+    builder: reqwest::RequestBuilder,
+    translation_ctx: translation::TranslationContext,
+    is_messages_endpoint: bool,
+    body_size: usize,
+    /// Index of the pooled API key used (None when the provider has no pool)
+    pool_key: Option<usize>,
+    /// Cache breakpoints placed after translating into Anthropic format
+    cache_breakpoints: Vec<String>,
+}
+
+/// Build the forwarded request for one upstream target
+///
+/// Translation, path resolution, header filtering and auth all depend on the
+/// target provider, so this runs again for every failover attempt.
+fn prepare_upstream_request(
+    state: &ProxyState,
+    outgoing: &OutgoingRequest<'_>,
+    target: &UpstreamTarget<'_>,
+) -> Result<UpstreamRequest, ProxyError> {
+    // Determine target API format based on provider config
+    // If provider expects OpenAI format, translate Anthropic → OpenAI
+    let target_format = target
+        .provider
+        .map(|p| match p.api_format {
+            crate::config::ApiFormat::Anthropic => translation::ApiFormat::Anthropic,
+            crate::config::ApiFormat::Openai => translation::ApiFormat::OpenAI,
+        })
+        .unwrap_or(translation::ApiFormat::Anthropic);
+
+    // Get provider-specific model mapping (if configured)
+    // This takes precedence over global [translation.model_mapping]
+    let provider_model_mapping = target
+        .provider
+        .map(|p| &p.model_mapping)
+        .filter(|m| !m.is_empty())
+        .map(ModelMapping::from_config);
+
+    // Apply translation if enabled, targeting the provider's expected format
+    let (mut translated_body, translation_ctx, translated_path) = state
+        .translation
+        .translate_request_for_target_with_mapping(
+            outgoing.api_path,
+            outgoing.headers,
+            outgoing.body,
+            target_format,
+            provider_model_mapping.as_ref(),
+        )
+        .map_err(|e| ProxyError::BodyRead(format!("Translation failed: {}", e)))?;
+
+    // Get provider's custom api_path if configured (overrides default /v1/messages or /v1/chat/completions)
+    let provider_api_path = target.provider.map(|p| p.effective_api_path());
+
+    // Determine effective API path for forwarding
+    // Priority: provider's custom api_path > translated path > original routing path
+    let effective_api_path = if let Some(custom_path) = provider_api_path {
+        // Provider has custom api_path configured - use it
+        if translation_ctx.needs_response_translation() {
+            // Log translation with custom path
+            if let Ok(translated_json) =
+                serde_json::from_slice::<serde_json::Value>(&translated_body)
+            {
+                let model = translated_json
+                    .get("model")
+                    .and_then(|m| m.as_str())
+                    .unwrap_or("unknown");
+                tracing::info!(
+                    "Translation: {} -> {} | model: {} | path: {} -> {} (custom)",
+                    translation_ctx.client_format,
+                    translation_ctx.backend_format,
+                    model,
+                    outgoing.api_path,
+                    custom_path
+                );
+            }
+        }
+        custom_path.to_string()
+    } else if translation_ctx.needs_response_translation() {
+        // No custom path, but translation happened - use translated path (default /v1/...)
+        if let Ok(translated_json) = serde_json::from_slice::<serde_json::Value>(&translated_body) {
+            let model = translated_json
+                .get("model")
+                .and_then(|m| m.as_str())
+                .unwrap_or("unknown");
+            tracing::info!(
+                "Translation: {} -> {} | model: {} | path: {} -> {}",
+                translation_ctx.client_format,
+                translation_ctx.backend_format,
+                model,
+                outgoing.api_path,
+                translated_path
+            );
+        }
+        translated_path.clone()
+    } else {
+        // No translation, no custom path - use original routing path
+        outgoing.api_path.to_string()
+    };
+
+    // Check if this is a completion endpoint (Anthropic /messages or OpenAI /chat/completions)
+    let is_messages_endpoint = effective_api_path.contains("/messages")
+        || effective_api_path.contains("/chat/completions");
+
+    // Build the forward URL using client routing and translated path
+    let forward_url = format!("{}{}", target.base_url, effective_api_path);
+
+    // Don't pass Anthropic-specific query params (like ?beta=true) to OpenAI targets
+    let forward_url = if target_format == translation::ApiFormat::OpenAI {
+        // Strip query params entirely for OpenAI targets
+        forward_url
+    } else {
+        let query = outgoing.uri.query().unwrap_or("");
+        if query.is_empty() {
+            forward_url
+        } else {
+            format!("{}?{}", forward_url, query)
+        }
+    };
+
+    // Requests translated into Anthropic format skipped the cache optimizer
+    // (it only understands Anthropic bodies), so place breakpoints now
+    let mut cache_breakpoints = Vec::new();
+    if let Some(optimizer) = state.transformation.cache_optimizer() {
+        if state.transformers_config.enabled
+            && translation_ctx.needs_response_translation()
+            && target_format == translation::ApiFormat::Anthropic
+        {
+            if let Ok(mut json) = serde_json::from_slice::<serde_json::Value>(&translated_body) {
+                if let Some(modifications) = optimizer.optimize(&mut json) {
+                    if let Ok(bytes) = serde_json::to_vec(&json) {
+                        translated_body = bytes;
+                        cache_breakpoints = modifications;
+                    }
+                }
+            }
+        }
+    }
+
+    // Final body is the translated body (transformation already happened earlier)
+    let final_body = translated_body;
+    let body_size = final_body.len();
+
+    // Build the forwarded request
+    // With reqwest 0.12, Method types align with axum (both use http 1.0 crate)
+    let mut forward_req = state
+        .client
+        .request(outgoing.method.clone(), &forward_url)
+        .body(final_body);
+
+    // Copy relevant headers with auth transformation
+    let body_changed =
+        outgoing.body_was_transformed || translation_ctx.needs_response_translation();
+    forward_req = copy_forward_headers(
+        forward_req,
+        outgoing.headers,
+        body_changed,
+        target,
+        target_format,
+    );
+
+    // Ensure Content-Type is set for translated requests
+    if translation_ctx.needs_response_translation() {
+        forward_req = forward_req.header("content-type", "application/json");
+    }
+
+    // Pick a key from the provider's pool (only when the auth in use has one;
+    // a client auth override without a pool keeps its own key)
+    let pooled_key = target
+        .provider_id
+        .filter(|_| target.auth.is_some_and(|a| !a.pool.is_empty()))
+        .and_then(|pid| state.key_pools.select(pid));
+    if let Some(ref key) = pooled_key {
+        tracing::debug!(
+            provider = target.provider_id.unwrap_or("unknown"),
+            key = %key.label,
+            "Using pooled API key"
+        );
+    }
+
+    // Add provider's auth header if configured (after stripping incoming auth)
+    let auth_header_added = if let Some(auth) = target.auth {
+        let header = match &pooled_key {
+            Some(pooled) => auth.build_header_for_key(pooled.key.clone()),
+            None => auth.build_header(),
+        };
+        if let Some((header_name, header_value)) = header {
+            forward_req = forward_req.header(&header_name, &header_value);
+            Some((header_name, header_value.len()))
+        } else {
+            tracing::warn!(
+                "Auth config present but no header built - check key_env is set for provider {}",
+                target.provider_id.unwrap_or("unknown")
+            );
+            None
+        }
+    } else {
+        None
+    };
+
+    // Log forwarding summary
+    tracing::trace!(
+        "Forwarding: {} | body: {} bytes | auth: {} | format: {}",
+        forward_url,
+        body_size,
+        auth_header_added
+            .as_ref()
+            .map(|(h, len)| format!("{}({} chars)", h, len))
+            .unwrap_or_else(|| "passthrough".to_string()),
+        target_format
+    );
+
+    Ok(UpstreamRequest {
+        builder: forward_req,
+        translation_ctx,
+        is_messages_endpoint,
+        body_size,
+        pool_key: pooled_key.map(|k| k.index),
+        cache_breakpoints,
+    })
+}
+
+/// Copy the client's headers onto a forwarded request
+///
+/// `body_changed` drops the client's content-length (breakpoint edit,
+/// transformation or translation changed the body size); reqwest then sets
+/// it from the actual body.
+fn copy_forward_headers(
+    mut forward_req: reqwest::RequestBuilder,
+    headers: &HeaderMap,
+    body_changed: bool,
+    target: &UpstreamTarget<'_>,
+    target_format: translation::ApiFormat,
+) -> reqwest::RequestBuilder {
+    for (key, value) in headers.iter() {
+        let key_str = key.as_str();
+
+        // Skip connection control headers
+        if key_str == "host" || key_str == "connection" || key_str == "transfer-encoding" {
+            continue;
+        }
+
+        // Skip content-length if body was modified (edit, transformation or translation changed size)
+        if key_str == "content-length" && body_changed {
+            continue;
+        }
+
+        // Skip auth headers if strip_incoming is enabled, or if they belong
+        // to a different provider (failover, model router)
+        let strip_auth =
+            target.strip_client_auth || target.auth.is_some_and(|a| a.should_strip_incoming());
+        if strip_auth && is_auth_header(key_str) {
+            tracing::debug!("Stripping auth header: {}", key);
+            continue;
+        }
+
+        // Skip Anthropic-specific headers when targeting OpenAI format
+        if target_format == translation::ApiFormat::OpenAI && is_anthropic_header(key_str) {
+            tracing::debug!("Stripping Anthropic header for OpenAI target: {}", key);
+            continue;
+        }
+
+        forward_req = forward_req.header(key, value);
+    }
+
+    forward_req
+}
+
+/// Classify an upstream send error and build its full source chain
+fn describe_upstream_error(e: &reqwest::Error) -> (&'static str, String) {
+    let mut error_chain = format!("{}", e);
+    let mut source = StdError::source(e);
+    while let Some(s) = source {
+        error_chain.push_str(&format!(" <- {}", s));
+        source = s.source();
+    }
+
+    let error_type = if e.is_connect() {
+        "Connection"
+    } else if e.is_timeout() {
+        "Timeout"
+    } else if e.is_request() {
+        "Request"
+    } else if e.is_body() {
+        "Body"
+    } else if e.is_decode() {
+        "Decode"
+    } else {
+        "Unknown"
+    };
+
+    (error_type, error_chain)
+}
+
+/// Emit a ProviderFailover event for a re-routed request
+async fn emit_provider_failover(
+    state: &ProxyState,
+    request_id: &str,
+    (from, to): (&str, &str),
+    reason: String,
+    status: Option<u16>,
+    user_id: Option<&str>,
+) {
+    state
+        .send_event(
+            ProxyEvent::ProviderFailover {
+                timestamp: Utc::now(),
+                request_id: request_id.to_string(),
+                from_provider: from.to_string(),
+                to_provider: to.to_string(),
+                reason,
+                status,
+            },
+            user_id,
+        )
+        .await;
+}
+
+/// Hold a request at a matching breakpoint until the TUI decides
+///
+/// Returns the body to forward (edited or original) and whether it was
+/// edited, or a rejection.
+async fn hold_at_breakpoint(
+    hub: &breakpoints::BreakpointHub,
+    request_id: &str,
+    api_path: &str,
+    user_id: Option<&str>,
+    body_bytes: Bytes,
+) -> Result<(Bytes, bool), ProxyError> {
+    let Ok(body_json) = serde_json::from_slice::<serde_json::Value>(&body_bytes) else {
+        return Ok((body_bytes, false));
+    };
+    let Some(reason) = hub.matching_rule(&body_json, user_id) else {
+        return Ok((body_bytes, false));
+    };
+
+    tracing::info!("Breakpoint hit ({}): holding {}", reason, request_id);
+    let hold = hub.hold(breakpoints::HeldRequest {
+        id: 0,
+        request_id: request_id.to_string(),
+        client: user_id.map(str::to_string),
+        model: body_json
+            .get("model")
+            .and_then(|m| m.as_str())
+            .map(str::to_string),
+        path: api_path.to_string(),
+        reason: reason.to_string(),
+        body: serde_json::to_string_pretty(&body_json).unwrap_or_default(),
+        held_at: Utc::now(),
+    });
+
+    let id = hold.id();
+    match hold.wait().await {
+        breakpoints::BreakpointDecision::Continue { body: Some(edited) } => {
+            tracing::info!("Breakpoint #{}: continuing with edited body", id);
+            let changed = edited != body_bytes;
+            Ok((Bytes::from(edited), changed))
+        }
+        breakpoints::BreakpointDecision::Continue { body: None } => Ok((body_bytes, false)),
+        breakpoints::BreakpointDecision::Reject { status, message } => Err(ProxyError::Rejected {
+            status: StatusCode::from_u16(status).unwrap_or(StatusCode::BAD_REQUEST),
+            message,
+        }),
+    }
+}
+
 /// Main proxy handler - intercepts and forwards all requests
 ///
 /// For SSE (streaming) responses: Streams chunks directly to client while
@@ -183,6 +645,62 @@ async fn proxy_handler(
     let is_likely_messages =
         routing.api_path.contains("/messages") || routing.api_path.contains("/chat/completions");
 
+    // ─────────────────────────────────────────────────────────────────────────
+    // PER-CLIENT RATE LIMITING
+    // ─────────────────────────────────────────────────────────────────────────
+    // Checked before anything is forwarded. The permit holds a concurrent
+    // stream slot until the response (or stream) completes.
+    let rate_permit = match user_id.as_deref() {
+        Some(uid)
+            if method == "POST"
+                && is_likely_messages
+                && !count_tokens::is_count_tokens_path(&routing.api_path) =>
+        {
+            match state.rate_limiter.limits_for(uid) {
+                Some(limits) => {
+                    let input_tokens = if limits.input_tokens_per_minute.is_some() {
+                        rate_limit::estimate_input_tokens(&body_bytes)
+                    } else {
+                        0
+                    };
+                    let permit = state
+                        .rate_limiter
+                        .admit(uid, &limits, input_tokens, Instant::now())
+                        .map_err(|limited| ProxyError::RateLimited {
+                            message: limited.message,
+                            retry_after_secs: limited.retry_after_secs,
+                        })?;
+                    Some(permit)
+                }
+                None => None,
+            }
+        }
+        _ => None,
+    };
+
+    // ─────────────────────────────────────────────────────────────────────────
+    // BREAKPOINTS (hold for inspection in the TUI)
+    // ─────────────────────────────────────────────────────────────────────────
+    // Runs on the body exactly as the client sent it; an edited body then goes
+    // through transformation and translation like any other request.
+    let (body_bytes, body_was_edited) = match &state.breakpoints {
+        Some(hub)
+            if method == "POST"
+                && is_likely_messages
+                && !count_tokens::is_count_tokens_path(&routing.api_path) =>
+        {
+            hold_at_breakpoint(
+                hub,
+                &request_id,
+                &routing.api_path,
+                user_id.as_deref(),
+                body_bytes,
+            )
+            .await?
+        }
+        _ => (body_bytes, false),
+    };
+
     // ─────────────────────────────────────────────────────────────────────────
     // COUNT TOKENS HANDLING (provider-aware)
     // ─────────────────────────────────────────────────────────────────────────
@@ -211,13 +729,19 @@ async fn proxy_handler(
                 tracing::trace!("count_tokens passthrough, forwarding to backend");
             }
             CountTokensHandling::Synthetic => {
-                // Return synthetic response immediately (for providers without count_tokens)
-                tracing::debug!("count_tokens synthetic, returning synthetic response");
+                // Count locally (for providers without count_tokens)
+                let input_tokens = serde_json::from_slice::<serde_json::Value>(&body_bytes)
+                    .map(|body| state.token_counter.count(&body))
+                    .unwrap_or(0);
+                tracing::debug!(
+                    input_tokens,
+                    "count_tokens synthetic, returning local count"
+                );
                 return Response::builder()
                     .status(200)
                     .header("content-type", "application/json")
                     .header("x-aspy-cache", "synthetic")
-                    .body(Body::from(count_tokens::synthetic_response()))
+                    .body(Body::from(count_tokens::synthetic_response(input_tokens)))
                     .map_err(|e| ProxyError::ResponseBuild(e.to_string()));
             }
             CountTokensHandling::Dedupe => {
@@ -267,6 +791,17 @@ async fn proxy_handler(
     // ─────────────────────────────────────────────────────────────────────────
     // SystemReminderEditor and future transformers expect Anthropic message format.
     // We transform first, then translate to target format if needed.
+    // Provider/model choice from the model router (set inside the pipeline branch)
+    let mut route_decision: Option<transformation::RouteDecision> = None;
+    // Project the request's working directory belongs to (set inside the pipeline branch)
+    let mut request_project: Option<String> = None;
+    // Secrets the redactor replaced, restored in tool_use inputs of the response
+    let redactions = std::sync::Arc::new(transformation::Redactions::default());
+    // Tools the filter renamed, restored to their original names in the response
+    let tool_renames = std::sync::Arc::new(transformation::ToolRenames::default());
+    // Originals of tool results the governor truncated, archived via events
+    let truncations = transformation::Truncations::default();
+
     let (
         body_bytes,
         body_was_transformed,
@@ -280,8 +815,20 @@ async fn proxy_handler(
     {
         if let Ok(body_json) = serde_json::from_slice::<serde_json::Value>(&body_bytes) {
             let model = body_json.get("model").and_then(|m| m.as_str());
+            let user_prompt = extract_user_prompt(&body_json);
+            let tool_result_names = last_tool_result_names(&body_json);
             let mut ctx =
                 transformation::TransformContext::new(user_id.as_deref(), &routing.api_path, model);
+            ctx.user_prompt = user_prompt.as_deref();
+            ctx.tool_result_names = Some(&tool_result_names);
+            ctx.redactions = Some(&redactions);
+            ctx.tool_renames = Some(&tool_renames);
+            ctx.truncations = Some(&truncations);
+            request_project = state
+                .transformation
+                .projects()
+                .and_then(|p| p.detect(&body_json))
+                .map(str::to_string);
 
             // Extract tool_result_count and compute session turn_number
             if let Some(messages) = body_json.get("messages").and_then(|m| m.as_array()) {
@@ -325,8 +872,17 @@ async fn proxy_handler(
                             ctx.turn_number = Some(sessions.get_turn_count(&sid));
                         }
 
+                        // Tag the session with its project (cortex groups stats by it)
+                        if let Some(ref project) = request_project {
+                            sessions.set_project(&sid, project);
+                        }
+
                         // Extract todos for CompactEnhancer
                         if let Some(session) = sessions.get_user_session(&sid) {
+                            ctx = ctx.with_context_state(
+                                session.context.current_tokens,
+                                session.context.limit,
+                            );
                             if !session.todos.is_empty() {
                                 session_todos = session.todos.clone();
                             }
@@ -349,6 +905,21 @@ async fn proxy_handler(
                 }
             }
 
+            // Retrieve cortex history for the context enricher (async prep)
+            let semantic_context = match (
+                state.transformation.context_enricher(),
+                state.cortex_query.as_ref(),
+                user_id.as_deref(),
+            ) {
+                (Some(enricher), Some(cortex), Some(uid)) if enricher.wants(&ctx) => {
+                    enricher
+                        .prepare(std::sync::Arc::clone(cortex), uid, &body_json)
+                        .await
+                }
+                _ => None,
+            };
+            ctx.semantic_context = semantic_context.as_deref();
+
             tracing::debug!(
                 turn = ctx.turn_number,
                 tool_results = ctx.tool_result_count,
@@ -359,11 +930,23 @@ async fn proxy_handler(
                 ctx.client_id.unwrap_or("unknown")
             );
 
+            // Route on the original body. The router is registered ahead of
+            // every transformer that edits the body, so its transform in the
+            // pipeline sees this same input and any model rewrite matches
+            route_decision = state.transformation.route(&body_json, &ctx);
+
             tracing::debug!(
                 transformers = ?state.transformation.transformer_names(),
                 "Running transformation pipeline on request"
             );
-            match state.transformation.transform(&body_json, &ctx) {
+            // External hooks wait on I/O and scripts burn CPU; let this
+            // worker's other tasks move to another thread while they run
+            let result = if state.transformation.blocks() {
+                tokio::task::block_in_place(|| state.transformation.transform(&body_json, &ctx))
+            } else {
+                state.transformation.transform(&body_json, &ctx)
+            };
+            match result {
                 transformation::TransformResult::Modified {
                     body: new_body,
                     tokens,
@@ -403,10 +986,10 @@ async fn proxy_handler(
                         reason,
                         status
                     );
-                    return Err(ProxyError::BodyRead(format!(
-                        "Request blocked: {} (status {})",
-                        reason, status
-                    )));
+                    return Err(ProxyError::Rejected {
+                        status,
+                        message: reason,
+                    });
                 }
                 transformation::TransformResult::Error(e) => {
                     tracing::warn!("Transformation error (continuing with original): {}", e);
@@ -423,93 +1006,70 @@ async fn proxy_handler(
         (body_bytes.to_vec(), false, None, Vec::new(), Vec::new())
     };
 
-    // Determine target API format based on provider config
-    // If provider expects OpenAI format, translate Anthropic → OpenAI
-    let target_format = routing
-        .client_id
-        .as_ref()
-        .and_then(|cid| state.clients.get_client_api_format(cid))
-        .map(|fmt| match fmt {
-            crate::config::ApiFormat::Anthropic => translation::ApiFormat::Anthropic,
-            crate::config::ApiFormat::Openai => translation::ApiFormat::OpenAI,
-        })
-        .unwrap_or(translation::ApiFormat::Anthropic);
-
-    // Get provider-specific model mapping (if configured)
-    // This takes precedence over global [translation.model_mapping]
-    let provider_model_mapping = routing
-        .client_id
-        .as_ref()
-        .and_then(|cid| state.clients.get_client_model_mapping(cid))
-        .map(ModelMapping::from_config);
-
-    // Apply translation if enabled, targeting the provider's expected format
-    let (translated_body, translation_ctx, translated_path) = state
-        .translation
-        .translate_request_for_target_with_mapping(
-            &routing.api_path,
-            &headers,
-            &body_bytes,
-            target_format,
-            provider_model_mapping.as_ref(),
-        )
-        .map_err(|e| ProxyError::BodyRead(format!("Translation failed: {}", e)))?;
+    // A body edited at a breakpoint no longer matches the client's content-length
+    let body_was_transformed = body_was_transformed || body_was_edited;
+    let redactions = (body_was_transformed && !redactions.is_empty()).then_some(redactions);
+    let tool_renames = (body_was_transformed && !tool_renames.is_empty()).then_some(tool_renames);
 
-    // Get provider's custom api_path if configured (overrides default /v1/messages or /v1/chat/completions)
-    let provider_api_path = routing
-        .client_id
-        .as_ref()
-        .and_then(|cid| state.clients.get_client_api_path(cid));
-
-    // Determine effective API path for forwarding
-    // Priority: provider's custom api_path > translated path > original routing path
-    let effective_api_path = if let Some(custom_path) = provider_api_path {
-        // Provider has custom api_path configured - use it
-        if translation_ctx.needs_response_translation() {
-            // Log translation with custom path
-            if let Ok(translated_json) =
-                serde_json::from_slice::<serde_json::Value>(&translated_body)
-            {
-                let model = translated_json
-                    .get("model")
-                    .and_then(|m| m.as_str())
-                    .unwrap_or("unknown");
-                tracing::info!(
-                    "Translation: {} -> {} | model: {} | path: {} -> {} (custom)",
-                    translation_ctx.client_format,
-                    translation_ctx.backend_format,
-                    model,
-                    routing.api_path,
-                    custom_path
-                );
-            }
-        }
-        custom_path.to_string()
-    } else if translation_ctx.needs_response_translation() {
-        // No custom path, but translation happened - use translated path (default /v1/...)
-        if let Ok(translated_json) = serde_json::from_slice::<serde_json::Value>(&translated_body) {
-            let model = translated_json
-                .get("model")
-                .and_then(|m| m.as_str())
-                .unwrap_or("unknown");
-            tracing::info!(
-                "Translation: {} -> {} | model: {} | path: {} -> {}",
-                translation_ctx.client_format,
-                translation_ctx.backend_format,
-                model,
-                routing.api_path,
-                translated_path
+    // ─────────────────────────────────────────────────────────────────────────
+    // UPSTREAM SELECTION (provider chain with failover)
+    // ─────────────────────────────────────────────────────────────────────────
+    // Routed clients may have a fallback chain. Providers in cooldown after
+    // repeated failures are skipped up front; if every provider is cooling
+    // down we still try the primary rather than failing outright.
+    let routed_provider = route_decision.as_ref().and_then(|d| d.provider.as_deref());
+    if let Some(pid) = routed_provider {
+        if !state.clients.providers.contains_key(pid) {
+            tracing::warn!(
+                provider = pid,
+                "Model router selected unknown provider, using client routing"
             );
         }
-        translated_path.clone()
-    } else {
-        // No translation, no custom path - use original routing path
-        routing.api_path.clone()
+    }
+    let targets = resolve_upstream_chain(&routing, &state.clients, &state.api_url, routed_provider);
+    let chain_ids: Vec<&str> = targets
+        .iter()
+        .map(|t| t.provider_id.unwrap_or_default())
+        .collect();
+    let mut target_idx = if targets.len() > 1 {
+        state
+            .provider_health
+            .next_available(&chain_ids, 0)
+            .unwrap_or(0)
+    } else {
+        0
+    };
+
+    // Calibrate local count_tokens answers for providers that can't count:
+    // remember this request's estimate until its usage comes back
+    if method == "POST"
+        && is_likely_messages
+        && !count_tokens::is_count_tokens_path(&routing.api_path)
+    {
+        let counts_locally = routing
+            .client_id
+            .as_ref()
+            .and_then(|cid| state.clients.get_client_count_tokens(cid))
+            == Some(CountTokensHandling::Synthetic);
+        if let (true, Some(uid)) = (counts_locally, user_id.as_deref()) {
+            if let Ok(body) = serde_json::from_slice::<serde_json::Value>(&body_bytes) {
+                state.token_counter.expect_usage(uid, &body);
+            }
+        }
+    }
+
+    let outgoing = OutgoingRequest {
+        method: &method,
+        uri: &uri,
+        headers: &headers,
+        api_path: &routing.api_path,
+        body: &body_bytes,
+        body_was_transformed,
     };
+    let mut upstream = prepare_upstream_request(&state, &outgoing, &targets[target_idx])?;
 
     // Check if this is a completion endpoint (Anthropic /messages or OpenAI /chat/completions)
-    let is_messages_endpoint = effective_api_path.contains("/messages")
-        || effective_api_path.contains("/chat/completions");
+    let is_messages_endpoint = upstream.is_messages_endpoint;
 
     // Parse transformed body for Request event display
     let request_body = if is_messages_endpoint {
@@ -518,6 +1078,22 @@ async fn proxy_handler(
         None
     };
 
+    // Attribute the request (and everything it emits) to a running subagent
+    // if it opens with that subagent's Task prompt
+    let agent_id = match (&request_body, &user_id) {
+        (Some(body), Some(uid)) => state.sessions.lock().ok().and_then(|sessions| {
+            sessions
+                .get_user_session(&sessions::UserId::new(uid))
+                .and_then(|session| session.subagents.attribute(body))
+                .map(str::to_string)
+        }),
+        _ => None,
+    };
+    let state = state
+        .clone()
+        .for_agent(agent_id)
+        .for_project(request_project);
+
     // Extract and emit user prompt from request (if POST to /messages)
     if is_messages_endpoint && method == "POST" {
         if let Some(ref body) = request_body {
@@ -572,6 +1148,48 @@ async fn proxy_handler(
             .await;
     }
 
+    // Archive originals of newly truncated tool results (stored by cortex)
+    for truncated in truncations.take() {
+        let tool_use_id = truncated.tool_use_id.clone();
+        state
+            .send_event(
+                ProxyEvent::ToolResultTruncated {
+                    timestamp: Utc::now(),
+                    tool_use_id: truncated.tool_use_id,
+                    tool_name: truncated.tool_name,
+                    tokens_before: truncated.tokens_before,
+                    tokens_after: truncated.tokens_after,
+                    original: truncated.original,
+                },
+                user_id.as_deref(),
+            )
+            .await;
+        if let Some(governor) = state.transformation.tool_governor() {
+            governor.mark_archived(&tool_use_id);
+        }
+    }
+
+    // Usage of cache-optimized requests is reported separately in the stats
+    let cache_optimized = transform_names.iter().any(|name| name == "cache-optimizer")
+        || !upstream.cache_breakpoints.is_empty();
+
+    // Breakpoints placed on the translated body are reported separately
+    // (the pipeline event above describes the client-format body)
+    if !upstream.cache_breakpoints.is_empty() {
+        state
+            .send_event(
+                ProxyEvent::RequestTransformed {
+                    timestamp: Utc::now(),
+                    transformer: "cache-optimizer".to_string(),
+                    tokens_before: 0,
+                    tokens_after: 0,
+                    modifications: std::mem::take(&mut upstream.cache_breakpoints),
+                },
+                user_id.as_deref(),
+            )
+            .await;
+    }
+
     // Parse request for tool results if this is a messages endpoint
     if is_messages_endpoint && method == "POST" {
         match state
@@ -676,138 +1294,217 @@ async fn proxy_handler(
         }
     }
 
-    // Build the forward URL using client routing and translated path
-    let forward_url = format!("{}{}", routing.base_url, effective_api_path);
+    // Primary skipped because it is cooling down after repeated failures
+    if target_idx > 0 {
+        emit_provider_failover(
+            &state,
+            &request_id,
+            (chain_ids[0], chain_ids[target_idx]),
+            "Provider in cooldown".to_string(),
+            None,
+            user_id.as_deref(),
+        )
+        .await;
+    }
 
-    // Don't pass Anthropic-specific query params (like ?beta=true) to OpenAI targets
-    let forward_url = if target_format == translation::ApiFormat::OpenAI {
-        // Strip query params entirely for OpenAI targets
-        forward_url
-    } else {
-        let query = uri.query().unwrap_or("");
-        if query.is_empty() {
-            forward_url
-        } else {
-            format!("{}?{}", forward_url, query)
+    // Send the request, re-routing to the next healthy provider on failure.
+    // Failover wins over retry: when another provider is available we switch
+    // immediately; otherwise retryable statuses (429/529) wait and re-send.
+    // In cassette replay mode the recorded exchange stands in for the upstream
+    let cassette_key = state.cassette.as_ref().map(|_| {
+        cassette::RequestKey::new(outgoing.method.as_str(), outgoing.api_path, outgoing.body)
+    });
+    let mut replayed = match (&state.cassette, &cassette_key) {
+        (Some(cassette), Some(key)) if cassette.is_replay() => {
+            Some(cassette.replay(key).ok_or_else(|| {
+                tracing::warn!("No cassette match for {} {}", key.method, key.path);
+                ProxyError::Upstream(format!("No cassette match for {} {}", key.method, key.path))
+            })?)
         }
+        _ => None,
     };
 
-    // Final body is the translated body (transformation already happened earlier)
-    let final_body = translated_body;
-    let body_size = final_body.len();
-
-    // Build the forwarded request
-    // With reqwest 0.12, Method types align with axum (both use http 1.0 crate)
-    let mut forward_req = state.client.request(method, &forward_url).body(final_body);
-
-    // Get effective auth config for this client (if routed)
-    let auth_config = routing
-        .client_id
-        .as_ref()
-        .and_then(|cid| state.clients.get_effective_auth(cid));
-
-    // Copy relevant headers with auth transformation
-    for (key, value) in headers.iter() {
-        let key_str = key.as_str();
+    // Mirror to the client's shadow provider (never in replay: there is no upstream)
+    let shadow_tx = match routing.client_id.as_deref() {
+        Some(cid)
+            if replayed.is_none()
+                && method == "POST"
+                && is_messages_endpoint
+                && !count_tokens::is_count_tokens_path(&routing.api_path) =>
+        {
+            shadow::spawn(&state, &outgoing, cid, &request_id, user_id.as_deref())
+        }
+        _ => None,
+    };
 
-        // Skip connection control headers
-        if key_str == "host" || key_str == "connection" || key_str == "transfer-encoding" {
-            continue;
+    let mut attempt: u32 = 1;
+    let response = loop {
+        if let Some(response) = replayed.take() {
+            break response;
         }
+        let provider_id = targets[target_idx].provider_id;
+        let result = upstream.builder.send().await;
+
+        let failure = match &result {
+            Ok(resp)
+                if state
+                    .provider_health
+                    .is_failover_status(resp.status().as_u16()) =>
+            {
+                let code = resp.status().as_u16();
+                Some((format!("HTTP {}", code), Some(code)))
+            }
+            Ok(_) => None,
+            Err(e) => {
+                let (kind, error_chain) = describe_upstream_error(e);
+                Some((format!("{} error: {}", kind, error_chain), None))
+            }
+        };
 
-        // Skip content-length if body was modified (transformation or translation changed size)
-        if key_str == "content-length"
-            && (body_was_transformed || translation_ctx.needs_response_translation())
-        {
-            continue;
+        // Feed rate limit headers back to the key pool
+        if let (Some(pid), Some(key_idx), Ok(resp)) = (provider_id, upstream.pool_key, &result) {
+            state
+                .key_pools
+                .record_response(pid, key_idx, resp.status().as_u16(), resp.headers());
         }
 
-        // Skip auth headers if strip_incoming is enabled
-        if let Some(auth) = auth_config {
-            if auth.should_strip_incoming() && is_auth_header(key_str) {
-                tracing::debug!("Stripping auth header: {}", key);
-                continue;
+        if let Some(pid) = provider_id {
+            match &failure {
+                Some(_) => {
+                    state.provider_health.record_failure(pid);
+                }
+                None => state.provider_health.record_success(pid),
             }
         }
 
-        // Skip Anthropic-specific headers when targeting OpenAI format
-        if target_format == translation::ApiFormat::OpenAI && is_anthropic_header(key_str) {
-            tracing::debug!("Stripping Anthropic header for OpenAI target: {}", key);
-            continue;
-        }
+        let next_idx = failure.as_ref().and_then(|_| {
+            state
+                .provider_health
+                .next_available(&chain_ids, target_idx + 1)
+        });
 
-        forward_req = forward_req.header(key, value);
-    }
+        match (failure, next_idx) {
+            (Some((reason, status)), Some(next_idx)) => {
+                tracing::warn!(
+                    from = chain_ids[target_idx],
+                    to = chain_ids[next_idx],
+                    reason = %reason,
+                    "Provider failed, failing over"
+                );
+                emit_provider_failover(
+                    &state,
+                    &request_id,
+                    (chain_ids[target_idx], chain_ids[next_idx]),
+                    reason,
+                    status,
+                    user_id.as_deref(),
+                )
+                .await;
+                target_idx = next_idx;
+                upstream = prepare_upstream_request(&state, &outgoing, &targets[target_idx])?;
+            }
+            _ => {
+                let retry = result.as_ref().ok().and_then(|resp| {
+                    let status = resp.status().as_u16();
+                    // A rate-limited pooled key doesn't need waiting out if
+                    // another key in the pool is ready
+                    let other_key_ready = status == 429
+                        && upstream.pool_key.is_some()
+                        && provider_id.is_some_and(|pid| state.key_pools.has_available(pid));
+                    let decision = if other_key_ready {
+                        state.retry_policy.immediate_retry(attempt, status)
+                    } else {
+                        state
+                            .retry_policy
+                            .next_retry(attempt, status, resp.headers())
+                    };
+                    decision.map(|decision| (status, decision))
+                });
 
-    // Ensure Content-Type is set for translated requests
-    if translation_ctx.needs_response_translation() {
-        forward_req = forward_req.header("content-type", "application/json");
-    }
+                if let Some((status, decision)) = retry {
+                    // Release the failed response's connection before waiting
+                    drop(result);
 
-    // Add provider's auth header if configured (after stripping incoming auth)
-    let auth_header_added = if let Some(auth) = auth_config {
-        if let Some((header_name, header_value)) = auth.build_header() {
-            forward_req = forward_req.header(&header_name, &header_value);
-            Some((header_name, header_value.len()))
-        } else {
-            tracing::warn!(
-                "Auth config present but no header built - check key_env is set for client {}",
-                routing.client_id.as_deref().unwrap_or("unknown")
-            );
-            None
+                    let delay_ms = decision.delay.as_millis() as u64;
+                    tracing::info!(
+                        attempt,
+                        status,
+                        delay_ms,
+                        source = decision.source.as_str(),
+                        "Retrying upstream request"
+                    );
+                    state
+                        .send_event(
+                            ProxyEvent::UpstreamRetry {
+                                timestamp: Utc::now(),
+                                request_id: request_id.clone(),
+                                attempt,
+                                max_attempts: state.retry_policy.max_attempts(),
+                                status,
+                                delay_ms,
+                                delay_source: decision.source.as_str().to_string(),
+                            },
+                            user_id.as_deref(),
+                        )
+                        .await;
+
+                    tokio::time::sleep(decision.delay).await;
+                    attempt += 1;
+                    upstream = prepare_upstream_request(&state, &outgoing, &targets[target_idx])?;
+                    continue;
+                }
+
+                break result.map_err(|e| {
+                    let (kind, error_chain) = describe_upstream_error(&e);
+                    tracing::error!(
+                        "Upstream {} error: {} | Body size: {} bytes",
+                        kind,
+                        error_chain,
+                        upstream.body_size
+                    );
+                    ProxyError::Upstream(format!("{} error: {}", kind, error_chain))
+                })?;
+            }
         }
-    } else {
-        None
     };
-
-    // Log forwarding summary
-    tracing::trace!(
-        "Forwarding: {} | body: {} bytes | auth: {} | format: {}",
-        forward_url,
-        body_size,
-        auth_header_added
-            .as_ref()
-            .map(|(h, len)| format!("{}({} chars)", h, len))
-            .unwrap_or_else(|| "passthrough".to_string()),
-        target_format
-    );
-
-    // Send the request
-    let response = forward_req.send().await.map_err(|e| {
-        // Provide detailed error information with full source chain
-        let mut error_chain = format!("{}", e);
-        let mut source = StdError::source(&e);
-        while let Some(s) = source {
-            error_chain.push_str(&format!(" <- {}", s));
-            source = s.source();
+    let response = match (&state.cassette, cassette_key) {
+        (Some(cassette), Some(key)) if cassette.is_record() => {
+            cassette.record(key, outgoing.body, response)
         }
-
-        let error_type = if e.is_connect() {
-            "Connection"
-        } else if e.is_timeout() {
-            "Timeout"
-        } else if e.is_request() {
-            "Request"
-        } else if e.is_body() {
-            "Body"
-        } else if e.is_decode() {
-            "Decode"
-        } else {
-            "Unknown"
-        };
-
-        tracing::error!(
-            "Upstream {} error: {} | Body size: {} bytes",
-            error_type,
-            error_chain,
-            body_size
-        );
-        ProxyError::Upstream(format!("{} error: {}", error_type, error_chain))
-    })?;
+        _ => response,
+    };
+    let translation_ctx = upstream.translation_ctx;
 
     // TTFB: Time to first byte - captured immediately after headers received
     let ttfb = start.elapsed();
 
+    // Emit routing decision with the provider that served the request (the
+    // rule's provider may have been skipped by cooldown or failed over)
+    if let Some(decision) = route_decision {
+        let provider = targets[target_idx].provider_id.unwrap_or("default");
+        tracing::info!(
+            rule = %decision.rule,
+            provider,
+            model = ?decision.model,
+            routed_model = ?decision.routed_model,
+            "Model router matched rule '{}'",
+            decision.rule
+        );
+        state
+            .send_event(
+                ProxyEvent::ModelRouted {
+                    timestamp: Utc::now(),
+                    request_id: request_id.clone(),
+                    rule: decision.rule,
+                    provider: provider.to_string(),
+                    model: decision.model,
+                    routed_model: decision.routed_model,
+                },
+                user_id.as_deref(),
+            )
+            .await;
+    }
+
     let status = response.status();
     let response_headers = response.headers().clone();
 
@@ -861,6 +1558,11 @@ async fn proxy_handler(
         user_id,
         translation_ctx,
         count_tokens_request_body,
+        rate_permit,
+        shadow_tx,
+        redactions,
+        tool_renames,
+        cache_optimized,
     };
 
     // Decide: streaming (SSE) or buffered (JSON) response handling
@@ -887,6 +1589,11 @@ async fn handle_streaming_response(ctx: ResponseContext) -> Result<Response<Body
         user_id,
         translation_ctx,
         count_tokens_request_body: _, // Not used for streaming (count_tokens is always buffered)
+        rate_permit,
+        shadow_tx,
+        redactions,
+        tool_renames,
+        cache_optimized,
     } = ctx;
 
     // ─────────────────────────────────────────────────────────────────────────
@@ -920,9 +1627,32 @@ async fn handle_streaming_response(ctx: ResponseContext) -> Result<Response<Body
     let user_id_clone = user_id.clone();
     let translation_pipeline = state.translation.clone();
     let mut translation_ctx = translation_ctx;
+    // Swaps redacted placeholders back into streamed tool_use inputs
+    let mut restorer = redactions.map(transformation::StreamRestorer::new);
+    // Puts original names back on streamed calls to renamed tools
+    let mut name_restorer = tool_renames
+        .clone()
+        .map(transformation::ToolNameRestorer::new);
+    // Checks tool calls against the tool guard before anything else reads the stream
+    let mut guard = state
+        .tool_policy
+        .as_ref()
+        .filter(|_| {
+            is_messages_endpoint
+                && translation_ctx.backend_format == translation::ApiFormat::Anthropic
+        })
+        .map(|policy| {
+            tool_guard::GuardedStream::new(
+                std::sync::Arc::clone(policy),
+                user_id.clone(),
+                tool_renames,
+            )
+        });
 
     // Spawn task to stream response while accumulating
     tokio::spawn(async move {
+        // Hold the concurrent stream slot until the stream ends
+        let _rate_permit = rate_permit;
         let mut accumulated = Vec::new(); // What gets sent to client (translated if needed)
         let mut raw_accumulated = Vec::new(); // Raw response for parsing events (always Anthropic format)
         let mut byte_stream = response.bytes_stream();
@@ -931,8 +1661,8 @@ async fn handle_streaming_response(ctx: ResponseContext) -> Result<Response<Body
         let mut line_buffer = String::new();
         // Track content block index for potential injection
         let mut max_block_index: u32 = 0;
-        // Track if we've injected this response and how many tokens (only inject once)
-        let mut injected_tokens: Option<u32> = None;
+        // Track which augmenter injected into this response and how many tokens (only inject once)
+        let mut injected_tokens: Option<(&'static str, u32)> = None;
         // Track model for injection filtering (skip Haiku utility calls)
         let mut response_model = String::new();
 
@@ -952,6 +1682,14 @@ async fn handle_streaming_response(ctx: ResponseContext) -> Result<Response<Body
             match chunk_result {
                 Ok(chunk) => {
                     total_bytes += chunk.len();
+                    // Denied calls are replaced here, so everything below sees the guarded stream
+                    let chunk = match guard.as_mut() {
+                        Some(g) => g.push(&chunk),
+                        None => chunk,
+                    };
+                    if chunk.is_empty() {
+                        continue;
+                    }
                     raw_accumulated.extend_from_slice(&chunk); // Always store raw for parsing
 
                     // CRITICAL: Register tool_use IDs immediately as we see them
@@ -1030,10 +1768,12 @@ async fn handle_streaming_response(ctx: ResponseContext) -> Result<Response<Body
                                                 stop_reason,
                                                 next_block_index: max_block_index,
                                                 context_state: &context_state,
+                                                user_id: user_id_clone.as_deref(),
                                             };
 
                                             // Run augmentation pipeline
-                                            if let Some(augmented) = augmentation.process(&aug_ctx)
+                                            if let Some((augmenter, augmented)) =
+                                                augmentation.process(&aug_ctx)
                                             {
                                                 // Translate injection if needed (augmentation produces Anthropic SSE)
                                                 let injection_to_send = if let Some(t) = &translator
@@ -1059,8 +1799,18 @@ async fn handle_streaming_response(ctx: ResponseContext) -> Result<Response<Body
                                                 } else {
                                                     Bytes::from(augmented.sse_bytes.clone())
                                                 };
+                                                // Release anything the restorers hold first so the
+                                                // injection doesn't land inside a held-back event
+                                                let held = flush_restorers(
+                                                    &mut restorer,
+                                                    &mut name_restorer,
+                                                );
+                                                if !held.is_empty() {
+                                                    let _ = tx.send(Ok(held)).await;
+                                                }
                                                 let _ = tx.send(Ok(injection_to_send)).await;
-                                                injected_tokens = Some(augmented.tokens_injected);
+                                                injected_tokens =
+                                                    Some((augmenter, augmented.tokens_injected));
 
                                                 // Track token injection for stats
                                                 tracing::debug!(
@@ -1094,6 +1844,19 @@ async fn handle_streaming_response(ctx: ResponseContext) -> Result<Response<Body
 
                     if let Some(bytes_to_send) = chunk_to_send {
                         accumulated.extend_from_slice(&bytes_to_send); // Store what we actually send
+
+                        // Restore after accumulating so logs keep the placeholders
+                        let bytes_to_send = match restorer.as_mut() {
+                            Some(r) => r.push(&bytes_to_send),
+                            None => bytes_to_send,
+                        };
+                        let bytes_to_send = match name_restorer.as_mut() {
+                            Some(r) => r.push(&bytes_to_send),
+                            None => bytes_to_send,
+                        };
+                        if bytes_to_send.is_empty() {
+                            continue;
+                        }
                         if tx.send(Ok(bytes_to_send)).await.is_err() {
                             // Client disconnected, but continue accumulating for logging
                             tracing::debug!("Client disconnected during streaming");
@@ -1126,6 +1889,28 @@ async fn handle_streaming_response(ctx: ResponseContext) -> Result<Response<Body
             }
         }
 
+        // Release a call the guard was still holding (stream cut off mid-call)
+        if let Some(rest) = guard.as_mut().map(|g| g.finish()) {
+            if !rest.is_empty() {
+                raw_accumulated.extend_from_slice(&rest);
+                let rest = match &translator {
+                    Some(t) => t
+                        .translate_chunk(&rest, &mut translation_ctx)
+                        .map(Bytes::from)
+                        .unwrap_or_default(),
+                    None => rest,
+                };
+                accumulated.extend_from_slice(&rest);
+                let _ = tx.send(Ok(rest)).await;
+            }
+        }
+
+        // Flush any partial event held for restoration
+        let rest = flush_restorers(&mut restorer, &mut name_restorer);
+        if !rest.is_empty() {
+            let _ = tx.send(Ok(rest)).await;
+        }
+
         // Send stream terminator if translation is active (e.g., "data: [DONE]" for OpenAI)
         if let Some(t) = &translator {
             if let Some(terminator) = t.finalize(&translation_ctx) {
@@ -1143,6 +1928,17 @@ async fn handle_streaming_response(ctx: ResponseContext) -> Result<Response<Body
         // Stream complete - now parse and emit events
         let duration = start.elapsed();
 
+        // Report what the client received to a pending shadow comparison
+        if let Some(tx) = shadow_tx {
+            let _ = tx.send(shadow::sample_body(
+                &raw_accumulated,
+                translation_ctx.backend_format,
+                status.as_u16(),
+                ttfb,
+                duration,
+            ));
+        }
+
         // Helper to send events through pipeline (includes session recording, cortex, etc.)
         let send_event = |event: ProxyEvent| {
             let state_ref = state.clone();
@@ -1198,7 +1994,14 @@ async fn handle_streaming_response(ctx: ResponseContext) -> Result<Response<Body
                 .parse_response(parse_buffer, user_id_clone.as_deref())
                 .await
             {
-                for event in events {
+                for mut event in events {
+                    if let ProxyEvent::ApiUsage {
+                        cache_optimized: optimized,
+                        ..
+                    } = &mut event
+                    {
+                        *optimized = cache_optimized;
+                    }
                     // Update context state when we see ApiUsage (skip Haiku utility calls)
                     if let ProxyEvent::ApiUsage {
                         input_tokens,
@@ -1231,11 +2034,20 @@ async fn handle_streaming_response(ctx: ResponseContext) -> Result<Response<Body
             }
         }
 
+        // Report tool guard decisions
+        for decision in guard
+            .as_mut()
+            .map(|g| g.take_decisions())
+            .unwrap_or_default()
+        {
+            send_event(decision.into_event(&request_id_clone)).await;
+        }
+
         // Emit augmentation event if tokens were injected
-        if let Some(tokens) = injected_tokens {
+        if let Some((augmenter, tokens)) = injected_tokens {
             send_event(ProxyEvent::ResponseAugmented {
                 timestamp: Utc::now(),
-                augmenter: "context-warning".to_string(),
+                augmenter: augmenter.to_string(),
                 tokens_injected: tokens,
             })
             .await;
@@ -1282,6 +2094,11 @@ async fn handle_buffered_response(ctx: ResponseContext) -> Result<Response<Body>
         user_id,
         translation_ctx,
         count_tokens_request_body,
+        rate_permit: _rate_permit, // Released when the buffered response is returned
+        shadow_tx,
+        redactions,
+        tool_renames,
+        cache_optimized,
     } = ctx;
     // Read full response body
     let response_body = response
@@ -1307,6 +2124,68 @@ async fn handle_buffered_response(ctx: ResponseContext) -> Result<Response<Body>
 
     let duration = start.elapsed();
 
+    // Report what the upstream returned to a pending shadow comparison
+    if let Some(tx) = shadow_tx {
+        let _ = tx.send(shadow::sample_body(
+            &response_body,
+            translation_ctx.backend_format,
+            status.as_u16(),
+            ttfb,
+            duration,
+        ));
+    }
+
+    // Replace denied tool calls before anything else reads the body
+    let mut guarded = false;
+    let response_body = match &state.tool_policy {
+        Some(policy)
+            if is_messages_endpoint
+                && status.is_success()
+                && translation_ctx.backend_format == translation::ApiFormat::Anthropic =>
+        {
+            let mut json = serde_json::from_slice::<serde_json::Value>(&response_body).ok();
+            let decisions = json
+                .as_mut()
+                .map(|json| {
+                    policy.apply_buffered(user_id.as_deref(), tool_renames.as_deref(), json)
+                })
+                .unwrap_or_default();
+            for decision in decisions.iter().cloned() {
+                state
+                    .send_event(decision.into_event(&request_id), user_id.as_deref())
+                    .await;
+            }
+            match json.filter(|_| !decisions.is_empty()) {
+                Some(json) => match serde_json::to_vec(&json) {
+                    Ok(body) => {
+                        guarded = true;
+                        Bytes::from(body)
+                    }
+                    Err(_) => response_body,
+                },
+                None => response_body,
+            }
+        }
+        _ => response_body,
+    };
+
+    // Run augmenters on the Anthropic-format body (before translation to the client format)
+    let mut injected_tokens = None;
+    let augmented_body = if is_messages_endpoint
+        && status.is_success()
+        && translation_ctx.backend_format == translation::ApiFormat::Anthropic
+    {
+        augment_buffered_response(&state, user_id.as_deref(), &response_body).map(
+            |(body, augmenter, tokens)| {
+                injected_tokens = Some((augmenter, tokens));
+                body
+            },
+        )
+    } else {
+        None
+    };
+    let upstream_body = augmented_body.as_ref().unwrap_or(&response_body);
+
     // Apply response translation FIRST (so parser and display see Anthropic format)
     let final_response_body = if translation_ctx.needs_response_translation() && status.is_success()
     {
@@ -1315,27 +2194,27 @@ async fn handle_buffered_response(ctx: ResponseContext) -> Result<Response<Body>
             translation_ctx.backend_format,
             translation_ctx.client_format,
         ) {
-            match translator.translate_buffered(&response_body, &translation_ctx) {
+            match translator.translate_buffered(upstream_body, &translation_ctx) {
                 Ok(translated) => {
                     tracing::debug!(
                         "Response translated: {} -> {} ({} -> {} bytes)",
                         translation_ctx.backend_format,
                         translation_ctx.client_format,
-                        response_body.len(),
+                        upstream_body.len(),
                         translated.len()
                     );
                     translated.into()
                 }
                 Err(e) => {
                     tracing::warn!("Response translation failed, returning original: {}", e);
-                    response_body.clone()
+                    upstream_body.clone()
                 }
             }
         } else {
-            response_body.clone()
+            upstream_body.clone()
         }
     } else {
-        response_body.clone()
+        upstream_body.clone()
     };
 
     // Try to parse translated response body for display
@@ -1402,7 +2281,14 @@ async fn handle_buffered_response(ctx: ResponseContext) -> Result<Response<Body>
             .parse_response(&final_response_body, user_id.as_deref())
             .await
         {
-            for event in events {
+            for mut event in events {
+                if let ProxyEvent::ApiUsage {
+                    cache_optimized: optimized,
+                    ..
+                } = &mut event
+                {
+                    *optimized = cache_optimized;
+                }
                 // Update context state when we see ApiUsage (skip Haiku utility calls)
                 if let ProxyEvent::ApiUsage {
                     input_tokens,
@@ -1435,6 +2321,38 @@ async fn handle_buffered_response(ctx: ResponseContext) -> Result<Response<Body>
         }
     }
 
+    // Emit augmentation event if tokens were injected (same stats as streaming)
+    if let Some((augmenter, tokens)) = injected_tokens {
+        state
+            .send_event(
+                ProxyEvent::ResponseAugmented {
+                    timestamp: Utc::now(),
+                    augmenter: augmenter.to_string(),
+                    tokens_injected: tokens,
+                },
+                user_id.as_deref(),
+            )
+            .await;
+    }
+
+    // Swap redacted placeholders back into tool_use inputs and original
+    // names onto renamed tools (after parsing, so logged events keep what
+    // the model actually produced)
+    let restored_body = (redactions.is_some() || tool_renames.is_some())
+        .then(|| serde_json::from_slice::<serde_json::Value>(&final_response_body).ok())
+        .flatten()
+        .and_then(|mut json| {
+            let restored = redactions.is_some_and(|r| r.restore_response(&mut json));
+            let renamed = tool_renames.is_some_and(|r| r.restore_response(&mut json));
+            (restored || renamed)
+                .then(|| serde_json::to_vec(&json).ok())
+                .flatten()
+        });
+    let body_restored = restored_body.is_some();
+    let final_response_body = restored_body
+        .map(Bytes::from)
+        .unwrap_or(final_response_body);
+
     // Build response to return to client
     let mut builder = Response::builder().status(status.as_u16());
 
@@ -1442,8 +2360,13 @@ async fn handle_buffered_response(ctx: ResponseContext) -> Result<Response<Body>
         if key == "transfer-encoding" || key == "connection" {
             continue;
         }
-        // Update content-length if translation occurred
-        if key == "content-length" && translation_ctx.needs_response_translation() {
+        // Update content-length if translation, augmentation, guarding or restoration changed the body
+        if key == "content-length"
+            && (translation_ctx.needs_response_translation()
+                || injected_tokens.is_some()
+                || body_restored
+                || guarded)
+        {
             continue; // Will be set automatically from body
         }
         builder = builder.header(key, value);
@@ -1459,6 +2382,57 @@ async fn handle_buffered_response(ctx: ResponseContext) -> Result<Response<Body>
         .map_err(|e| ProxyError::ResponseBuild(e.to_string()))
 }
 
+/// Run the augmentation pipeline over a buffered Anthropic JSON response
+///
+/// Returns the re-serialized body with injected content blocks appended,
+/// plus the augmenter name and tokens injected. None if nothing was added
+/// (or the body isn't a message response).
+fn augment_buffered_response(
+    state: &ProxyState,
+    user_id: Option<&str>,
+    body: &[u8],
+) -> Option<(Bytes, &'static str, u32)> {
+    if state.augmentation.is_empty() {
+        return None;
+    }
+    let mut json = serde_json::from_slice::<serde_json::Value>(body).ok()?;
+    let stop_reason = StopReason::parse(json.get("stop_reason")?.as_str()?);
+    let model = json
+        .get("model")
+        .and_then(|m| m.as_str())
+        .unwrap_or_default()
+        .to_string();
+    let next_block_index = json.get("content")?.as_array()?.len() as u32;
+
+    let aug_ctx = AugmentationContext {
+        model: &model,
+        stop_reason,
+        next_block_index,
+        context_state: &state.context_state,
+        user_id,
+    };
+    let (augmenter, tokens) = state.augmentation.process_buffered(&aug_ctx, &mut json)?;
+    let augmented = serde_json::to_vec(&json).ok()?;
+    tracing::debug!(
+        tokens_injected = tokens,
+        "Augmentation appended ~{} tokens to buffered response",
+        tokens
+    );
+    Some((Bytes::from(augmented), augmenter, tokens))
+}
+
+/// Flush the partial event held by the streaming restorers
+fn flush_restorers(
+    restorer: &mut Option<transformation::StreamRestorer>,
+    name_restorer: &mut Option<transformation::ToolNameRestorer>,
+) -> Bytes {
+    let rest = restorer.as_mut().map(|r| r.finish()).unwrap_or_default();
+    match name_restorer.as_mut() {
+        Some(r) => [r.push(&rest), r.finish()].concat().into(),
+        None => rest,
+    }
+}
+
 /// Merge request and response headers into combined struct
 fn merge_headers(mut req: CapturedHeaders, resp: CapturedHeaders) -> CapturedHeaders {
     req.request_id = resp.request_id;
@@ -1487,6 +2461,8 @@ mod tests {
                 provider: "anthropic".to_string(),
                 tags: vec!["dev".to_string()],
                 auth: None,
+                fallback: vec![],
+                shadow: None,
             },
         );
         clients.insert(
@@ -1496,6 +2472,8 @@ mod tests {
                 provider: "foundry".to_string(),
                 tags: vec![],
                 auth: None,
+                fallback: vec![],
+                shadow: None,
             },
         );
 
@@ -1510,6 +2488,7 @@ mod tests {
                 auth: None,
                 count_tokens: None,
                 model_mapping: HashMap::new(),
+                fallback: vec![],
             },
         );
         providers.insert(
@@ -1522,6 +2501,7 @@ mod tests {
                 auth: None,
                 model_mapping: HashMap::new(),
                 count_tokens: None,
+                fallback: vec![],
             },
         );
 
@@ -1602,4 +2582,167 @@ mod tests {
         assert_eq!(routing.client_id, Some("dev-1".to_string()));
         assert_eq!(routing.api_path, "/v1/messages/count_tokens");
     }
+
+    #[test]
+    fn test_upstream_chain_follows_provider_fallback() {
+        let mut clients = make_test_clients();
+        clients
+            .providers
+            .get_mut("anthropic")
+            .unwrap()
+            .fallback
+            .push("foundry".to_string());
+        let default_url = "https://default.example.com";
+
+        let routing = extract_client_routing("/dev-1/v1/messages", &clients, default_url);
+        let chain = resolve_upstream_chain(&routing, &clients, default_url, None);
+
+        let ids: Vec<_> = chain.iter().map(|t| t.provider_id).collect();
+        assert_eq!(ids, vec![Some("anthropic"), Some("foundry")]);
+        assert_eq!(chain[1].base_url, "https://foundry.example.com");
+
+        // The client's credentials only go to its own provider
+        assert!(!chain[0].strip_client_auth);
+        assert!(chain[1].strip_client_auth);
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-api-key", "sk-ant-client".parse().unwrap());
+        headers.insert("authorization", "Bearer client-token".parse().unwrap());
+        headers.insert("anthropic-version", "2023-06-01".parse().unwrap());
+        let forward = |target: &UpstreamTarget<'_>| {
+            let builder = reqwest::Client::new().post("http://upstream.test/v1/messages");
+            copy_forward_headers(
+                builder,
+                &headers,
+                false,
+                target,
+                translation::ApiFormat::Anthropic,
+            )
+            .build()
+            .unwrap()
+        };
+
+        let primary = forward(&chain[0]);
+        assert_eq!(primary.headers()["x-api-key"], "sk-ant-client");
+        let fallback = forward(&chain[1]);
+        assert!(fallback.headers().get("x-api-key").is_none());
+        assert!(fallback.headers().get("authorization").is_none());
+        assert!(fallback.headers().get("anthropic-version").is_some());
+    }
+
+    #[test]
+    fn test_upstream_chain_unrouted_uses_default() {
+        let clients = make_test_clients();
+        let default_url = "https://default.example.com";
+
+        let routing = extract_client_routing("/v1/messages", &clients, default_url);
+        let chain = resolve_upstream_chain(&routing, &clients, default_url, None);
+
+        assert_eq!(chain.len(), 1);
+        assert_eq!(chain[0].provider_id, None);
+        assert_eq!(chain[0].base_url, default_url);
+    }
+
+    #[test]
+    fn test_upstream_chain_routed_provider_override() {
+        let mut clients = make_test_clients();
+        clients
+            .providers
+            .get_mut("foundry")
+            .unwrap()
+            .fallback
+            .push("anthropic".to_string());
+        let default_url = "https://default.example.com";
+
+        // Routed client: the routed provider and its fallbacks replace the client's chain
+        let routing = extract_client_routing("/dev-1/v1/messages", &clients, default_url);
+        let chain = resolve_upstream_chain(&routing, &clients, default_url, Some("foundry"));
+        let ids: Vec<_> = chain.iter().map(|t| t.provider_id).collect();
+        assert_eq!(ids, vec![Some("foundry"), Some("anthropic")]);
+
+        // Unrouted request can still be routed to a provider
+        let routing = extract_client_routing("/v1/messages", &clients, default_url);
+        let chain = resolve_upstream_chain(&routing, &clients, default_url, Some("foundry"));
+        assert_eq!(chain[0].base_url, "https://foundry.example.com");
+        assert!(chain[0].strip_client_auth);
+
+        // Unknown provider falls back to normal routing
+        let chain = resolve_upstream_chain(&routing, &clients, default_url, Some("nope"));
+        assert_eq!(chain[0].provider_id, None);
+    }
+
+    #[tokio::test]
+    async fn test_breakpoint_edit_drops_stale_content_length() {
+        let hub = std::sync::Arc::new(
+            breakpoints::BreakpointHub::from_config(&crate::config::Breakpoints {
+                rules: vec![crate::config::BreakpointRule::default()],
+                ..Default::default()
+            })
+            .unwrap(),
+        );
+        let original = Bytes::from_static(br#"{"model":"claude-haiku","messages":[]}"#);
+        let edited = br#"{"model":"claude-sonnet-4-5","messages":[],"max_tokens":64}"#.to_vec();
+
+        let resolver = {
+            let hub = std::sync::Arc::clone(&hub);
+            let edited = edited.clone();
+            tokio::spawn(async move {
+                loop {
+                    if let Some(held) = hub.next_pending() {
+                        let decision =
+                            breakpoints::BreakpointDecision::Continue { body: Some(edited) };
+                        assert!(hub.resolve(held.id, decision));
+                        break;
+                    }
+                    tokio::task::yield_now().await;
+                }
+            })
+        };
+        let (body, changed) =
+            hold_at_breakpoint(&hub, "req_1", "/v1/messages", None, original.clone())
+                .await
+                .unwrap();
+        resolver.await.unwrap();
+        assert!(changed);
+        assert_eq!(body.as_ref(), edited.as_slice());
+
+        let mut headers = HeaderMap::new();
+        headers.insert("content-length", original.len().into());
+        headers.insert("anthropic-version", "2023-06-01".parse().unwrap());
+        let target = UpstreamTarget {
+            provider_id: None,
+            base_url: "http://upstream.test",
+            provider: None,
+            auth: None,
+            strip_client_auth: false,
+        };
+        let forward = |body: Bytes, changed: bool| {
+            let builder = reqwest::Client::new()
+                .post("http://upstream.test/v1/messages")
+                .body(body);
+            copy_forward_headers(
+                builder,
+                &headers,
+                changed,
+                &target,
+                translation::ApiFormat::Anthropic,
+            )
+            .build()
+            .unwrap()
+        };
+
+        // Edited body: stale length is dropped so reqwest sets the real one
+        let request = forward(body.clone(), changed);
+        assert!(request.headers().get("content-length").is_none());
+        assert!(request.headers().get("anthropic-version").is_some());
+        let sent = request.body().and_then(|b| b.as_bytes()).unwrap();
+        assert_eq!(sent.len(), edited.len());
+
+        // Untouched body keeps the client's (still correct) length
+        let request = forward(original.clone(), false);
+        assert_eq!(
+            request.headers().get("content-length").unwrap(),
+            &original.len().to_string()
+        );
+    }
 }
@@ -0,0 +1,349 @@
+//! Per-client request and token rate limiting
+//!
+//! Several developers (or agents) often share one upstream org. A single
+//! runaway agent loop can burn through the org's rate limits and starve
+//! everyone else, so the proxy enforces its own limits per identity before
+//! anything is forwarded.
+//!
+//! # Limits
+//!
+//! ```text
+//! requests_per_minute      → requests admitted in the last 60s
+//! input_tokens_per_minute  → estimated input tokens admitted in the last 60s
+//! concurrent_streams       → responses in flight (held until the stream ends)
+//! ```
+//!
+//! Identities match session tracking: the client id for routed requests,
+//! otherwise the API key hash. A request over any limit is rejected with a
+//! 429 whose `retry-after` says when the window will have room again.
+//!
+//! A single request larger than the whole token budget is still admitted
+//! when the window is empty, so oversized prompts are slowed, not blocked
+//! forever.
+
+use crate::config::{RateLimit, RateLimits};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Length of the rolling window for per-minute limits
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Retry hint when the concurrent stream limit is hit (no way to know when
+/// an in-flight stream will finish)
+const CONCURRENT_RETRY_SECS: u64 = 1;
+
+/// Admission history for one identity
+#[derive(Debug, Default)]
+struct Window {
+    /// Admission time of each request in the window
+    requests: VecDeque<Instant>,
+    /// Admission time and estimated input tokens of each request in the window
+    tokens: VecDeque<(Instant, u64)>,
+    /// Sum of `tokens` (kept to avoid re-summing on every request)
+    token_total: u64,
+    /// Responses currently in flight (shared with outstanding permits)
+    active: Arc<AtomicU32>,
+}
+
+impl Window {
+    /// Drop entries older than the window
+    fn prune(&mut self, now: Instant) {
+        while self
+            .requests
+            .front()
+            .is_some_and(|t| now.duration_since(*t) >= WINDOW)
+        {
+            self.requests.pop_front();
+        }
+        while let Some(&(t, tokens)) = self.tokens.front() {
+            if now.duration_since(t) < WINDOW {
+                break;
+            }
+            self.token_total -= tokens;
+            self.tokens.pop_front();
+        }
+    }
+
+    /// Seconds until an entry admitted at `t` leaves the window (at least 1)
+    fn secs_until_expiry(t: Instant, now: Instant) -> u64 {
+        let remaining = (t + WINDOW).saturating_duration_since(now);
+        remaining.as_secs_f64().ceil().max(1.0) as u64
+    }
+
+    /// Seconds until enough tokens expire to fit `tokens` under `limit`
+    fn secs_until_tokens_fit(&self, tokens: u64, limit: u64, now: Instant) -> u64 {
+        let mut total = self.token_total;
+        for &(t, entry) in &self.tokens {
+            total -= entry;
+            // An oversized request only fits once the window is empty
+            if total == 0 || total + tokens <= limit {
+                return Self::secs_until_expiry(t, now);
+            }
+        }
+        CONCURRENT_RETRY_SECS
+    }
+}
+
+/// A request that was turned away
+#[derive(Debug, Clone, PartialEq)]
+pub struct Limited {
+    /// Human-readable reason (sent to the client)
+    pub message: String,
+    /// Seconds the client should wait before retrying
+    pub retry_after_secs: u64,
+}
+
+/// Held for the lifetime of an admitted response; releases its concurrent
+/// stream slot when dropped
+#[derive(Debug)]
+pub struct StreamPermit {
+    active: Arc<AtomicU32>,
+}
+
+impl Drop for StreamPermit {
+    fn drop(&mut self) {
+        self.active.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Shared rate limiter for all identities
+pub struct RateLimiter {
+    config: RateLimit,
+    windows: Mutex<HashMap<String, Window>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimit) -> Self {
+        Self {
+            config,
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn new_shared(config: RateLimit) -> Arc<Self> {
+        Arc::new(Self::new(config))
+    }
+
+    /// Effective limits for an identity, or None if it isn't limited
+    pub fn limits_for(&self, identity: &str) -> Option<RateLimits> {
+        if !self.config.enabled {
+            return None;
+        }
+        Some(self.config.limits_for(identity)).filter(|l| !l.is_unlimited())
+    }
+
+    /// Admit a request or explain why not
+    ///
+    /// On success the request counts against the window and the returned
+    /// permit occupies a concurrent stream slot until dropped.
+    pub fn admit(
+        &self,
+        identity: &str,
+        limits: &RateLimits,
+        input_tokens: u64,
+        now: Instant,
+    ) -> Result<StreamPermit, Limited> {
+        let mut windows = self.windows.lock().unwrap_or_else(|e| e.into_inner());
+        let window = windows.entry(identity.to_string()).or_default();
+        window.prune(now);
+
+        if let Some(max) = limits.concurrent_streams {
+            if window.active.load(Ordering::Relaxed) >= max {
+                return Err(Limited {
+                    message: format!(
+                        "aspy: '{}' reached its limit of {} concurrent streams",
+                        identity, max
+                    ),
+                    retry_after_secs: CONCURRENT_RETRY_SECS,
+                });
+            }
+        }
+
+        if let Some(max) = limits.requests_per_minute {
+            if window.requests.len() >= max as usize {
+                let retry_after_secs = window
+                    .requests
+                    .front()
+                    .map(|t| Window::secs_until_expiry(*t, now))
+                    .unwrap_or(CONCURRENT_RETRY_SECS);
+                return Err(Limited {
+                    message: format!("aspy: '{}' exceeded {} requests per minute", identity, max),
+                    retry_after_secs,
+                });
+            }
+        }
+
+        if let Some(max) = limits.input_tokens_per_minute {
+            if !window.tokens.is_empty() && window.token_total + input_tokens > max {
+                return Err(Limited {
+                    message: format!(
+                        "aspy: '{}' exceeded {} input tokens per minute ({} used, ~{} requested)",
+                        identity, max, window.token_total, input_tokens
+                    ),
+                    retry_after_secs: window.secs_until_tokens_fit(input_tokens, max, now),
+                });
+            }
+        }
+
+        window.requests.push_back(now);
+        window.tokens.push_back((now, input_tokens));
+        window.token_total += input_tokens;
+        window.active.fetch_add(1, Ordering::Relaxed);
+
+        Ok(StreamPermit {
+            active: window.active.clone(),
+        })
+    }
+}
+
+/// Estimate input tokens for a request body (system + messages + tools)
+///
+/// Uses the same local heuristic as transformation token tracking, so it
+/// works for both Anthropic and OpenAI-format bodies without an API call.
+pub fn estimate_input_tokens(body: &[u8]) -> u64 {
+    let Ok(json) = serde_json::from_slice::<serde_json::Value>(body) else {
+        return 0;
+    };
+    ["system", "messages", "tools"]
+        .iter()
+        .filter_map(|key| json.get(key))
+        .map(|value| crate::tokens::estimate_json_tokens(value) as u64)
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enabled_limiter(limits: RateLimits) -> RateLimiter {
+        RateLimiter::new(RateLimit {
+            enabled: true,
+            limits,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_requests_per_minute_window() {
+        let limits = RateLimits {
+            requests_per_minute: Some(2),
+            ..Default::default()
+        };
+        let limiter = enabled_limiter(limits);
+        let start = Instant::now();
+
+        assert!(limiter.admit("dev-1", &limits, 0, start).is_ok());
+        let later = start + Duration::from_secs(20);
+        assert!(limiter.admit("dev-1", &limits, 0, later).is_ok());
+
+        let limited = limiter
+            .admit("dev-1", &limits, 0, later)
+            .expect_err("third request should be limited");
+        assert_eq!(
+            limited.retry_after_secs, 40,
+            "oldest request expires at 60s"
+        );
+
+        // Other identities have their own window
+        assert!(limiter.admit("dev-2", &limits, 0, later).is_ok());
+
+        // Once the first request ages out there is room again
+        assert!(limiter.admit("dev-1", &limits, 0, start + WINDOW).is_ok());
+    }
+
+    #[test]
+    fn test_input_tokens_per_minute() {
+        let limits = RateLimits {
+            input_tokens_per_minute: Some(1000),
+            ..Default::default()
+        };
+        let limiter = enabled_limiter(limits);
+        let start = Instant::now();
+
+        assert!(limiter.admit("dev-1", &limits, 600, start).is_ok());
+        let later = start + Duration::from_secs(30);
+        assert!(limiter.admit("dev-1", &limits, 300, later).is_ok());
+
+        let limited = limiter
+            .admit("dev-1", &limits, 400, later)
+            .expect_err("would exceed token budget");
+        assert_eq!(limited.retry_after_secs, 30, "fits once the 600 expire");
+        assert!(limited.message.contains("input tokens per minute"));
+
+        // Oversized requests wait for an empty window, then go through
+        let huge = limiter
+            .admit("dev-1", &limits, 5000, later)
+            .expect_err("window not empty");
+        assert_eq!(huge.retry_after_secs, 60);
+        assert!(limiter
+            .admit("dev-1", &limits, 5000, later + WINDOW)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_concurrent_streams_released_on_drop() {
+        let limits = RateLimits {
+            concurrent_streams: Some(1),
+            ..Default::default()
+        };
+        let limiter = enabled_limiter(limits);
+        let now = Instant::now();
+
+        let permit = limiter.admit("dev-1", &limits, 0, now).unwrap();
+        let limited = limiter.admit("dev-1", &limits, 0, now).unwrap_err();
+        assert_eq!(limited.retry_after_secs, CONCURRENT_RETRY_SECS);
+
+        drop(permit);
+        assert!(limiter.admit("dev-1", &limits, 0, now).is_ok());
+    }
+
+    #[test]
+    fn test_limits_for_overrides_and_disabled() {
+        let mut config = RateLimit {
+            enabled: true,
+            limits: RateLimits {
+                requests_per_minute: Some(60),
+                concurrent_streams: Some(4),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        config.clients.insert(
+            "ci".to_string(),
+            RateLimits {
+                concurrent_streams: Some(1),
+                ..Default::default()
+            },
+        );
+        let limiter = RateLimiter::new(config.clone());
+
+        let ci = limiter.limits_for("ci").unwrap();
+        assert_eq!(ci.concurrent_streams, Some(1));
+        assert_eq!(ci.requests_per_minute, Some(60), "falls back to defaults");
+        assert_eq!(
+            limiter.limits_for("dev-1").unwrap().concurrent_streams,
+            Some(4)
+        );
+
+        config.enabled = false;
+        assert!(RateLimiter::new(config).limits_for("ci").is_none());
+        assert!(enabled_limiter(RateLimits::default())
+            .limits_for("ci")
+            .is_none());
+    }
+
+    #[test]
+    fn test_estimate_input_tokens() {
+        let body = serde_json::json!({
+            "model": "claude-sonnet-4",
+            "system": "You are helpful.",
+            "messages": [{"role": "user", "content": "Hello there, how are you today?"}],
+            "max_tokens": 1024
+        });
+        let tokens = estimate_input_tokens(&serde_json::to_vec(&body).unwrap());
+        assert!(tokens > 0);
+        assert_eq!(estimate_input_tokens(b"not json"), 0);
+    }
+}
@@ -0,0 +1,291 @@
+//! Upstream retry policy for rate-limit (429) and overload (529) responses
+//!
+//! Retries run inside `proxy_handler` after response headers arrive but
+//! before any bytes are forwarded, so the client only ever sees the final
+//! attempt.
+//!
+//! # Delay Selection
+//!
+//! ```text
+//! retry-after header present        → wait that long
+//! anthropic-ratelimit-*-reset with
+//!   remaining = 0                   → wait until the latest exhausted reset
+//! otherwise                         → exponential backoff with jitter
+//! ```
+//!
+//! Server-requested waits longer than `max_backoff_ms` are not retried: the
+//! 429 goes straight to the client, which can decide for itself.
+
+use crate::config::Retry;
+use crate::parser::models::CapturedHeaders;
+use chrono::{DateTime, Utc};
+use reqwest::header::HeaderMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Where a retry delay came from (shown in events)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DelaySource {
+    /// `retry-after` response header
+    RetryAfter,
+    /// `anthropic-ratelimit-*-reset` response headers
+    RateLimitReset,
+    /// Exponential backoff with jitter
+    Backoff,
+    /// Another pooled API key is available, no wait needed
+    KeyPool,
+}
+
+impl DelaySource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::RetryAfter => "retry-after",
+            Self::RateLimitReset => "ratelimit-reset",
+            Self::Backoff => "backoff",
+            Self::KeyPool => "key-pool",
+        }
+    }
+}
+
+/// A scheduled retry
+#[derive(Debug, Clone, Copy)]
+pub struct RetryDecision {
+    pub delay: Duration,
+    pub source: DelaySource,
+}
+
+/// Retry policy shared across requests
+pub struct RetryPolicy {
+    config: Retry,
+}
+
+impl RetryPolicy {
+    /// Create a new retry policy
+    pub fn new(config: Retry) -> Self {
+        Self { config }
+    }
+
+    /// Create a shared instance wrapped in Arc
+    pub fn new_shared(config: Retry) -> Arc<Self> {
+        Arc::new(Self::new(config))
+    }
+
+    /// Maximum attempts per request (including the first)
+    pub fn max_attempts(&self) -> u32 {
+        self.config.max_attempts
+    }
+
+    /// Decide whether to retry after `attempt` (1-based) returned `status`
+    ///
+    /// Returns None when retries are disabled, the status isn't retryable,
+    /// attempts are exhausted, or the server asked for a longer wait than
+    /// `max_backoff_ms`.
+    pub fn next_retry(
+        &self,
+        attempt: u32,
+        status: u16,
+        headers: &HeaderMap,
+    ) -> Option<RetryDecision> {
+        if !self.is_retryable(attempt, status) {
+            return None;
+        }
+
+        let max = Duration::from_millis(self.config.max_backoff_ms);
+        let captured = super::helpers::extract_response_headers(headers);
+
+        if let Some((delay, source)) = server_delay(headers, &captured, Utc::now()) {
+            if delay > max {
+                tracing::debug!(
+                    delay_ms = delay.as_millis() as u64,
+                    source = source.as_str(),
+                    "Server-requested wait exceeds max_backoff_ms, not retrying"
+                );
+                return None;
+            }
+            return Some(RetryDecision { delay, source });
+        }
+
+        Some(RetryDecision {
+            delay: backoff_delay(&self.config, attempt, jitter_fraction()),
+            source: DelaySource::Backoff,
+        })
+    }
+
+    /// Retry right away because another pooled API key is available
+    ///
+    /// Same eligibility rules as `next_retry`, but the rate-limited key's
+    /// reset time doesn't matter since the next attempt uses a different key.
+    pub fn immediate_retry(&self, attempt: u32, status: u16) -> Option<RetryDecision> {
+        self.is_retryable(attempt, status).then_some(RetryDecision {
+            delay: Duration::ZERO,
+            source: DelaySource::KeyPool,
+        })
+    }
+
+    fn is_retryable(&self, attempt: u32, status: u16) -> bool {
+        self.config.enabled
+            && attempt < self.config.max_attempts
+            && self.config.status_codes.contains(&status)
+    }
+}
+
+/// Exponential backoff for the retry following `attempt`, with equal jitter
+///
+/// The base delay doubles per attempt (capped at `max_backoff_ms`); half of
+/// it is fixed and half is scaled by `jitter` (0.0..1.0) so concurrent
+/// clients don't retry in lockstep.
+fn backoff_delay(config: &Retry, attempt: u32, jitter: f64) -> Duration {
+    let exponent = attempt.saturating_sub(1).min(20);
+    let base_ms = config
+        .initial_backoff_ms
+        .saturating_mul(1u64 << exponent)
+        .min(config.max_backoff_ms);
+    let half = base_ms / 2;
+    let jittered = half + ((base_ms - half) as f64 * jitter.clamp(0.0, 1.0)) as u64;
+    Duration::from_millis(jittered)
+}
+
+/// Server-provided wait from `retry-after` or exhausted rate limit resets
+pub(super) fn server_delay(
+    headers: &HeaderMap,
+    captured: &CapturedHeaders,
+    now: DateTime<Utc>,
+) -> Option<(Duration, DelaySource)> {
+    if let Some(value) = headers.get("retry-after").and_then(|v| v.to_str().ok()) {
+        let value = value.trim();
+        if let Ok(secs) = value.parse::<f64>() {
+            if secs.is_finite() && secs >= 0.0 {
+                return Some((Duration::from_secs_f64(secs), DelaySource::RetryAfter));
+            }
+        }
+        if let Ok(date) = DateTime::parse_from_rfc2822(value) {
+            return Some((
+                until(date.with_timezone(&Utc), now),
+                DelaySource::RetryAfter,
+            ));
+        }
+    }
+
+    // Only buckets that are actually exhausted matter; take the latest reset
+    let exhausted = [
+        (
+            captured.requests_remaining,
+            captured.requests_reset.as_deref(),
+        ),
+        (captured.tokens_remaining, captured.tokens_reset.as_deref()),
+    ];
+    exhausted
+        .iter()
+        .filter(|(remaining, _)| *remaining == Some(0))
+        .filter_map(|(_, reset)| DateTime::parse_from_rfc3339((*reset)?).ok())
+        .map(|reset| until(reset.with_timezone(&Utc), now))
+        .max()
+        .map(|delay| (delay, DelaySource::RateLimitReset))
+}
+
+/// Duration from `now` until `target` (zero if already past)
+fn until(target: DateTime<Utc>, now: DateTime<Utc>) -> Duration {
+    (target - now).to_std().unwrap_or(Duration::ZERO)
+}
+
+/// Random fraction in [0, 1) for jitter
+///
+/// `RandomState` is seeded from OS randomness, which is plenty for spreading
+/// retries without adding a dependency.
+fn jitter_fraction() -> f64 {
+    use std::hash::{BuildHasher, Hasher};
+
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u64(0);
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn config() -> Retry {
+        Retry {
+            enabled: true,
+            max_attempts: 3,
+            initial_backoff_ms: 1000,
+            max_backoff_ms: 8000,
+            status_codes: vec![429, 529],
+        }
+    }
+
+    #[test]
+    fn test_backoff_doubles_and_caps() {
+        let config = config();
+        // No jitter: half the base delay
+        assert_eq!(backoff_delay(&config, 1, 0.0), Duration::from_millis(500));
+        assert_eq!(backoff_delay(&config, 2, 0.0), Duration::from_millis(1000));
+        // Full jitter: the whole base delay
+        assert_eq!(backoff_delay(&config, 3, 1.0), Duration::from_millis(4000));
+        // Capped at max_backoff_ms
+        assert_eq!(backoff_delay(&config, 10, 1.0), Duration::from_millis(8000));
+    }
+
+    #[test]
+    fn test_next_retry_respects_policy() {
+        let policy = RetryPolicy::new(config());
+        let headers = HeaderMap::new();
+
+        assert!(policy.next_retry(1, 429, &headers).is_some());
+        assert!(policy.next_retry(2, 529, &headers).is_some());
+        // Attempts exhausted
+        assert!(policy.next_retry(3, 429, &headers).is_none());
+        // Not a retryable status
+        assert!(policy.next_retry(1, 500, &headers).is_none());
+
+        let disabled = RetryPolicy::new(Retry {
+            enabled: false,
+            ..config()
+        });
+        assert!(disabled.next_retry(1, 429, &headers).is_none());
+    }
+
+    #[test]
+    fn test_retry_after_header() {
+        let policy = RetryPolicy::new(config());
+        let mut headers = HeaderMap::new();
+        headers.insert("retry-after", HeaderValue::from_static("2"));
+
+        let decision = policy.next_retry(1, 429, &headers).unwrap();
+        assert_eq!(decision.delay, Duration::from_secs(2));
+        assert_eq!(decision.source, DelaySource::RetryAfter);
+
+        // Longer than max_backoff_ms: surface the 429 instead of waiting
+        headers.insert("retry-after", HeaderValue::from_static("60"));
+        assert!(policy.next_retry(1, 429, &headers).is_none());
+    }
+
+    #[test]
+    fn test_ratelimit_reset_uses_exhausted_buckets() {
+        let now = Utc::now();
+        let soon = (now + chrono::Duration::seconds(3)).to_rfc3339();
+        let later = (now + chrono::Duration::seconds(30)).to_rfc3339();
+
+        let captured = CapturedHeaders {
+            requests_remaining: Some(0),
+            requests_reset: Some(soon),
+            // Tokens bucket still has room, so its later reset is ignored
+            tokens_remaining: Some(5000),
+            tokens_reset: Some(later),
+            ..Default::default()
+        };
+
+        let (delay, source) = server_delay(&HeaderMap::new(), &captured, now).unwrap();
+        assert_eq!(source, DelaySource::RateLimitReset);
+        assert!(delay <= Duration::from_secs(3) && delay > Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_jitter_fraction_in_range() {
+        for _ in 0..100 {
+            let j = jitter_fraction();
+            assert!((0.0..1.0).contains(&j));
+        }
+    }
+}
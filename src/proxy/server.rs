@@ -8,12 +8,21 @@ use tokio::net::TcpListener;
 
 use crate::config::Config;
 use crate::parser::Parser;
+use crate::pipeline::embeddings::{AuthMethod, EmbeddingConfig, ProviderType};
 
 use super::api;
-use super::augmentation::AugmentationPipeline;
+use super::augmentation::{AugmentationPipeline, BudgetWarningAugmenter};
+use super::cassette::Cassette;
 use super::count_tokens;
+use super::failover;
+use super::key_pool;
 use super::proxy_handler;
+use super::rate_limit;
+use super::retry;
+use super::sessions;
 use super::state::{EventChannels, ProxyState, SharedState};
+use super::token_counter;
+use super::tool_guard;
 use super::transformation;
 use super::translation::TranslationPipeline;
 
@@ -39,17 +48,6 @@ pub async fn start_proxy(
         .build()
         .context("Failed to create HTTP client")?;
 
-    // Create augmentation pipeline from config (opt-in augmenters)
-    let augmentation = Arc::new(AugmentationPipeline::from_config(&config.augmentation));
-    if augmentation.is_empty() {
-        tracing::debug!("Augmentation pipeline: no augmenters enabled");
-    } else {
-        tracing::debug!(
-            "Augmentation pipeline initialized with: {:?}",
-            augmentation.augmenter_names()
-        );
-    }
-
     // Create translation pipeline from config (opt-in feature)
     let translation = Arc::new(TranslationPipeline::from_config(&config.translation));
     if translation.is_enabled() {
@@ -71,6 +69,83 @@ pub async fn start_proxy(
         tracing::debug!("Transformation pipeline: no transformers enabled");
     }
 
+    // Seed spending budgets with spend recorded before this run
+    if let Some(budget) = transformation.budget() {
+        match shared.cortex_query.as_deref() {
+            Some(query) => budget.seed_from_cortex(query),
+            None => tracing::warn!("Budgets enabled without cortex: spend resets on restart"),
+        }
+    }
+    if let Some(projects) = transformation.projects().filter(|p| p.has_budgets()) {
+        match shared.cortex_query.as_deref() {
+            Some(query) => projects.seed_from_cortex(query),
+            None => {
+                tracing::warn!("Project budgets enabled without cortex: spend resets on restart")
+            }
+        }
+    }
+
+    // Restore sessions from before the restart, then keep cortex up to date
+    if let (Some(query), Some(writer)) = (shared.cortex_query.as_deref(), shared.session_state) {
+        let snapshots = query.get_session_snapshots().unwrap_or_else(|e| {
+            tracing::warn!("Failed to load saved sessions: {}", e);
+            Vec::new()
+        });
+        let tracker = sessions::SnapshotTracker::seeded(&snapshots);
+        if let Ok(mut manager) = shared.sessions.lock() {
+            let restored = manager.restore(snapshots);
+            if restored > 0 {
+                tracing::info!("Restored {} session(s) from cortex", restored);
+            }
+        }
+        tokio::spawn(sessions::persist_sessions(
+            shared.sessions.clone(),
+            writer,
+            tracker,
+        ));
+    }
+
+    // Give the context enricher a query embedder for hybrid search
+    if let Some(enricher) = transformation.context_enricher() {
+        if shared.cortex_query.is_none() {
+            tracing::warn!("Context enricher enabled without cortex: nothing will be injected");
+        } else if config.embeddings.is_enabled() {
+            enricher.use_embeddings(EmbeddingConfig {
+                provider: match config.embeddings.provider.as_str() {
+                    "local" => ProviderType::Local,
+                    "remote" => ProviderType::Remote,
+                    _ => ProviderType::None,
+                },
+                model: config.embeddings.model.clone(),
+                api_key: config.embeddings.api_key.clone(),
+                api_base: config.embeddings.api_base.clone(),
+                api_version: config.embeddings.api_version.clone(),
+                auth_method: match config.embeddings.auth_method.as_str() {
+                    "api-key" => AuthMethod::ApiKey,
+                    _ => AuthMethod::Bearer,
+                },
+                dimensions: None,
+                batch_size: 1,    // One prompt per request
+                timeout_secs: 10, // Short timeout, the request is waiting
+            });
+        }
+    }
+
+    // Create augmentation pipeline from config (opt-in augmenters)
+    let mut augmentation = AugmentationPipeline::from_config(&config.augmentation);
+    if let Some(budget) = transformation.budget() {
+        augmentation.register(BudgetWarningAugmenter::new(Arc::clone(budget)));
+    }
+    let augmentation = Arc::new(augmentation);
+    if augmentation.is_empty() {
+        tracing::debug!("Augmentation pipeline: no augmenters enabled");
+    } else {
+        tracing::debug!(
+            "Augmentation pipeline initialized with: {:?}",
+            augmentation.augmenter_names()
+        );
+    }
+
     // Log client routing config if present
     if config.clients.is_configured() {
         tracing::info!(
@@ -88,6 +163,40 @@ pub async fn start_proxy(
         }
     }
 
+    // Tool guard (a broken rule disables the guard rather than the proxy)
+    let tool_policy = if config.tool_guard.is_configured() {
+        match tool_guard::ToolPolicy::from_config(&config.tool_guard) {
+            Ok(policy) => {
+                tracing::info!("Tool guard enabled ({} rules)", policy.rule_count());
+                // The guard reads Anthropic-format responses only
+                let mut unguarded: Vec<&str> = config
+                    .clients
+                    .providers
+                    .iter()
+                    .filter(|(_, p)| p.api_format == crate::config::ApiFormat::Openai)
+                    .map(|(id, _)| id.as_str())
+                    .collect();
+                if !unguarded.is_empty() {
+                    unguarded.sort_unstable();
+                    tracing::warn!(
+                        "Tool guard does not check responses from OpenAI-format providers: {}",
+                        unguarded.join(", ")
+                    );
+                }
+                Some(Arc::new(policy))
+            }
+            Err(e) => {
+                tracing::warn!("Failed to create tool guard: {}. Guard disabled.", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    // Cassette record/replay (replay fails fast if the cassette can't be loaded)
+    let cassette = Cassette::from_config(&config.cassette)?;
+
     let state = ProxyState {
         client,
         parser: Parser::new(),
@@ -105,6 +214,8 @@ pub async fn start_proxy(
         pipeline: shared.pipeline,
         cortex_query: shared.cortex_query,
         embedding_indexer: shared.embedding_indexer,
+        agent_id: None,
+        project: None,
         translation,
         transformation,
         transformers_config: config.transformers.clone(),
@@ -115,6 +226,14 @@ pub async fn start_proxy(
                 rate_limit_per_second: config.count_tokens.rate_limit_per_second,
             },
         ),
+        provider_health: failover::ProviderHealth::new_shared(config.failover.clone()),
+        retry_policy: retry::RetryPolicy::new_shared(config.retry.clone()),
+        key_pools: key_pool::KeyPools::new_shared(&config.clients),
+        rate_limiter: rate_limit::RateLimiter::new_shared(config.rate_limit.clone()),
+        token_counter: token_counter::TokenCounter::new_shared(),
+        cassette,
+        breakpoints: shared.breakpoints,
+        tool_policy,
     };
 
     // Build the router - API endpoints + proxy handler
@@ -180,6 +299,10 @@ pub async fn start_proxy(
             axum::routing::get(api::cortex_context),
         )
         .route("/api/cortex/stats", axum::routing::get(api::cortex_stats))
+        .route(
+            "/api/cortex/tool-result/:call_id",
+            axum::routing::get(api::cortex_tool_result),
+        )
         // User-scoped cortex endpoints
         .route(
             "/api/cortex/search/user/:user_id/thinking",
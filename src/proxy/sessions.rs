@@ -15,10 +15,13 @@
 // until these features are wired up.
 #![allow(dead_code)]
 
+use super::subagents::{self, SubagentTree};
 use crate::events::{ProxyEvent, Stats};
+use crate::pipeline::cortex::{SessionStateBatch, SessionStateRow, SessionStateWriter};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
 // ─────────────────────────────────────────────────────────────────────────────
@@ -263,7 +266,7 @@ pub fn parse_todos_from_input(input: &serde_json::Value) -> Option<Vec<TodoItem>
 ///
 /// Mixing these in Stats caused semantic confusion in multi-user scenarios
 /// where global "current context" was meaningless (just "whoever was last").
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ContextState {
     /// Current context size from last ApiUsage event
     /// This is input + cache_creation + cache_read tokens
@@ -394,6 +397,21 @@ pub struct Session {
     /// Enables cross-referencing aspy events with Claude's native storage.
     /// Example: ~/.claude/projects/.../abc123.jsonl
     pub transcript_path: Option<String>,
+
+    /// Project from the projects transformer's registry
+    ///
+    /// Detected from the working directory in the system prompt. Stored with
+    /// the session in cortex so stats can be grouped per repository.
+    pub project: Option<String>,
+
+    /// Subagent (Task tool) conversations spawned in this session
+    pub subagents: SubagentTree,
+
+    /// Restored from a snapshot and untouched since
+    ///
+    /// A SessionStart for the same key continues such a session (a resume
+    /// after a proxy restart) instead of superseding it.
+    pub restored: bool,
 }
 
 impl Session {
@@ -425,6 +443,9 @@ impl Session {
             todos: Vec::new(),
             todos_updated: None,
             transcript_path: None,
+            project: None,
+            subagents: SubagentTree::default(),
+            restored: false,
         }
     }
 
@@ -433,8 +454,22 @@ impl Session {
     /// Returns an optional synthesized event to emit (e.g., TodoSnapshot when
     /// we intercept a TodoWrite tool call).
     pub fn record_event(&mut self, event: ProxyEvent) -> Option<ProxyEvent> {
+        self.record_agent_event(event, None)
+    }
+
+    /// Record an event from a subagent conversation (None = main conversation)
+    ///
+    /// Besides `record_event`'s bookkeeping, this maintains the subagent tree:
+    /// Task calls spawn nodes, usage is rolled up, and the Task tool result
+    /// synthesizes a SubagentCompleted event.
+    pub fn record_agent_event(
+        &mut self,
+        event: ProxyEvent,
+        agent: Option<&str>,
+    ) -> Option<ProxyEvent> {
         self.last_activity = Instant::now();
         self.status = SessionStatus::Active;
+        self.restored = false;
 
         // Update stats based on event type
         self.stats.update(&event);
@@ -444,29 +479,57 @@ impl Session {
 
         // Update context state and intercept special tool calls
         match &event {
+            // Subagent calls count towards the subagent, not the main context window
             ProxyEvent::ApiUsage {
                 input_tokens,
+                output_tokens,
                 cache_creation_tokens,
                 cache_read_tokens,
                 model,
                 ..
-            } => {
-                // Don't update context for Haiku (summarization, not main conversation)
-                if !model.contains("haiku") {
-                    self.context.update_from_api_usage(
-                        *input_tokens,
-                        *cache_creation_tokens,
-                        *cache_read_tokens,
-                    );
-                }
+            } if agent.is_some() => {
+                self.subagents.record_usage(
+                    agent.unwrap_or_default(),
+                    model,
+                    *input_tokens,
+                    *output_tokens,
+                    *cache_creation_tokens,
+                    *cache_read_tokens,
+                );
+            }
+            ProxyEvent::ApiUsage {
+                input_tokens,
+                cache_creation_tokens,
+                cache_read_tokens,
+                model,
+                ..
+            } if !model.contains("haiku") => {
+                // Haiku calls are summarization, not main conversation context
+                self.context.update_from_api_usage(
+                    *input_tokens,
+                    *cache_creation_tokens,
+                    *cache_read_tokens,
+                );
+            }
+            ProxyEvent::ToolCall {
+                id,
+                tool_name,
+                input,
+                ..
+            } if subagents::is_subagent_tool(tool_name) => {
+                self.subagents.spawn(id, input, agent);
+            }
+            ProxyEvent::ToolResult { id, success, .. } => {
+                synthesized_event = self.subagents.finish(id, *success);
             }
             ProxyEvent::ContextCompact { new_context, .. } => {
                 self.context.update_from_compact(*new_context);
             }
             // Intercept TodoWrite tool calls to track Claude's current tasks
+            // (subagents keep their own todo lists, not the session's)
             ProxyEvent::ToolCall {
                 tool_name, input, ..
-            } if tool_name == "TodoWrite" => {
+            } if tool_name == "TodoWrite" && agent.is_none() => {
                 if let Some(todos) = parse_todos_from_input(input) {
                     tracing::debug!(
                         user = %self.user_id.short(),
@@ -542,6 +605,176 @@ impl Session {
     pub fn is_active(&self) -> bool {
         !matches!(self.status, SessionStatus::Ended { .. })
     }
+
+    /// Rebuild a session from a persisted snapshot
+    ///
+    /// The event buffer starts empty and activity is reset to now, so a
+    /// restored session isn't immediately treated as idle.
+    pub fn from_snapshot(snapshot: SessionSnapshot, context_limit: u64) -> Self {
+        let mut context = snapshot.context;
+        context.limit = context_limit;
+
+        Self {
+            key: snapshot.key,
+            user_id: snapshot.user_id,
+            claude_session_id: snapshot.claude_session_id,
+            source: snapshot.source,
+            started: snapshot.started,
+            last_activity: Instant::now(),
+            stats: snapshot.stats,
+            context,
+            events: VecDeque::with_capacity(MAX_SESSION_EVENTS),
+            status: SessionStatus::Active,
+            todos: snapshot.todos,
+            todos_updated: snapshot.todos_updated,
+            transcript_path: snapshot.transcript_path,
+            project: snapshot.project,
+            subagents: snapshot.subagents,
+            restored: true,
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Persistence
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// How often live sessions are compared against the last saved snapshots
+pub const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Serializable state of a live session
+///
+/// Everything needed to continue a session after a proxy restart. The event
+/// buffer and idle tracking are runtime-only and start fresh.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionSnapshot {
+    pub key: SessionKey,
+    pub user_id: UserId,
+    pub claude_session_id: Option<String>,
+    pub source: SessionSource,
+    pub started: DateTime<Utc>,
+    #[serde(default)]
+    pub stats: Stats,
+    #[serde(default)]
+    pub context: ContextState,
+    #[serde(default)]
+    pub todos: Vec<TodoItem>,
+    #[serde(default)]
+    pub todos_updated: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub transcript_path: Option<String>,
+    #[serde(default)]
+    pub project: Option<String>,
+    #[serde(default)]
+    pub subagents: SubagentTree,
+}
+
+impl From<&Session> for SessionSnapshot {
+    fn from(session: &Session) -> Self {
+        Self {
+            key: session.key.clone(),
+            user_id: session.user_id.clone(),
+            claude_session_id: session.claude_session_id.clone(),
+            source: session.source,
+            started: session.started,
+            stats: session.stats.clone(),
+            context: session.context.clone(),
+            todos: session.todos.clone(),
+            todos_updated: session.todos_updated,
+            transcript_path: session.transcript_path.clone(),
+            project: session.project.clone(),
+            subagents: session.subagents.clone(),
+        }
+    }
+}
+
+/// Tracks what was last written so only changed sessions are saved
+#[derive(Debug, Default)]
+pub struct SnapshotTracker {
+    /// Session key (display form) → last written state JSON
+    written: HashMap<String, String>,
+}
+
+impl SnapshotTracker {
+    /// Start from the snapshots that were just restored (already on disk)
+    pub fn seeded(snapshots: &[SessionSnapshot]) -> Self {
+        let written = snapshots
+            .iter()
+            .filter_map(|s| Some((s.key.to_string(), serde_json::to_string(s).ok()?)))
+            .collect();
+        Self { written }
+    }
+
+    /// Diff live snapshots against the last written state
+    pub fn changes(&self, snapshots: &[SessionSnapshot]) -> SessionStateBatch {
+        let mut batch = SessionStateBatch::default();
+
+        for snapshot in snapshots {
+            let key = snapshot.key.to_string();
+            let Ok(state_json) = serde_json::to_string(snapshot) else {
+                continue;
+            };
+            if self.written.get(&key) != Some(&state_json) {
+                batch.upserts.push(SessionStateRow {
+                    session_key: key,
+                    user_id: snapshot.user_id.0.clone(),
+                    state_json,
+                });
+            }
+        }
+
+        batch.removed = self
+            .written
+            .keys()
+            .filter(|key| !snapshots.iter().any(|s| s.key.to_string() == **key))
+            .cloned()
+            .collect();
+
+        batch
+    }
+
+    /// Record a batch as written
+    pub fn commit(&mut self, batch: &SessionStateBatch) {
+        for key in &batch.removed {
+            self.written.remove(key);
+        }
+        for row in &batch.upserts {
+            self.written
+                .insert(row.session_key.clone(), row.state_json.clone());
+        }
+    }
+}
+
+/// Periodically save changed sessions to cortex
+///
+/// Runs for the lifetime of the proxy. A batch the writer can't accept
+/// (channel full) isn't committed, so it's retried on the next tick.
+pub async fn persist_sessions(
+    sessions: Arc<Mutex<SessionManager>>,
+    writer: SessionStateWriter,
+    mut tracker: SnapshotTracker,
+) {
+    let mut interval = tokio::time::interval(SNAPSHOT_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+    loop {
+        interval.tick().await;
+
+        let snapshots = match sessions.lock() {
+            Ok(manager) => manager.snapshots(),
+            Err(_) => continue,
+        };
+
+        let batch = tracker.changes(&snapshots);
+        if batch.is_empty() {
+            continue;
+        }
+        if writer.save(batch.clone()) {
+            tracker.commit(&batch);
+        } else {
+            tracing::debug!("Cortex writer busy, deferring session snapshot");
+        }
+    }
 }
 
 // ─────────────────────────────────────────────────────────────────────────────
@@ -627,6 +860,36 @@ impl SessionManager {
             }
         };
 
+        // A resume after a proxy restart continues the restored session
+        // rather than superseding it with an empty one. Any other repeat
+        // (e.g. the SessionStart after a compaction) supersedes as usual.
+        let resumed = match self.sessions.get(&key) {
+            Some(session)
+                if session.restored
+                    && key.is_explicit()
+                    && self.active_by_user.get(&user_id) == Some(&key) =>
+            {
+                self.sessions.remove(&key)
+            }
+            _ => None,
+        };
+        if let Some(mut session) = resumed {
+            tracing::debug!(
+                session_key = %key,
+                user = %user_id.short(),
+                "Continuing restored session for user {} with session_key {}",
+                user_id.short(),
+                key
+            );
+            session.restored = false;
+            session.last_activity = Instant::now();
+            session.status = SessionStatus::Active;
+            if transcript_path.is_some() {
+                session.transcript_path = transcript_path;
+            }
+            return self.insert_active(user_id, key, session);
+        }
+
         // Supersede existing session for this user
         if let Some(old_key) = self.active_by_user.remove(&user_id) {
             if let Some(mut old_session) = self.sessions.remove(&old_key) {
@@ -667,10 +930,13 @@ impl SessionManager {
             );
         }
 
-        self.sessions.insert(key.clone(), session);
-        self.active_by_user.insert(user_id, key.clone());
+        self.insert_active(user_id, key, session)
+    }
 
-        self.sessions.get(&key).unwrap()
+    /// Store a session as the user's active one
+    fn insert_active(&mut self, user_id: UserId, key: SessionKey, session: Session) -> &Session {
+        self.active_by_user.insert(user_id, key.clone());
+        self.sessions.entry(key).insert_entry(session).into_mut()
     }
 
     /// End a session explicitly (from hook)
@@ -791,13 +1057,23 @@ impl SessionManager {
     /// If no session exists, creates an implicit one (FirstSeen).
     /// Returns an optional synthesized event to emit (e.g., TodoSnapshot).
     pub fn record_event(&mut self, user_id: &UserId, event: ProxyEvent) -> Option<ProxyEvent> {
+        self.record_agent_event(user_id, event, None)
+    }
+
+    /// Record an event for a user, attributed to a subagent conversation
+    pub fn record_agent_event(
+        &mut self,
+        user_id: &UserId,
+        event: ProxyEvent,
+        agent: Option<&str>,
+    ) -> Option<ProxyEvent> {
         // Ensure user has a session
         let key = self.active_by_user.get(user_id).cloned();
 
         match key {
             Some(key) => {
                 if let Some(session) = self.sessions.get_mut(&key) {
-                    return session.record_event(event);
+                    return session.record_agent_event(event, agent);
                 }
             }
             None => {
@@ -806,7 +1082,7 @@ impl SessionManager {
                 // Record the event in the newly created session
                 if let Some(key) = self.active_by_user.get(user_id) {
                     if let Some(session) = self.sessions.get_mut(key) {
-                        return session.record_event(event);
+                        return session.record_agent_event(event, agent);
                     }
                 }
             }
@@ -928,6 +1204,44 @@ impl SessionManager {
         }
     }
 
+    /// Attach a detected project to the user's active session
+    ///
+    /// The latest detection wins (a user can move between repositories
+    /// without starting a new session).
+    pub fn set_project(&mut self, user_id: &UserId, project: &str) {
+        if let Some(session) = self.get_user_session_mut(user_id) {
+            if session.project.as_deref() != Some(project) {
+                session.project = Some(project.to_string());
+            }
+        }
+    }
+
+    /// Snapshot every live session for persistence
+    pub fn snapshots(&self) -> Vec<SessionSnapshot> {
+        self.sessions.values().map(SessionSnapshot::from).collect()
+    }
+
+    /// Restore sessions saved before a restart
+    ///
+    /// Users that already have a session keep it. Returns the number of
+    /// sessions restored.
+    pub fn restore(&mut self, snapshots: Vec<SessionSnapshot>) -> usize {
+        let mut restored = 0;
+        for snapshot in snapshots {
+            if self.active_by_user.contains_key(&snapshot.user_id)
+                || self.sessions.contains_key(&snapshot.key)
+            {
+                continue;
+            }
+            let session = Session::from_snapshot(snapshot, self.context_limit);
+            self.active_by_user
+                .insert(session.user_id.clone(), session.key.clone());
+            self.sessions.insert(session.key.clone(), session);
+            restored += 1;
+        }
+        restored
+    }
+
     /// Backfill user_id for sessions with "unknown" user
     ///
     /// Called when we see a request with api_key_hash - updates any active
@@ -1019,4 +1333,165 @@ mod tests {
             }
         ));
     }
+
+    #[test]
+    fn test_snapshot_restore_continues_session() {
+        let mut manager = SessionManager::default();
+        let user = UserId::new("user1");
+        manager.start_session(
+            user.clone(),
+            Some("session1".to_string()),
+            SessionSource::Hook,
+            Some("/tmp/abc.jsonl".to_string()),
+            None,
+        );
+        manager.record_event(
+            &user,
+            ProxyEvent::ToolCall {
+                id: "t1".to_string(),
+                timestamp: Utc::now(),
+                tool_name: "TodoWrite".to_string(),
+                input: serde_json::json!({"todos": [
+                    {"content": "Fix bug", "status": "in_progress", "activeForm": "Fixing bug"}
+                ]}),
+            },
+        );
+        manager.set_project(&user, "aspy");
+
+        // Roundtrip through JSON as cortex would store it
+        let json = serde_json::to_string(&manager.snapshots()).unwrap();
+        let snapshots: Vec<SessionSnapshot> = serde_json::from_str(&json).unwrap();
+
+        let mut restarted = SessionManager::new(DEFAULT_IDLE_TIMEOUT, 200_000);
+        assert_eq!(restarted.restore(snapshots), 1);
+
+        let session = restarted.get_user_session(&user).unwrap();
+        assert_eq!(session.key, SessionKey::explicit("session1"));
+        assert_eq!(session.stats.total_tool_calls, 1);
+        assert_eq!(session.todos.len(), 1);
+        assert_eq!(session.project.as_deref(), Some("aspy"));
+        assert_eq!(session.context.limit, 200_000);
+
+        // The same session starting again continues instead of resetting
+        restarted.start_session(
+            user.clone(),
+            Some("session1".to_string()),
+            SessionSource::Hook,
+            None,
+            None,
+        );
+        let session = restarted.get_user_session(&user).unwrap();
+        assert_eq!(session.stats.total_tool_calls, 1);
+        assert_eq!(session.transcript_path.as_deref(), Some("/tmp/abc.jsonl"));
+        assert!(restarted.history.is_empty());
+
+        // Later starts for the same key (e.g. after a compaction) supersede it
+        restarted.start_session(
+            user.clone(),
+            Some("session1".to_string()),
+            SessionSource::Hook,
+            None,
+            None,
+        );
+        let session = restarted.get_user_session(&user).unwrap();
+        assert_eq!(session.stats.total_tool_calls, 0);
+        assert_eq!(restarted.history.len(), 1);
+    }
+
+    #[test]
+    fn test_snapshot_tracker_only_writes_changes() {
+        let mut manager = SessionManager::default();
+        let user = UserId::new("user1");
+        manager.start_session(
+            user.clone(),
+            Some("session1".to_string()),
+            SessionSource::Hook,
+            None,
+            None,
+        );
+
+        let mut tracker = SnapshotTracker::default();
+        let batch = tracker.changes(&manager.snapshots());
+        assert_eq!(batch.upserts.len(), 1);
+        tracker.commit(&batch);
+
+        // Nothing changed since the last write
+        assert!(tracker.changes(&manager.snapshots()).is_empty());
+
+        manager.increment_turn_count(&user);
+        let batch = tracker.changes(&manager.snapshots());
+        assert_eq!(batch.upserts.len(), 1);
+        assert!(batch.removed.is_empty());
+        tracker.commit(&batch);
+
+        // Ended sessions are removed from the table
+        manager.end_session(&SessionKey::explicit("session1"), EndReason::Hook);
+        let batch = tracker.changes(&manager.snapshots());
+        assert!(batch.upserts.is_empty());
+        assert_eq!(batch.removed, vec!["session1".to_string()]);
+    }
+
+    #[test]
+    fn test_subagent_usage_stays_out_of_main_context() {
+        let mut manager = SessionManager::default();
+        let user = UserId::new("user1");
+        let usage = |input_tokens| ProxyEvent::ApiUsage {
+            timestamp: Utc::now(),
+            model: "claude-sonnet-4-5".to_string(),
+            input_tokens,
+            output_tokens: 100,
+            cache_creation_tokens: 0,
+            cache_read_tokens: 0,
+            cache_optimized: false,
+        };
+
+        manager.record_event(&user, usage(80_000));
+        manager.record_event(
+            &user,
+            ProxyEvent::ToolCall {
+                id: "toolu_task".to_string(),
+                timestamp: Utc::now(),
+                tool_name: "Task".to_string(),
+                input: serde_json::json!({"description": "Explore", "prompt": "Map the repo"}),
+            },
+        );
+        manager.record_agent_event(&user, usage(5_000), Some("toolu_task"));
+
+        // A subagent's TodoWrite doesn't replace the main conversation's todos
+        manager.record_agent_event(
+            &user,
+            ProxyEvent::ToolCall {
+                id: "toolu_todo".to_string(),
+                timestamp: Utc::now(),
+                tool_name: "TodoWrite".to_string(),
+                input: serde_json::json!({"todos": [
+                    {"content": "Scan src", "status": "in_progress", "activeForm": "Scanning src"}
+                ]}),
+            },
+            Some("toolu_task"),
+        );
+
+        let session = manager.get_user_session(&user).unwrap();
+        assert_eq!(session.context.current_tokens, 80_000);
+        assert!(session.todos.is_empty());
+        let subagent = session.subagents.get("toolu_task").unwrap();
+        assert_eq!(subagent.usage.input_tokens, 5_000);
+
+        // The Task tool result completes the subagent
+        let completed = manager.record_event(
+            &user,
+            ProxyEvent::ToolResult {
+                id: "toolu_task".to_string(),
+                timestamp: Utc::now(),
+                tool_name: "Task".to_string(),
+                output: serde_json::json!("done"),
+                duration: Duration::from_secs(3),
+                success: true,
+            },
+        );
+        assert!(matches!(
+            completed,
+            Some(ProxyEvent::SubagentCompleted { requests: 1, .. })
+        ));
+    }
 }
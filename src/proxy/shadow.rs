@@ -0,0 +1,423 @@
+//! Shadow traffic mirroring
+//!
+//! Before moving a team to another backend it helps to see how it behaves
+//! on real traffic. A client with `shadow = "<provider>"` has each
+//! `/v1/messages` request mirrored to that provider in the background,
+//! translated for its `api_format` like any failover target.
+//!
+//! ```text
+//! client ──► primary provider ──► client
+//!    └────► shadow provider ──► (discarded)
+//!                 │
+//!                 ▼
+//!      ShadowComparison event → cortex shadow_comparisons
+//! ```
+//!
+//! The shadow response never reaches the client and shadow failures never
+//! affect the primary request. Once both sides finish, a `ShadowComparison`
+//! event records usage, latency, stop_reason and tool choices side by side.
+
+use super::sse;
+use super::state::ProxyState;
+use super::translation::ApiFormat;
+use super::{describe_upstream_error, prepare_upstream_request, OutgoingRequest, UpstreamTarget};
+use crate::config::ClientsConfig;
+use crate::events::{ProxyEvent, ResponseSample};
+use chrono::Utc;
+use serde_json::Value;
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+
+/// How long a finished shadow waits for the primary response
+///
+/// Long agent turns can stream for minutes; after this the comparison is
+/// recorded with the primary marked as missing.
+const PRIMARY_WAIT: Duration = Duration::from_secs(600);
+
+/// Sender for the primary response's sample
+pub(super) type PrimarySender = oneshot::Sender<ResponseSample>;
+
+/// Mirror a request to the client's shadow provider in the background
+///
+/// Returns the sender the response handler uses to report the primary
+/// sample, or None if the client has no (known) shadow provider.
+pub(super) fn spawn(
+    state: &ProxyState,
+    outgoing: &OutgoingRequest<'_>,
+    client_id: &str,
+    request_id: &str,
+    user_id: Option<&str>,
+) -> Option<PrimarySender> {
+    let target = shadow_target(&state.clients, client_id)?;
+    let provider_id = target.provider_id.unwrap_or_default();
+    let upstream = match prepare_upstream_request(state, outgoing, &target) {
+        Ok(upstream) => upstream,
+        Err(e) => {
+            tracing::warn!(provider = provider_id, "Shadow request not sent: {:?}", e);
+            return None;
+        }
+    };
+
+    let (tx, rx) = oneshot::channel();
+    let state = state.clone();
+    let provider_id = provider_id.to_string();
+    let request_id = request_id.to_string();
+    let user_id = user_id.map(str::to_string);
+    let backend_format = upstream.translation_ctx.backend_format;
+
+    tokio::spawn(async move {
+        let sent_at = Instant::now();
+        let shadow = match upstream.builder.send().await {
+            Ok(response) => {
+                if let Some(key_idx) = upstream.pool_key {
+                    state.key_pools.record_response(
+                        &provider_id,
+                        key_idx,
+                        response.status().as_u16(),
+                        response.headers(),
+                    );
+                }
+                let status = response.status().as_u16();
+                let ttfb = sent_at.elapsed();
+                match response.bytes().await {
+                    Ok(body) => sample_body(&body, backend_format, status, ttfb, sent_at.elapsed()),
+                    Err(e) => failed_sample(e.to_string(), sent_at.elapsed()),
+                }
+            }
+            Err(e) => {
+                let (kind, error_chain) = describe_upstream_error(&e);
+                failed_sample(
+                    format!("{} error: {}", kind, error_chain),
+                    sent_at.elapsed(),
+                )
+            }
+        };
+        tracing::debug!(
+            provider = %provider_id,
+            status = ?shadow.status,
+            latency_ms = shadow.latency_ms,
+            "Shadow response complete"
+        );
+
+        let primary = match tokio::time::timeout(PRIMARY_WAIT, rx).await {
+            Ok(Ok(primary)) => primary,
+            Ok(Err(_)) => {
+                failed_sample("primary response not captured".to_string(), Duration::ZERO)
+            }
+            Err(_) => failed_sample("timed out waiting for primary".to_string(), PRIMARY_WAIT),
+        };
+
+        state
+            .send_event(
+                ProxyEvent::ShadowComparison {
+                    timestamp: Utc::now(),
+                    request_id,
+                    shadow_provider: provider_id,
+                    primary,
+                    shadow,
+                },
+                user_id.as_deref(),
+            )
+            .await;
+    });
+
+    Some(tx)
+}
+
+/// Upstream target for a client's shadow provider
+///
+/// The client's own credentials are never mirrored: the shadow is always a
+/// different provider, so it only gets its own configured auth.
+fn shadow_target<'a>(clients: &'a ClientsConfig, client_id: &str) -> Option<UpstreamTarget<'a>> {
+    let (provider_id, provider) = clients.get_client_shadow(client_id)?;
+    Some(UpstreamTarget {
+        provider_id: Some(provider_id),
+        base_url: &provider.base_url,
+        provider: Some(provider),
+        auth: clients.get_provider_auth(client_id, provider_id),
+        strip_client_auth: true,
+    })
+}
+
+/// Sample a complete response body (JSON or accumulated SSE) in the
+/// upstream's native format
+pub(super) fn sample_body(
+    body: &[u8],
+    format: ApiFormat,
+    status: u16,
+    ttfb: Duration,
+    latency: Duration,
+) -> ResponseSample {
+    let text = String::from_utf8_lossy(body);
+    let trimmed = text.trim_start();
+    let is_sse = trimmed.starts_with("event:") || trimmed.starts_with("data:");
+    let json = if is_sse {
+        match format {
+            ApiFormat::Anthropic => sse::assemble_to_json(&text),
+            ApiFormat::OpenAI => sse::assemble_openai_to_json(&text),
+        }
+    } else {
+        serde_json::from_slice::<Value>(body).ok()
+    };
+
+    let mut sample = match &json {
+        Some(json) => sample_json(json),
+        None => ResponseSample {
+            error: Some("unparseable response body".to_string()),
+            ..Default::default()
+        },
+    };
+    // Anthropic streams report input usage in message_start, not message_delta
+    if is_sse && format == ApiFormat::Anthropic && sample.input_tokens == 0 {
+        sample.input_tokens = message_start_input_tokens(&text).unwrap_or(0);
+    }
+    sample.status = Some(status);
+    sample.ttfb_ms = ttfb.as_millis() as u64;
+    sample.latency_ms = latency.as_millis() as u64;
+    if !(200..300).contains(&status) && sample.error.is_none() {
+        sample.error = json
+            .as_ref()
+            .and_then(|j| j.pointer("/error/message"))
+            .and_then(|m| m.as_str())
+            .map(str::to_string)
+            .or_else(|| Some(format!("HTTP {}", status)));
+    }
+    sample
+}
+
+/// Extract usage, stop reason and tool choices from an Anthropic or
+/// OpenAI-format response
+fn sample_json(json: &Value) -> ResponseSample {
+    let model = json
+        .get("model")
+        .and_then(|m| m.as_str())
+        .filter(|m| !m.is_empty())
+        .map(str::to_string);
+    let usage = json.get("usage");
+    let usage_field = |keys: &[&str]| {
+        keys.iter()
+            .find_map(|k| usage.and_then(|u| u.get(*k)).and_then(|v| v.as_u64()))
+            .unwrap_or(0)
+    };
+    let input_tokens = usage_field(&["input_tokens", "prompt_tokens"]);
+    let output_tokens = usage_field(&["output_tokens", "completion_tokens"]);
+
+    let (stop_reason, tool_uses) = match json.pointer("/choices/0") {
+        // OpenAI: finish_reason + message.tool_calls
+        Some(choice) => (
+            choice
+                .get("finish_reason")
+                .and_then(|r| r.as_str())
+                .map(normalize_finish_reason),
+            choice
+                .pointer("/message/tool_calls")
+                .and_then(|t| t.as_array())
+                .map(|calls| {
+                    calls
+                        .iter()
+                        .filter_map(|c| c.pointer("/function/name").and_then(|n| n.as_str()))
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default(),
+        ),
+        // Anthropic: stop_reason + tool_use content blocks
+        None => (
+            json.get("stop_reason")
+                .and_then(|r| r.as_str())
+                .map(str::to_string),
+            json.get("content")
+                .and_then(|c| c.as_array())
+                .map(|blocks| {
+                    blocks
+                        .iter()
+                        .filter(|b| b.get("type").and_then(|t| t.as_str()) == Some("tool_use"))
+                        .filter_map(|b| b.get("name").and_then(|n| n.as_str()))
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default(),
+        ),
+    };
+
+    ResponseSample {
+        model,
+        input_tokens,
+        output_tokens,
+        stop_reason,
+        tool_uses,
+        ..Default::default()
+    }
+}
+
+/// Input tokens from an Anthropic stream's message_start event
+fn message_start_input_tokens(text: &str) -> Option<u64> {
+    text.lines()
+        .filter_map(|line| line.trim().strip_prefix("data:"))
+        .filter_map(|data| serde_json::from_str::<Value>(data.trim()).ok())
+        .find(|data| data.get("type").and_then(|t| t.as_str()) == Some("message_start"))?
+        .pointer("/message/usage/input_tokens")?
+        .as_u64()
+}
+
+/// Map OpenAI finish reasons onto Anthropic stop reasons
+fn normalize_finish_reason(reason: &str) -> String {
+    match reason {
+        "stop" => "end_turn",
+        "tool_calls" | "function_call" => "tool_use",
+        "length" => "max_tokens",
+        other => other,
+    }
+    .to_string()
+}
+
+/// Sample for a request that produced no usable response
+fn failed_sample(error: String, latency: Duration) -> ResponseSample {
+    ResponseSample {
+        latency_ms: latency.as_millis() as u64,
+        error: Some(error),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ApiFormat as ProviderFormat, ClientConfig, ProviderConfig};
+    use axum::http::HeaderMap;
+    use std::collections::HashMap;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    fn provider(base_url: &str) -> ProviderConfig {
+        ProviderConfig {
+            base_url: base_url.to_string(),
+            name: None,
+            api_format: ProviderFormat::Anthropic,
+            api_path: None,
+            auth: None,
+            model_mapping: HashMap::new(),
+            count_tokens: None,
+            fallback: vec![],
+        }
+    }
+
+    #[test]
+    fn test_shadow_never_gets_client_credentials() {
+        let mut clients = ClientsConfig::default();
+        clients.clients.insert(
+            "dev-1".to_string(),
+            ClientConfig {
+                name: "Dev".to_string(),
+                provider: "anthropic".to_string(),
+                tags: vec![],
+                auth: None,
+                fallback: vec![],
+                shadow: Some("mirror".to_string()),
+            },
+        );
+        clients.providers.insert(
+            "anthropic".to_string(),
+            provider("https://api.anthropic.com"),
+        );
+        // No auth configured for the shadow provider
+        clients
+            .providers
+            .insert("mirror".to_string(), provider("https://mirror.example.com"));
+
+        let target = shadow_target(&clients, "dev-1").unwrap();
+        assert_eq!(target.provider_id, Some("mirror"));
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-api-key", "sk-ant-client".parse().unwrap());
+        headers.insert("authorization", "Bearer client-token".parse().unwrap());
+        let request = super::super::copy_forward_headers(
+            reqwest::Client::new().post("https://mirror.example.com/v1/messages"),
+            &headers,
+            false,
+            &target,
+            ApiFormat::Anthropic,
+        )
+        .build()
+        .unwrap();
+        assert!(request.headers().get("x-api-key").is_none());
+        assert!(request.headers().get("authorization").is_none());
+    }
+
+    #[test]
+    fn test_sample_anthropic_json() {
+        let body = serde_json::json!({
+            "model": "claude-sonnet-4",
+            "content": [
+                {"type": "text", "text": "Let me look."},
+                {"type": "tool_use", "id": "t1", "name": "Read", "input": {}},
+                {"type": "tool_use", "id": "t2", "name": "Grep", "input": {}}
+            ],
+            "stop_reason": "tool_use",
+            "usage": {"input_tokens": 1200, "output_tokens": 48}
+        });
+        let sample = sample_body(
+            &serde_json::to_vec(&body).unwrap(),
+            ApiFormat::Anthropic,
+            200,
+            100 * MS,
+            900 * MS,
+        );
+
+        assert_eq!(sample.status, Some(200));
+        assert_eq!(sample.model.as_deref(), Some("claude-sonnet-4"));
+        assert_eq!((sample.input_tokens, sample.output_tokens), (1200, 48));
+        assert_eq!(sample.stop_reason.as_deref(), Some("tool_use"));
+        assert_eq!(sample.tool_uses, vec!["Read", "Grep"]);
+        assert_eq!((sample.ttfb_ms, sample.latency_ms), (100, 900));
+        assert!(sample.error.is_none());
+    }
+
+    #[test]
+    fn test_sample_anthropic_stream_usage() {
+        let body = concat!(
+            "event: message_start\n",
+            "data: {\"type\":\"message_start\",\"message\":{\"model\":\"claude-sonnet-4\",\"usage\":{\"input_tokens\":800,\"output_tokens\":1}}}\n\n",
+            "event: content_block_start\n",
+            "data: {\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"text\",\"text\":\"\"}}\n\n",
+            "event: message_delta\n",
+            "data: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"end_turn\"},\"usage\":{\"output_tokens\":12}}\n\n"
+        );
+        let sample = sample_body(body.as_bytes(), ApiFormat::Anthropic, 200, MS, MS);
+
+        assert_eq!(sample.stop_reason.as_deref(), Some("end_turn"));
+        assert_eq!((sample.input_tokens, sample.output_tokens), (800, 12));
+        assert!(sample.tool_uses.is_empty());
+    }
+
+    #[test]
+    fn test_sample_openai_stream_normalizes_stop_reason() {
+        let body = concat!(
+            "data: {\"model\":\"glm-4.6\",\"choices\":[{\"index\":0,\"delta\":{\"tool_calls\":[{\"index\":0,\"id\":\"c1\",\"function\":{\"name\":\"Bash\",\"arguments\":\"{}\"}}]}}]}\n\n",
+            "data: {\"model\":\"glm-4.6\",\"choices\":[{\"index\":0,\"delta\":{},\"finish_reason\":\"tool_calls\"}],\"usage\":{\"prompt_tokens\":1100,\"completion_tokens\":30}}\n\n",
+            "data: [DONE]\n\n"
+        );
+        let sample = sample_body(body.as_bytes(), ApiFormat::OpenAI, 200, MS, MS);
+
+        assert_eq!(sample.model.as_deref(), Some("glm-4.6"));
+        assert_eq!(sample.stop_reason.as_deref(), Some("tool_use"));
+        assert_eq!(sample.tool_uses, vec!["Bash"]);
+        assert_eq!((sample.input_tokens, sample.output_tokens), (1100, 30));
+    }
+
+    #[test]
+    fn test_sample_errors() {
+        let body =
+            br#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#;
+        let sample = sample_body(body, ApiFormat::Anthropic, 529, MS, MS);
+        assert_eq!(sample.status, Some(529));
+        assert_eq!(sample.error.as_deref(), Some("Overloaded"));
+
+        let sample = sample_body(b"<html>", ApiFormat::Anthropic, 502, MS, MS);
+        assert_eq!(sample.error.as_deref(), Some("unparseable response body"));
+
+        let sample = failed_sample("Connection error: refused".to_string(), 5 * MS);
+        assert_eq!(sample.status, None);
+        assert_eq!(sample.latency_ms, 5);
+    }
+}
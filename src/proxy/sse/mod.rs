@@ -41,6 +41,29 @@ pub fn is_sse_response(headers: &reqwest::header::HeaderMap) -> bool {
         .unwrap_or(false)
 }
 
+// ============================================================================
+// Event Framing
+// Used by stream rewriters that work on whole SSE events
+// ============================================================================
+
+/// End (exclusive) of the first complete SSE event in `buffer`
+pub fn event_end(buffer: &[u8]) -> Option<usize> {
+    let lf = buffer.windows(2).position(|w| w == b"\n\n").map(|p| p + 2);
+    let crlf = buffer
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .map(|p| p + 4);
+    match (lf, crlf) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Append a complete SSE event with a JSON payload to `output`
+pub fn write_event(output: &mut Vec<u8>, event_type: &str, data: &serde_json::Value) {
+    output.extend_from_slice(format!("event: {}\ndata: {}\n\n", event_type, data).as_bytes());
+}
+
 // ============================================================================
 // Line-Level Extractors
 // Used during streaming for real-time metadata extraction
@@ -14,8 +14,17 @@ use crate::{SharedContextState, StreamingThinking};
 
 use super::api;
 use super::augmentation::AugmentationPipeline;
+use super::breakpoints::SharedBreakpoints;
+use super::cassette;
 use super::count_tokens;
+use super::failover;
+use super::key_pool;
+use super::rate_limit;
+use super::retry;
 use super::sessions;
+use super::shadow;
+use super::token_counter;
+use super::tool_guard;
 use super::transformation;
 use super::translation::{TranslationContext, TranslationPipeline};
 
@@ -64,36 +73,152 @@ pub struct ProxyState {
     pub(super) transformers_config: crate::config::Transformers,
     /// Count tokens request cache and rate limiter
     pub(super) count_tokens_cache: Arc<count_tokens::CountTokensCache>,
+    /// Provider health tracker for failover chains
+    pub(super) provider_health: Arc<failover::ProviderHealth>,
+    /// Upstream retry policy for 429/529 responses
+    pub(super) retry_policy: Arc<retry::RetryPolicy>,
+    /// API key pools (per provider) with rate limit tracking
+    pub(super) key_pools: Arc<key_pool::KeyPools>,
+    /// Per-client request/token/concurrency limits
+    pub(super) rate_limiter: Arc<rate_limit::RateLimiter>,
+    /// Calibrated local token counter (answers synthetic count_tokens)
+    pub(super) token_counter: Arc<token_counter::TokenCounter>,
+    /// Record/replay cassette (None when cassettes are off)
+    pub(super) cassette: Option<Arc<cassette::Cassette>>,
+    /// Request breakpoints shared with the TUI (None when not configured)
+    pub(super) breakpoints: Option<SharedBreakpoints>,
+    /// Policy for tool calls in responses (None when not configured)
+    pub(super) tool_policy: Option<Arc<tool_guard::ToolPolicy>>,
     /// Handle to the embedding indexer (optional, requires embeddings enabled)
     pub embedding_indexer: Option<crate::pipeline::embedding_indexer::IndexerHandle>,
+    /// Subagent conversation of the request being handled (None = main
+    /// conversation, and always None on the server-wide state)
+    pub(super) agent_id: Option<String>,
+    /// Project of the request being handled, charged for its usage (always
+    /// None on the server-wide state)
+    pub(super) project: Option<String>,
 }
 
 impl ProxyState {
+    /// Copy of the state whose events are attributed to a subagent
+    pub(super) fn for_agent(mut self, agent_id: Option<String>) -> Self {
+        self.agent_id = agent_id;
+        self
+    }
+
+    /// Copy of the state whose usage is charged to a project
+    pub(super) fn for_project(mut self, project: Option<String>) -> Self {
+        self.project = project;
+        self
+    }
+
     /// Send an event to TUI, storage, and user's session
     ///
     /// Events are processed through the pipeline (if configured) before dispatch.
     /// Events are wrapped in TrackedEvent with user/session context for filtering.
     /// We ignore errors here to avoid blocking the proxy if a receiver is slow or closed.
     pub(super) async fn send_event(&self, event: ProxyEvent, user_id: Option<&str>) {
+        // Charge usage against the user's spending budget (if any)
+        if let (
+            ProxyEvent::ApiUsage {
+                model,
+                input_tokens,
+                output_tokens,
+                cache_read_tokens,
+                cache_creation_tokens,
+                ..
+            },
+            Some(uid),
+            Some(budget),
+        ) = (&event, user_id, self.transformation.budget())
+        {
+            let cost = crate::pricing::calculate_cost(
+                model,
+                *input_tokens,
+                *output_tokens,
+                *cache_creation_tokens,
+                *cache_read_tokens,
+            );
+            budget.record(uid, cost, chrono::Utc::now());
+        }
+
+        // Calibrate the local token counter against billed input
+        if let (
+            ProxyEvent::ApiUsage {
+                model,
+                input_tokens,
+                cache_read_tokens,
+                cache_creation_tokens,
+                ..
+            },
+            Some(uid),
+        ) = (&event, user_id)
+        {
+            let observed =
+                *input_tokens as u64 + *cache_read_tokens as u64 + *cache_creation_tokens as u64;
+            self.token_counter.observe_usage(uid, model, observed);
+        }
+
         // Build ProcessContext for pipeline
-        // Extract session_id and transcript_path together (single lock)
-        let (session_id, transcript_path) = user_id
+        // Extract session_id, transcript_path and project together (single lock)
+        let (session_id, transcript_path, project) = user_id
             .and_then(|uid| {
                 self.sessions.lock().ok().and_then(|sessions| {
                     sessions
                         .get_user_session(&sessions::UserId::new(uid))
-                        .map(|s| (s.key.to_string(), s.transcript_path.clone()))
+                        .map(|s| {
+                            (
+                                s.key.to_string(),
+                                s.transcript_path.clone(),
+                                s.project.clone(),
+                            )
+                        })
                 })
             })
-            .map(|(sid, tp)| (Some(sid), tp))
-            .unwrap_or((None, None));
+            .map(|(sid, tp, project)| (Some(sid), tp, project))
+            .unwrap_or((None, None, None));
+
+        // Charge usage against the request's project budget (if any). The
+        // session's project is only the latest detection, which another
+        // instance under the same identity may have changed meanwhile.
+        if let (
+            ProxyEvent::ApiUsage {
+                model,
+                input_tokens,
+                output_tokens,
+                cache_read_tokens,
+                cache_creation_tokens,
+                ..
+            },
+            Some(project),
+            Some(projects),
+        ) = (
+            &event,
+            self.project.as_deref(),
+            self.transformation.projects(),
+        ) {
+            let cost = crate::pricing::calculate_cost(
+                model,
+                *input_tokens,
+                *output_tokens,
+                *cache_creation_tokens,
+                *cache_read_tokens,
+            );
+            projects.record(project, cost);
+        }
 
         let ctx = ProcessContext::new(
             session_id.as_deref(),
             user_id,
             transcript_path.as_deref(),
             false, // is_demo = false for real traffic
-        );
+        )
+        .with_project(match &event {
+            // Usage rows keep the project they were charged to (budgets are
+            // seeded from them after a restart)
+            ProxyEvent::ApiUsage { .. } => self.project.as_deref(),
+            _ => self.project.as_deref().or(project.as_deref()),
+        });
 
         // Process through pipeline if available
         let final_event = if let Some(pipeline) = &self.pipeline {
@@ -105,12 +230,13 @@ impl ProxyState {
             event
         };
 
-        // Wrap in TrackedEvent with user/session context
+        // Wrap in TrackedEvent with user/session/subagent context
         let tracked = TrackedEvent::new(
             final_event.clone(),
             user_id.map(|s| s.to_string()),
             session_id.clone(),
-        );
+        )
+        .with_agent(self.agent_id.clone());
 
         // Send tracked event to TUI and storage channels
         let _ = self.event_tx_tui.send(tracked.clone()).await;
@@ -120,7 +246,11 @@ impl ProxyState {
         // May return synthesized events (e.g., TodoSnapshot) to emit
         if let Some(uid) = user_id {
             let synthesized = if let Ok(mut sessions) = self.sessions.lock() {
-                sessions.record_event(&sessions::UserId::new(uid), final_event)
+                sessions.record_agent_event(
+                    &sessions::UserId::new(uid),
+                    final_event,
+                    self.agent_id.as_deref(),
+                )
             } else {
                 None
             };
@@ -169,13 +299,14 @@ impl ProxyState {
         };
 
         // Wrap in TrackedEvent
-        let tracked = TrackedEvent::new(final_event, user_id.map(|s| s.to_string()), session_id);
+        let tracked = TrackedEvent::new(final_event, user_id.map(|s| s.to_string()), session_id)
+            .with_agent(self.agent_id.clone());
 
         // Send to TUI and storage (NOT to sessions - that would recurse)
         let _ = self.event_tx_tui.send(tracked.clone()).await;
         let _ = self.event_tx_storage.send(tracked).await;
 
-        tracing::debug!("Emitted synthesized TodoSnapshot event");
+        tracing::debug!("Emitted synthesized event");
     }
 }
 
@@ -216,6 +347,10 @@ pub struct SharedState {
     pub cortex_query: Option<Arc<crate::pipeline::cortex_query::CortexQuery>>,
     /// Handle to the embedding indexer (optional, requires embeddings enabled)
     pub embedding_indexer: Option<crate::pipeline::embedding_indexer::IndexerHandle>,
+    /// Request breakpoints (optional, requires rules and the TUI)
+    pub breakpoints: Option<SharedBreakpoints>,
+    /// Writer for session snapshots (optional, requires cortex enabled)
+    pub session_state: Option<crate::pipeline::cortex::SessionStateWriter>,
 }
 
 // ─────────────────────────────────────────────────────────────────────────────
@@ -238,4 +373,14 @@ pub(super) struct ResponseContext {
     pub translation_ctx: TranslationContext,
     /// Original request body for count_tokens caching (if applicable)
     pub count_tokens_request_body: Option<Bytes>,
+    /// Concurrent stream slot, released when the response finishes
+    pub rate_permit: Option<rate_limit::StreamPermit>,
+    /// Reports the primary response to a pending shadow comparison
+    pub shadow_tx: Option<shadow::PrimarySender>,
+    /// Secrets redacted from the request, restored in the response
+    pub redactions: Option<Arc<transformation::Redactions>>,
+    /// Tools renamed in the request, restored to their original names in the response
+    pub tool_renames: Option<Arc<transformation::ToolRenames>>,
+    /// The cache optimizer placed breakpoints in the request (tags its usage)
+    pub cache_optimized: bool,
 }
@@ -0,0 +1,261 @@
+//! Subagent (Task tool) conversation tracking
+//!
+//! Claude Code runs subagents as separate conversations inside the same
+//! session: the parent calls the Task tool, the subagent's API calls arrive
+//! interleaved with the parent's, and the tool result carries its answer
+//! back. A subagent conversation opens with the Task prompt as its first user
+//! message, which is how requests are attributed to it.
+//!
+//! Subagents can spawn their own subagents, so the session holds a tree keyed
+//! by the Task tool_use id. Usage is rolled up the tree: each node's totals
+//! include its descendants.
+
+use crate::events::ProxyEvent;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::helpers::first_user_text;
+
+/// Tool names Claude Code uses to spawn subagents
+const SUBAGENT_TOOLS: &[&str] = &["Task", "Agent"];
+
+/// Maximum subagents kept per session (oldest finished ones are dropped)
+const MAX_SUBAGENTS: usize = 200;
+
+/// Whether a tool call spawns a subagent
+pub fn is_subagent_tool(tool_name: &str) -> bool {
+    SUBAGENT_TOOLS.contains(&tool_name)
+}
+
+/// Token and cost totals for a subagent (including its descendants)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SubagentUsage {
+    pub requests: u32,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_read_tokens: u64,
+    pub cache_creation_tokens: u64,
+    pub cost_usd: f64,
+}
+
+/// One subagent conversation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Subagent {
+    /// Task tool_use id that spawned it
+    pub id: String,
+    /// Spawning subagent (None for the main conversation)
+    pub parent: Option<String>,
+    pub description: String,
+    pub subagent_type: Option<String>,
+    /// Task prompt (the subagent's opening user message)
+    pub prompt: String,
+    pub started: DateTime<Utc>,
+    /// Set when the Task tool result arrives
+    pub ended: Option<DateTime<Utc>>,
+    pub usage: SubagentUsage,
+}
+
+impl Subagent {
+    /// Still waiting for its tool result
+    pub fn is_running(&self) -> bool {
+        self.ended.is_none()
+    }
+}
+
+/// Subagents spawned in one session
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SubagentTree {
+    nodes: Vec<Subagent>,
+}
+
+impl SubagentTree {
+    /// Register a subagent from a Task tool call
+    ///
+    /// `parent` is the conversation that made the call (None for the main one).
+    pub fn spawn(&mut self, id: &str, input: &serde_json::Value, parent: Option<&str>) {
+        if self.get(id).is_some() {
+            return;
+        }
+        if self.nodes.len() >= MAX_SUBAGENTS {
+            if let Some(idx) = self.nodes.iter().position(|n| !n.is_running()) {
+                self.nodes.remove(idx);
+            }
+        }
+
+        let text = |key: &str| input.get(key).and_then(|v| v.as_str()).map(str::to_string);
+        self.nodes.push(Subagent {
+            id: id.to_string(),
+            parent: parent.map(str::to_string),
+            description: text("description").unwrap_or_else(|| "subagent".to_string()),
+            subagent_type: text("subagent_type"),
+            prompt: text("prompt").unwrap_or_default(),
+            started: Utc::now(),
+            ended: None,
+            usage: SubagentUsage::default(),
+        });
+    }
+
+    /// Find the running subagent a request belongs to
+    ///
+    /// Matches the request's first user message against running subagents'
+    /// prompts (Claude Code may append reminders, so containment is enough).
+    /// The longest matching prompt wins when parallel subagents overlap.
+    pub fn attribute(&self, body: &serde_json::Value) -> Option<&str> {
+        let opening = first_user_text(body)?;
+        self.nodes
+            .iter()
+            .filter(|n| n.is_running())
+            .filter(|n| {
+                let prompt = n.prompt.trim();
+                !prompt.is_empty() && opening.contains(prompt)
+            })
+            .max_by_key(|n| n.prompt.len())
+            .map(|n| n.id.as_str())
+    }
+
+    /// Add an API call's usage to a subagent and all of its ancestors
+    pub fn record_usage(
+        &mut self,
+        id: &str,
+        model: &str,
+        input_tokens: u32,
+        output_tokens: u32,
+        cache_creation_tokens: u32,
+        cache_read_tokens: u32,
+    ) {
+        let cost = crate::pricing::calculate_cost(
+            model,
+            input_tokens,
+            output_tokens,
+            cache_creation_tokens,
+            cache_read_tokens,
+        );
+
+        let mut current = Some(id.to_string());
+        // Bounded walk in case a corrupt snapshot contains a cycle
+        for _ in 0..self.nodes.len() {
+            let Some(node) = current.and_then(|id| self.nodes.iter_mut().find(|n| n.id == id))
+            else {
+                break;
+            };
+            node.usage.requests += 1;
+            node.usage.input_tokens += input_tokens as u64;
+            node.usage.output_tokens += output_tokens as u64;
+            node.usage.cache_creation_tokens += cache_creation_tokens as u64;
+            node.usage.cache_read_tokens += cache_read_tokens as u64;
+            node.usage.cost_usd += cost;
+            current = node.parent.clone();
+        }
+    }
+
+    /// Mark a subagent finished when its Task tool result arrives
+    ///
+    /// Returns a SubagentCompleted event for storage, or None if the tool
+    /// result isn't for a running subagent.
+    pub fn finish(&mut self, id: &str, success: bool) -> Option<ProxyEvent> {
+        let node = self
+            .nodes
+            .iter_mut()
+            .find(|n| n.id == id && n.is_running())?;
+        let ended = Utc::now();
+        node.ended = Some(ended);
+
+        Some(ProxyEvent::SubagentCompleted {
+            timestamp: ended,
+            agent_id: node.id.clone(),
+            parent_id: node.parent.clone(),
+            description: node.description.clone(),
+            subagent_type: node.subagent_type.clone(),
+            started: node.started,
+            duration: (ended - node.started).to_std().unwrap_or_default(),
+            requests: node.usage.requests,
+            input_tokens: node.usage.input_tokens,
+            output_tokens: node.usage.output_tokens,
+            cache_read_tokens: node.usage.cache_read_tokens,
+            cache_creation_tokens: node.usage.cache_creation_tokens,
+            cost_usd: node.usage.cost_usd,
+            success,
+        })
+    }
+
+    /// Look up a subagent by its Task tool_use id
+    pub fn get(&self, id: &str) -> Option<&Subagent> {
+        self.nodes.iter().find(|n| n.id == id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn task(prompt: &str) -> serde_json::Value {
+        json!({
+            "description": "Find callers",
+            "subagent_type": "Explore",
+            "prompt": prompt
+        })
+    }
+
+    fn request(opening: &str) -> serde_json::Value {
+        json!({
+            "messages": [
+                {"role": "user", "content": [
+                    {"type": "text", "text": opening},
+                    {"type": "text", "text": "<system-reminder>...</system-reminder>"}
+                ]},
+                {"role": "assistant", "content": "Looking"}
+            ]
+        })
+    }
+
+    #[test]
+    fn test_attributes_requests_by_opening_prompt() {
+        let mut tree = SubagentTree::default();
+        tree.spawn("toolu_a", &task("Find every caller of parse_request"), None);
+        tree.spawn("toolu_b", &task("Summarize the README"), None);
+
+        let body = request("Find every caller of parse_request");
+        assert_eq!(tree.attribute(&body), Some("toolu_a"));
+        assert_eq!(tree.attribute(&request("Fix the login bug")), None);
+
+        // Finished subagents no longer claim requests
+        tree.finish("toolu_a", true);
+        assert_eq!(tree.attribute(&body), None);
+    }
+
+    #[test]
+    fn test_usage_rolls_up_to_ancestors() {
+        let mut tree = SubagentTree::default();
+        tree.spawn("parent", &task("Investigate"), None);
+        tree.spawn("child", &task("Read the logs"), Some("parent"));
+
+        tree.record_usage("child", "claude-haiku-4-5", 1000, 200, 0, 0);
+        tree.record_usage("parent", "claude-haiku-4-5", 500, 100, 0, 0);
+
+        let child = tree.get("child").unwrap();
+        assert_eq!(child.usage.requests, 1);
+        assert_eq!(child.usage.input_tokens, 1000);
+
+        let parent = tree.get("parent").unwrap();
+        assert_eq!(parent.usage.requests, 2);
+        assert_eq!(parent.usage.input_tokens, 1500);
+        assert_eq!(parent.usage.output_tokens, 300);
+        assert!(parent.usage.cost_usd > child.usage.cost_usd);
+
+        match tree.finish("child", false) {
+            Some(ProxyEvent::SubagentCompleted {
+                parent_id,
+                requests,
+                success,
+                ..
+            }) => {
+                assert_eq!(parent_id.as_deref(), Some("parent"));
+                assert_eq!(requests, 1);
+                assert!(!success);
+            }
+            other => panic!("expected SubagentCompleted, got {:?}", other),
+        }
+        assert!(tree.finish("child", true).is_none());
+    }
+}
@@ -0,0 +1,312 @@
+//! Local token counting for providers without a count_tokens endpoint
+//!
+//! OpenAI-compatible providers have no `/v1/messages/count_tokens`, so the
+//! proxy answers those calls itself. A fixed answer breaks Claude Code's
+//! context accounting, so instead the request is counted locally:
+//!
+//! ```text
+//! count_tokens body → walk system/tools/messages → raw estimate
+//!                                                      ↓
+//!                                 × calibration factor (per model family)
+//!                                                      ↓
+//!                                           {"input_tokens": N}
+//! ```
+//!
+//! # Calibration
+//!
+//! The heuristic in `crate::tokens` is a rough character-based estimate and
+//! each provider tokenizes differently. Every forwarded messages request
+//! records its raw estimate; when the matching `ApiUsage` arrives, the ratio
+//! of observed input tokens to the estimate updates a running factor for
+//! the model family. The factor starts at 1.0 and settles after a few turns.
+//!
+//! Usage is matched to requests per identity, by model family first and
+//! oldest pending request otherwise.
+
+use crate::tokens::{estimate_json_tokens, estimate_tokens};
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+/// Framing tokens per message (role markers, separators)
+const MESSAGE_OVERHEAD: u64 = 4;
+
+/// Framing tokens per tool definition
+const TOOL_OVERHEAD: u64 = 8;
+
+/// Image cost when dimensions are unknown (about 1100×1100 pixels)
+const IMAGE_TOKENS: u64 = 1600;
+
+/// Requests smaller than this are too dominated by fixed overhead to calibrate from
+const MIN_CALIBRATION_TOKENS: u64 = 200;
+
+/// Calibration averages this many samples, then follows recent usage
+const CALIBRATION_WINDOW: u64 = 20;
+
+/// Bounds on the calibration factor (guards against mismatched samples)
+const FACTOR_RANGE: (f64, f64) = (0.25, 4.0);
+
+/// Requests awaiting usage per identity (oldest dropped beyond this)
+const MAX_PENDING: usize = 8;
+
+/// Estimate input tokens for an Anthropic-format request
+///
+/// Walks the system prompt, tool definitions and messages, counting text
+/// with `estimate_tokens` and structured values (tool inputs, schemas)
+/// with `estimate_json_tokens`.
+pub fn estimate_request_tokens(body: &Value) -> u64 {
+    let mut total = content_tokens(&body["system"]);
+
+    if let Some(tools) = body["tools"].as_array() {
+        total += tools
+            .iter()
+            .map(|tool| estimate_json_tokens(tool) as u64 + TOOL_OVERHEAD)
+            .sum::<u64>();
+    }
+
+    if let Some(messages) = body["messages"].as_array() {
+        total += messages
+            .iter()
+            .map(|msg| MESSAGE_OVERHEAD + content_tokens(&msg["content"]))
+            .sum::<u64>();
+    }
+
+    total
+}
+
+/// Tokens in a string or content block array
+fn content_tokens(content: &Value) -> u64 {
+    match content {
+        Value::String(text) => estimate_tokens(text) as u64,
+        Value::Array(blocks) => blocks.iter().map(block_tokens).sum(),
+        _ => 0,
+    }
+}
+
+fn block_tokens(block: &Value) -> u64 {
+    let text = |key: &str| block[key].as_str().map_or(0, |t| estimate_tokens(t) as u64);
+    match block["type"].as_str() {
+        Some("text") => text("text"),
+        Some("thinking") => text("thinking"),
+        Some("image") => IMAGE_TOKENS,
+        Some("tool_use") => text("name") + estimate_json_tokens(&block["input"]) as u64,
+        Some("tool_result") => content_tokens(&block["content"]),
+        _ => estimate_json_tokens(block) as u64,
+    }
+}
+
+/// Model family used as the calibration key
+///
+/// Claude models group by tier (`opus`, `sonnet`, `haiku`); other models
+/// by the first segment of their name (`gpt`, `llama`, ...).
+pub fn model_family(model: &str) -> String {
+    let lower = model.to_lowercase();
+    for family in ["opus", "sonnet", "haiku"] {
+        if lower.contains(family) {
+            return family.to_string();
+        }
+    }
+    let name = lower.rsplit('/').next().unwrap_or_default();
+    name.split(['-', ':', '.'])
+        .find(|s| !s.is_empty())
+        .unwrap_or("unknown")
+        .to_string()
+}
+
+/// Running ratio of observed to estimated tokens for one family
+#[derive(Debug, Clone, Copy)]
+struct Calibration {
+    factor: f64,
+    samples: u64,
+}
+
+impl Calibration {
+    fn update(&mut self, estimate: u64, observed: u64) {
+        let ratio = (observed as f64 / estimate as f64).clamp(FACTOR_RANGE.0, FACTOR_RANGE.1);
+        self.samples += 1;
+        let weight = 1.0 / self.samples.min(CALIBRATION_WINDOW) as f64;
+        self.factor += weight * (ratio - self.factor);
+    }
+}
+
+/// A forwarded request whose usage hasn't arrived yet
+#[derive(Debug)]
+struct Pending {
+    family: String,
+    estimate: u64,
+}
+
+/// Calibrated local token counter shared by all requests
+#[derive(Default)]
+pub struct TokenCounter {
+    calibrations: Mutex<HashMap<String, Calibration>>,
+    pending: Mutex<HashMap<String, VecDeque<Pending>>>,
+}
+
+impl TokenCounter {
+    pub fn new_shared() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// Count input tokens for a request body, calibrated for its model
+    pub fn count(&self, body: &Value) -> u64 {
+        let estimate = estimate_request_tokens(body);
+        let family = model_family(body["model"].as_str().unwrap_or_default());
+        let factor = self
+            .calibrations
+            .lock()
+            .ok()
+            .and_then(|c| c.get(&family).map(|cal| cal.factor))
+            .unwrap_or(1.0);
+        (estimate as f64 * factor).round() as u64
+    }
+
+    /// Remember a forwarded request's raw estimate until its usage arrives
+    pub fn expect_usage(&self, identity: &str, body: &Value) {
+        let estimate = estimate_request_tokens(body);
+        if estimate < MIN_CALIBRATION_TOKENS {
+            return;
+        }
+        let family = model_family(body["model"].as_str().unwrap_or_default());
+        let Ok(mut pending) = self.pending.lock() else {
+            return;
+        };
+        let queue = pending.entry(identity.to_string()).or_default();
+        if queue.len() >= MAX_PENDING {
+            queue.pop_front();
+        }
+        queue.push_back(Pending { family, estimate });
+    }
+
+    /// Calibrate against the input tokens the provider actually billed
+    pub fn observe_usage(&self, identity: &str, model: &str, observed: u64) {
+        let family = model_family(model);
+        let request = {
+            let Ok(mut pending) = self.pending.lock() else {
+                return;
+            };
+            let Some(queue) = pending.get_mut(identity) else {
+                return;
+            };
+            // Model mapping can rename the model, so fall back to the oldest
+            let index = queue.iter().position(|p| p.family == family).unwrap_or(0);
+            queue.remove(index)
+        };
+        let Some(request) = request else {
+            return;
+        };
+        if observed == 0 {
+            return;
+        }
+
+        let Ok(mut calibrations) = self.calibrations.lock() else {
+            return;
+        };
+        let calibration = calibrations
+            .entry(request.family.clone())
+            .or_insert(Calibration {
+                factor: 1.0,
+                samples: 0,
+            });
+        calibration.update(request.estimate, observed);
+        tracing::debug!(
+            family = %request.family,
+            estimate = request.estimate,
+            observed,
+            factor = calibration.factor,
+            samples = calibration.samples,
+            "Token counter calibrated"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn request(model: &str, words: usize) -> Value {
+        json!({
+            "model": model,
+            "system": "You are a helpful assistant.",
+            "tools": [{"name": "Read", "input_schema": {"type": "object"}}],
+            "messages": [
+                {"role": "user", "content": "word ".repeat(words)},
+                {"role": "assistant", "content": [
+                    {"type": "tool_use", "id": "t1", "name": "Read", "input": {"path": "a.rs"}}
+                ]},
+                {"role": "user", "content": [
+                    {"type": "tool_result", "tool_use_id": "t1", "content": "fn main() {}"},
+                    {"type": "image", "source": {"type": "base64", "data": "AAAA"}}
+                ]}
+            ]
+        })
+    }
+
+    #[test]
+    fn test_estimate_walks_all_parts() {
+        let small = estimate_request_tokens(&request("claude-sonnet-4", 10));
+        let large = estimate_request_tokens(&request("claude-sonnet-4", 1000));
+        assert!(small > IMAGE_TOKENS, "image counted at a fixed cost");
+        assert!(large > small + 500, "message text counted");
+
+        // Image data isn't counted as text
+        let mut body = request("claude-sonnet-4", 10);
+        body["messages"][2]["content"][1]["source"]["data"] = json!("A".repeat(100_000));
+        assert_eq!(estimate_request_tokens(&body), small);
+    }
+
+    #[test]
+    fn test_model_family() {
+        assert_eq!(model_family("claude-sonnet-4-20250514"), "sonnet");
+        assert_eq!(model_family("claude-3-5-haiku-latest"), "haiku");
+        assert_eq!(model_family("gpt-4o-mini"), "gpt");
+        assert_eq!(model_family("meta-llama/llama-3.1-70b"), "llama");
+        assert_eq!(model_family(""), "unknown");
+    }
+
+    #[test]
+    fn test_calibrates_per_family() {
+        let counter = TokenCounter::default();
+        let body = request("gpt-4o", 1000);
+        let estimate = estimate_request_tokens(&body);
+        assert_eq!(counter.count(&body), estimate, "uncalibrated factor is 1.0");
+
+        // Provider consistently bills 1.5x the estimate
+        for _ in 0..3 {
+            counter.expect_usage("dev-1", &body);
+            counter.observe_usage("dev-1", "gpt-4o", estimate * 3 / 2);
+        }
+        let counted = counter.count(&body);
+        assert!(
+            counted.abs_diff(estimate * 3 / 2) <= 1,
+            "{} vs {}",
+            counted,
+            estimate * 3 / 2
+        );
+
+        // Other families are unaffected
+        let sonnet = request("claude-sonnet-4", 1000);
+        assert_eq!(counter.count(&sonnet), estimate_request_tokens(&sonnet));
+    }
+
+    #[test]
+    fn test_usage_matched_by_family() {
+        let counter = TokenCounter::default();
+        let sonnet = request("claude-sonnet-4", 1000);
+        let haiku = request("claude-haiku-4", 1000);
+        let estimate = estimate_request_tokens(&sonnet);
+
+        // Concurrent subagent request: haiku usage arrives first
+        counter.expect_usage("dev-1", &sonnet);
+        counter.expect_usage("dev-1", &haiku);
+        counter.observe_usage("dev-1", "claude-haiku-4", estimate * 2);
+
+        assert_eq!(counter.count(&sonnet), estimate);
+        assert_eq!(counter.count(&haiku), estimate * 2);
+
+        // Usage with nothing pending is ignored
+        counter.observe_usage("dev-2", "claude-haiku-4", 1);
+        assert_eq!(counter.count(&haiku), estimate * 2);
+    }
+}
@@ -0,0 +1,665 @@
+//! Tool guard - policy checks for tool calls in responses
+//!
+//! Claude Code runs whatever `tool_use` blocks the model emits (subject to its
+//! own permission prompts). The guard checks each call against `[tool_guard]`
+//! rules before the client sees it:
+//!
+//! ```text
+//! Bash {"command": "git push --force"}   →   deny: replaced with a refusal text block
+//! Edit {"file_path": "/etc/hosts"}       →   warn: call kept, warning block after it
+//! ```
+//!
+//! # Streaming
+//!
+//! A call's input is only complete at its `content_block_stop`, so the events
+//! of each `tool_use` block are held back until then. Everything else streams
+//! through untouched. A denied call becomes a text block at the same index;
+//! warnings are collected into one text block just before `message_delta`.
+//! If every call in a response was denied, `stop_reason` becomes `end_turn`
+//! so the client doesn't wait for tool results that will never come.
+//!
+//! The guard runs on the upstream (Anthropic-format) stream before anything
+//! else reads it, so logs, events and augmentations see what the client gets.
+//! Each triggered rule is reported as a `ToolPolicyDecision` event.
+//!
+//! Rules name the client's tools. Calls to tools the tool filter exposed
+//! under another name are checked under their original name, so a rename
+//! can't get around a rule.
+
+use crate::config::{GuardAction, ToolGuard, ToolGuardRule};
+use crate::events::ProxyEvent;
+use crate::proxy::augmentation::text_block_sse;
+use crate::proxy::sse::{event_end, write_event};
+use crate::proxy::transformation::ToolRenames;
+use bytes::Bytes;
+use chrono::Utc;
+use regex::Regex;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+/// A triggered rule for one tool call
+#[derive(Debug, Clone)]
+pub struct PolicyDecision {
+    pub tool_use_id: String,
+    pub tool_name: String,
+    pub action: GuardAction,
+    /// Name of the rule that triggered
+    pub rule: String,
+    pub reason: String,
+    /// The call's input as the model produced it
+    pub input: Value,
+}
+
+impl PolicyDecision {
+    /// Text that replaces a denied call
+    fn refusal(&self) -> String {
+        format!(
+            "[aspy tool guard] Blocked {} call ({}): {}. The tool was not run.",
+            self.tool_name, self.rule, self.reason
+        )
+    }
+
+    /// Event reporting this decision
+    pub fn into_event(self, request_id: &str) -> ProxyEvent {
+        ProxyEvent::ToolPolicyDecision {
+            timestamp: Utc::now(),
+            request_id: request_id.to_string(),
+            tool_use_id: self.tool_use_id,
+            tool_name: self.tool_name,
+            action: self.action.as_str().to_string(),
+            rule: self.rule,
+            reason: self.reason,
+            input: self.input,
+        }
+    }
+}
+
+/// Text block listing warned calls
+fn warning_text(warnings: &[PolicyDecision]) -> String {
+    warnings
+        .iter()
+        .map(|d| {
+            format!(
+                "[aspy tool guard] Warning for {} call {} ({}): {}",
+                d.tool_name, d.tool_use_id, d.rule, d.reason
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// ============================================================================
+// Rules
+// ============================================================================
+
+/// A compiled rule
+#[derive(Debug)]
+struct Rule {
+    name: String,
+    tool: Regex,
+    field: Option<String>,
+    pattern: Option<Regex>,
+    allow: Option<Regex>,
+    action: GuardAction,
+    reason: Option<String>,
+    client: Option<String>,
+}
+
+impl Rule {
+    fn from_config(rule: &ToolGuardRule) -> anyhow::Result<Self> {
+        if rule.pattern.is_none() && rule.allow.is_none() {
+            anyhow::bail!("rule for '{}' needs a pattern or an allow list", rule.tool);
+        }
+        let compile = |pattern: &str| {
+            Regex::new(pattern).map_err(|e| anyhow::anyhow!("invalid pattern '{}': {}", pattern, e))
+        };
+        Ok(Self {
+            name: rule.name.clone().unwrap_or_else(|| rule.tool.clone()),
+            tool: compile(&format!("^(?:{})$", rule.tool))?,
+            field: rule.field.clone(),
+            pattern: rule.pattern.as_deref().map(compile).transpose()?,
+            allow: rule.allow.as_deref().map(compile).transpose()?,
+            action: rule.action,
+            reason: rule.reason.clone(),
+            client: rule.client.clone(),
+        })
+    }
+
+    /// Reason the rule triggers for this call, if it does
+    fn check(&self, client: Option<&str>, name: &str, input: &Value) -> Option<String> {
+        if self.client.as_deref().is_some_and(|c| client != Some(c)) || !self.tool.is_match(name) {
+            return None;
+        }
+        let (subject, value) = match &self.field {
+            Some(field) => (
+                field.as_str(),
+                match input.get(field) {
+                    Some(Value::String(s)) => s.clone(),
+                    Some(other) => other.to_string(),
+                    None => String::new(),
+                },
+            ),
+            None => ("input", input.to_string()),
+        };
+
+        let default_reason =
+            if let Some(pattern) = self.pattern.as_ref().filter(|p| p.is_match(&value)) {
+                format!("{} matches `{}`", subject, pattern)
+            } else if let Some(allow) = self.allow.as_ref().filter(|a| !a.is_match(&value)) {
+                format!("{} is outside `{}`", subject, allow)
+            } else {
+                return None;
+            };
+        Some(self.reason.clone().unwrap_or(default_reason))
+    }
+}
+
+/// Compiled `[tool_guard]` rules
+#[derive(Debug)]
+pub struct ToolPolicy {
+    rules: Vec<Rule>,
+}
+
+impl ToolPolicy {
+    /// Create from configuration
+    pub fn from_config(config: &ToolGuard) -> anyhow::Result<Self> {
+        let rules = config
+            .rules
+            .iter()
+            .map(Rule::from_config)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Self { rules })
+    }
+
+    pub fn rule_count(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Check one call (the first matching deny wins, otherwise the first warn)
+    pub fn evaluate(
+        &self,
+        client: Option<&str>,
+        tool_use_id: &str,
+        tool_name: &str,
+        input: &Value,
+    ) -> Option<PolicyDecision> {
+        let mut warning = None;
+        for rule in &self.rules {
+            let Some(reason) = rule.check(client, tool_name, input) else {
+                continue;
+            };
+            let decision = || PolicyDecision {
+                tool_use_id: tool_use_id.to_string(),
+                tool_name: tool_name.to_string(),
+                action: rule.action,
+                rule: rule.name.clone(),
+                reason: reason.clone(),
+                input: input.clone(),
+            };
+            match rule.action {
+                GuardAction::Deny => return Some(decision()),
+                GuardAction::Warn if warning.is_none() => warning = Some(decision()),
+                GuardAction::Warn => {}
+            }
+        }
+        warning
+    }
+
+    /// Apply the policy to a buffered Anthropic response in place
+    ///
+    /// Returns the decisions made (empty = body unchanged).
+    pub fn apply_buffered(
+        &self,
+        client: Option<&str>,
+        renames: Option<&ToolRenames>,
+        body: &mut Value,
+    ) -> Vec<PolicyDecision> {
+        let Some(blocks) = body.get_mut("content").and_then(|c| c.as_array_mut()) else {
+            return Vec::new();
+        };
+        let mut decisions = Vec::new();
+        let mut kept_calls = 0;
+        for block in blocks.iter_mut().filter(|b| b["type"] == "tool_use") {
+            let id = block["id"].as_str().unwrap_or_default();
+            let name = block["name"].as_str().unwrap_or_default();
+            let name = original_name(renames, name);
+            match self.evaluate(client, id, &name, &block["input"]) {
+                Some(decision) if decision.action == GuardAction::Deny => {
+                    *block = json!({"type": "text", "text": decision.refusal()});
+                    decisions.push(decision);
+                }
+                decision => {
+                    kept_calls += 1;
+                    decisions.extend(decision);
+                }
+            }
+        }
+
+        let warnings: Vec<_> = decisions
+            .iter()
+            .filter(|d| d.action == GuardAction::Warn)
+            .cloned()
+            .collect();
+        if !warnings.is_empty() {
+            blocks.push(json!({"type": "text", "text": warning_text(&warnings)}));
+        }
+        if kept_calls == 0 && !decisions.is_empty() && body["stop_reason"] == "tool_use" {
+            body["stop_reason"] = json!("end_turn");
+        }
+        decisions
+    }
+}
+
+/// Name the client knows a tool by (undoes tool filter renames)
+fn original_name(renames: Option<&ToolRenames>, name: &str) -> String {
+    renames
+        .and_then(|r| r.original(name))
+        .unwrap_or_else(|| name.to_string())
+}
+
+// ============================================================================
+// Streaming
+// ============================================================================
+
+/// Events of a `tool_use` block held back until its input is complete
+struct HeldCall {
+    index: u64,
+    id: String,
+    name: String,
+    partial_json: String,
+    events: Vec<u8>,
+}
+
+/// Applies the policy to a streamed (Anthropic-format) response
+///
+/// Works on whole SSE events (incomplete events wait for the next chunk).
+pub struct GuardedStream {
+    policy: Arc<ToolPolicy>,
+    client: Option<String>,
+    /// Tools the filter renamed in the request
+    renames: Option<Arc<ToolRenames>>,
+    /// Bytes of an incomplete SSE event
+    buffer: Vec<u8>,
+    held: Option<HeldCall>,
+    /// Next free content block index
+    next_index: u64,
+    /// Warnings waiting for their text block
+    warnings: Vec<PolicyDecision>,
+    /// Decisions not yet taken for reporting
+    decisions: Vec<PolicyDecision>,
+    kept_calls: usize,
+    denied_calls: usize,
+}
+
+impl GuardedStream {
+    pub fn new(
+        policy: Arc<ToolPolicy>,
+        client: Option<String>,
+        renames: Option<Arc<ToolRenames>>,
+    ) -> Self {
+        Self {
+            policy,
+            client,
+            renames,
+            buffer: Vec::new(),
+            held: None,
+            next_index: 0,
+            warnings: Vec::new(),
+            decisions: Vec::new(),
+            kept_calls: 0,
+            denied_calls: 0,
+        }
+    }
+
+    /// Feed a chunk of SSE, returning what to forward
+    pub fn push(&mut self, chunk: &[u8]) -> Bytes {
+        self.buffer.extend_from_slice(chunk);
+        let mut output = Vec::with_capacity(self.buffer.len());
+        while let Some(end) = event_end(&self.buffer) {
+            let event: Vec<u8> = self.buffer.drain(..end).collect();
+            self.process_event(&event, &mut output);
+        }
+        Bytes::from(output)
+    }
+
+    /// Flush whatever is left at the end of the stream
+    ///
+    /// A call cut off mid-stream is passed through unchecked (its input never
+    /// completed, so the client can't run it either).
+    pub fn finish(&mut self) -> Bytes {
+        let mut output = self.held.take().map(|h| h.events).unwrap_or_default();
+        output.append(&mut self.buffer);
+        Bytes::from(output)
+    }
+
+    /// Decisions made since the last call
+    pub fn take_decisions(&mut self) -> Vec<PolicyDecision> {
+        std::mem::take(&mut self.decisions)
+    }
+
+    fn process_event(&mut self, event: &[u8], output: &mut Vec<u8>) {
+        let Ok(text) = std::str::from_utf8(event) else {
+            output.extend_from_slice(event);
+            return;
+        };
+        let interesting = self.held.is_some()
+            || text.contains("content_block_start")
+            || text.contains("message_delta");
+        let data = interesting
+            .then(|| text.lines().find_map(|l| l.strip_prefix("data:")))
+            .flatten()
+            .and_then(|d| serde_json::from_str::<Value>(d.trim()).ok());
+        let Some(mut data) = data else {
+            match self.held.as_mut() {
+                Some(held) => held.events.extend_from_slice(event),
+                None => output.extend_from_slice(event),
+            }
+            return;
+        };
+
+        if let Some(held) = self.held.as_mut() {
+            held.events.extend_from_slice(event);
+            match data["type"].as_str() {
+                Some("content_block_delta") => {
+                    held.partial_json
+                        .push_str(data["delta"]["partial_json"].as_str().unwrap_or_default());
+                }
+                Some("content_block_stop") => self.resolve(output),
+                _ => {}
+            }
+            return;
+        }
+
+        match data["type"].as_str() {
+            Some("content_block_start") => {
+                let index = data["index"].as_u64().unwrap_or_default();
+                self.next_index = self.next_index.max(index + 1);
+                let block = &data["content_block"];
+                if block["type"] == "tool_use" {
+                    self.held = Some(HeldCall {
+                        index,
+                        id: block["id"].as_str().unwrap_or_default().to_string(),
+                        name: block["name"].as_str().unwrap_or_default().to_string(),
+                        partial_json: String::new(),
+                        events: event.to_vec(),
+                    });
+                } else {
+                    output.extend_from_slice(event);
+                }
+            }
+            Some("message_delta") => {
+                if !self.warnings.is_empty() {
+                    let text = warning_text(&std::mem::take(&mut self.warnings));
+                    output.extend_from_slice(&text_block_sse(self.next_index as u32, &text));
+                    self.next_index += 1;
+                }
+                if self.kept_calls == 0
+                    && self.denied_calls > 0
+                    && data["delta"]["stop_reason"] == "tool_use"
+                {
+                    data["delta"]["stop_reason"] = json!("end_turn");
+                    write_event(output, "message_delta", &data);
+                } else {
+                    output.extend_from_slice(event);
+                }
+            }
+            _ => output.extend_from_slice(event),
+        }
+    }
+
+    /// Decide on the held call now that its input is complete
+    fn resolve(&mut self, output: &mut Vec<u8>) {
+        let Some(held) = self.held.take() else {
+            return;
+        };
+        let input = if held.partial_json.trim().is_empty() {
+            json!({})
+        } else {
+            serde_json::from_str(&held.partial_json).unwrap_or(Value::String(held.partial_json))
+        };
+        let name = original_name(self.renames.as_deref(), &held.name);
+        let decision = self
+            .policy
+            .evaluate(self.client.as_deref(), &held.id, &name, &input);
+
+        match decision {
+            Some(decision) if decision.action == GuardAction::Deny => {
+                output.extend_from_slice(&text_block_sse(held.index as u32, &decision.refusal()));
+                self.denied_calls += 1;
+                self.decisions.push(decision);
+            }
+            decision => {
+                output.extend_from_slice(&held.events);
+                self.kept_calls += 1;
+                if let Some(decision) = decision {
+                    self.warnings.push(decision.clone());
+                    self.decisions.push(decision);
+                }
+            }
+        }
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> Arc<ToolPolicy> {
+        let config = ToolGuard {
+            enabled: true,
+            rules: vec![
+                ToolGuardRule {
+                    name: Some("destructive-shell".to_string()),
+                    tool: "Bash".to_string(),
+                    field: Some("command".to_string()),
+                    pattern: Some(r"rm\s+-rf\s+/(\s|$)|git\s+push\s+.*--force".to_string()),
+                    ..Default::default()
+                },
+                ToolGuardRule {
+                    tool: "Edit|Write".to_string(),
+                    field: Some("file_path".to_string()),
+                    allow: Some("^/work/".to_string()),
+                    action: GuardAction::Warn,
+                    ..Default::default()
+                },
+                ToolGuardRule {
+                    tool: "Write".to_string(),
+                    field: Some("file_path".to_string()),
+                    pattern: Some(r"\.env$".to_string()),
+                    reason: Some("Writes to .env files are not allowed".to_string()),
+                    client: Some("review-bot".to_string()),
+                    ..Default::default()
+                },
+            ],
+        };
+        Arc::new(ToolPolicy::from_config(&config).unwrap())
+    }
+
+    /// SSE for a response with the given tool calls, split per event
+    fn stream(calls: &[(&str, &str, Value)]) -> Vec<String> {
+        let mut events = vec![
+            "event: message_start\ndata: {\"type\":\"message_start\",\"message\":{\"model\":\"claude-sonnet-4\"}}\n\n".to_string(),
+            "event: content_block_start\ndata: {\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"text\",\"text\":\"\"}}\n\n".to_string(),
+            "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"On it.\"}}\n\n".to_string(),
+            "event: content_block_stop\ndata: {\"type\":\"content_block_stop\",\"index\":0}\n\n".to_string(),
+        ];
+        for (i, (id, name, input)) in calls.iter().enumerate() {
+            let index = i + 1;
+            let start = json!({"type": "content_block_start", "index": index,
+                "content_block": {"type": "tool_use", "id": id, "name": name, "input": {}}});
+            events.push(format!("event: content_block_start\ndata: {}\n\n", start));
+            // Input arrives in two pieces
+            let input = input.to_string();
+            let (a, b) = input.split_at(input.len() / 2);
+            for part in [a, b] {
+                let delta = json!({"type": "content_block_delta", "index": index,
+                    "delta": {"type": "input_json_delta", "partial_json": part}});
+                events.push(format!("event: content_block_delta\ndata: {}\n\n", delta));
+            }
+            events.push(format!(
+                "event: content_block_stop\ndata: {{\"type\":\"content_block_stop\",\"index\":{}}}\n\n",
+                index
+            ));
+        }
+        events.push("event: message_delta\ndata: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"tool_use\"},\"usage\":{\"output_tokens\":40}}\n\n".to_string());
+        events.push("event: message_stop\ndata: {\"type\":\"message_stop\"}\n\n".to_string());
+        events
+    }
+
+    fn run(guard: &mut GuardedStream, events: &[String]) -> String {
+        let mut output = Vec::new();
+        for event in events {
+            // Split every event across two chunks
+            let (a, b) = event.split_at(event.len() / 2);
+            output.extend_from_slice(&guard.push(a.as_bytes()));
+            output.extend_from_slice(&guard.push(b.as_bytes()));
+        }
+        output.extend_from_slice(&guard.finish());
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_evaluates_patterns_allow_lists_and_clients() {
+        let policy = policy();
+        let deny = policy
+            .evaluate(
+                None,
+                "t1",
+                "Bash",
+                &json!({"command": "git push origin main --force"}),
+            )
+            .unwrap();
+        assert_eq!(deny.action, GuardAction::Deny);
+        assert_eq!(deny.rule, "destructive-shell");
+
+        assert!(policy
+            .evaluate(None, "t2", "Bash", &json!({"command": "rm -rf /tmp/build"}))
+            .is_none());
+
+        let warn = policy
+            .evaluate(None, "t3", "Edit", &json!({"file_path": "/etc/hosts"}))
+            .unwrap();
+        assert_eq!(warn.action, GuardAction::Warn);
+        assert!(warn.reason.contains("file_path is outside"));
+
+        // Deny beats an earlier warn, but only for its client
+        let env = json!({"file_path": "/work/.env"});
+        assert!(policy
+            .evaluate(Some("dev-1"), "t4", "Write", &env)
+            .is_none());
+        let denied = policy
+            .evaluate(Some("review-bot"), "t4", "Write", &env)
+            .unwrap();
+        assert_eq!(denied.reason, "Writes to .env files are not allowed");
+    }
+
+    #[test]
+    fn test_stream_denies_and_warns() {
+        let events = stream(&[
+            (
+                "toolu_1",
+                "Bash",
+                json!({"command": "rm -rf / --no-preserve-root"}),
+            ),
+            (
+                "toolu_2",
+                "Edit",
+                json!({"file_path": "/etc/hosts", "old_string": "a"}),
+            ),
+            ("toolu_3", "Read", json!({"file_path": "/work/src/main.rs"})),
+        ]);
+        let mut guard = GuardedStream::new(policy(), None, None);
+        let output = run(&mut guard, &events);
+
+        let assembled = crate::proxy::sse::assemble_to_json(&output).unwrap();
+        let content = assembled["content"].as_array().unwrap();
+        assert_eq!(content.len(), 5);
+        assert_eq!(content[1]["type"], "text");
+        assert!(content[1]["text"]
+            .as_str()
+            .unwrap()
+            .contains("Blocked Bash call"));
+        assert_eq!(content[2]["name"], "Edit");
+        assert_eq!(content[3]["name"], "Read");
+        assert!(content[4]["text"]
+            .as_str()
+            .unwrap()
+            .contains("Warning for Edit call toolu_2"));
+        assert!(
+            output.contains("\"index\":4"),
+            "warning takes the next index"
+        );
+        assert_eq!(assembled["stop_reason"], "tool_use", "calls remain");
+
+        let decisions = guard.take_decisions();
+        assert_eq!(decisions.len(), 2);
+        assert_eq!(decisions[0].input["command"], "rm -rf / --no-preserve-root");
+        assert!(guard.take_decisions().is_empty());
+    }
+
+    #[test]
+    fn test_stream_passes_clean_calls_through_unchanged() {
+        let events = stream(&[("toolu_1", "Bash", json!({"command": "cargo test"}))]);
+        let mut guard = GuardedStream::new(policy(), None, None);
+        assert_eq!(run(&mut guard, &events), events.concat());
+        assert!(guard.take_decisions().is_empty());
+    }
+
+    #[test]
+    fn test_all_denied_ends_the_turn() {
+        let calls = [("toolu_1", "Bash", json!({"command": "git push -f --force"}))];
+        let mut guard = GuardedStream::new(policy(), None, None);
+        let output = run(&mut guard, &stream(&calls));
+        let assembled = crate::proxy::sse::assemble_to_json(&output).unwrap();
+        assert_eq!(assembled["stop_reason"], "end_turn");
+
+        let mut body = json!({
+            "content": [
+                {"type": "text", "text": "Pushing"},
+                {"type": "tool_use", "id": "toolu_1", "name": "Bash", "input": calls[0].2}
+            ],
+            "stop_reason": "tool_use"
+        });
+        let decisions = policy().apply_buffered(None, None, &mut body);
+        assert_eq!(decisions.len(), 1);
+        assert_eq!(body["content"][1]["type"], "text");
+        assert_eq!(body["stop_reason"], "end_turn");
+    }
+
+    #[test]
+    fn test_renamed_tools_are_checked_by_original_name() {
+        let renames = Arc::new(ToolRenames::default());
+        renames.replace([("shell".to_string(), "Bash".to_string())].into());
+        let calls = [("toolu_1", "shell", json!({"command": "git push --force"}))];
+
+        let mut guard = GuardedStream::new(policy(), None, Some(Arc::clone(&renames)));
+        run(&mut guard, &stream(&calls));
+        let decisions = guard.take_decisions();
+        assert_eq!(decisions.len(), 1);
+        assert_eq!(decisions[0].tool_name, "Bash");
+
+        let mut body = json!({
+            "content": [{"type": "tool_use", "id": "toolu_1", "name": "shell", "input": calls[0].2}],
+            "stop_reason": "tool_use"
+        });
+        let decisions = policy().apply_buffered(None, Some(&renames), &mut body);
+        assert_eq!(decisions.len(), 1);
+        assert_eq!(body["content"][0]["type"], "text");
+    }
+
+    #[test]
+    fn test_rule_needs_a_condition() {
+        let config = ToolGuard {
+            enabled: true,
+            rules: vec![ToolGuardRule {
+                tool: "Bash".to_string(),
+                ..Default::default()
+            }],
+        };
+        assert!(ToolPolicy::from_config(&config).is_err());
+    }
+}
@@ -0,0 +1,531 @@
+//! Spending budgets per client and user
+//!
+//! Tracks USD spend per identity over daily, weekly and monthly windows
+//! (UTC; weeks start on Monday). Identities are the ones aspy already tracks:
+//! the client id for routed requests, otherwise the user id (API key hash).
+//!
+//! - Once a hard limit is reached, new `/messages` requests are blocked with
+//!   a 402 `billing_error` until the window resets.
+//! - Crossing `warn_percent` of a limit adds a one-time warning to the next
+//!   response (see the budget-warning augmenter).
+//!
+//! Spend is recorded from `ApiUsage` events (priced with
+//! `pricing::calculate_cost`) and seeded from cortex at startup, so limits
+//! survive proxy restarts.
+//!
+//! # Configuration
+//!
+//! ```toml
+//! [transformers.budget]
+//! enabled = true
+//! warn_percent = 80
+//!
+//! [transformers.budget.clients."dev-1"]
+//! daily_usd = 5.0
+//! monthly_usd = 100.0
+//!
+//! [transformers.budget.users."a1b2c3d4e5f6"]
+//! weekly_usd = 20.0
+//! ```
+
+use super::{RequestTransformer, TransformContext, TransformResult};
+use axum::http::StatusCode;
+use chrono::{DateTime, Datelike, Duration, TimeZone, Utc};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+// ============================================================================
+// Configuration
+// ============================================================================
+
+/// USD limits for one identity (unset = no limit for that window)
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq)]
+pub struct BudgetLimits {
+    #[serde(default)]
+    pub daily_usd: Option<f64>,
+    #[serde(default)]
+    pub weekly_usd: Option<f64>,
+    #[serde(default)]
+    pub monthly_usd: Option<f64>,
+}
+
+impl BudgetLimits {
+    /// Limit for a window
+    pub fn get(&self, period: BudgetPeriod) -> Option<f64> {
+        match period {
+            BudgetPeriod::Daily => self.daily_usd,
+            BudgetPeriod::Weekly => self.weekly_usd,
+            BudgetPeriod::Monthly => self.monthly_usd,
+        }
+    }
+}
+
+fn default_warn_percent() -> u8 {
+    80
+}
+
+/// Configuration for the budget transformer
+#[derive(Debug, Clone, Deserialize)]
+pub struct BudgetConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Percentage of a limit at which to warn (0 disables warnings)
+    #[serde(default = "default_warn_percent")]
+    pub warn_percent: u8,
+    /// Limits by client id (routed requests)
+    #[serde(default)]
+    pub clients: HashMap<String, BudgetLimits>,
+    /// Limits by user id (API key hash, unrouted requests)
+    #[serde(default)]
+    pub users: HashMap<String, BudgetLimits>,
+}
+
+impl Default for BudgetConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            warn_percent: default_warn_percent(),
+            clients: HashMap::new(),
+            users: HashMap::new(),
+        }
+    }
+}
+
+// ============================================================================
+// Budget Windows
+// ============================================================================
+
+/// A budget window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BudgetPeriod {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl BudgetPeriod {
+    pub const ALL: [BudgetPeriod; 3] = [Self::Daily, Self::Weekly, Self::Monthly];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Daily => "daily",
+            Self::Weekly => "weekly",
+            Self::Monthly => "monthly",
+        }
+    }
+
+    /// Start of the window containing `now`
+    pub fn start(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let date = now.date_naive();
+        let start = match self {
+            Self::Daily => date,
+            Self::Weekly => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+            Self::Monthly => date.with_day(1).unwrap_or(date),
+        };
+        Utc.from_utc_datetime(&start.and_hms_opt(0, 0, 0).unwrap_or_default())
+    }
+
+    /// Start of the next window (when the budget resets)
+    pub fn reset(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let start = self.start(now);
+        match self {
+            Self::Daily => start + Duration::days(1),
+            Self::Weekly => start + Duration::weeks(1),
+            Self::Monthly => {
+                let (year, month) = if start.month() == 12 {
+                    (start.year() + 1, 1)
+                } else {
+                    (start.year(), start.month() + 1)
+                };
+                Utc.with_ymd_and_hms(year, month, 1, 0, 0, 0)
+                    .single()
+                    .unwrap_or(start + Duration::days(31))
+            }
+        }
+    }
+}
+
+/// Spend against one limit
+#[derive(Debug, Clone, PartialEq)]
+pub struct BudgetStatus {
+    pub identity: String,
+    pub period: BudgetPeriod,
+    pub spent_usd: f64,
+    pub limit_usd: f64,
+    pub resets_at: DateTime<Utc>,
+}
+
+impl BudgetStatus {
+    pub fn percent(&self) -> f64 {
+        if self.limit_usd > 0.0 {
+            self.spent_usd / self.limit_usd * 100.0
+        } else {
+            100.0
+        }
+    }
+}
+
+/// Spend within one window
+#[derive(Debug, Clone, Copy)]
+struct WindowSpend {
+    start: DateTime<Utc>,
+    usd: f64,
+    warned: bool,
+}
+
+// ============================================================================
+// Budget Tracker
+// ============================================================================
+
+/// Tracks spend per identity and enforces limits
+pub struct BudgetTracker {
+    config: BudgetConfig,
+    ledgers: Mutex<HashMap<String, HashMap<BudgetPeriod, WindowSpend>>>,
+}
+
+impl BudgetTracker {
+    pub fn new(config: BudgetConfig) -> Self {
+        Self {
+            config,
+            ledgers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Number of identities with limits
+    pub fn identity_count(&self) -> usize {
+        self.config.clients.len() + self.config.users.len()
+    }
+
+    /// Limits for an identity (client ids take precedence over user ids)
+    fn limits(&self, identity: &str) -> Option<&BudgetLimits> {
+        self.config
+            .clients
+            .get(identity)
+            .or_else(|| self.config.users.get(identity))
+    }
+
+    /// Whether an identity has any budget configured
+    pub fn is_tracked(&self, identity: &str) -> bool {
+        self.limits(identity).is_some()
+    }
+
+    /// Add spend for an identity (ignored for identities without limits)
+    pub fn record(&self, identity: &str, cost_usd: f64, now: DateTime<Utc>) {
+        if !self.is_tracked(identity) || cost_usd <= 0.0 {
+            return;
+        }
+        if let Ok(mut ledgers) = self.ledgers.lock() {
+            let ledger = ledgers.entry(identity.to_string()).or_default();
+            for period in BudgetPeriod::ALL {
+                window(ledger, period, now).usd += cost_usd;
+            }
+        }
+    }
+
+    /// Load spend recorded before startup
+    ///
+    /// `totals` are per-identity sums since the start of `period`'s current
+    /// window. Replaces (not adds to) the tracked amount.
+    pub fn seed(&self, period: BudgetPeriod, totals: &[(String, f64)], now: DateTime<Utc>) {
+        if let Ok(mut ledgers) = self.ledgers.lock() {
+            for (identity, usd) in totals {
+                if !self.is_tracked(identity) {
+                    continue;
+                }
+                let ledger = ledgers.entry(identity.clone()).or_default();
+                window(ledger, period, now).usd = *usd;
+            }
+        }
+    }
+
+    /// Seed all windows from cortex `api_usage` history
+    pub fn seed_from_cortex(&self, query: &crate::pipeline::cortex_query::CortexQuery) {
+        self.seed_with(|since| query.get_spend_since(since));
+    }
+
+    /// Seed all windows using `load(since)` to total spend per identity
+    pub fn seed_with(&self, load: impl Fn(&str) -> anyhow::Result<Vec<(String, f64)>>) {
+        let now = Utc::now();
+        for period in BudgetPeriod::ALL {
+            match load(&period.start(now).to_rfc3339()) {
+                Ok(totals) => self.seed(period, &totals, now),
+                Err(e) => {
+                    tracing::warn!(
+                        "Failed to load {} spend from cortex: {}",
+                        period.as_str(),
+                        e
+                    );
+                }
+            }
+        }
+    }
+
+    /// Current spend against each configured limit
+    pub fn statuses(&self, identity: &str, now: DateTime<Utc>) -> Vec<BudgetStatus> {
+        let Some(limits) = self.limits(identity) else {
+            return Vec::new();
+        };
+        let Ok(mut ledgers) = self.ledgers.lock() else {
+            return Vec::new();
+        };
+        let ledger = ledgers.entry(identity.to_string()).or_default();
+
+        BudgetPeriod::ALL
+            .iter()
+            .filter_map(|&period| {
+                let limit_usd = limits.get(period)?;
+                Some(BudgetStatus {
+                    identity: identity.to_string(),
+                    period,
+                    spent_usd: window(ledger, period, now).usd,
+                    limit_usd,
+                    resets_at: period.reset(now),
+                })
+            })
+            .collect()
+    }
+
+    /// First limit the identity has used up, if any
+    pub fn exhausted(&self, identity: &str, now: DateTime<Utc>) -> Option<BudgetStatus> {
+        self.statuses(identity, now)
+            .into_iter()
+            .find(|s| s.spent_usd >= s.limit_usd)
+    }
+
+    /// Take a pending soft-threshold warning: the first window over the
+    /// threshold that hasn't warned yet (each window warns at most once)
+    pub fn take_warning(&self, identity: &str, now: DateTime<Utc>) -> Option<BudgetStatus> {
+        let warn_percent = self.config.warn_percent;
+        if warn_percent == 0 {
+            return None;
+        }
+        let statuses = self.statuses(identity, now);
+
+        let mut ledgers = self.ledgers.lock().ok()?;
+        let ledger = ledgers.get_mut(identity)?;
+        statuses
+            .into_iter()
+            .filter(|s| s.percent() >= warn_percent as f64 && s.spent_usd < s.limit_usd)
+            .find(|status| {
+                let spend = window(ledger, status.period, now);
+                !std::mem::replace(&mut spend.warned, true)
+            })
+    }
+}
+
+/// Window entry for `period`, reset if `now` has moved into a new window
+fn window(
+    ledger: &mut HashMap<BudgetPeriod, WindowSpend>,
+    period: BudgetPeriod,
+    now: DateTime<Utc>,
+) -> &mut WindowSpend {
+    let start = period.start(now);
+    let spend = ledger.entry(period).or_insert(WindowSpend {
+        start,
+        usd: 0.0,
+        warned: false,
+    });
+    if spend.start != start {
+        *spend = WindowSpend {
+            start,
+            usd: 0.0,
+            warned: false,
+        };
+    }
+    spend
+}
+
+impl RequestTransformer for BudgetTracker {
+    fn name(&self) -> &'static str {
+        "budget"
+    }
+
+    fn should_apply(&self, ctx: &TransformContext) -> bool {
+        // Only new completions cost money (not count_tokens)
+        ctx.path.ends_with("/messages") && ctx.client_id.is_some_and(|id| self.is_tracked(id))
+    }
+
+    fn transform(&self, _body: &Value, ctx: &TransformContext) -> TransformResult {
+        let Some(identity) = ctx.client_id else {
+            return TransformResult::Unchanged;
+        };
+        match self.exhausted(identity, Utc::now()) {
+            Some(status) => TransformResult::Block {
+                reason: format!(
+                    "aspy: {} budget exhausted for '{}' (${:.2} of ${:.2} spent). Resets {}.",
+                    status.period.as_str(),
+                    identity,
+                    status.spent_usd,
+                    status.limit_usd,
+                    status.resets_at.format("%Y-%m-%d %H:%M UTC")
+                ),
+                status: StatusCode::PAYMENT_REQUIRED,
+            },
+            None => TransformResult::Unchanged,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracker() -> BudgetTracker {
+        let mut config = BudgetConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        config.clients.insert(
+            "dev-1".to_string(),
+            BudgetLimits {
+                daily_usd: Some(5.0),
+                monthly_usd: Some(50.0),
+                ..Default::default()
+            },
+        );
+        config.users.insert(
+            "abc123".to_string(),
+            BudgetLimits {
+                weekly_usd: Some(10.0),
+                ..Default::default()
+            },
+        );
+        BudgetTracker::new(config)
+    }
+
+    fn at(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_period_windows() {
+        // Friday, mid-month
+        let now = at("2026-10-16T15:30:00Z");
+        assert_eq!(BudgetPeriod::Daily.start(now), at("2026-10-16T00:00:00Z"));
+        assert_eq!(BudgetPeriod::Weekly.start(now), at("2026-10-12T00:00:00Z"));
+        assert_eq!(BudgetPeriod::Monthly.start(now), at("2026-10-01T00:00:00Z"));
+        assert_eq!(BudgetPeriod::Weekly.reset(now), at("2026-10-19T00:00:00Z"));
+
+        let december = at("2026-12-31T23:59:00Z");
+        assert_eq!(
+            BudgetPeriod::Monthly.reset(december),
+            at("2027-01-01T00:00:00Z")
+        );
+    }
+
+    #[test]
+    fn test_record_and_exhaust_then_reset() {
+        let tracker = tracker();
+        let now = at("2026-10-16T10:00:00Z");
+
+        tracker.record("dev-1", 3.0, now);
+        assert!(tracker.exhausted("dev-1", now).is_none());
+
+        tracker.record("dev-1", 2.5, now);
+        let status = tracker.exhausted("dev-1", now).unwrap();
+        assert_eq!(status.period, BudgetPeriod::Daily);
+        assert_eq!(status.limit_usd, 5.0);
+
+        // Next day: daily window resets, monthly keeps accumulating
+        let tomorrow = at("2026-10-17T01:00:00Z");
+        assert!(tracker.exhausted("dev-1", tomorrow).is_none());
+        let monthly = tracker
+            .statuses("dev-1", tomorrow)
+            .into_iter()
+            .find(|s| s.period == BudgetPeriod::Monthly)
+            .unwrap();
+        assert_eq!(monthly.spent_usd, 5.5);
+
+        // Untracked identities are ignored
+        tracker.record("someone-else", 100.0, now);
+        assert!(tracker.statuses("someone-else", now).is_empty());
+    }
+
+    #[test]
+    fn test_warning_fires_once_per_window() {
+        let tracker = tracker();
+        let now = at("2026-10-16T10:00:00Z");
+
+        tracker.record("abc123", 7.0, now);
+        assert!(tracker.take_warning("abc123", now).is_none());
+
+        tracker.record("abc123", 1.5, now);
+        let warning = tracker.take_warning("abc123", now).unwrap();
+        assert_eq!(warning.period, BudgetPeriod::Weekly);
+        assert!(warning.percent() >= 80.0);
+        assert!(tracker.take_warning("abc123", now).is_none());
+
+        // New week, warning can fire again
+        let next_week = at("2026-10-19T10:00:00Z");
+        tracker.record("abc123", 9.0, next_week);
+        assert!(tracker.take_warning("abc123", next_week).is_some());
+    }
+
+    #[test]
+    fn test_warning_fires_for_each_window() {
+        let mut config = BudgetConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        config.clients.insert(
+            "dev-2".to_string(),
+            BudgetLimits {
+                daily_usd: Some(10.0),
+                weekly_usd: Some(11.0),
+                ..Default::default()
+            },
+        );
+        let tracker = BudgetTracker::new(config);
+        let now = at("2026-10-16T10:00:00Z");
+
+        tracker.record("dev-2", 8.5, now);
+        let warning = tracker.take_warning("dev-2", now).unwrap();
+        assert_eq!(warning.period, BudgetPeriod::Daily);
+
+        // Daily was already warned, weekly crossing the threshold still warns
+        tracker.record("dev-2", 0.5, now);
+        let warning = tracker.take_warning("dev-2", now).unwrap();
+        assert_eq!(warning.period, BudgetPeriod::Weekly);
+        assert!(tracker.take_warning("dev-2", now).is_none());
+    }
+
+    #[test]
+    fn test_seed_replaces_window_spend() {
+        let tracker = tracker();
+        let now = at("2026-10-16T10:00:00Z");
+
+        tracker.seed(
+            BudgetPeriod::Monthly,
+            &[("dev-1".to_string(), 49.0), ("untracked".to_string(), 1.0)],
+            now,
+        );
+        tracker.record("dev-1", 1.0, now);
+
+        let status = tracker.exhausted("dev-1", now).unwrap();
+        assert_eq!(status.period, BudgetPeriod::Monthly);
+        assert_eq!(status.spent_usd, 50.0);
+    }
+
+    #[test]
+    fn test_transformer_blocks_messages_only() {
+        let tracker = tracker();
+        tracker.record("dev-1", 6.0, Utc::now());
+
+        let ctx = TransformContext::new(Some("dev-1"), "/v1/messages", None);
+        assert!(tracker.should_apply(&ctx));
+        match tracker.transform(&Value::Null, &ctx) {
+            TransformResult::Block { reason, status } => {
+                assert_eq!(status, StatusCode::PAYMENT_REQUIRED);
+                assert!(reason.contains("daily budget exhausted for 'dev-1'"));
+            }
+            other => panic!("expected Block, got {:?}", other),
+        }
+
+        let count = TransformContext::new(Some("dev-1"), "/v1/messages/count_tokens", None);
+        assert!(!tracker.should_apply(&count));
+        let other = TransformContext::new(Some("dev-2"), "/v1/messages", None);
+        assert!(!tracker.should_apply(&other));
+    }
+}
@@ -0,0 +1,395 @@
+//! Cache optimizer transformer - places prompt-cache breakpoints
+//!
+//! Anthropic only caches a prompt prefix when the request marks it with a
+//! `cache_control` breakpoint, and allows at most four of them. Claude Code
+//! places its own, but OpenAI-translated and custom clients usually send
+//! none, so every turn pays full price for the same system prompt, tools
+//! and conversation history.
+//!
+//! # Placement
+//!
+//! ```text
+//! tools[last]              → tool definitions (stable across the session)
+//! system[last]             → system prompt
+//! messages[prev user]      → prefix written by the previous turn (cache read)
+//! messages[last]           → prefix for the next turn (cache write)
+//! ```
+//!
+//! A breakpoint is only placed when the prefix up to it is at least
+//! `min_prefix_tokens` long; shorter prefixes are not cached by the API.
+//! Client breakpoints that already sit on one of these positions are kept.
+//! Others are kept while slots remain and moved otherwise, so the request
+//! never exceeds the four-breakpoint limit.
+//!
+//! Requests using extended cache TTLs are left alone: TTL ordering rules
+//! mean moving their breakpoints could turn a valid request into an error.
+
+use super::{RequestTransformer, TransformContext, TransformResult};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+/// Maximum cache breakpoints allowed per request by the API
+const MAX_BREAKPOINTS: usize = 4;
+
+/// Smallest prefix the API will cache (Sonnet/Opus; Haiku needs 2048)
+const DEFAULT_MIN_PREFIX_TOKENS: u32 = 1024;
+
+// ============================================================================
+// Configuration
+// ============================================================================
+
+/// Configuration for the CacheOptimizer transformer
+#[derive(Debug, Clone, Deserialize)]
+pub struct CacheOptimizerConfig {
+    /// Whether the cache optimizer is enabled
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Minimum estimated prefix size (tokens) worth a breakpoint
+    #[serde(default = "default_min_prefix_tokens")]
+    pub min_prefix_tokens: u32,
+}
+
+fn default_min_prefix_tokens() -> u32 {
+    DEFAULT_MIN_PREFIX_TOKENS
+}
+
+impl Default for CacheOptimizerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_prefix_tokens: DEFAULT_MIN_PREFIX_TOKENS,
+        }
+    }
+}
+
+// ============================================================================
+// Breakpoint Positions
+// ============================================================================
+
+/// A block that can carry `cache_control`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Breakpoint {
+    /// `tools[i]`
+    Tool(usize),
+    /// `system[i]` (index 0 for a string system prompt)
+    System(usize),
+    /// `messages[i].content[j]` (block 0 for string content)
+    Message(usize, usize),
+}
+
+impl Breakpoint {
+    fn describe(&self) -> String {
+        match self {
+            Breakpoint::Tool(_) => "tools".to_string(),
+            Breakpoint::System(_) => "system".to_string(),
+            Breakpoint::Message(i, _) => format!("messages[{}]", i),
+        }
+    }
+}
+
+/// Find every block that already carries `cache_control`
+fn existing_breakpoints(body: &Value) -> Vec<(Breakpoint, &Value)> {
+    let mut found = Vec::new();
+
+    if let Some(tools) = body["tools"].as_array() {
+        for (i, tool) in tools.iter().enumerate() {
+            if let Some(cc) = tool.get("cache_control") {
+                found.push((Breakpoint::Tool(i), cc));
+            }
+        }
+    }
+    if let Some(system) = body["system"].as_array() {
+        for (i, block) in system.iter().enumerate() {
+            if let Some(cc) = block.get("cache_control") {
+                found.push((Breakpoint::System(i), cc));
+            }
+        }
+    }
+    if let Some(messages) = body["messages"].as_array() {
+        for (i, msg) in messages.iter().enumerate() {
+            if let Some(blocks) = msg["content"].as_array() {
+                for (j, block) in blocks.iter().enumerate() {
+                    if let Some(cc) = block.get("cache_control") {
+                        found.push((Breakpoint::Message(i, j), cc));
+                    }
+                }
+            }
+        }
+    }
+
+    found
+}
+
+/// Last block of a message that can carry a breakpoint
+///
+/// Thinking blocks and empty text can't be marked.
+fn cacheable_block(message: &Value) -> Option<usize> {
+    match &message["content"] {
+        Value::String(s) if !s.is_empty() => Some(0),
+        Value::Array(blocks) => blocks.iter().rposition(|b| match b["type"].as_str() {
+            Some("thinking") | Some("redacted_thinking") => false,
+            Some("text") => b["text"].as_str().is_some_and(|t| !t.is_empty()),
+            _ => true,
+        }),
+        _ => None,
+    }
+}
+
+/// Resolve a breakpoint to its block, converting string content to a
+/// single text block so it can carry `cache_control`
+fn block_mut(body: &mut Value, bp: Breakpoint) -> Option<&mut Value> {
+    let (slot, index) = match bp {
+        Breakpoint::Tool(i) => return body["tools"].get_mut(i),
+        Breakpoint::System(i) => (&mut body["system"], i),
+        Breakpoint::Message(i, j) => (body["messages"].get_mut(i)?.get_mut("content")?, j),
+    };
+    if let Value::String(text) = slot {
+        *slot = json!([{"type": "text", "text": std::mem::take(text)}]);
+    }
+    slot.get_mut(index)
+}
+
+// ============================================================================
+// Cache Optimizer
+// ============================================================================
+
+/// Transformer that places `cache_control` breakpoints on stable prefixes
+pub struct CacheOptimizer {
+    min_prefix_tokens: u32,
+}
+
+impl CacheOptimizer {
+    pub fn new(config: &CacheOptimizerConfig) -> Self {
+        Self {
+            min_prefix_tokens: config.min_prefix_tokens,
+        }
+    }
+
+    /// Breakpoints this request should have, in prefix order (at most four)
+    fn plan(&self, body: &Value) -> Vec<Breakpoint> {
+        let mut planned = Vec::new();
+        let mut prefix_tokens: u32 = 0;
+
+        if let Some(tools) = body["tools"].as_array().filter(|t| !t.is_empty()) {
+            prefix_tokens += crate::tokens::estimate_json_tokens(&body["tools"]);
+            if prefix_tokens >= self.min_prefix_tokens {
+                planned.push(Breakpoint::Tool(tools.len() - 1));
+            }
+        }
+
+        let system_last = match &body["system"] {
+            Value::String(s) if !s.is_empty() => Some(0),
+            Value::Array(blocks) if !blocks.is_empty() => Some(blocks.len() - 1),
+            _ => None,
+        };
+        if let Some(last) = system_last {
+            prefix_tokens += crate::tokens::estimate_json_tokens(&body["system"]);
+            if prefix_tokens >= self.min_prefix_tokens {
+                planned.push(Breakpoint::System(last));
+            }
+        }
+
+        let Some(messages) = body["messages"].as_array().filter(|m| !m.is_empty()) else {
+            return planned;
+        };
+
+        // The previous turn ended at the user message before the latest one;
+        // marking it reads what that turn wrote. The last message is marked
+        // so the next turn can read this one.
+        let previous_user = messages
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, m)| m["role"].as_str() == Some("user"))
+            .nth(1)
+            .map(|(i, _)| i);
+        let last = messages.len() - 1;
+
+        for (i, message) in messages.iter().enumerate() {
+            prefix_tokens += crate::tokens::estimate_json_tokens(message);
+            if (Some(i) == previous_user || i == last) && prefix_tokens >= self.min_prefix_tokens {
+                if let Some(j) = cacheable_block(message) {
+                    planned.push(Breakpoint::Message(i, j));
+                }
+            }
+        }
+
+        planned
+    }
+
+    /// Place breakpoints on a request body in place
+    ///
+    /// Returns descriptions of what changed, or None if the request was
+    /// already well marked (or can't safely be changed).
+    pub fn optimize(&self, body: &mut Value) -> Option<Vec<String>> {
+        let existing = existing_breakpoints(body);
+        if existing
+            .iter()
+            .any(|(_, cc)| cc.get("ttl").is_some_and(|t| t != "5m"))
+        {
+            return None;
+        }
+        let existing: Vec<Breakpoint> = existing.into_iter().map(|(bp, _)| bp).collect();
+
+        let planned = self.plan(body);
+        if existing.len() <= MAX_BREAKPOINTS && planned.iter().all(|bp| existing.contains(bp)) {
+            return None;
+        }
+
+        // Client breakpoints outside the plan keep the remaining slots,
+        // earliest first (earlier prefixes are the most stable)
+        let free_slots = MAX_BREAKPOINTS - planned.len();
+        let moved: Vec<Breakpoint> = existing
+            .iter()
+            .filter(|bp| !planned.contains(bp))
+            .skip(free_slots)
+            .copied()
+            .collect();
+
+        let mut modifications = Vec::new();
+        for bp in moved {
+            if let Some(block) = block_mut(body, bp).and_then(|b| b.as_object_mut()) {
+                block.remove("cache_control");
+                modifications.push(format!("Moved cache breakpoint off {}", bp.describe()));
+            }
+        }
+        for bp in planned.iter().filter(|bp| !existing.contains(bp)) {
+            if let Some(block) = block_mut(body, *bp).and_then(|b| b.as_object_mut()) {
+                block.insert("cache_control".to_string(), json!({"type": "ephemeral"}));
+                modifications.push(format!("Cache breakpoint on {}", bp.describe()));
+            }
+        }
+
+        (!modifications.is_empty()).then_some(modifications)
+    }
+}
+
+impl RequestTransformer for CacheOptimizer {
+    fn name(&self) -> &'static str {
+        "cache-optimizer"
+    }
+
+    fn should_apply(&self, ctx: &TransformContext) -> bool {
+        // Anthropic-format bodies only; OpenAI clients are optimized after
+        // translation (see `optimize`)
+        ctx.path.ends_with("/messages")
+    }
+
+    fn transform(&self, body: &Value, _ctx: &TransformContext) -> TransformResult {
+        let mut new_body = body.clone();
+        match self.optimize(&mut new_body) {
+            // Breakpoints don't change the prompt's token count
+            Some(modifications) => {
+                TransformResult::modified_with_info(new_body, 0, 0, modifications)
+            }
+            None => TransformResult::Unchanged,
+        }
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn optimizer(min_prefix_tokens: u32) -> CacheOptimizer {
+        CacheOptimizer::new(&CacheOptimizerConfig {
+            enabled: true,
+            min_prefix_tokens,
+        })
+    }
+
+    fn long_text(words: usize) -> String {
+        "lorem ipsum dolor sit amet ".repeat(words / 5)
+    }
+
+    fn conversation() -> Value {
+        json!({
+            "model": "claude-sonnet-4",
+            "tools": [
+                {"name": "Read", "input_schema": {"type": "object"}},
+                {"name": "Edit", "input_schema": {"type": "object"}}
+            ],
+            "system": long_text(2000),
+            "messages": [
+                {"role": "user", "content": "first question"},
+                {"role": "assistant", "content": [
+                    {"type": "thinking", "thinking": "hmm", "signature": "sig"},
+                    {"type": "text", "text": "first answer"}
+                ]},
+                {"role": "user", "content": [{"type": "text", "text": "second question"}]}
+            ]
+        })
+    }
+
+    fn marked(body: &Value) -> Vec<Breakpoint> {
+        existing_breakpoints(body)
+            .into_iter()
+            .map(|(bp, _)| bp)
+            .collect()
+    }
+
+    #[test]
+    fn test_places_breakpoints_on_stable_prefixes() {
+        let mut body = conversation();
+        let modifications = optimizer(1024).optimize(&mut body).expect("should modify");
+
+        // Tools alone are too short to cache; system and both turns qualify
+        assert_eq!(
+            marked(&body),
+            vec![
+                Breakpoint::System(0),
+                Breakpoint::Message(0, 0),
+                Breakpoint::Message(2, 0)
+            ]
+        );
+        assert_eq!(modifications.len(), 3);
+        assert_eq!(body["system"][0]["cache_control"]["type"], "ephemeral");
+        assert_eq!(body["messages"][0]["content"][0]["text"], "first question");
+
+        // Already optimized requests pass through untouched
+        assert!(optimizer(1024).optimize(&mut body).is_none());
+    }
+
+    #[test]
+    fn test_short_prompts_unchanged() {
+        let mut body = json!({
+            "system": "Be brief.",
+            "messages": [{"role": "user", "content": "hi"}]
+        });
+        assert!(optimizer(1024).optimize(&mut body).is_none());
+        assert_eq!(body["system"], "Be brief.", "string system left as-is");
+    }
+
+    #[test]
+    fn test_moves_excess_client_breakpoints() {
+        let mut body = conversation();
+        let cc = json!({"type": "ephemeral"});
+        body["tools"][0]["cache_control"] = cc.clone();
+        body["tools"][1]["cache_control"] = cc.clone();
+        body["messages"][1]["content"][1]["cache_control"] = cc.clone();
+
+        let modifications = optimizer(1024).optimize(&mut body).expect("should modify");
+
+        // One slot is left after the plan: the earliest client breakpoint keeps it
+        let bps = marked(&body);
+        assert_eq!(bps.len(), MAX_BREAKPOINTS);
+        assert!(bps.contains(&Breakpoint::Tool(0)));
+        assert!(!bps.contains(&Breakpoint::Tool(1)));
+        assert!(!bps.contains(&Breakpoint::Message(1, 1)));
+        assert!(modifications.iter().any(|m| m.starts_with("Moved")));
+    }
+
+    #[test]
+    fn test_extended_ttl_left_alone() {
+        let mut body = conversation();
+        body["tools"][1]["cache_control"] = json!({"type": "ephemeral", "ttl": "1h"});
+        let before = body.clone();
+        assert!(optimizer(1024).optimize(&mut body).is_none());
+        assert_eq!(body, before);
+    }
+}
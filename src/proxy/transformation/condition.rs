@@ -0,0 +1,579 @@
+//! When conditions - the shared predicate language for transformer rules
+//!
+//! Rules in the tag editor, system editor, model router, tool filter and
+//! context enricher accept an optional `when` table:
+//!
+//! ```toml
+//! [[transformers.tag-editor.rules]]
+//! # ...
+//! when.turn_number = "=1|every:5"        # Conversation turn
+//! when.has_tool_results = "=0"           # tool_result blocks in the last user message
+//! when.client_id = "dev-1|foundry"       # Client ID
+//! when.model = "opus"                    # Case-insensitive regex on the model
+//! when.context_usage = ">80"             # Percent of the session's context limit
+//! when.prompt = "(?i)\\bdeploy\\b"       # Regex on the latest user prompt
+//! when.tool_result = "Bash|Edit"         # Tool answered in the last user message
+//! when.time = "09:00-17:00"              # Local time window (may wrap midnight)
+//! when.any = [{ model = "haiku" }, { turn_number = "=1" }]
+//! when.not = { client_id = "ci" }
+//! ```
+//!
+//! All set fields must hold (AND). Pipe-separated values within a field are
+//! OR'd, except for the regex fields where `|` is regex alternation anyway.
+//! `any`, `all` and `not` take nested conditions, so predicates compose.
+//!
+//! A predicate whose data isn't available for a request (no session yet,
+//! no model, no client) passes - rules stay permissive rather than silently
+//! never firing. `prompt` is the exception: tool-result continuations carry
+//! no new prompt, and a prompt rule passing on all of them would fire on
+//! nearly every request of the turn, so a missing prompt never matches.
+
+use super::TransformContext;
+use chrono::NaiveTime;
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+use std::sync::OnceLock;
+
+/// Conditions that must be met for a rule to apply
+///
+/// Multiple conditions in the same WhenCondition are AND'd together.
+/// Pipe-separated values within a condition are OR'd (e.g., "dev-1|foundry").
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct WhenCondition {
+    /// Turn number condition: "=1", ">5", "<10", "every:3"
+    #[serde(default)]
+    pub turn_number: Option<String>,
+
+    /// Tool results condition: ">0", "=0", ">3"
+    #[serde(default)]
+    pub has_tool_results: Option<String>,
+
+    /// Client ID condition: "dev-1", "foundry|local" (pipe = OR)
+    #[serde(default)]
+    pub client_id: Option<String>,
+
+    /// Model condition: case-insensitive regex, "opus", "sonnet|haiku"
+    #[serde(default)]
+    pub model: Option<String>,
+
+    /// Context usage in percent of the limit: ">80", "<50"
+    #[serde(default)]
+    pub context_usage: Option<String>,
+
+    /// Regex on the latest user prompt
+    #[serde(default)]
+    pub prompt: Option<String>,
+
+    /// Tool answered by a tool_result in the last user message: "Bash|Edit"
+    #[serde(default)]
+    pub tool_result: Option<String>,
+
+    /// Local time windows: "09:00-17:00", "22:00-06:00|12:00-13:00"
+    #[serde(default)]
+    pub time: Option<String>,
+
+    /// At least one of these conditions must hold
+    #[serde(default)]
+    pub any: Vec<WhenCondition>,
+
+    /// All of these conditions must hold
+    #[serde(default)]
+    pub all: Vec<WhenCondition>,
+
+    /// This condition must NOT hold
+    #[serde(default)]
+    pub not: Option<Box<WhenCondition>>,
+
+    /// Compiled `model` / `prompt` regexes (filled by `validate`, or on first use)
+    #[serde(skip)]
+    pub(crate) patterns: OnceLock<Patterns>,
+}
+
+/// Regexes compiled once per condition (None = unset or invalid, which passes)
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Patterns {
+    model: Option<Regex>,
+    prompt: Option<Regex>,
+}
+
+impl WhenCondition {
+    /// Check if all conditions are met
+    pub fn evaluate(&self, ctx: &TransformContext) -> bool {
+        self.evaluate_at(ctx, chrono::Local::now().time())
+    }
+
+    /// Check all conditions against a given local time
+    fn evaluate_at(&self, ctx: &TransformContext, now: NaiveTime) -> bool {
+        let turn_ok = self.check_turn_number(ctx);
+        let tools_ok = self.check_tool_results(ctx);
+        let result = turn_ok
+            && tools_ok
+            && self.check_client_id(ctx)
+            && self.check_model(ctx)
+            && self.check_context_usage(ctx)
+            && self.check_prompt(ctx)
+            && self.check_tool_result(ctx)
+            && self.check_time(now)
+            && self.all.iter().all(|c| c.evaluate_at(ctx, now))
+            && (self.any.is_empty() || self.any.iter().any(|c| c.evaluate_at(ctx, now)))
+            && !self.not.as_ref().is_some_and(|c| c.evaluate_at(ctx, now));
+
+        tracing::debug!(
+            turn_cond = ?self.turn_number,
+            actual_turn = ctx.turn_number,
+            turn_ok,
+            tools_cond = ?self.has_tool_results,
+            actual_tools = ctx.tool_result_count,
+            tools_ok,
+            result,
+            "Condition evaluation"
+        );
+
+        result
+    }
+
+    /// Check that patterns and time windows are valid
+    ///
+    /// Evaluation treats invalid values as passing, so transformers call
+    /// this from `from_config` to reject typos up front.
+    /// Compiles the regexes once so evaluation doesn't rebuild them per request.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let model = match self.model {
+            Some(ref pattern) => Some(
+                model_regex(pattern)
+                    .map_err(|e| anyhow::anyhow!("invalid when.model '{}': {}", pattern, e))?,
+            ),
+            None => None,
+        };
+        let prompt = match self.prompt {
+            Some(ref pattern) => Some(
+                Regex::new(pattern)
+                    .map_err(|e| anyhow::anyhow!("invalid when.prompt '{}': {}", pattern, e))?,
+            ),
+            None => None,
+        };
+        let _ = self.patterns.set(Patterns { model, prompt });
+        if let Some(ref windows) = self.time {
+            for window in windows.split('|') {
+                if parse_time_window(window).is_none() {
+                    anyhow::bail!(
+                        "invalid when.time window '{}' (expected HH:MM-HH:MM)",
+                        window
+                    );
+                }
+            }
+        }
+        for nested in self.any.iter().chain(&self.all).chain(self.not.as_deref()) {
+            nested.validate()?;
+        }
+        Ok(())
+    }
+
+    fn check_turn_number(&self, ctx: &TransformContext) -> bool {
+        let Some(ref condition) = self.turn_number else {
+            return true;
+        };
+        let Some(turn) = ctx.turn_number else {
+            return true; // No turn info = pass (permissive)
+        };
+        parse_numeric_condition(condition, turn)
+    }
+
+    fn check_tool_results(&self, ctx: &TransformContext) -> bool {
+        let Some(ref condition) = self.has_tool_results else {
+            return true;
+        };
+        let count = ctx.tool_result_count.unwrap_or(0) as u64;
+        parse_numeric_condition(condition, count)
+    }
+
+    fn check_client_id(&self, ctx: &TransformContext) -> bool {
+        let Some(ref condition) = self.client_id else {
+            return true;
+        };
+        let Some(client) = ctx.client_id else {
+            return true;
+        };
+        // Pipe-separated = OR
+        condition.split('|').any(|c| c.trim() == client)
+    }
+
+    /// Compiled regexes (compiled here if `validate` was never called)
+    fn patterns(&self) -> &Patterns {
+        self.patterns.get_or_init(|| Patterns {
+            model: self.model.as_deref().and_then(|p| model_regex(p).ok()),
+            prompt: self.prompt.as_deref().and_then(|p| Regex::new(p).ok()),
+        })
+    }
+
+    fn check_model(&self, ctx: &TransformContext) -> bool {
+        let Some(model) = ctx.model else {
+            return true;
+        };
+        self.patterns()
+            .model
+            .as_ref()
+            .is_none_or(|re| re.is_match(model))
+    }
+
+    fn check_context_usage(&self, ctx: &TransformContext) -> bool {
+        let Some(ref condition) = self.context_usage else {
+            return true;
+        };
+        match (ctx.context_tokens, ctx.context_limit) {
+            (Some(tokens), Some(limit)) if limit > 0 => {
+                parse_numeric_condition(condition, tokens.saturating_mul(100) / limit)
+            }
+            _ => true,
+        }
+    }
+
+    fn check_prompt(&self, ctx: &TransformContext) -> bool {
+        if self.prompt.is_none() {
+            return true;
+        }
+        // No prompt (tool-result continuation) = no match
+        let Some(prompt) = ctx.user_prompt else {
+            return false;
+        };
+        self.patterns()
+            .prompt
+            .as_ref()
+            .is_none_or(|re| re.is_match(prompt))
+    }
+
+    fn check_tool_result(&self, ctx: &TransformContext) -> bool {
+        let (Some(condition), Some(names)) = (&self.tool_result, ctx.tool_result_names) else {
+            return true;
+        };
+        condition
+            .split('|')
+            .any(|tool| names.iter().any(|name| name == tool.trim()))
+    }
+
+    fn check_time(&self, now: NaiveTime) -> bool {
+        let Some(ref windows) = self.time else {
+            return true;
+        };
+        windows
+            .split('|')
+            .any(|window| match parse_time_window(window) {
+                Some((start, end)) if start <= end => start <= now && now < end,
+                Some((start, end)) => now >= start || now < end, // Wraps midnight
+                None => true,                                    // Invalid = pass
+            })
+    }
+
+    /// TOML key/value pairs for the set fields (values already quoted)
+    fn toml_fields(&self) -> Vec<(&'static str, String)> {
+        let mut fields: Vec<(&'static str, String)> = [
+            ("turn_number", &self.turn_number),
+            ("has_tool_results", &self.has_tool_results),
+            ("client_id", &self.client_id),
+            ("model", &self.model),
+            ("context_usage", &self.context_usage),
+            ("prompt", &self.prompt),
+            ("tool_result", &self.tool_result),
+            ("time", &self.time),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| {
+            value
+                .as_ref()
+                .map(|v| (key, toml::Value::String(v.clone()).to_string()))
+        })
+        .collect();
+
+        let inline_list = |conditions: &[WhenCondition]| {
+            let tables: Vec<String> = conditions.iter().map(|c| c.inline_toml()).collect();
+            format!("[{}]", tables.join(", "))
+        };
+        if !self.any.is_empty() {
+            fields.push(("any", inline_list(&self.any)));
+        }
+        if !self.all.is_empty() {
+            fields.push(("all", inline_list(&self.all)));
+        }
+        if let Some(ref not) = self.not {
+            fields.push(("not", not.inline_toml()));
+        }
+        fields
+    }
+
+    /// This condition as a TOML inline table
+    fn inline_toml(&self) -> String {
+        let entries: Vec<String> = self
+            .toml_fields()
+            .into_iter()
+            .map(|(key, value)| format!("{} = {}", key, value))
+            .collect();
+        format!("{{ {} }}", entries.join(", "))
+    }
+
+    /// Write this condition as TOML dotted keys to the output string.
+    ///
+    /// Used by config serialization to generate valid TOML for array elements.
+    /// Each field becomes a dotted key (e.g., `when.turn_number = "..."`);
+    /// nested conditions are written as inline tables.
+    pub fn write_toml(&self, output: &mut String) {
+        for (key, value) in self.toml_fields() {
+            output.push_str(&format!("when.{} = {}\n", key, value));
+        }
+    }
+}
+
+/// Model patterns match case-insensitively ("opus" matches "claude-opus-4")
+fn model_regex(pattern: &str) -> Result<Regex, regex::Error> {
+    RegexBuilder::new(pattern).case_insensitive(true).build()
+}
+
+/// Parse a "HH:MM-HH:MM" window
+fn parse_time_window(window: &str) -> Option<(NaiveTime, NaiveTime)> {
+    let (start, end) = window.trim().split_once('-')?;
+    let parse = |t: &str| NaiveTime::parse_from_str(t.trim(), "%H:%M").ok();
+    Some((parse(start)?, parse(end)?))
+}
+
+/// Parse numeric conditions like "=1", ">5", "<10", "every:3"
+/// Supports pipe-separated OR conditions: "=1|every:3" matches 1 OR multiples of 3
+pub(super) fn parse_numeric_condition(condition: &str, value: u64) -> bool {
+    let condition = condition.trim();
+
+    // Support pipe-separated OR conditions (e.g., "=1|every:3")
+    if condition.contains('|') {
+        return condition
+            .split('|')
+            .any(|c| parse_single_condition(c.trim(), value));
+    }
+
+    parse_single_condition(condition, value)
+}
+
+/// Parse a single numeric condition (no pipe)
+fn parse_single_condition(condition: &str, value: u64) -> bool {
+    if let Some(n) = condition.strip_prefix("every:") {
+        if let Ok(interval) = n.parse::<u64>() {
+            return interval > 0 && value.is_multiple_of(interval);
+        }
+        return true; // Invalid = pass
+    }
+
+    if let Some(n) = condition.strip_prefix(">=") {
+        return n.parse::<u64>().map(|n| value >= n).unwrap_or(true);
+    }
+    if let Some(n) = condition.strip_prefix("<=") {
+        return n.parse::<u64>().map(|n| value <= n).unwrap_or(true);
+    }
+    if let Some(n) = condition.strip_prefix('>') {
+        return n.parse::<u64>().map(|n| value > n).unwrap_or(true);
+    }
+    if let Some(n) = condition.strip_prefix('<') {
+        return n.parse::<u64>().map(|n| value < n).unwrap_or(true);
+    }
+    if let Some(n) = condition.strip_prefix('=') {
+        return n.parse::<u64>().map(|n| value == n).unwrap_or(true);
+    }
+
+    // Plain number = equals
+    condition.parse::<u64>().map(|n| value == n).unwrap_or(true)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx() -> TransformContext<'static> {
+        TransformContext::new(Some("dev-1"), "/v1/messages", Some("claude-opus-4"))
+    }
+
+    fn noon() -> NaiveTime {
+        NaiveTime::from_hms_opt(12, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_model_prompt_and_tool_result_predicates() {
+        let names = vec!["Bash".to_string()];
+        let mut ctx = ctx();
+        ctx.user_prompt = Some("please deploy to staging");
+        ctx.tool_result_names = Some(&names);
+
+        let cond = WhenCondition {
+            model: Some("OPUS".to_string()),
+            prompt: Some(r"\bdeploy\b".to_string()),
+            tool_result: Some("Edit|Bash".to_string()),
+            ..Default::default()
+        };
+        assert!(cond.evaluate_at(&ctx, noon()));
+
+        let other_tool = WhenCondition {
+            tool_result: Some("Edit".to_string()),
+            ..Default::default()
+        };
+        assert!(!other_tool.evaluate_at(&ctx, noon()));
+
+        let other_model = WhenCondition {
+            model: Some("haiku".to_string()),
+            ..Default::default()
+        };
+        assert!(!other_model.evaluate_at(&ctx, noon()));
+    }
+
+    #[test]
+    fn test_prompt_never_matches_tool_continuation() {
+        let body = serde_json::json!({
+            "messages": [
+                {"role": "user", "content": "deploy the release"},
+                {"role": "assistant", "content": [
+                    {"type": "tool_use", "id": "toolu_1", "name": "Bash", "input": {}}
+                ]},
+                {"role": "user", "content": [
+                    {"type": "tool_result", "tool_use_id": "toolu_1", "content": "ok"}
+                ]}
+            ]
+        });
+        let prompt = crate::proxy::helpers::extract_user_prompt(&body);
+        let mut ctx = ctx();
+        ctx.user_prompt = prompt.as_deref();
+
+        let cond = WhenCondition {
+            prompt: Some("deploy".to_string()),
+            ..Default::default()
+        };
+        cond.validate().unwrap();
+        assert!(!cond.evaluate_at(&ctx, noon()));
+        let negated = WhenCondition {
+            not: Some(Box::new(cond.clone())),
+            ..Default::default()
+        };
+        assert!(negated.evaluate_at(&ctx, noon()));
+
+        ctx.user_prompt = Some("now deploy it");
+        assert!(cond.evaluate_at(&ctx, noon()));
+        assert!(cond.patterns.get().is_some_and(|p| p.prompt.is_some()));
+    }
+
+    #[test]
+    fn test_context_usage_percent() {
+        let cond = WhenCondition {
+            context_usage: Some(">80".to_string()),
+            ..Default::default()
+        };
+        // No context info = pass
+        assert!(cond.evaluate_at(&ctx(), noon()));
+
+        let high = ctx().with_context_state(170_000, 200_000);
+        assert!(cond.evaluate_at(&high, noon()));
+        let low = ctx().with_context_state(50_000, 200_000);
+        assert!(!cond.evaluate_at(&low, noon()));
+    }
+
+    #[test]
+    fn test_time_windows_wrap_midnight() {
+        let cond = WhenCondition {
+            time: Some("22:00-06:00|12:00-13:00".to_string()),
+            ..Default::default()
+        };
+        let at = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+        assert!(cond.evaluate_at(&ctx(), at(23, 30)));
+        assert!(cond.evaluate_at(&ctx(), at(5, 59)));
+        assert!(cond.evaluate_at(&ctx(), at(12, 30)));
+        assert!(!cond.evaluate_at(&ctx(), at(6, 0)));
+        assert!(!cond.evaluate_at(&ctx(), at(17, 0)));
+    }
+
+    #[test]
+    fn test_any_all_not_composition() {
+        let cond: WhenCondition = toml::from_str(
+            r#"
+            any = [{ client_id = "foundry" }, { model = "opus" }]
+            all = [{ turn_number = ">1" }]
+            not = { has_tool_results = ">0" }
+            "#,
+        )
+        .unwrap();
+
+        let mut ctx = ctx();
+        ctx.turn_number = Some(3);
+        ctx.tool_result_count = Some(0);
+        assert!(cond.evaluate_at(&ctx, noon()));
+
+        ctx.tool_result_count = Some(2);
+        assert!(!cond.evaluate_at(&ctx, noon()), "not should reject");
+
+        ctx.tool_result_count = Some(0);
+        ctx.model = Some("claude-haiku");
+        assert!(!cond.evaluate_at(&ctx, noon()), "no any branch matches");
+    }
+
+    #[test]
+    fn test_validate_and_toml_roundtrip() {
+        let bad = WhenCondition {
+            not: Some(Box::new(WhenCondition {
+                time: Some("9am-5pm".to_string()),
+                ..Default::default()
+            })),
+            ..Default::default()
+        };
+        assert!(bad.validate().is_err());
+
+        let cond = WhenCondition {
+            prompt: Some(r#"(?i)"deploy"\s"#.to_string()),
+            any: vec![
+                WhenCondition {
+                    time: Some("09:00-17:00".to_string()),
+                    ..Default::default()
+                },
+                WhenCondition {
+                    not: Some(Box::new(WhenCondition {
+                        client_id: Some("ci".to_string()),
+                        ..Default::default()
+                    })),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        assert!(cond.validate().is_ok());
+
+        let mut output = String::new();
+        cond.write_toml(&mut output);
+        #[derive(Deserialize)]
+        struct Rule {
+            when: WhenCondition,
+        }
+        let parsed: Rule = toml::from_str(&output).expect("when should round-trip");
+        assert_eq!(parsed.when.prompt, cond.prompt);
+        assert_eq!(parsed.when.any.len(), 2);
+        assert_eq!(
+            parsed.when.any[1]
+                .not
+                .as_ref()
+                .unwrap()
+                .client_id
+                .as_deref(),
+            Some("ci")
+        );
+    }
+
+    #[test]
+    fn test_toml_roundtrip_non_ascii() {
+        // Debug escaping would write `\u{200b}`, which TOML rejects
+        let cond = WhenCondition {
+            prompt: Some("café\u{200b}|naïve\t\"quoted\"".to_string()),
+            ..Default::default()
+        };
+
+        let mut output = String::new();
+        cond.write_toml(&mut output);
+        #[derive(Deserialize)]
+        struct Rule {
+            when: WhenCondition,
+        }
+        let parsed: Rule = toml::from_str(&output).expect("when should round-trip");
+        assert_eq!(parsed.when.prompt, cond.prompt);
+    }
+}
@@ -0,0 +1,799 @@
+//! ContextEnricher - Inject relevant history from cortex into the prompt
+//!
+//! Cortex remembers every prompt, reply and thinking block across sessions,
+//! but the model only sees what is in the current context window. This
+//! transformer searches the user's cortex history for the latest prompt and
+//! prepends the best matches to it:
+//!
+//! ```text
+//! <aspy-context>
+//! Possibly relevant notes from earlier sessions (retrieved by aspy, may be outdated):
+//!
+//! [2025-11-02 · user prompt] the solarized theme breaks in tmux ...
+//! [2025-11-02 · reasoning] tmux needs terminal-overrides for truecolor ...
+//! </aspy-context>
+//! ```
+//!
+//! # Retrieval
+//!
+//! Searching needs the database and possibly an embedding provider, so it
+//! runs as an async prep stage in the proxy handler (`prepare`) and the
+//! result reaches the sync pipeline through `TransformContext::semantic_context`.
+//! With embeddings configured the search is hybrid (FTS + vectors, fused with
+//! RRF); otherwise, or when embedding the prompt fails, it is FTS only.
+//!
+//! Retrieval runs once per turn: tool continuations reuse the matches found
+//! for the prompt that started the turn, so the injected block is identical
+//! on every request of the turn and the prompt cache keeps working. Matches
+//! whose text is already in the request are skipped.
+//!
+//! # Example Config
+//!
+//! ```toml
+//! [transformers.context-enricher]
+//! enabled = true
+//! max_tokens = 1000       # budget for the injected block
+//! max_results = 5
+//! min_relevance = 0.3     # 0.0 - 1.0
+//! when.turn_number = ">1"
+//! ```
+
+use super::{RequestTransformer, TransformContext, TransformResult, WhenCondition};
+use crate::pipeline::cortex_query::{ContextMatch, CortexQuery, MatchType, SearchMode};
+use crate::pipeline::embeddings::{create_provider, EmbeddingConfig, EmbeddingProvider};
+use crate::tokens::{estimate_json_tokens, estimate_tokens};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// Keywords taken from a prompt for the FTS query
+const MAX_KEYWORDS: usize = 12;
+
+/// Prompt characters sent to the embedding provider
+const MAX_EMBED_CHARS: usize = 2000;
+
+/// Remaining budget below which no further match is added
+const MIN_MATCH_TOKENS: u32 = 40;
+
+/// Leading characters of a match used to detect it in the request
+const DUPLICATE_PROBE_CHARS: usize = 200;
+
+/// RRF score of a document ranked first by both FTS and vectors (k = 60)
+const MAX_RRF_SCORE: f64 = 2.0 / 61.0;
+
+/// Words too common to be useful search terms
+const STOPWORDS: &[&str] = &[
+    "about", "after", "also", "and", "any", "are", "because", "been", "but", "can", "could", "did",
+    "does", "for", "from", "get", "had", "has", "have", "how", "into", "its", "just", "let",
+    "like", "make", "more", "need", "not", "now", "our", "out", "please", "should", "some", "than",
+    "that", "the", "their", "them", "then", "there", "these", "they", "this", "use", "want", "was",
+    "what", "when", "where", "which", "while", "who", "why", "will", "with", "would", "you",
+    "your",
+];
+
+// ============================================================================
+// Configuration
+// ============================================================================
+
+/// Configuration for the ContextEnricher transformer
+#[derive(Debug, Clone, Deserialize)]
+pub struct ContextEnricherConfig {
+    /// Whether the transformer is enabled
+    #[serde(default)]
+    pub enabled: bool,
+    /// Token budget for the injected `<aspy-context>` block
+    #[serde(default = "default_max_tokens")]
+    pub max_tokens: u32,
+    /// Maximum number of matches injected
+    #[serde(default = "default_max_results")]
+    pub max_results: usize,
+    /// Matches below this relevance (0.0 - 1.0) are dropped
+    #[serde(default = "default_min_relevance")]
+    pub min_relevance: f64,
+    /// Prompts shorter than this (e.g. "yes", "go on") are not searched
+    #[serde(default = "default_min_prompt_chars")]
+    pub min_prompt_chars: usize,
+    /// Only enrich requests matching this condition
+    #[serde(default)]
+    pub when: Option<WhenCondition>,
+}
+
+fn default_max_tokens() -> u32 {
+    1000
+}
+
+fn default_max_results() -> usize {
+    5
+}
+
+fn default_min_relevance() -> f64 {
+    0.3
+}
+
+fn default_min_prompt_chars() -> usize {
+    12
+}
+
+impl Default for ContextEnricherConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_tokens: default_max_tokens(),
+            max_results: default_max_results(),
+            min_relevance: default_min_relevance(),
+            min_prompt_chars: default_min_prompt_chars(),
+            when: None,
+        }
+    }
+}
+
+// ============================================================================
+// Semantic Context
+// ============================================================================
+
+/// How the matches were found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchKind {
+    /// FTS and vector search fused with RRF
+    Hybrid,
+    /// Full-text search only (no embeddings available)
+    Fts,
+}
+
+impl SearchKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SearchKind::Hybrid => "hybrid",
+            SearchKind::Fts => "fts",
+        }
+    }
+}
+
+/// A cortex match with a relevance score from 0.0 to 1.0
+#[derive(Debug, Clone)]
+pub struct ScoredMatch {
+    pub match_type: MatchType,
+    pub timestamp: String,
+    pub content: String,
+    pub relevance: f64,
+}
+
+impl ScoredMatch {
+    fn from_match(m: ContextMatch, search: SearchKind, keywords: &[String]) -> Self {
+        Self {
+            relevance: relevance(&m, search, keywords),
+            match_type: m.match_type,
+            timestamp: m.timestamp,
+            content: m.content,
+        }
+    }
+}
+
+/// Matches retrieved for the current turn, best first
+///
+/// Built by `ContextEnricher::prepare` and passed to the pipeline through
+/// `TransformContext::semantic_context`.
+#[derive(Debug, Clone)]
+pub struct SemanticContext {
+    /// FTS query derived from the prompt
+    pub query: String,
+    pub search: SearchKind,
+    pub matches: Vec<ScoredMatch>,
+}
+
+/// Score a match from 0.0 to 1.0 (higher = more relevant)
+///
+/// Hybrid ranks are negated RRF scores, scaled by the best possible score:
+/// 1.0 when both searches rank the match first, about 0.5 when one does.
+/// BM25 scores depend on the size of the corpus, so FTS matches are scored
+/// by the share of prompt keywords they contain instead.
+fn relevance(m: &ContextMatch, search: SearchKind, keywords: &[String]) -> f64 {
+    match search {
+        SearchKind::Hybrid => ((-m.rank).max(0.0) / MAX_RRF_SCORE).min(1.0),
+        SearchKind::Fts => {
+            if keywords.is_empty() {
+                return 0.0;
+            }
+            let words: Vec<String> = m
+                .content
+                .split(|c: char| !c.is_alphanumeric() && c != '_')
+                .map(str::to_lowercase)
+                .collect();
+            let found = keywords.iter().filter(|k| words.contains(k)).count();
+            found as f64 / keywords.len() as f64
+        }
+    }
+}
+
+// ============================================================================
+// Prompt Extraction
+// ============================================================================
+
+/// Remove every `<tag>...</tag>` section from text
+fn strip_tag(text: &str, tag: &str) -> String {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(&open) {
+        out.push_str(&rest[..start]);
+        match rest[start..].find(&close) {
+            Some(end) => rest = &rest[start + end + close.len()..],
+            None => {
+                rest = "";
+                break;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Index of the user message that started the current turn
+///
+/// Tool continuations end with a user message of tool results; the turn
+/// started at the last user message without any.
+fn turn_start(messages: &[Value]) -> Option<usize> {
+    messages.iter().rposition(|msg| {
+        msg["role"] == "user"
+            && !msg["content"]
+                .as_array()
+                .is_some_and(|blocks| blocks.iter().any(|b| b["type"] == "tool_result"))
+    })
+}
+
+/// The user's own words in a message, without injected reminders
+fn prompt_text(message: &Value) -> String {
+    let raw = match &message["content"] {
+        Value::String(s) => s.clone(),
+        Value::Array(blocks) => blocks
+            .iter()
+            .filter(|b| b["type"] == "text")
+            .filter_map(|b| b["text"].as_str())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    };
+    let text = strip_tag(&raw, "system-reminder");
+    strip_tag(&text, "aspy-context").trim().to_string()
+}
+
+/// Significant words of a prompt, lowercased and deduplicated
+fn keywords(prompt: &str) -> Vec<String> {
+    let mut keywords: Vec<String> = Vec::new();
+    for word in prompt.split(|c: char| !c.is_alphanumeric() && c != '_') {
+        let word = word.to_lowercase();
+        if word.chars().count() < 3
+            || word.chars().all(|c| c.is_ascii_digit())
+            || STOPWORDS.contains(&word.as_str())
+            || keywords.contains(&word)
+        {
+            continue;
+        }
+        keywords.push(word);
+        if keywords.len() == MAX_KEYWORDS {
+            break;
+        }
+    }
+
+    keywords
+}
+
+/// Build an FTS5 query matching any of the keywords
+///
+/// Phrase search would almost never match a whole prompt, so keywords are
+/// OR-ed together and BM25 ranks documents matching more of them higher.
+/// Keywords are alphanumeric and quoted, so the raw query is always valid
+/// FTS5 syntax.
+fn fts_query(keywords: &[String]) -> String {
+    keywords
+        .iter()
+        .map(|k| format!("\"{}\"", k))
+        .collect::<Vec<_>>()
+        .join(" OR ")
+}
+
+/// All text sent in the request, for skipping matches already present
+fn request_text(messages: &[Value]) -> String {
+    fn collect(content: &Value, text: &mut String) {
+        match content {
+            Value::String(s) => text.push_str(s),
+            Value::Array(blocks) => {
+                for block in blocks {
+                    if let Some(t) = block["text"].as_str().or(block["thinking"].as_str()) {
+                        text.push_str(t);
+                    } else if block["type"] == "tool_result" {
+                        collect(&block["content"], text);
+                    }
+                    text.push('\n');
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut text = String::new();
+    for msg in messages {
+        collect(&msg["content"], &mut text);
+        text.push('\n');
+    }
+    text
+}
+
+/// Cut text to roughly `max_tokens`, on a char boundary
+fn clip(text: &str, max_tokens: u32) -> String {
+    if estimate_tokens(text) <= max_tokens {
+        return text.to_string();
+    }
+    let keep = max_tokens as usize * 3;
+    let cut: String = text.chars().take(keep).collect();
+    format!("{} …", cut.trim_end())
+}
+
+fn prompt_hash(prompt: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    prompt.hash(&mut hasher);
+    hasher.finish()
+}
+
+// ============================================================================
+// Transformer
+// ============================================================================
+
+/// Retrieval cached for a user's current turn
+struct CachedRetrieval {
+    prompt_hash: u64,
+    context: Option<Arc<SemanticContext>>,
+}
+
+/// Injects relevant cortex history as an `<aspy-context>` block
+pub struct ContextEnricher {
+    max_tokens: u32,
+    max_results: usize,
+    min_relevance: f64,
+    min_prompt_chars: usize,
+    when: Option<WhenCondition>,
+    /// Embedding settings, set at startup when embeddings are configured
+    embedding_config: OnceLock<EmbeddingConfig>,
+    /// Provider created on first use (None if it never became ready)
+    embedder: OnceLock<Option<Box<dyn EmbeddingProvider>>>,
+    /// Last retrieval per user, reused until the turn's prompt changes
+    recent: Mutex<HashMap<String, CachedRetrieval>>,
+}
+
+impl ContextEnricher {
+    pub fn new(config: &ContextEnricherConfig) -> Self {
+        Self {
+            max_tokens: config.max_tokens,
+            max_results: config.max_results.max(1),
+            min_relevance: config.min_relevance.clamp(0.0, 1.0),
+            min_prompt_chars: config.min_prompt_chars,
+            when: config.when.clone(),
+            embedding_config: OnceLock::new(),
+            embedder: OnceLock::new(),
+            recent: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Use an embedding provider for hybrid search
+    ///
+    /// The provider is created lazily on a blocking thread, since remote
+    /// providers use a blocking HTTP client.
+    pub fn use_embeddings(&self, config: EmbeddingConfig) {
+        let _ = self.embedding_config.set(config);
+    }
+
+    /// Whether this request is eligible for enrichment (before retrieval)
+    pub fn wants(&self, ctx: &TransformContext) -> bool {
+        ctx.path.contains("/messages") && self.when.as_ref().is_none_or(|w| w.evaluate(ctx))
+    }
+
+    /// Retrieve matches for the prompt that started the current turn
+    ///
+    /// Returns None when the prompt is too short or nothing relevant was
+    /// found. Search errors are logged, never propagated.
+    pub async fn prepare(
+        self: &Arc<Self>,
+        cortex: Arc<CortexQuery>,
+        user_id: &str,
+        body: &Value,
+    ) -> Option<Arc<SemanticContext>> {
+        let messages = body["messages"].as_array()?;
+        let prompt = prompt_text(&messages[turn_start(messages)?]);
+        if prompt.chars().count() < self.min_prompt_chars {
+            return None;
+        }
+
+        let hash = prompt_hash(&prompt);
+        if let Some(cached) = self.cached(user_id, hash) {
+            return cached;
+        }
+
+        let known = request_text(messages);
+        let enricher = Arc::clone(self);
+        let uid = user_id.to_string();
+        let context =
+            tokio::task::spawn_blocking(move || enricher.retrieve(&cortex, &uid, &prompt, &known))
+                .await
+                .unwrap_or_else(|e| {
+                    tracing::warn!("Context enricher retrieval panicked: {}", e);
+                    None
+                })
+                .map(Arc::new);
+
+        self.remember(user_id, hash, context.clone());
+        context
+    }
+
+    /// Cached retrieval for this prompt, if it is still the user's latest
+    fn cached(&self, user_id: &str, hash: u64) -> Option<Option<Arc<SemanticContext>>> {
+        let recent = self.recent.lock().ok()?;
+        recent
+            .get(user_id)
+            .filter(|c| c.prompt_hash == hash)
+            .map(|c| c.context.clone())
+    }
+
+    fn remember(&self, user_id: &str, hash: u64, context: Option<Arc<SemanticContext>>) {
+        if let Ok(mut recent) = self.recent.lock() {
+            recent.insert(
+                user_id.to_string(),
+                CachedRetrieval {
+                    prompt_hash: hash,
+                    context,
+                },
+            );
+        }
+    }
+
+    /// Embed the prompt, if a provider is configured and ready (blocking)
+    fn embed(&self, prompt: &str) -> Option<Vec<f32>> {
+        let provider = self
+            .embedder
+            .get_or_init(|| {
+                let provider = create_provider(self.embedding_config.get()?);
+                provider.is_ready().then_some(provider)
+            })
+            .as_ref()?;
+
+        let text: String = prompt.chars().take(MAX_EMBED_CHARS).collect();
+        match provider.embed(&text) {
+            Ok(result) => Some(result.embedding),
+            Err(e) => {
+                tracing::debug!("Context enricher falling back to FTS: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Search cortex and keep relevant matches not already in the request (blocking)
+    fn retrieve(
+        &self,
+        cortex: &CortexQuery,
+        user_id: &str,
+        prompt: &str,
+        known: &str,
+    ) -> Option<SemanticContext> {
+        let keywords = keywords(prompt);
+        if keywords.is_empty() {
+            return None;
+        }
+        let query = fts_query(&keywords);
+        let embedding = self.embed(prompt);
+        let search = match embedding {
+            Some(_) => SearchKind::Hybrid,
+            None => SearchKind::Fts,
+        };
+
+        // Fetch extra candidates, some are dropped as duplicates
+        let found = match cortex.recover_context_hybrid_user(
+            user_id,
+            &query,
+            embedding.as_deref(),
+            self.max_results * 2,
+            SearchMode::Raw,
+        ) {
+            Ok(found) => found,
+            Err(e) => {
+                tracing::warn!("Context enricher search failed: {}", e);
+                return None;
+            }
+        };
+
+        let matches = self.select(found, search, &keywords, known);
+        tracing::debug!(
+            search = search.as_str(),
+            matches = matches.len(),
+            "Context enricher retrieved {} matches",
+            matches.len()
+        );
+
+        (!matches.is_empty()).then_some(SemanticContext {
+            query,
+            search,
+            matches,
+        })
+    }
+
+    /// Apply the relevance threshold, skip known text, keep the best few
+    fn select(
+        &self,
+        found: Vec<ContextMatch>,
+        search: SearchKind,
+        keywords: &[String],
+        known: &str,
+    ) -> Vec<ScoredMatch> {
+        let mut seen = HashSet::new();
+        let mut matches: Vec<ScoredMatch> = found
+            .into_iter()
+            .map(|m| ScoredMatch::from_match(m, search, keywords))
+            .filter(|m| m.relevance >= self.min_relevance)
+            .filter(|m| {
+                let probe: String = m
+                    .content
+                    .trim()
+                    .chars()
+                    .take(DUPLICATE_PROBE_CHARS)
+                    .collect();
+                !probe.is_empty() && !known.contains(&probe) && seen.insert(probe)
+            })
+            .collect();
+        matches.sort_by(|a, b| b.relevance.total_cmp(&a.relevance));
+        matches.truncate(self.max_results);
+        matches
+    }
+
+    /// Render matches into an `<aspy-context>` block within the token budget
+    fn render(&self, context: &SemanticContext) -> Option<(String, usize)> {
+        let header = "Possibly relevant notes from earlier sessions \
+                      (retrieved by aspy, may be outdated):";
+        let mut remaining = self.max_tokens.saturating_sub(estimate_tokens(header) + 10);
+        let mut lines = Vec::new();
+
+        for m in &context.matches {
+            let label = match m.match_type {
+                MatchType::UserPrompt => "user prompt",
+                MatchType::AssistantResponse => "assistant reply",
+                MatchType::Thinking => "reasoning",
+            };
+            let date = m.timestamp.get(..10).unwrap_or(&m.timestamp);
+            let prefix = format!("[{} · {}] ", date, label);
+            let available = remaining.saturating_sub(estimate_tokens(&prefix));
+            if available < MIN_MATCH_TOKENS {
+                break;
+            }
+            let content = m.content.split_whitespace().collect::<Vec<_>>().join(" ");
+            let line = format!("{}{}", prefix, clip(&content, available));
+            remaining = remaining.saturating_sub(estimate_tokens(&line));
+            lines.push(line);
+        }
+
+        if lines.is_empty() {
+            return None;
+        }
+        let count = lines.len();
+        Some((
+            format!(
+                "<aspy-context>\n{}\n\n{}\n</aspy-context>",
+                header,
+                lines.join("\n")
+            ),
+            count,
+        ))
+    }
+}
+
+impl RequestTransformer for ContextEnricher {
+    fn name(&self) -> &'static str {
+        "context-enricher"
+    }
+
+    fn should_apply(&self, ctx: &TransformContext) -> bool {
+        ctx.semantic_context.is_some() && self.wants(ctx)
+    }
+
+    fn transform(&self, body: &Value, ctx: &TransformContext) -> TransformResult {
+        let Some(context) = ctx.semantic_context else {
+            return TransformResult::Unchanged;
+        };
+        let Some((block, count)) = self.render(context) else {
+            return TransformResult::Unchanged;
+        };
+        let Some(index) = body["messages"].as_array().and_then(|m| turn_start(m)) else {
+            return TransformResult::Unchanged;
+        };
+
+        let mut modified = body.clone();
+        let message = &mut modified["messages"][index];
+        let injected = json!({"type": "text", "text": block});
+        match &mut message["content"] {
+            Value::Array(blocks) => blocks.insert(0, injected),
+            content => {
+                let original = content.take();
+                *content = json!([injected, {"type": "text", "text": original}]);
+            }
+        }
+
+        let before = estimate_json_tokens(body);
+        let after = estimate_json_tokens(&modified);
+        tracing::debug!(query = %context.query, count, "Context enricher injected matches");
+        let modification = format!(
+            "Injected {} cortex match{} ({}, ~{} tokens)",
+            count,
+            if count == 1 { "" } else { "es" },
+            context.search.as_str(),
+            after.saturating_sub(before)
+        );
+        TransformResult::modified_with_info(modified, before, after, vec![modification])
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scored(content: &str, relevance: f64) -> ScoredMatch {
+        ScoredMatch {
+            match_type: MatchType::UserPrompt,
+            timestamp: "2025-11-02T10:00:00Z".to_string(),
+            content: content.to_string(),
+            relevance,
+        }
+    }
+
+    fn semantic(matches: Vec<ScoredMatch>) -> SemanticContext {
+        SemanticContext {
+            query: "\"tmux\"".to_string(),
+            search: SearchKind::Fts,
+            matches,
+        }
+    }
+
+    fn request() -> Value {
+        json!({
+            "messages": [
+                {"role": "user", "content": "Why does the solarized theme break in tmux?"},
+                {"role": "assistant", "content": [{"type": "tool_use", "id": "t1", "name": "Read", "input": {}}]},
+                {"role": "user", "content": [{"type": "tool_result", "tool_use_id": "t1", "content": "set -g default-terminal tmux-256color truecolor"}]}
+            ]
+        })
+    }
+
+    #[test]
+    fn test_keyword_query_from_prompt() {
+        let message = json!({"role": "user", "content": [
+            {"type": "text", "text": "<system-reminder>Todo list is empty</system-reminder>"},
+            {"type": "text", "text": "Why does the solarized theme break in tmux? The theme is fine in 2024."}
+        ]});
+        let prompt = prompt_text(&message);
+        assert!(!prompt.contains("Todo"));
+        assert_eq!(
+            fts_query(&keywords(&prompt)),
+            "\"solarized\" OR \"theme\" OR \"break\" OR \"tmux\" OR \"fine\""
+        );
+        assert!(keywords("is it ok?").is_empty());
+    }
+
+    fn found(content: &str, rank: f64) -> ContextMatch {
+        ContextMatch {
+            match_type: MatchType::Thinking,
+            session_id: None,
+            timestamp: String::new(),
+            content: content.to_string(),
+            rank,
+        }
+    }
+
+    #[test]
+    fn test_relevance_scores() {
+        let keywords = keywords("tmux truecolor terminal");
+        let score = |content: &str, rank: f64, search: SearchKind| {
+            relevance(&found(content, rank), search, &keywords)
+        };
+        // Hybrid: rank-based, independent of the text
+        assert!((score("", -MAX_RRF_SCORE, SearchKind::Hybrid) - 1.0).abs() < 1e-9);
+        assert!((score("", -1.0 / 61.0, SearchKind::Hybrid) - 0.5).abs() < 1e-9);
+        // FTS: share of keywords present, whatever BM25 says
+        assert!((score("TMUX needs truecolor", -1e-6, SearchKind::Fts) - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(score("nothing related", -9.0, SearchKind::Fts), 0.0);
+    }
+
+    #[test]
+    fn test_select_applies_threshold_and_skips_known_text() {
+        let enricher = ContextEnricher::new(&ContextEnricherConfig {
+            max_results: 2,
+            ..Default::default()
+        });
+        let known = request_text(request()["messages"].as_array().unwrap());
+        let matches = enricher.select(
+            vec![
+                found("weak match about tmux", -9.0),
+                found("set -g default-terminal tmux-256color truecolor", -8.0),
+                found("truecolor in tmux", -7.0),
+                found("tmux needs terminal overrides", -6.0),
+                found("tmux needs terminal overrides", -5.0),
+                found("tmux truecolor terminal", -4.0),
+            ],
+            SearchKind::Fts,
+            &keywords("tmux truecolor terminal overrides"),
+            &known,
+        );
+        let contents: Vec<_> = matches.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(
+            contents,
+            vec!["tmux needs terminal overrides", "tmux truecolor terminal"]
+        );
+    }
+
+    #[test]
+    fn test_injects_into_turn_start_within_budget() {
+        let enricher = ContextEnricher::new(&ContextEnricherConfig {
+            max_tokens: 120,
+            ..Default::default()
+        });
+        let context = semantic(vec![
+            scored("tmux needs terminal-overrides for truecolor", 0.9),
+            scored(&"very long earlier answer ".repeat(200), 0.8),
+            scored("never reached", 0.7),
+        ]);
+        let body = request();
+        let mut ctx = TransformContext::new(None, "/v1/messages", None);
+        ctx.semantic_context = Some(&context);
+
+        let TransformResult::Modified {
+            body: out,
+            modifications,
+            ..
+        } = enricher.transform(&body, &ctx)
+        else {
+            panic!("expected modification");
+        };
+        let blocks = out["messages"][0]["content"].as_array().unwrap();
+        let injected = blocks[0]["text"].as_str().unwrap();
+        assert!(injected.starts_with("<aspy-context>"));
+        assert!(injected.contains("[2025-11-02 · user prompt] tmux needs"));
+        assert!(!injected.contains("never reached"));
+        assert!(estimate_tokens(injected) <= 130);
+        assert_eq!(
+            blocks[1]["text"],
+            "Why does the solarized theme break in tmux?"
+        );
+        assert!(modifications[0].starts_with("Injected 2 cortex matches (fts"));
+        // Tool results untouched
+        assert_eq!(out["messages"][2], body["messages"][2]);
+    }
+
+    #[test]
+    fn test_when_condition_and_turn_cache() {
+        let enricher = ContextEnricher::new(&ContextEnricherConfig {
+            when: Some(WhenCondition {
+                turn_number: Some(">1".to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        let context = semantic(vec![scored("tmux needs terminal-overrides", 0.9)]);
+        let mut ctx = TransformContext::new(None, "/v1/messages", None);
+        ctx.semantic_context = Some(&context);
+        ctx.turn_number = Some(1);
+        assert!(!enricher.should_apply(&ctx));
+        ctx.turn_number = Some(2);
+        assert!(enricher.should_apply(&ctx));
+
+        let hash = prompt_hash("Why does the theme break?");
+        assert!(enricher.cached("alice", hash).is_none());
+        enricher.remember("alice", hash, Some(Arc::new(context.clone())));
+        assert!(enricher.cached("alice", hash).unwrap().is_some());
+        assert!(enricher
+            .cached("alice", prompt_hash("next prompt"))
+            .is_none());
+        assert!(enricher.cached("bob", hash).is_none());
+    }
+}
@@ -0,0 +1,419 @@
+//! Dry run - run the transformation pipeline offline
+//!
+//! Backs `aspy transform --dry-run`: a request body (or `Request` events from
+//! a JSONL session log) goes through the configured pipeline without any
+//! traffic, and the report shows what each transformer did and a unified
+//! diff of the body.
+//!
+//! The context is built like the proxy builds it, with two gaps: there is no
+//! session (turn number comes from `--turn` or the count of user messages,
+//! context usage is unknown) and no cortex, so the context enricher has no
+//! matches to inject. External transformers are called for real.
+
+use super::{
+    Redactions, StepOutcome, ToolRenames, TransformContext, TransformResult, TransformStep,
+    TransformationPipeline, Truncations,
+};
+use crate::proxy::helpers::{extract_user_prompt, last_tool_result_names};
+use anyhow::Context;
+use serde_json::Value;
+use similar::TextDiff;
+
+/// Default API path when the input doesn't carry one
+const DEFAULT_PATH: &str = "/v1/messages";
+
+/// A request to run through the pipeline
+#[derive(Debug, Clone)]
+pub struct DryRunInput {
+    /// Where the request came from ("request.json", "request req_123 at ...")
+    pub label: String,
+    pub body: Value,
+    /// Identity recorded with a logged request
+    pub client: Option<String>,
+    /// API path recorded with a logged request
+    pub path: Option<String>,
+    /// Request ID recorded with a logged request
+    pub request_id: Option<String>,
+}
+
+/// Overrides from the command line
+#[derive(Debug, Clone, Default)]
+pub struct DryRunOptions {
+    /// Client ID for `client_id` conditions (overrides the logged identity)
+    pub client: Option<String>,
+    /// Turn number for `turn_number` conditions
+    pub turn: Option<u64>,
+}
+
+/// What the pipeline did to one request
+#[derive(Debug)]
+pub struct DryRunReport {
+    pub label: String,
+    pub steps: Vec<TransformStep>,
+    /// Block reason and status, if a transformer blocked the request
+    pub blocked: Option<(String, u16)>,
+    pub tokens_before: u32,
+    pub tokens_after: u32,
+    /// Unified diff of the pretty-printed body (empty if unchanged)
+    pub diff: String,
+}
+
+/// Parse dry-run input: a request body, a single `Request` event, or a
+/// JSONL session log (every `Request` event with a body)
+pub fn parse_input(text: &str, name: &str) -> anyhow::Result<Vec<DryRunInput>> {
+    if let Ok(value) = serde_json::from_str::<Value>(text) {
+        if let Some(input) = from_event(&value) {
+            return Ok(vec![input]);
+        }
+        anyhow::ensure!(
+            value.get("messages").is_some_and(Value::is_array),
+            "{} is not a messages request (no `messages` array)",
+            name
+        );
+        return Ok(vec![DryRunInput {
+            label: name.to_string(),
+            body: value,
+            client: None,
+            path: None,
+            request_id: None,
+        }]);
+    }
+
+    let mut inputs = Vec::new();
+    for (idx, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let value: Value = serde_json::from_str(line)
+            .with_context(|| format!("{} line {} is not valid JSON", name, idx + 1))?;
+        inputs.extend(from_event(&value));
+    }
+    anyhow::ensure!(
+        !inputs.is_empty(),
+        "{} has no Request events with a body",
+        name
+    );
+    Ok(inputs)
+}
+
+/// A logged `Request` event (as written to the JSONL session log)
+fn from_event(value: &Value) -> Option<DryRunInput> {
+    if value.get("type").and_then(Value::as_str) != Some("Request") {
+        return None;
+    }
+    let body = value.get("body").filter(|b| b.is_object())?.clone();
+    let str_field = |key: &str| value.get(key).and_then(Value::as_str).map(str::to_string);
+    let request_id = str_field("id");
+    let when = str_field("tracked_at")
+        .or_else(|| str_field("timestamp"))
+        .unwrap_or_default();
+
+    Some(DryRunInput {
+        label: format!(
+            "request {} at {}",
+            request_id.as_deref().unwrap_or("?"),
+            when
+        ),
+        body,
+        client: str_field("user_id"),
+        path: str_field("path"),
+        request_id,
+    })
+}
+
+/// Run one request through the pipeline
+pub fn run(
+    pipeline: &TransformationPipeline,
+    input: &DryRunInput,
+    options: &DryRunOptions,
+) -> DryRunReport {
+    let body = &input.body;
+    let messages = body
+        .get("messages")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let is_user = |msg: &&Value| msg.get("role").and_then(Value::as_str) == Some("user");
+
+    let client = options.client.as_deref().or(input.client.as_deref());
+    let path = input.path.as_deref().unwrap_or(DEFAULT_PATH);
+    let model = body.get("model").and_then(Value::as_str);
+    let user_prompt = extract_user_prompt(body);
+    let tool_result_names = last_tool_result_names(body);
+    let redactions = Redactions::default();
+    let tool_renames = ToolRenames::default();
+    let truncations = Truncations::default();
+
+    let mut ctx = TransformContext::new(client, path, model);
+    ctx.user_prompt = user_prompt.as_deref();
+    ctx.tool_result_names = Some(&tool_result_names);
+    ctx.redactions = Some(&redactions);
+    ctx.tool_renames = Some(&tool_renames);
+    ctx.truncations = Some(&truncations);
+    ctx.tool_result_count = Some(
+        messages
+            .iter()
+            .rfind(is_user)
+            .and_then(|msg| msg.get("content").and_then(Value::as_array))
+            .map(|blocks| {
+                blocks
+                    .iter()
+                    .filter(|b| b.get("type").and_then(Value::as_str) == Some("tool_result"))
+                    .count()
+            })
+            .unwrap_or(0),
+    );
+    ctx.turn_number = Some(
+        options
+            .turn
+            .unwrap_or_else(|| messages.iter().filter(is_user).count() as u64),
+    );
+
+    let (result, steps) = pipeline.transform_traced(body, &ctx);
+    let tokens_before = crate::tokens::estimate_json_tokens(body);
+    let (blocked, transformed) = match result {
+        TransformResult::Modified { body, .. } => (None, Some(body)),
+        TransformResult::Block { reason, status } => (Some((reason, status.as_u16())), None),
+        TransformResult::Unchanged | TransformResult::Error(_) => (None, None),
+    };
+    let tokens_after = transformed
+        .as_ref()
+        .map_or(tokens_before, crate::tokens::estimate_json_tokens);
+    let diff = transformed
+        .map(|after| unified_diff(body, &after))
+        .unwrap_or_default();
+
+    DryRunReport {
+        label: input.label.clone(),
+        steps,
+        blocked,
+        tokens_before,
+        tokens_after,
+        diff,
+    }
+}
+
+/// Unified diff of two JSON bodies, pretty-printed
+fn unified_diff(before: &Value, after: &Value) -> String {
+    let pretty = |v: &Value| serde_json::to_string_pretty(v).unwrap_or_default() + "\n";
+    let (before, after) = (pretty(before), pretty(after));
+    TextDiff::from_lines(&before, &after)
+        .unified_diff()
+        .context_radius(3)
+        .header("original", "transformed")
+        .to_string()
+}
+
+impl DryRunReport {
+    /// Human-readable report (ANSI colors when `color` is set)
+    pub fn render(&self, color: bool) -> String {
+        let paint = |code: &str, text: &str| {
+            if color {
+                format!("\x1b[{}m{}\x1b[0m", code, text)
+            } else {
+                text.to_string()
+            }
+        };
+        let mut out = format!("{}\n\n", paint("1", &format!("── {} ──", self.label)));
+
+        out.push_str("Transformers:\n");
+        if self.steps.is_empty() {
+            out.push_str("  (none enabled)\n");
+        }
+        for step in &self.steps {
+            let name = step.transformer;
+            match &step.outcome {
+                StepOutcome::Skipped => {
+                    out.push_str(&format!("  {:<18} {}\n", name, paint("2", "skipped")))
+                }
+                StepOutcome::Unchanged => out.push_str(&format!("  {:<18} unchanged\n", name)),
+                StepOutcome::Modified {
+                    tokens,
+                    modifications,
+                } => {
+                    let delta = tokens
+                        .map(|t| format!(" ({:+} tokens)", t.delta()))
+                        .unwrap_or_default();
+                    out.push_str(&format!(
+                        "  {:<18} {}{}\n",
+                        name,
+                        paint("32", "modified"),
+                        delta
+                    ));
+                    for modification in modifications {
+                        out.push_str(&format!("      - {}\n", modification));
+                    }
+                }
+                StepOutcome::Blocked { reason, status } => out.push_str(&format!(
+                    "  {:<18} {} ({}): {}\n",
+                    name,
+                    paint("31", "blocked"),
+                    status.as_u16(),
+                    reason
+                )),
+                StepOutcome::Error(error) => out.push_str(&format!(
+                    "  {:<18} {}: {}\n",
+                    name,
+                    paint("33", "error"),
+                    error
+                )),
+            }
+        }
+
+        if let Some((ref reason, status)) = self.blocked {
+            out.push_str(&format!("\nBlocked with status {}: {}\n", status, reason));
+            return out;
+        }
+
+        out.push_str(&format!(
+            "\nTokens: {} → {} ({:+})\n",
+            self.tokens_before,
+            self.tokens_after,
+            self.tokens_after as i64 - self.tokens_before as i64
+        ));
+        if self.diff.is_empty() {
+            out.push_str("\nBody unchanged\n");
+            return out;
+        }
+
+        out.push('\n');
+        for line in self.diff.lines() {
+            let code = match line.chars().next() {
+                Some('+') if !line.starts_with("+++") => "32",
+                Some('-') if !line.starts_with("---") => "31",
+                Some('@') => "36",
+                _ => "",
+            };
+            if code.is_empty() {
+                out.push_str(line);
+            } else {
+                out.push_str(&paint(code, line));
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::transformation::{SystemEditor, SystemEditorConfig};
+    use serde_json::json;
+
+    fn request() -> Value {
+        json!({
+            "model": "claude-sonnet-4",
+            "system": [{"type": "text", "text": "You are Claude."}],
+            "messages": [{"role": "user", "content": "hello"}]
+        })
+    }
+
+    fn pipeline(rules: &str) -> TransformationPipeline {
+        let config: SystemEditorConfig =
+            toml::from_str(&format!("enabled = true\n{}", rules)).unwrap();
+        let mut pipeline = TransformationPipeline::new();
+        pipeline.register(SystemEditor::from_config(&config).unwrap());
+        pipeline
+    }
+
+    #[test]
+    fn test_parse_body_event_and_session_log() {
+        let body = parse_input(&request().to_string(), "req.json").unwrap();
+        assert_eq!(body.len(), 1);
+        assert_eq!(body[0].label, "req.json");
+        assert!(body[0].client.is_none());
+
+        let event = json!({
+            "user_id": "dev-1",
+            "tracked_at": "2025-11-27T10:35:00Z",
+            "type": "Request",
+            "id": "req-1",
+            "method": "POST",
+            "path": "/v1/messages",
+            "body_size": 10,
+            "body": request()
+        });
+        let other = json!({"type": "ApiUsage", "model": "claude-sonnet-4"});
+        let log = format!("{}\n{}\n\n{}\n", event, other, event);
+        let inputs = parse_input(&log, "session.jsonl").unwrap();
+        assert_eq!(inputs.len(), 2);
+        assert_eq!(inputs[0].client.as_deref(), Some("dev-1"));
+        assert_eq!(inputs[0].request_id.as_deref(), Some("req-1"));
+
+        assert!(parse_input(&other.to_string(), "x.json").is_err());
+        assert!(parse_input(&format!("{}\n", other), "x.jsonl").is_err());
+    }
+
+    #[test]
+    fn test_run_reports_steps_and_diff() {
+        let pipeline = pipeline(
+            r#"
+            [[rules]]
+            type = "append"
+            content = " Be brief."
+            [[rules]]
+            type = "prepend"
+            content = "First turns only. "
+            when.turn_number = "=1"
+            "#,
+        );
+        let input = &parse_input(&request().to_string(), "req.json").unwrap()[0];
+
+        let report = run(&pipeline, input, &DryRunOptions::default());
+        assert_eq!(report.steps.len(), 1);
+        let StepOutcome::Modified {
+            ref modifications, ..
+        } = report.steps[0].outcome
+        else {
+            panic!("expected a modification, got {:?}", report.steps[0].outcome);
+        };
+        assert_eq!(modifications.len(), 2);
+        assert!(report.diff.contains("--- original"));
+        assert!(report
+            .diff
+            .contains("+      \"text\": \"First turns only. You are Claude. Be brief.\""));
+        assert!(report.tokens_after > report.tokens_before);
+
+        // --turn overrides the counted turn, so the prepend rule no longer fires
+        let options = DryRunOptions {
+            turn: Some(4),
+            ..Default::default()
+        };
+        let report = run(&pipeline, input, &options);
+        let rendered = report.render(false);
+        assert!(rendered.contains("Appended to system prompt"));
+        assert!(!rendered.contains("Prepended"));
+    }
+
+    #[test]
+    fn test_unchanged_body_has_no_diff() {
+        let pipeline = pipeline(
+            r#"
+            [[rules]]
+            type = "append"
+            content = "only for dev-1"
+            when.client_id = "dev-1"
+            "#,
+        );
+        let input = DryRunInput {
+            client: Some("dev-1".to_string()),
+            ..parse_input(&request().to_string(), "req.json").unwrap()[0].clone()
+        };
+
+        let options = DryRunOptions {
+            client: Some("ci".to_string()),
+            ..Default::default()
+        };
+        let report = run(&pipeline, &input, &options);
+        assert!(report.diff.is_empty());
+        assert!(report.render(false).contains("Body unchanged"));
+
+        let report = run(&pipeline, &input, &DryRunOptions::default());
+        assert!(!report.diff.is_empty(), "logged client should apply");
+    }
+}
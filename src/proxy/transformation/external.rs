@@ -0,0 +1,768 @@
+//! External - Delegate request transformation to another service
+//!
+//! Sends each request to a local HTTP endpoint or a long-lived child process
+//! and applies its answer, so policy services can plug into the pipeline
+//! without forking aspy (in the spirit of Envoy's ext_proc).
+//!
+//! # Protocol (version 1)
+//!
+//! Request (HTTP POST body, or one line on the process's stdin):
+//!
+//! ```json
+//! {"version": 1, "id": 7,
+//!  "context": {"client_id": "dev-1", "path": "/v1/messages", "model": "...",
+//!              "turn_number": 3, "tool_result_count": 0,
+//!              "context_tokens": null, "context_limit": null},
+//!  "body": { ...request JSON... }}
+//! ```
+//!
+//! Response (HTTP 200 body, or one line on stdout), `id` optional:
+//!
+//! ```json
+//! {"id": 7, "action": "continue"}
+//! {"id": 7, "action": "patch", "patch": [{"op": "remove", "path": "/tools/3"}], "note": "..."}
+//! {"id": 7, "action": "replace", "body": { ... }, "note": "..."}
+//! {"id": 7, "action": "block", "reason": "...", "status": 403}
+//! ```
+//!
+//! Patches are RFC 6902 JSON Patch documents, applied atomically.
+//!
+//! # Failure Handling
+//!
+//! Errors, timeouts and invalid answers either let the request through
+//! unchanged (`failure_mode = "open"`, default) or reject it with 503
+//! (`"closed"`). Latency, timeouts and errors are reported in `/api/stats`.
+//!
+//! Every job carries the caller's deadline. Workers drop jobs that expired
+//! while queued, and a child process that overruns is killed and restarted,
+//! so a stuck service can't build a backlog that times out later requests.
+//!
+//! Transformers are synchronous, so the request waits for up to `timeout_ms`.
+//! The proxy runs the pipeline in `block_in_place`, which hands the runtime
+//! worker's other tasks to another thread while the call is in flight.
+//!
+//! # Example Config
+//!
+//! ```toml
+//! [transformers.external]
+//! enabled = true
+//! url = "http://127.0.0.1:9000/transform"   # or: command = ["python3", "policy.py"]
+//! timeout_ms = 200
+//! failure_mode = "closed"
+//! ```
+
+use super::{RequestTransformer, TransformContext, TransformResult};
+use crate::tokens::estimate_json_tokens;
+use anyhow::{anyhow, bail, Context};
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Protocol version sent with every request
+const PROTOCOL_VERSION: u32 = 1;
+
+/// Concurrent requests to an HTTP endpoint
+const HTTP_WORKERS: usize = 4;
+
+// ============================================================================
+// Configuration
+// ============================================================================
+
+/// What happens when the external service fails or times out
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FailureMode {
+    /// Forward the request unchanged
+    #[default]
+    Open,
+    /// Reject the request with 503
+    Closed,
+}
+
+impl FailureMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FailureMode::Open => "open",
+            FailureMode::Closed => "closed",
+        }
+    }
+}
+
+/// Configuration for the External transformer
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ExternalConfig {
+    /// Whether the external transformer is enabled
+    #[serde(default)]
+    pub enabled: bool,
+    /// HTTP endpoint receiving each request as a POST
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Long-lived process speaking the protocol as JSON lines (program + args)
+    #[serde(default)]
+    pub command: Vec<String>,
+    /// Time allowed per request, including queueing
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Behavior when the service fails
+    #[serde(default)]
+    pub failure_mode: FailureMode,
+}
+
+fn default_timeout_ms() -> u64 {
+    200
+}
+
+// ============================================================================
+// Metrics
+// ============================================================================
+
+/// External transformer metrics reported by `/api/stats`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExternalStats {
+    /// Requests sent to the service
+    pub calls: u64,
+    /// Calls that failed (errors, invalid answers; excludes timeouts)
+    pub errors: u64,
+    /// Calls that ran out of time
+    pub timeouts: u64,
+    /// Requests rejected because of a failure in closed mode
+    pub failed_closed: u64,
+    pub avg_latency_ms: f64,
+    pub max_latency_ms: u64,
+    pub last_latency_ms: u64,
+}
+
+impl ExternalStats {
+    fn record_latency(&mut self, latency: Duration) {
+        let ms = latency.as_millis() as u64;
+        self.avg_latency_ms += (ms as f64 - self.avg_latency_ms) / self.calls.max(1) as f64;
+        self.max_latency_ms = self.max_latency_ms.max(ms);
+        self.last_latency_ms = ms;
+    }
+}
+
+// ============================================================================
+// Backends
+// ============================================================================
+
+type Reply = Result<Value, String>;
+
+/// A request waiting for a backend worker
+struct Job {
+    request: Value,
+    /// When the caller stops waiting for the answer
+    deadline: Instant,
+    reply: mpsc::Sender<Reply>,
+}
+
+impl Job {
+    /// Time left before the caller gives up (None once it has)
+    fn remaining(&self) -> Option<Duration> {
+        Some(self.deadline.saturating_duration_since(Instant::now())).filter(|d| !d.is_zero())
+    }
+}
+
+/// Start HTTP workers sharing one job queue
+///
+/// The blocking client lives on the worker threads, never on the async
+/// runtime, where creating or dropping it would panic.
+fn spawn_http(url: String, timeout: Duration) -> mpsc::Sender<Job> {
+    let (tx, rx) = mpsc::channel::<Job>();
+    let rx = Arc::new(Mutex::new(rx));
+    for i in 0..HTTP_WORKERS {
+        let rx = Arc::clone(&rx);
+        let url = url.clone();
+        let spawned = std::thread::Builder::new()
+            .name(format!("aspy-external-http-{}", i))
+            .spawn(move || {
+                let client = match reqwest::blocking::Client::builder()
+                    .timeout(timeout)
+                    .build()
+                {
+                    Ok(client) => client,
+                    Err(e) => {
+                        tracing::error!("External transformer HTTP client failed: {}", e);
+                        return;
+                    }
+                };
+                loop {
+                    let job = match rx.lock() {
+                        Ok(rx) => rx.recv(),
+                        Err(_) => return,
+                    };
+                    let Ok(job) = job else { return };
+                    // Nobody is waiting for jobs that expired in the queue
+                    let Some(remaining) = job.remaining() else {
+                        continue;
+                    };
+                    let _ = job
+                        .reply
+                        .send(call_http(&client, &url, &job.request, remaining));
+                }
+            });
+        if let Err(e) = spawned {
+            tracing::error!("Failed to start external transformer worker: {}", e);
+        }
+    }
+    tx
+}
+
+fn call_http(
+    client: &reqwest::blocking::Client,
+    url: &str,
+    request: &Value,
+    timeout: Duration,
+) -> Reply {
+    let response = client
+        .post(url)
+        .timeout(timeout)
+        .json(request)
+        .send()
+        .map_err(|e| e.to_string())?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!("HTTP {}", status));
+    }
+    response.json::<Value>().map_err(|e| e.to_string())
+}
+
+/// A running child process and its pipes
+///
+/// stdin and stdout are serviced by their own threads so the worker never
+/// blocks on a pipe and can kill the child when an answer is late.
+struct ChildProcess {
+    child: Child,
+    stdin: mpsc::Sender<String>,
+    stdout: mpsc::Receiver<String>,
+}
+
+impl ChildProcess {
+    fn spawn(command: &[String]) -> anyhow::Result<Self> {
+        let (program, args) = command.split_first().context("empty command")?;
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("failed to start {}", program))?;
+
+        // Forward stderr to the log (the TUI owns the terminal)
+        if let Some(stderr) = child.stderr.take() {
+            std::thread::spawn(move || {
+                for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                    tracing::info!(target: "aspy::external", "{}", line);
+                }
+            });
+        }
+
+        let mut pipe = child.stdin.take().context("no stdin")?;
+        let (stdin, lines) = mpsc::channel::<String>();
+        std::thread::spawn(move || {
+            for line in lines {
+                if writeln!(pipe, "{}", line)
+                    .and_then(|_| pipe.flush())
+                    .is_err()
+                {
+                    return;
+                }
+            }
+        });
+
+        let pipe = BufReader::new(child.stdout.take().context("no stdout")?);
+        let (answers, stdout) = mpsc::channel::<String>();
+        std::thread::spawn(move || {
+            for line in pipe.lines().map_while(Result::ok) {
+                if answers.send(line).is_err() {
+                    return;
+                }
+            }
+        });
+
+        Ok(Self {
+            child,
+            stdin,
+            stdout,
+        })
+    }
+
+    fn call(&mut self, request: &Value, timeout: Duration) -> anyhow::Result<Value> {
+        if self.stdin.send(request.to_string()).is_err() {
+            bail!("process exited");
+        }
+        match self.stdout.recv_timeout(timeout) {
+            Ok(line) => Ok(serde_json::from_str(&line)?),
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                bail!("no answer within {}ms", timeout.as_millis())
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => bail!("process exited"),
+        }
+    }
+
+    /// Kill the process and reap it
+    fn kill(mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Start the worker owning the child process
+///
+/// Requests are answered one at a time, in order. The process is started on
+/// first use and restarted after it exits, its pipes break, or it misses a
+/// deadline (a late answer would be read as the next request's).
+fn spawn_process(command: Vec<String>) -> mpsc::Sender<Job> {
+    let (tx, rx) = mpsc::channel::<Job>();
+    let spawned = std::thread::Builder::new()
+        .name("aspy-external-process".to_string())
+        .spawn(move || {
+            let mut process: Option<ChildProcess> = None;
+            for job in rx {
+                // Nobody is waiting for jobs that expired in the queue
+                let Some(remaining) = job.remaining() else {
+                    continue;
+                };
+                if process.is_none() {
+                    match ChildProcess::spawn(&command) {
+                        Ok(p) => process = Some(p),
+                        Err(e) => {
+                            let _ = job.reply.send(Err(e.to_string()));
+                            continue;
+                        }
+                    }
+                }
+                let Some(p) = process.as_mut() else { continue };
+                let reply = p.call(&job.request, remaining).map_err(|e| e.to_string());
+                if let Err(ref e) = reply {
+                    if let Some(dead) = process.take() {
+                        tracing::warn!("External transformer process failed ({}), restarting", e);
+                        dead.kill();
+                    }
+                }
+                let _ = job.reply.send(reply);
+            }
+            if let Some(p) = process {
+                p.kill();
+            }
+        });
+    if let Err(e) = spawned {
+        tracing::error!("Failed to start external transformer worker: {}", e);
+    }
+    tx
+}
+
+// ============================================================================
+// Transformer
+// ============================================================================
+
+/// Forwards requests to an external service and applies its answers
+pub struct External {
+    jobs: Mutex<mpsc::Sender<Job>>,
+    /// Endpoint or command, for logs
+    target: String,
+    timeout: Duration,
+    failure_mode: FailureMode,
+    next_id: AtomicU64,
+    stats: Mutex<ExternalStats>,
+}
+
+impl External {
+    pub fn from_config(config: &ExternalConfig) -> anyhow::Result<Self> {
+        let timeout = Duration::from_millis(config.timeout_ms);
+        let (jobs, target) = match (&config.url, config.command.is_empty()) {
+            (Some(url), true) => (spawn_http(url.clone(), timeout), url.clone()),
+            (None, false) => (
+                spawn_process(config.command.clone()),
+                config.command.join(" "),
+            ),
+            (Some(_), false) => bail!("set either url or command, not both"),
+            (None, true) => bail!("url or command is required"),
+        };
+
+        Ok(Self {
+            jobs: Mutex::new(jobs),
+            target,
+            timeout,
+            failure_mode: config.failure_mode,
+            next_id: AtomicU64::new(1),
+            stats: Mutex::new(ExternalStats::default()),
+        })
+    }
+
+    /// Endpoint URL or command line
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    /// Metrics snapshot for `/api/stats`
+    pub fn stats(&self) -> ExternalStats {
+        self.stats.lock().map(|s| s.clone()).unwrap_or_default()
+    }
+
+    fn update_stats(&self, f: impl FnOnce(&mut ExternalStats)) {
+        if let Ok(mut stats) = self.stats.lock() {
+            f(&mut stats);
+        }
+    }
+
+    /// Send a request and wait for the answer within the timeout
+    ///
+    /// Blocks the calling thread until the answer arrives or the timeout
+    /// passes (the proxy calls it from `block_in_place`).
+    fn call(&self, id: u64, body: &Value, ctx: &TransformContext) -> anyhow::Result<Value> {
+        let request = json!({
+            "version": PROTOCOL_VERSION,
+            "id": id,
+            "context": {
+                "client_id": ctx.client_id,
+                "path": ctx.path,
+                "model": ctx.model,
+                "turn_number": ctx.turn_number,
+                "tool_result_count": ctx.tool_result_count,
+                "context_tokens": ctx.context_tokens,
+                "context_limit": ctx.context_limit,
+            },
+            "body": body,
+        });
+
+        let start = Instant::now();
+        let (reply_tx, reply_rx) = mpsc::channel();
+        let job = Job {
+            request,
+            deadline: start + self.timeout,
+            reply: reply_tx,
+        };
+        let sent = self.jobs.lock().is_ok_and(|jobs| jobs.send(job).is_ok());
+        if !sent {
+            self.update_stats(|s| s.errors += 1);
+            bail!("worker stopped");
+        }
+
+        let reply = reply_rx.recv_timeout(self.timeout);
+        let latency = start.elapsed();
+        self.update_stats(|s| {
+            s.calls += 1;
+            s.record_latency(latency);
+        });
+
+        let error = match reply {
+            Ok(Ok(response)) => return Ok(response),
+            Ok(Err(e)) => anyhow!(e),
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                self.update_stats(|s| s.timeouts += 1);
+                bail!("timed out after {}ms", self.timeout.as_millis())
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => anyhow!("worker stopped"),
+        };
+        self.update_stats(|s| s.errors += 1);
+        Err(error)
+    }
+
+    /// Turn a protocol response into a transform result
+    fn apply(&self, id: u64, response: &Value, body: &Value) -> anyhow::Result<TransformResult> {
+        if let Some(answered) = response.get("id").and_then(|v| v.as_u64()) {
+            if answered != id {
+                bail!("answer for request {} received for {}", answered, id);
+            }
+        }
+        let note = response["note"].as_str();
+
+        let modified = match response["action"].as_str() {
+            Some("continue") => return Ok(TransformResult::Unchanged),
+            Some("block") => {
+                let reason = response["reason"]
+                    .as_str()
+                    .unwrap_or("aspy: blocked by external transformer")
+                    .to_string();
+                let status = response["status"]
+                    .as_u64()
+                    .and_then(|s| u16::try_from(s).ok())
+                    .and_then(|s| StatusCode::from_u16(s).ok())
+                    .filter(|s| s.is_client_error() || s.is_server_error())
+                    .unwrap_or(StatusCode::FORBIDDEN);
+                return Ok(TransformResult::Block { reason, status });
+            }
+            Some("patch") => {
+                let patch: json_patch::Patch =
+                    serde_json::from_value(response["patch"].clone()).context("invalid patch")?;
+                let mut modified = body.clone();
+                json_patch::patch(&mut modified, &patch).context("patch failed")?;
+                let description = note
+                    .map(str::to_string)
+                    .unwrap_or_else(|| format!("applied {} patch operations", patch.0.len()));
+                (modified, description)
+            }
+            Some("replace") if response["body"].is_object() => (
+                response["body"].clone(),
+                note.unwrap_or("replaced request body").to_string(),
+            ),
+            Some(other) => bail!("unknown action {:?}", other),
+            None => bail!("response has no action"),
+        };
+
+        let (modified, description) = modified;
+        if modified == *body {
+            return Ok(TransformResult::Unchanged);
+        }
+        let before = estimate_json_tokens(body);
+        let after = estimate_json_tokens(&modified);
+        Ok(TransformResult::modified_with_info(
+            modified,
+            before,
+            after,
+            vec![description],
+        ))
+    }
+}
+
+impl RequestTransformer for External {
+    fn name(&self) -> &'static str {
+        "external"
+    }
+
+    fn should_apply(&self, _ctx: &TransformContext) -> bool {
+        true
+    }
+
+    fn blocks(&self) -> bool {
+        true
+    }
+
+    fn transform(&self, body: &Value, ctx: &TransformContext) -> TransformResult {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let result = self.call(id, body, ctx).and_then(|response| {
+            self.apply(id, &response, body)
+                .inspect_err(|_| self.update_stats(|s| s.errors += 1))
+        });
+
+        match result {
+            Ok(result) => result,
+            Err(e) => match self.failure_mode {
+                FailureMode::Open => TransformResult::Error(
+                    e.context(format!("external transformer {}", self.target)),
+                ),
+                FailureMode::Closed => {
+                    self.update_stats(|s| s.failed_closed += 1);
+                    tracing::warn!("External transformer failed closed: {}", e);
+                    TransformResult::Block {
+                        reason: format!("aspy: external transformer unavailable ({})", e),
+                        status: StatusCode::SERVICE_UNAVAILABLE,
+                    }
+                }
+            },
+        }
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::net::TcpListener;
+
+    fn request() -> Value {
+        json!({
+            "model": "claude-sonnet-4",
+            "tools": [{"name": "Read"}, {"name": "WebSearch"}],
+            "messages": [{"role": "user", "content": "hi"}]
+        })
+    }
+
+    /// External transformer running a shell loop that answers every line
+    #[cfg(unix)]
+    fn process(script: &str, failure_mode: FailureMode, timeout_ms: u64) -> External {
+        External::from_config(&ExternalConfig {
+            enabled: true,
+            command: vec![
+                "sh".to_string(),
+                "-c".to_string(),
+                format!("while read line; do {}; done", script),
+            ],
+            timeout_ms,
+            failure_mode,
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_process_patch_applied() {
+        let external = process(
+            r#"echo '{"action":"patch","patch":[{"op":"remove","path":"/tools/1"}],"note":"no web"}'"#,
+            FailureMode::Open,
+            5000,
+        );
+        let ctx = TransformContext::new(Some("dev-1"), "/v1/messages", None);
+        for _ in 0..2 {
+            match external.transform(&request(), &ctx) {
+                TransformResult::Modified {
+                    body,
+                    modifications,
+                    ..
+                } => {
+                    assert_eq!(body["tools"], json!([{"name": "Read"}]));
+                    assert_eq!(modifications, vec!["no web"]);
+                }
+                other => panic!("Expected Modified, got {:?}", other),
+            }
+        }
+        let stats = external.stats();
+        assert_eq!(stats.calls, 2);
+        assert_eq!(stats.errors, 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_process_block_and_invalid_answers() {
+        let external = process(
+            r#"echo '{"action":"block","reason":"policy says no","status":451}'"#,
+            FailureMode::Open,
+            5000,
+        );
+        let ctx = TransformContext::new(None, "/v1/messages", None);
+        match external.transform(&request(), &ctx) {
+            TransformResult::Block { reason, status } => {
+                assert_eq!(reason, "policy says no");
+                assert_eq!(status, StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS);
+            }
+            other => panic!("Expected Block, got {:?}", other),
+        }
+
+        // A patch that doesn't apply fails open, leaving the body alone
+        let external = process(
+            r#"echo '{"action":"patch","patch":[{"op":"remove","path":"/nope"}]}'"#,
+            FailureMode::Open,
+            5000,
+        );
+        assert!(matches!(
+            external.transform(&request(), &ctx),
+            TransformResult::Error(_)
+        ));
+        assert_eq!(external.stats().errors, 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_timeout_fail_open_and_closed() {
+        let ctx = TransformContext::new(None, "/v1/messages", None);
+        let slow = r#"sleep 2; echo '{"action":"continue"}'"#;
+
+        let external = process(slow, FailureMode::Open, 50);
+        let start = Instant::now();
+        assert!(matches!(
+            external.transform(&request(), &ctx),
+            TransformResult::Error(_)
+        ));
+        assert!(start.elapsed() < Duration::from_secs(1));
+
+        let external = process(slow, FailureMode::Closed, 50);
+        match external.transform(&request(), &ctx) {
+            TransformResult::Block { status, reason } => {
+                assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+                assert!(reason.contains("timed out"));
+            }
+            other => panic!("Expected Block, got {:?}", other),
+        }
+        let stats = external.stats();
+        assert_eq!(
+            (stats.timeouts, stats.errors, stats.failed_closed),
+            (1, 0, 1)
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_hung_process_is_restarted() {
+        // Hangs on its first request only; a restarted process answers at once
+        let flag = std::env::temp_dir().join(format!("aspy-external-hung-{}", std::process::id()));
+        let _ = std::fs::remove_file(&flag);
+        let script = format!(
+            r#"if [ -e '{0}' ]; then echo '{{"action":"continue"}}'; else touch '{0}'; sleep 30; fi"#,
+            flag.display()
+        );
+        let external = process(&script, FailureMode::Open, 500);
+        let ctx = TransformContext::new(None, "/v1/messages", None);
+
+        assert!(matches!(
+            external.transform(&request(), &ctx),
+            TransformResult::Error(_)
+        ));
+        // Without the restart this would queue behind the hung child and time out
+        assert!(matches!(
+            external.transform(&request(), &ctx),
+            TransformResult::Unchanged
+        ));
+        let stats = external.stats();
+        assert_eq!((stats.calls, stats.timeouts), (2, 1));
+        let _ = std::fs::remove_file(&flag);
+    }
+
+    #[test]
+    fn test_http_replace() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/transform", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            // Read headers, then the body by Content-Length
+            let mut buf = Vec::new();
+            let mut chunk = [0u8; 4096];
+            let body_start = loop {
+                let n = stream.read(&mut chunk).unwrap();
+                buf.extend_from_slice(&chunk[..n]);
+                if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                    break pos + 4;
+                }
+            };
+            let headers = String::from_utf8_lossy(&buf[..body_start]).to_lowercase();
+            let length: usize = headers
+                .lines()
+                .find_map(|l| l.strip_prefix("content-length: "))
+                .unwrap()
+                .trim()
+                .parse()
+                .unwrap();
+            while buf.len() < body_start + length {
+                let n = stream.read(&mut chunk).unwrap();
+                buf.extend_from_slice(&chunk[..n]);
+            }
+            let request: Value = serde_json::from_slice(&buf[body_start..]).unwrap();
+
+            let mut body = request["body"].clone();
+            body["model"] = json!(format!("routed-for-{}", request["context"]["client_id"]));
+            let answer = json!({"id": request["id"], "action": "replace", "body": body});
+            let answer = answer.to_string();
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                answer.len(),
+                answer
+            )
+            .unwrap();
+        });
+
+        let external = External::from_config(&ExternalConfig {
+            enabled: true,
+            url: Some(url),
+            timeout_ms: 5000,
+            ..Default::default()
+        })
+        .unwrap();
+        let ctx = TransformContext::new(Some("dev-1"), "/v1/messages", None);
+        match external.transform(&request(), &ctx) {
+            TransformResult::Modified { body, .. } => {
+                assert_eq!(body["model"], "routed-for-\"dev-1\"");
+            }
+            other => panic!("Expected Modified, got {:?}", other),
+        }
+    }
+}
@@ -23,17 +23,54 @@
 //! The pipeline ALWAYS returns - one transformer failing never breaks the request.
 //! Worst case: the original unmodified request goes through.
 
+mod budget;
+mod cache_optimizer;
 mod compact_enhancer;
+mod condition;
+mod context_enricher;
+pub mod dry_run;
+mod external;
+mod model_router;
+mod projects;
+mod redactor;
+mod scripts;
 pub mod system_editor;
 mod tag_editor;
+mod tool_filter;
+mod tool_governor;
 
 // Re-exports for config parsing and transformer implementations
+pub use budget::{BudgetConfig, BudgetStatus, BudgetTracker};
+pub use cache_optimizer::{CacheOptimizer, CacheOptimizerConfig};
 pub use compact_enhancer::{CompactEnhancer, CompactEnhancerConfig};
+pub use condition::WhenCondition;
+pub use context_enricher::{ContextEnricher, ContextEnricherConfig, SemanticContext};
+pub use external::{External, ExternalConfig, ExternalStats};
+pub use model_router::{ModelRouter, ModelRouterConfig, RouteDecision};
+pub use projects::{Projects, ProjectsConfig};
+pub use redactor::{Redactions, Redactor, RedactorConfig, StreamRestorer};
+pub use scripts::{Scripts, ScriptsConfig};
 pub use system_editor::{SystemEditor, SystemEditorConfig};
 #[allow(unused_imports)]
 pub use tag_editor::{
-    InjectPosition, PositionConfig, RuleConfig, TagEditor, TagEditorConfig, TagRule, WhenCondition,
+    InjectPosition, PositionConfig, RuleConfig, TagEditor, TagEditorConfig, TagRule,
 };
+pub use tool_filter::{
+    ToolFilter, ToolFilterConfig, ToolNameRestorer, ToolRenames, ToolRuleConfig,
+};
+pub use tool_governor::{ToolGovernor, ToolGovernorConfig, Truncations};
+
+// Config types only named directly by tests
+#[cfg(test)]
+pub use budget::BudgetLimits;
+#[cfg(test)]
+pub use external::FailureMode;
+#[cfg(test)]
+pub use model_router::RouteRuleConfig;
+#[cfg(test)]
+pub use projects::ProjectConfig;
+#[cfg(test)]
+pub use redactor::PatternConfig;
 
 use axum::http::StatusCode;
 use serde_json::Value;
@@ -84,11 +121,10 @@ pub enum TransformResult {
         transformers: Vec<String>,
     },
 
-    /// Block request entirely (e.g., content policy violation)
+    /// Block request entirely (e.g., content policy violation, exhausted budget)
     ///
     /// Use sparingly - most errors should return `Error` and continue.
-    /// Used by content policy transformers (future: PII redaction, content filtering).
-    #[allow(dead_code)]
+    /// The client receives an Anthropic-format error with this status.
     Block {
         /// Human-readable reason for blocking
         reason: String,
@@ -148,19 +184,46 @@ impl TransformResult {
     }
 }
 
+/// What one transformer did to a request (see `TransformationPipeline::transform_traced`)
+#[derive(Debug)]
+pub struct TransformStep {
+    /// Transformer name
+    pub transformer: &'static str,
+    /// Outcome of this transformer
+    pub outcome: StepOutcome,
+}
+
+/// Outcome of a single transformer in a traced run
+#[derive(Debug)]
+pub enum StepOutcome {
+    /// `should_apply` returned false
+    Skipped,
+    /// Ran without changing the body
+    Unchanged,
+    /// Changed the body
+    Modified {
+        tokens: Option<TransformTokens>,
+        modifications: Vec<String>,
+    },
+    /// Blocked the request (later transformers did not run)
+    Blocked { reason: String, status: StatusCode },
+    /// Failed; the pipeline continued with the previous body
+    Error(String),
+}
+
 // ============================================================================
 // Transform Context
 // ============================================================================
 
 /// Context provided to transformers for decision-making
 ///
-/// Contains information available at request handling time.
-/// Future fields (like `semantic_context`) will be populated by
-/// async prep work in the proxy handler before the sync pipeline runs.
+/// Contains information available at request handling time. Fields that
+/// need async work (like `semantic_context`) are populated by a prep stage
+/// in the proxy handler before the sync pipeline runs.
 ///
 /// Fields are read by transformer implementations via pattern matching or direct access.
 /// Even if not currently used by TagEditor, they are part of the public API
-/// for future transformers (ContextEnricher, ModelRouter, etc.).
+/// for future transformers.
 #[derive(Debug, Clone, Default)]
 pub struct TransformContext<'a> {
     /// Client ID from routing (e.g., "dev-1")
@@ -171,20 +234,25 @@ pub struct TransformContext<'a> {
     pub path: &'a str,
 
     /// Model being requested (extracted from body)
-    /// Used by: ModelRouter, model-specific transformations (future)
-    #[allow(dead_code)]
+    /// Used by: ModelRouter model pattern rules
     pub model: Option<&'a str>,
 
-    /// Current token usage from context state
-    /// Used by: ContextEnricher for context-aware injection (future)
-    #[allow(dead_code)]
+    /// Current token usage from the session's context state
+    /// Used by: context_usage condition
     pub context_tokens: Option<u64>,
 
-    /// Context limit for the model
-    /// Used by: ContextEnricher for context-aware injection (future)
-    #[allow(dead_code)]
+    /// Context limit for the session
+    /// Used by: context_usage condition
     pub context_limit: Option<u64>,
 
+    /// Text of the latest user prompt (None if the last user message has no text)
+    /// Used by: prompt condition
+    pub user_prompt: Option<&'a str>,
+
+    /// Tools answered by tool_result blocks in the last user message
+    /// Used by: tool_result condition
+    pub tool_result_names: Option<&'a [String]>,
+
     /// Turn number in the conversation (1-indexed, counts user messages)
     /// Used by: turn_number condition for frequency-based rules
     pub turn_number: Option<u64>,
@@ -196,8 +264,22 @@ pub struct TransformContext<'a> {
     /// Current tracked todos (intercepted from TodoWrite)
     /// Used by: CompactEnhancer to inject task context into compaction prompt
     pub todos: Option<Arc<[crate::proxy::sessions::TodoItem]>>,
-    // Future: semantic_context for RAG injection
-    // pub semantic_context: Option<&'a SemanticContext>,
+
+    /// Per-request secret mapping, filled during transformation
+    /// Used by: Redactor (restored in the response by the proxy)
+    pub redactions: Option<&'a Redactions>,
+
+    /// Tool results truncated for the first time, reported for archiving
+    /// Used by: ToolGovernor (originals stored in cortex by the proxy)
+    pub truncations: Option<&'a Truncations>,
+
+    /// Per-request tool rename mapping, filled during transformation
+    /// Used by: ToolFilter (restored in the response by the proxy)
+    pub tool_renames: Option<&'a ToolRenames>,
+
+    /// Cortex matches retrieved for the current turn
+    /// Used by: ContextEnricher (retrieved by its async prep stage)
+    pub semantic_context: Option<&'a SemanticContext>,
 }
 
 impl<'a> TransformContext<'a> {
@@ -209,15 +291,19 @@ impl<'a> TransformContext<'a> {
             model,
             context_tokens: None,
             context_limit: None,
+            user_prompt: None,
+            tool_result_names: None,
             turn_number: None,
             tool_result_count: None,
             todos: None,
+            redactions: None,
+            truncations: None,
+            tool_renames: None,
+            semantic_context: None,
         }
     }
 
     /// Add context token information
-    /// Used by: ContextEnricher for context-aware injection (future)
-    #[allow(dead_code)]
     pub fn with_context_state(mut self, tokens: u64, limit: u64) -> Self {
         self.context_tokens = Some(tokens);
         self.context_limit = Some(limit);
@@ -270,6 +356,34 @@ pub trait RequestTransformer: Send + Sync {
     /// * `TransformResult::Block { ... }` - Reject the request
     /// * `TransformResult::Error(e)` - Log and continue with current body
     fn transform(&self, body: &Value, ctx: &TransformContext) -> TransformResult;
+
+    /// Whether `transform` can block its thread for a noticeable time
+    ///
+    /// The proxy moves pipelines containing such a transformer off the async
+    /// runtime's scheduling so a slow call doesn't stall other requests.
+    fn blocks(&self) -> bool {
+        false
+    }
+}
+
+/// Shared transformers (e.g., the model router, which the pipeline also
+/// queries directly for provider decisions)
+impl<T: RequestTransformer + ?Sized> RequestTransformer for Arc<T> {
+    fn name(&self) -> &'static str {
+        (**self).name()
+    }
+
+    fn should_apply(&self, ctx: &TransformContext) -> bool {
+        (**self).should_apply(ctx)
+    }
+
+    fn transform(&self, body: &Value, ctx: &TransformContext) -> TransformResult {
+        (**self).transform(body, ctx)
+    }
+
+    fn blocks(&self) -> bool {
+        (**self).blocks()
+    }
 }
 
 // ============================================================================
@@ -285,6 +399,24 @@ pub trait RequestTransformer: Send + Sync {
 /// - Worst case: original unmodified request goes through
 pub struct TransformationPipeline {
     transformers: Vec<Box<dyn RequestTransformer>>,
+    /// Model router, also registered as a transformer (for model rewrites)
+    router: Option<Arc<ModelRouter>>,
+    /// Budget tracker, also registered as a transformer (for blocking)
+    budget: Option<Arc<BudgetTracker>>,
+    /// Project registry, also registered as a transformer (for tagging
+    /// sessions and recording project spend)
+    projects: Option<Arc<Projects>>,
+    /// Cache optimizer, also registered as a transformer (re-applied to
+    /// requests translated into Anthropic format)
+    cache_optimizer: Option<Arc<CacheOptimizer>>,
+    /// Context enricher, also registered as a transformer (retrieval runs
+    /// in the proxy handler before the pipeline)
+    context_enricher: Option<Arc<ContextEnricher>>,
+    /// Tool governor, also registered as a transformer (the proxy marks
+    /// results archived once their events are sent)
+    tool_governor: Option<Arc<ToolGovernor>>,
+    /// External transformer, also registered as a transformer (for metrics)
+    external: Option<Arc<External>>,
 }
 
 impl TransformationPipeline {
@@ -292,6 +424,13 @@ impl TransformationPipeline {
     pub fn new() -> Self {
         Self {
             transformers: Vec::new(),
+            router: None,
+            budget: None,
+            projects: None,
+            cache_optimizer: None,
+            context_enricher: None,
+            tool_governor: None,
+            external: None,
         }
     }
 
@@ -302,6 +441,65 @@ impl TransformationPipeline {
     pub fn from_config(config: &crate::config::Transformers) -> Self {
         let mut pipeline = Self::new();
 
+        // Budget (opt-in) - registered first so exhausted budgets short-circuit
+        if let Some(ref budget_config) = config.budget {
+            if budget_config.enabled {
+                let tracker = Arc::new(BudgetTracker::new(budget_config.clone()));
+                tracing::info!(
+                    "Registered budget transformer ({} identities)",
+                    tracker.identity_count()
+                );
+                pipeline.register(Arc::clone(&tracker));
+                pipeline.budget = Some(tracker);
+            }
+        }
+
+        // Model router (opt-in) - registered early so its rules see the
+        // client's original request, same as `route()`
+        if let Some(ref router_config) = config.model_router {
+            if router_config.enabled {
+                match ModelRouter::from_config(router_config) {
+                    Ok(router) => {
+                        let rule_count = router.rule_count();
+                        let router = Arc::new(router);
+                        pipeline.register(Arc::clone(&router));
+                        pipeline.router = Some(router);
+                        tracing::info!(
+                            "Registered model-router transformer ({} rules)",
+                            rule_count
+                        );
+                    }
+                    Err(e) => {
+                        tracing::warn!(
+                            "Failed to create model-router: {}. Transformer disabled.",
+                            e
+                        );
+                    }
+                }
+            }
+        }
+
+        // Projects (opt-in) - before the editors so global rules also see
+        // project instructions
+        if let Some(ref projects_config) = config.projects {
+            if projects_config.enabled {
+                match Projects::from_config(projects_config) {
+                    Ok(projects) => {
+                        let projects = Arc::new(projects);
+                        pipeline.register(Arc::clone(&projects));
+                        tracing::info!(
+                            "Registered projects transformer ({} projects)",
+                            projects.project_count()
+                        );
+                        pipeline.projects = Some(projects);
+                    }
+                    Err(e) => {
+                        tracing::warn!("Failed to create projects: {}. Transformer disabled.", e);
+                    }
+                }
+            }
+        }
+
         // Tag editor (opt-in)
         if let Some(ref editor_config) = config.tag_editor {
             if editor_config.enabled {
@@ -340,6 +538,26 @@ impl TransformationPipeline {
             }
         }
 
+        // Tool filter (opt-in) - before anything that reads tool names, so
+        // later transformers see the tools the model is offered
+        if let Some(ref filter_config) = config.tool_filter {
+            if filter_config.enabled {
+                match ToolFilter::from_config(filter_config) {
+                    Ok(filter) => {
+                        let rule_count = filter.rule_count();
+                        pipeline.register(filter);
+                        tracing::info!("Registered tool-filter transformer ({} rules)", rule_count);
+                    }
+                    Err(e) => {
+                        tracing::warn!(
+                            "Failed to create tool-filter: {}. Transformer disabled.",
+                            e
+                        );
+                    }
+                }
+            }
+        }
+
         // Compact enhancer (opt-in)
         if let Some(ref compact_config) = config.compact_enhancer {
             if compact_config.enabled {
@@ -348,6 +566,107 @@ impl TransformationPipeline {
             }
         }
 
+        // Context enricher (opt-in) - before the redactor so retrieved history
+        // is scanned for secrets too
+        if let Some(ref enricher_config) = config.context_enricher {
+            if enricher_config.enabled {
+                let enricher = Arc::new(ContextEnricher::new(enricher_config));
+                pipeline.register(Arc::clone(&enricher));
+                pipeline.context_enricher = Some(enricher);
+                tracing::info!(
+                    "Registered context-enricher transformer (max {} tokens, {} results)",
+                    enricher_config.max_tokens,
+                    enricher_config.max_results
+                );
+            }
+        }
+
+        // Tool governor (opt-in)
+        if let Some(ref governor_config) = config.tool_governor {
+            if governor_config.enabled {
+                let governor = Arc::new(ToolGovernor::new(governor_config));
+                pipeline.register(Arc::clone(&governor));
+                pipeline.tool_governor = Some(governor);
+                tracing::info!(
+                    "Registered tool-governor transformer (max {} tokens, keeps {} turns)",
+                    governor_config.max_tokens,
+                    governor_config.keep_recent_turns
+                );
+            }
+        }
+
+        // User scripts (opt-in) - after the built-in editors, before the
+        // redactor so script output is still scanned for secrets
+        if let Some(ref scripts_config) = config.scripts {
+            if scripts_config.enabled {
+                let scripts = Scripts::new(scripts_config);
+                tracing::info!(
+                    "Registered scripts transformer ({} scripts in {})",
+                    scripts.script_count(),
+                    scripts.dir().display()
+                );
+                pipeline.register(scripts);
+            }
+        }
+
+        // External service (opt-in) - sees the request after local edits,
+        // before redaction and cache breakpoints
+        if let Some(ref external_config) = config.external {
+            if external_config.enabled {
+                match External::from_config(external_config) {
+                    Ok(external) => {
+                        let external = Arc::new(external);
+                        pipeline.register(Arc::clone(&external));
+                        tracing::info!(
+                            "Registered external transformer ({}, fail {})",
+                            external.target(),
+                            external_config.failure_mode.as_str()
+                        );
+                        pipeline.external = Some(external);
+                    }
+                    Err(e) => {
+                        tracing::warn!(
+                            "Failed to create external transformer: {}. Transformer disabled.",
+                            e
+                        );
+                    }
+                }
+            }
+        }
+
+        // Redactor (opt-in) - after every editor so injected text is scanned too
+        if let Some(ref redactor_config) = config.redactor {
+            if redactor_config.enabled {
+                match Redactor::from_config(redactor_config) {
+                    Ok(redactor) => {
+                        let detector_count = redactor.detector_count();
+                        pipeline.register(redactor);
+                        tracing::info!(
+                            "Registered redactor transformer ({} detectors)",
+                            detector_count
+                        );
+                    }
+                    Err(e) => {
+                        tracing::warn!("Failed to create redactor: {}. Transformer disabled.", e);
+                    }
+                }
+            }
+        }
+
+        // Cache optimizer (opt-in) - registered last so breakpoints land on
+        // the final prompt after every other edit
+        if let Some(ref cache_config) = config.cache_optimizer {
+            if cache_config.enabled {
+                let optimizer = Arc::new(CacheOptimizer::new(cache_config));
+                pipeline.register(Arc::clone(&optimizer));
+                pipeline.cache_optimizer = Some(optimizer);
+                tracing::info!(
+                    "Registered cache-optimizer transformer (min prefix {} tokens)",
+                    cache_config.min_prefix_tokens
+                );
+            }
+        }
+
         pipeline
     }
 
@@ -366,7 +685,38 @@ impl TransformationPipeline {
     ///
     /// Uses `Cow` internally to avoid cloning when all transformers pass through.
     /// Accumulates token deltas and modification descriptions from all transformers.
-    pub fn transform<'a>(&self, body: &'a Value, ctx: &TransformContext) -> TransformResult {
+    pub fn transform(&self, body: &Value, ctx: &TransformContext) -> TransformResult {
+        self.run(body, ctx, None)
+    }
+
+    /// Process a request like `transform`, also reporting what each transformer did
+    ///
+    /// Used by the dry-run CLI, which shows modifications per transformer.
+    pub fn transform_traced(
+        &self,
+        body: &Value,
+        ctx: &TransformContext,
+    ) -> (TransformResult, Vec<TransformStep>) {
+        let mut steps = Vec::with_capacity(self.transformers.len());
+        let result = self.run(body, ctx, Some(&mut steps));
+        (result, steps)
+    }
+
+    fn run<'a>(
+        &self,
+        body: &'a Value,
+        ctx: &TransformContext,
+        mut trace: Option<&mut Vec<TransformStep>>,
+    ) -> TransformResult {
+        let mut record = |transformer: &'static str, outcome: StepOutcome| {
+            if let Some(steps) = trace.as_deref_mut() {
+                steps.push(TransformStep {
+                    transformer,
+                    outcome,
+                });
+            }
+        };
+
         if self.transformers.is_empty() {
             return TransformResult::Unchanged;
         }
@@ -389,6 +739,7 @@ impl TransformationPipeline {
                     transformer = transformer.name(),
                     "Transformer skipped (should_apply=false)"
                 );
+                record(transformer.name(), StepOutcome::Skipped);
                 continue;
             }
 
@@ -398,6 +749,7 @@ impl TransformationPipeline {
                         transformer = transformer.name(),
                         "Transformer returned Unchanged"
                     );
+                    record(transformer.name(), StepOutcome::Unchanged);
                     // No change, keep current (borrowed or owned)
                 }
                 TransformResult::Modified {
@@ -428,6 +780,13 @@ impl TransformationPipeline {
                             "Transformer modified request (no token tracking)"
                         );
                     }
+                    record(
+                        transformer.name(),
+                        StepOutcome::Modified {
+                            tokens,
+                            modifications: modifications.clone(),
+                        },
+                    );
                     // Accumulate modifications from this transformer
                     all_modifications.extend(modifications);
                     // Track that this transformer fired
@@ -445,6 +804,13 @@ impl TransformationPipeline {
                         reason,
                         status
                     );
+                    record(
+                        transformer.name(),
+                        StepOutcome::Blocked {
+                            reason: reason.clone(),
+                            status,
+                        },
+                    );
                     return TransformResult::Block { reason, status };
                 }
                 TransformResult::Error(error) => {
@@ -456,6 +822,7 @@ impl TransformationPipeline {
                         transformer.name(),
                         error
                     );
+                    record(transformer.name(), StepOutcome::Error(error.to_string()));
                     // Don't modify current - proceed with what we have
                 }
             }
@@ -483,6 +850,48 @@ impl TransformationPipeline {
         }
     }
 
+    /// Pick the upstream provider for a request using the model router
+    ///
+    /// Must be called with the same (untransformed) body and context passed
+    /// to `transform()`, so the provider choice matches any model rewrite.
+    pub fn route(&self, body: &Value, ctx: &TransformContext) -> Option<RouteDecision> {
+        let router = self.router.as_ref()?;
+        if !router.should_apply(ctx) {
+            return None;
+        }
+        router.route(body, ctx)
+    }
+
+    /// Budget tracker (for recording spend and soft-limit warnings)
+    pub fn budget(&self) -> Option<&Arc<BudgetTracker>> {
+        self.budget.as_ref()
+    }
+
+    /// Project registry (for tagging sessions and recording project spend)
+    pub fn projects(&self) -> Option<&Arc<Projects>> {
+        self.projects.as_ref()
+    }
+
+    /// Cache optimizer (for requests translated into Anthropic format)
+    pub fn cache_optimizer(&self) -> Option<&Arc<CacheOptimizer>> {
+        self.cache_optimizer.as_ref()
+    }
+
+    /// Context enricher (for the async retrieval before transformation)
+    pub fn context_enricher(&self) -> Option<&Arc<ContextEnricher>> {
+        self.context_enricher.as_ref()
+    }
+
+    /// Tool governor (for marking archived results)
+    pub fn tool_governor(&self) -> Option<&Arc<ToolGovernor>> {
+        self.tool_governor.as_ref()
+    }
+
+    /// External transformer (for latency metrics)
+    pub fn external(&self) -> Option<&Arc<External>> {
+        self.external.as_ref()
+    }
+
     /// Check if pipeline has any transformers
     pub fn is_empty(&self) -> bool {
         self.transformers.is_empty()
@@ -492,6 +901,11 @@ impl TransformationPipeline {
     pub fn transformer_names(&self) -> Vec<&'static str> {
         self.transformers.iter().map(|t| t.name()).collect()
     }
+
+    /// Whether any registered transformer blocks (see `RequestTransformer::blocks`)
+    pub fn blocks(&self) -> bool {
+        self.transformers.iter().any(|t| t.blocks())
+    }
 }
 
 impl Default for TransformationPipeline {
@@ -652,4 +1066,54 @@ mod tests {
             other => panic!("Expected Modified, got {:?}", other),
         }
     }
+
+    #[test]
+    fn test_router_runs_before_body_edits() {
+        // `route()` is called on the untransformed body, so the router must
+        // come before every transformer that can edit it (budget only blocks)
+        let config = crate::config::Transformers {
+            enabled: true,
+            budget: Some(BudgetConfig {
+                enabled: true,
+                ..Default::default()
+            }),
+            model_router: Some(ModelRouterConfig {
+                enabled: true,
+                ..Default::default()
+            }),
+            projects: Some(ProjectsConfig {
+                enabled: true,
+                ..Default::default()
+            }),
+            tag_editor: Some(TagEditorConfig {
+                enabled: true,
+                ..Default::default()
+            }),
+            system_editor: Some(SystemEditorConfig {
+                enabled: true,
+                ..Default::default()
+            }),
+            tool_filter: Some(ToolFilterConfig {
+                enabled: true,
+                ..Default::default()
+            }),
+            tool_governor: Some(ToolGovernorConfig {
+                enabled: true,
+                ..Default::default()
+            }),
+            redactor: Some(RedactorConfig {
+                enabled: true,
+                ..Default::default()
+            }),
+            cache_optimizer: Some(CacheOptimizerConfig {
+                enabled: true,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let pipeline = TransformationPipeline::from_config(&config);
+        let names = pipeline.transformer_names();
+        assert!(names.len() > 2);
+        assert_eq!(&names[..2], &["budget", "model-router"]);
+    }
 }
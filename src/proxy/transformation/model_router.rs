@@ -0,0 +1,436 @@
+//! Model Router - Pick the upstream provider from request properties
+//!
+//! Client routing sends every request from a client to the same provider.
+//! The model router refines that per request: a rule table matches on the
+//! requested model, estimated context size, turn number or client id and
+//! picks the provider (and optionally rewrites the model) for that request.
+//!
+//! # Rules
+//!
+//! Rules are evaluated in order; the first match wins. All conditions on a
+//! rule are AND'd together, and a rule with no conditions matches everything.
+//! - **model**: Case-insensitive regex against the requested model
+//! - **context_tokens**: Numeric condition on the estimated request size
+//! - **when**: Shared `turn_number` / `has_tool_results` / `client_id` conditions
+//!
+//! A matching rule can set `provider` (any provider in `[providers]`, whose
+//! fallback chain is used for failover) and/or `model_mapping` to rewrite the
+//! model name before it is sent.
+//!
+//! # Example Config
+//!
+//! ```toml
+//! [transformers.model-router]
+//! enabled = true
+//!
+//! # Haiku utility calls go to a cheap OpenAI-compatible backend
+//! [[transformers.model-router.rules]]
+//! name = "haiku-to-openrouter"
+//! model = "haiku"
+//! provider = "openrouter"
+//! model_mapping = { "haiku" = "openai/gpt-4o-mini" }
+//!
+//! # Opus stays on Anthropic
+//! [[transformers.model-router.rules]]
+//! name = "opus-direct"
+//! model = "opus"
+//! provider = "anthropic"
+//! ```
+
+use super::condition::parse_numeric_condition;
+use super::{RequestTransformer, TransformContext, TransformResult, WhenCondition};
+use crate::proxy::translation::ModelMapping;
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+// ============================================================================
+// Configuration
+// ============================================================================
+
+/// Configuration for a single routing rule (from TOML)
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct RouteRuleConfig {
+    /// Rule name shown in routing events (defaults to "rule-N")
+    #[serde(default)]
+    pub name: Option<String>,
+
+    /// Model pattern (case-insensitive regex), e.g. "haiku" or "^claude-opus"
+    #[serde(default)]
+    pub model: Option<String>,
+
+    /// Estimated request size condition: ">100000", "<2000"
+    #[serde(default)]
+    pub context_tokens: Option<String>,
+
+    /// Provider to route to (must exist in `[providers]`)
+    #[serde(default)]
+    pub provider: Option<String>,
+
+    /// Model rewrite applied when the rule matches (same matching as provider mappings)
+    #[serde(default)]
+    pub model_mapping: HashMap<String, String>,
+
+    /// Additional turn/tool/client conditions
+    #[serde(default)]
+    pub when: Option<WhenCondition>,
+}
+
+/// Configuration for the ModelRouter transformer
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct ModelRouterConfig {
+    /// Whether the transformer is enabled
+    #[serde(default)]
+    pub enabled: bool,
+    /// Rules to evaluate (first match wins)
+    #[serde(default)]
+    pub rules: Vec<RouteRuleConfig>,
+}
+
+// ============================================================================
+// Route Decision
+// ============================================================================
+
+/// Outcome of routing a request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteDecision {
+    /// Name of the rule that matched
+    pub rule: String,
+    /// Provider selected by the rule (None = keep the client's provider)
+    pub provider: Option<String>,
+    /// Model requested by the client
+    pub model: Option<String>,
+    /// Model after the rule's rewrite (same as `model` when not rewritten)
+    pub routed_model: Option<String>,
+}
+
+impl RouteDecision {
+    /// Whether the rule rewrote the model name
+    pub fn model_rewritten(&self) -> bool {
+        self.model != self.routed_model
+    }
+}
+
+// ============================================================================
+// ModelRouter
+// ============================================================================
+
+/// A compiled routing rule
+struct RouteRule {
+    name: String,
+    model: Option<Regex>,
+    context_tokens: Option<String>,
+    when: Option<WhenCondition>,
+    provider: Option<String>,
+    model_mapping: Option<ModelMapping>,
+}
+
+impl RouteRule {
+    fn matches(&self, body: &Value, model: Option<&str>, ctx: &TransformContext) -> bool {
+        if let Some(ref pattern) = self.model {
+            if !model.is_some_and(|m| pattern.is_match(m)) {
+                return false;
+            }
+        }
+        if let Some(ref when) = self.when {
+            if !when.evaluate(ctx) {
+                return false;
+            }
+        }
+        if let Some(ref condition) = self.context_tokens {
+            // Estimated last, only when the cheaper conditions already passed
+            let tokens = crate::tokens::estimate_json_tokens(body) as u64;
+            if !parse_numeric_condition(condition, tokens) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Transformer that routes requests to providers by model and context
+///
+/// The transform step only rewrites the model name; the provider choice is
+/// read by the proxy through `TransformationPipeline::route` so both see
+/// the same rule match.
+pub struct ModelRouter {
+    rules: Vec<RouteRule>,
+}
+
+impl ModelRouter {
+    /// Create from configuration
+    ///
+    /// Fails on invalid model patterns or rules that neither pick a
+    /// provider nor rewrite the model.
+    pub fn from_config(config: &ModelRouterConfig) -> anyhow::Result<Self> {
+        let mut rules = Vec::with_capacity(config.rules.len());
+
+        for (idx, rule_config) in config.rules.iter().enumerate() {
+            let name = rule_config
+                .name
+                .clone()
+                .unwrap_or_else(|| format!("rule-{}", idx + 1));
+
+            if rule_config.provider.is_none() && rule_config.model_mapping.is_empty() {
+                anyhow::bail!(
+                    "rule '{}' needs a provider or model_mapping to do anything",
+                    name
+                );
+            }
+
+            let model = rule_config
+                .model
+                .as_deref()
+                .map(|p| RegexBuilder::new(p).case_insensitive(true).build())
+                .transpose()?;
+            if let Some(ref when) = rule_config.when {
+                when.validate()?;
+            }
+
+            tracing::debug!(
+                rule = %name,
+                model = ?rule_config.model,
+                provider = ?rule_config.provider,
+                "Loaded route rule"
+            );
+
+            rules.push(RouteRule {
+                name,
+                model,
+                context_tokens: rule_config.context_tokens.clone(),
+                when: rule_config.when.clone(),
+                provider: rule_config.provider.clone(),
+                model_mapping: (!rule_config.model_mapping.is_empty())
+                    .then(|| ModelMapping::from_config(&rule_config.model_mapping)),
+            });
+        }
+
+        Ok(Self { rules })
+    }
+
+    /// Get the number of rules
+    pub fn rule_count(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Find the first matching rule for a request
+    pub fn route(&self, body: &Value, ctx: &TransformContext) -> Option<RouteDecision> {
+        let model = ctx
+            .model
+            .or_else(|| body.get("model").and_then(|m| m.as_str()));
+
+        let rule = self.rules.iter().find(|r| r.matches(body, model, ctx))?;
+
+        let routed_model = match (&rule.model_mapping, model) {
+            (Some(mapping), Some(m)) => Some(mapping.to_target(m)),
+            _ => model.map(str::to_string),
+        };
+
+        Some(RouteDecision {
+            rule: rule.name.clone(),
+            provider: rule.provider.clone(),
+            model: model.map(str::to_string),
+            routed_model,
+        })
+    }
+}
+
+impl RequestTransformer for ModelRouter {
+    fn name(&self) -> &'static str {
+        "model-router"
+    }
+
+    fn should_apply(&self, ctx: &TransformContext) -> bool {
+        ctx.path.ends_with("/messages") || ctx.path.ends_with("/v1/messages")
+    }
+
+    fn transform(&self, body: &Value, ctx: &TransformContext) -> TransformResult {
+        let Some(decision) = self.route(body, ctx) else {
+            return TransformResult::Unchanged;
+        };
+        if !decision.model_rewritten() {
+            return TransformResult::Unchanged;
+        }
+
+        let mut new_body = body.clone();
+        new_body["model"] = Value::String(decision.routed_model.clone().unwrap_or_default());
+
+        TransformResult::Modified {
+            body: new_body,
+            tokens: None,
+            modifications: vec![format!(
+                "Rewrote model '{}' → '{}' (rule '{}')",
+                decision.model.unwrap_or_default(),
+                decision.routed_model.unwrap_or_default(),
+                decision.rule
+            )],
+            transformers: Vec::new(),
+        }
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn router(toml_str: &str) -> ModelRouter {
+        let config: ModelRouterConfig = toml::from_str(toml_str).unwrap();
+        ModelRouter::from_config(&config).unwrap()
+    }
+
+    fn haiku_opus_router() -> ModelRouter {
+        router(
+            r#"
+            enabled = true
+
+            [[rules]]
+            name = "haiku-cheap"
+            model = "haiku"
+            provider = "openrouter"
+            model_mapping = { "haiku" = "openai/gpt-4o-mini" }
+
+            [[rules]]
+            name = "opus-direct"
+            model = "opus"
+            provider = "anthropic"
+            "#,
+        )
+    }
+
+    #[test]
+    fn test_first_matching_rule_wins() {
+        let router = haiku_opus_router();
+        let body = json!({"model": "claude-3-5-HAIKU-20241022", "messages": []});
+        let ctx = TransformContext::new(None, "/v1/messages", body["model"].as_str());
+
+        let decision = router.route(&body, &ctx).unwrap();
+        assert_eq!(decision.rule, "haiku-cheap");
+        assert_eq!(decision.provider.as_deref(), Some("openrouter"));
+        assert_eq!(decision.routed_model.as_deref(), Some("openai/gpt-4o-mini"));
+
+        let body = json!({"model": "claude-opus-4", "messages": []});
+        let ctx = TransformContext::new(None, "/v1/messages", body["model"].as_str());
+        let decision = router.route(&body, &ctx).unwrap();
+        assert_eq!(decision.rule, "opus-direct");
+        assert!(!decision.model_rewritten());
+
+        let body = json!({"model": "claude-sonnet-4", "messages": []});
+        let ctx = TransformContext::new(None, "/v1/messages", body["model"].as_str());
+        assert!(router.route(&body, &ctx).is_none());
+    }
+
+    #[test]
+    fn test_transform_rewrites_model() {
+        let router = haiku_opus_router();
+        let body = json!({"model": "claude-haiku-4", "messages": []});
+        let ctx = TransformContext::new(None, "/v1/messages", body["model"].as_str());
+
+        match router.transform(&body, &ctx) {
+            TransformResult::Modified {
+                body,
+                modifications,
+                ..
+            } => {
+                assert_eq!(body["model"], "openai/gpt-4o-mini");
+                assert!(modifications[0].contains("haiku-cheap"));
+            }
+            other => panic!("Expected Modified, got {:?}", other),
+        }
+
+        // Provider-only rule leaves the body alone
+        let body = json!({"model": "claude-opus-4", "messages": []});
+        let ctx = TransformContext::new(None, "/v1/messages", body["model"].as_str());
+        assert!(matches!(
+            router.transform(&body, &ctx),
+            TransformResult::Unchanged
+        ));
+    }
+
+    #[test]
+    fn test_context_tokens_and_when_conditions() {
+        let router = router(
+            r#"
+            [[rules]]
+            name = "big-context"
+            context_tokens = ">1000"
+            provider = "long-context"
+
+            [[rules]]
+            name = "dev-late-turns"
+            provider = "cheap"
+            when.turn_number = ">10"
+            when.client_id = "dev-1"
+            "#,
+        );
+
+        let big =
+            json!({"model": "m", "messages": [{"role": "user", "content": "x".repeat(8000)}]});
+        let ctx = TransformContext::new(Some("dev-1"), "/v1/messages", Some("m"));
+        assert_eq!(router.route(&big, &ctx).unwrap().rule, "big-context");
+
+        let small = json!({"model": "m", "messages": []});
+        let mut ctx = TransformContext::new(Some("dev-1"), "/v1/messages", Some("m"));
+        ctx.turn_number = Some(3);
+        assert!(router.route(&small, &ctx).is_none());
+
+        ctx.turn_number = Some(11);
+        assert_eq!(router.route(&small, &ctx).unwrap().rule, "dev-late-turns");
+
+        ctx.client_id = Some("other");
+        assert!(router.route(&small, &ctx).is_none());
+    }
+
+    #[test]
+    fn test_unnamed_rules_get_index_names() {
+        let router = router(
+            r#"
+            [[rules]]
+            model = "nomatch"
+            provider = "a"
+
+            [[rules]]
+            provider = "b"
+            "#,
+        );
+        let body = json!({"model": "claude-sonnet-4"});
+        let ctx = TransformContext::new(None, "/v1/messages", Some("claude-sonnet-4"));
+        let decision = router.route(&body, &ctx).unwrap();
+        assert_eq!(decision.rule, "rule-2");
+        assert_eq!(decision.provider.as_deref(), Some("b"));
+    }
+
+    #[test]
+    fn test_invalid_rules_rejected() {
+        let config: ModelRouterConfig = toml::from_str(
+            r#"
+            [[rules]]
+            model = "haiku"
+            "#,
+        )
+        .unwrap();
+        assert!(ModelRouter::from_config(&config).is_err());
+
+        let config: ModelRouterConfig = toml::from_str(
+            r#"
+            [[rules]]
+            model = "(unclosed"
+            provider = "a"
+            "#,
+        )
+        .unwrap();
+        assert!(ModelRouter::from_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_skips_non_messages_paths() {
+        let router = haiku_opus_router();
+        let ctx = TransformContext::new(None, "/v1/messages/count_tokens", Some("claude-haiku"));
+        assert!(!router.should_apply(&ctx));
+    }
+}
@@ -0,0 +1,636 @@
+//! Scripts - User-defined transformers written in Rhai
+//!
+//! For one-off logic the editors can't express (rewriting a tool definition,
+//! dropping a reminder only under some condition), drop a `.rhai` file into
+//! the scripts directory (`~/.config/aspy/scripts` by default). Every script
+//! defining `transform(body, ctx)` runs in file name order:
+//!
+//! ```rhai
+//! // Drop the WebSearch tool for the ci client
+//! fn transform(body, ctx) {
+//!     if ctx.client_id != "ci" { return; }
+//!     body.tools = body.tools.filter(|t| t.name != "WebSearch");
+//!     modified(body, "removed WebSearch")
+//! }
+//! ```
+//!
+//! `body` is the request JSON as a map and `ctx` a copy of the
+//! `TransformContext` fields (`client_id`, `path`, `model`, `turn_number`,
+//! `tool_result_count`, `context_tokens`, `context_limit`; `()` when unknown).
+//! A script returns `()` to leave the request alone, `modified(body)` or
+//! `modified(body, note)` to replace it, or `block(reason)` /
+//! `block(reason, status)` to reject it.
+//!
+//! # Sandbox
+//!
+//! Scripts can't import modules, `eval` code or touch the filesystem. Each
+//! call is limited in wall time and operation count, and strings, arrays and
+//! maps are size-capped, which bounds memory. A script that errors or hits a
+//! limit is skipped; the request continues with the body so far.
+//!
+//! A script burns CPU for up to its wall time, so the transformer reports
+//! itself as blocking and the proxy runs the pipeline in `block_in_place`.
+//!
+//! # Hot Reload
+//!
+//! The directory is re-read at most once a second. Changed files are
+//! recompiled; if a new version fails to compile, the previous one keeps
+//! running until the file is fixed.
+//!
+//! # Example Config
+//!
+//! ```toml
+//! [transformers.scripts]
+//! enabled = true
+//! dir = "scripts"          # relative to ~/.config/aspy
+//! timeout_ms = 50
+//! max_operations = 1000000
+//! ```
+
+use super::{RequestTransformer, TransformContext, TransformResult};
+use crate::tokens::estimate_json_tokens;
+use axum::http::StatusCode;
+use rhai::module_resolvers::DummyModuleResolver;
+use rhai::{Dynamic, Engine, ImmutableString, Map, Scope, AST};
+use serde::Deserialize;
+use serde_json::Value;
+use std::cell::Cell;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Minimum time between scans of the scripts directory
+const RELOAD_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Largest string a script may build (bytes)
+const MAX_STRING_SIZE: usize = 8 * 1024 * 1024;
+
+/// Largest array or map a script may build (entries)
+const MAX_COLLECTION_SIZE: usize = 100_000;
+
+/// Operations between wall-clock checks
+const TIMEOUT_CHECK_INTERVAL: u64 = 1024;
+
+/// Function every script must define
+const ENTRY_POINT: &str = "transform";
+
+thread_local! {
+    /// Deadline of the script call running on this thread
+    static DEADLINE: Cell<Option<Instant>> = const { Cell::new(None) };
+}
+
+// ============================================================================
+// Configuration
+// ============================================================================
+
+/// Configuration for the Scripts transformer
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScriptsConfig {
+    /// Whether script transformers are enabled
+    #[serde(default)]
+    pub enabled: bool,
+    /// Scripts directory (relative paths are resolved against ~/.config/aspy)
+    #[serde(default = "default_dir")]
+    pub dir: PathBuf,
+    /// Wall-clock limit per script call
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Operation limit per script call
+    #[serde(default = "default_max_operations")]
+    pub max_operations: u64,
+}
+
+fn default_dir() -> PathBuf {
+    PathBuf::from("scripts")
+}
+
+fn default_timeout_ms() -> u64 {
+    50
+}
+
+fn default_max_operations() -> u64 {
+    1_000_000
+}
+
+impl Default for ScriptsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dir: default_dir(),
+            timeout_ms: default_timeout_ms(),
+            max_operations: default_max_operations(),
+        }
+    }
+}
+
+// ============================================================================
+// Script Outcomes
+// ============================================================================
+
+/// Value returned by `modified(..)` and `block(..)` inside a script
+#[derive(Debug, Clone)]
+enum Outcome {
+    Modified { body: Dynamic, note: Option<String> },
+    Block { reason: String, status: i64 },
+}
+
+/// Create the sandboxed engine with the script API registered
+fn build_engine(config: &ScriptsConfig) -> Engine {
+    let mut engine = Engine::new();
+
+    // Sandbox: no modules, no eval, bounded work and data
+    engine.set_module_resolver(DummyModuleResolver::new());
+    engine.disable_symbol("eval");
+    engine.set_max_operations(config.max_operations);
+    engine.set_max_string_size(MAX_STRING_SIZE);
+    engine.set_max_array_size(MAX_COLLECTION_SIZE);
+    engine.set_max_map_size(MAX_COLLECTION_SIZE);
+    engine.set_max_call_levels(32);
+    engine.on_progress(|ops| {
+        if ops % TIMEOUT_CHECK_INTERVAL != 0 {
+            return None;
+        }
+        let expired = DEADLINE.with(|d| d.get().is_some_and(|t| Instant::now() >= t));
+        expired.then(|| Dynamic::from("timeout"))
+    });
+
+    // print/debug go to the log, not stdout (the TUI owns the terminal)
+    engine.on_print(|text| tracing::info!(target: "aspy::scripts", "{}", text));
+    engine.on_debug(|text, source, pos| {
+        tracing::debug!(target: "aspy::scripts", "{} {:?} @ {}", text, source, pos)
+    });
+
+    engine.register_type_with_name::<Outcome>("Outcome");
+    engine.register_fn("modified", |body: Dynamic| Outcome::Modified {
+        body,
+        note: None,
+    });
+    engine.register_fn("modified", |body: Dynamic, note: ImmutableString| {
+        Outcome::Modified {
+            body,
+            note: Some(note.to_string()),
+        }
+    });
+    engine.register_fn("block", |reason: ImmutableString| Outcome::Block {
+        reason: reason.to_string(),
+        status: StatusCode::FORBIDDEN.as_u16() as i64,
+    });
+    engine.register_fn("block", |reason: ImmutableString, status: i64| {
+        Outcome::Block {
+            reason: reason.to_string(),
+            status,
+        }
+    });
+
+    engine
+}
+
+/// Read-only view of the transform context for scripts
+fn context_map(ctx: &TransformContext) -> Map {
+    fn opt<T: Into<Dynamic>>(value: Option<T>) -> Dynamic {
+        value.map(Into::into).unwrap_or(Dynamic::UNIT)
+    }
+
+    let mut map = Map::new();
+    map.insert("client_id".into(), opt(ctx.client_id.map(str::to_string)));
+    map.insert("path".into(), ctx.path.to_string().into());
+    map.insert("model".into(), opt(ctx.model.map(str::to_string)));
+    map.insert("turn_number".into(), opt(ctx.turn_number.map(|n| n as i64)));
+    map.insert(
+        "tool_result_count".into(),
+        opt(ctx.tool_result_count.map(|n| n as i64)),
+    );
+    map.insert(
+        "context_tokens".into(),
+        opt(ctx.context_tokens.map(|n| n as i64)),
+    );
+    map.insert(
+        "context_limit".into(),
+        opt(ctx.context_limit.map(|n| n as i64)),
+    );
+    map
+}
+
+// ============================================================================
+// Script Loading
+// ============================================================================
+
+/// A script file and its last successfully compiled version
+struct LoadedScript {
+    name: String,
+    /// Source as last read from disk (compiled or not)
+    source: String,
+    ast: Option<Arc<AST>>,
+}
+
+#[derive(Default)]
+struct ScriptSet {
+    scripts: Vec<LoadedScript>,
+    last_scan: Option<Instant>,
+}
+
+/// `*.rhai` files in a directory, sorted by name
+fn script_files(dir: &Path) -> Vec<(String, PathBuf)> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<(String, PathBuf)> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && p.extension().is_some_and(|ext| ext == "rhai"))
+        .filter_map(|p| Some((p.file_name()?.to_string_lossy().into_owned(), p)))
+        .collect();
+    files.sort();
+    files
+}
+
+// ============================================================================
+// Transformer
+// ============================================================================
+
+/// Runs user scripts from the scripts directory
+pub struct Scripts {
+    engine: Engine,
+    dir: PathBuf,
+    timeout: Duration,
+    reload_interval: Duration,
+    set: Mutex<ScriptSet>,
+}
+
+impl Scripts {
+    pub fn new(config: &ScriptsConfig) -> Self {
+        let dir = if config.dir.is_absolute() {
+            config.dir.clone()
+        } else {
+            crate::config::Config::config_path()
+                .and_then(|p| p.parent().map(|d| d.join(&config.dir)))
+                .unwrap_or_else(|| config.dir.clone())
+        };
+
+        let scripts = Self {
+            engine: build_engine(config),
+            dir,
+            timeout: Duration::from_millis(config.timeout_ms),
+            reload_interval: RELOAD_INTERVAL,
+            set: Mutex::new(ScriptSet::default()),
+        };
+        scripts.refresh();
+        scripts
+    }
+
+    /// Directory scripts are loaded from
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Number of scripts currently compiled and runnable
+    pub fn script_count(&self) -> usize {
+        self.refresh().len()
+    }
+
+    /// Re-read the directory if due, returning the runnable scripts
+    fn refresh(&self) -> Vec<(String, Arc<AST>)> {
+        let Ok(mut set) = self.set.lock() else {
+            return Vec::new();
+        };
+
+        let due = set
+            .last_scan
+            .is_none_or(|t| t.elapsed() >= self.reload_interval);
+        if due {
+            set.last_scan = Some(Instant::now());
+            let mut previous = std::mem::take(&mut set.scripts);
+            for (name, path) in script_files(&self.dir) {
+                let Ok(source) = std::fs::read_to_string(&path) else {
+                    continue;
+                };
+                let existing = previous
+                    .iter()
+                    .position(|s| s.name == name)
+                    .map(|i| previous.swap_remove(i));
+                set.scripts.push(self.load(name, source, existing));
+            }
+            for removed in previous {
+                tracing::info!("Script unloaded: {}", removed.name);
+            }
+        }
+
+        set.scripts
+            .iter()
+            .filter_map(|s| Some((s.name.clone(), Arc::clone(s.ast.as_ref()?))))
+            .collect()
+    }
+
+    /// Compile a script if its source changed since the last scan
+    fn load(&self, name: String, source: String, existing: Option<LoadedScript>) -> LoadedScript {
+        let previous_ast = match existing {
+            Some(loaded) if loaded.source == source => return loaded,
+            Some(loaded) => loaded.ast,
+            None => None,
+        };
+
+        let ast = match self.engine.compile(&source) {
+            Ok(ast)
+                if ast
+                    .iter_functions()
+                    .any(|f| f.name == ENTRY_POINT && f.params.len() == 2) =>
+            {
+                tracing::info!("Script loaded: {}", name);
+                Some(Arc::new(ast))
+            }
+            Ok(_) => {
+                tracing::warn!("Script {} has no {}(body, ctx) function", name, ENTRY_POINT);
+                previous_ast
+            }
+            Err(e) => {
+                tracing::warn!(
+                    "Script {} failed to compile: {}{}",
+                    name,
+                    e,
+                    if previous_ast.is_some() {
+                        " (keeping previous version)"
+                    } else {
+                        ""
+                    }
+                );
+                previous_ast
+            }
+        };
+
+        LoadedScript { name, source, ast }
+    }
+
+    /// Run one script's entry point under the time limit
+    fn call(&self, ast: &AST, body: &Value, ctx: Map) -> anyhow::Result<Dynamic> {
+        let body = rhai::serde::to_dynamic(body).map_err(|e| anyhow::anyhow!("{}", e))?;
+
+        DEADLINE.with(|d| d.set(Some(Instant::now() + self.timeout)));
+        let result =
+            self.engine
+                .call_fn::<Dynamic>(&mut Scope::new(), ast, ENTRY_POINT, (body, ctx));
+        DEADLINE.with(|d| d.set(None));
+
+        result.map_err(|e| match *e {
+            rhai::EvalAltResult::ErrorTerminated(..) => {
+                anyhow::anyhow!("exceeded {}ms time limit", self.timeout.as_millis())
+            }
+            other => anyhow::anyhow!("{}", other),
+        })
+    }
+}
+
+impl RequestTransformer for Scripts {
+    fn name(&self) -> &'static str {
+        "scripts"
+    }
+
+    fn should_apply(&self, _ctx: &TransformContext) -> bool {
+        true
+    }
+
+    fn blocks(&self) -> bool {
+        true
+    }
+
+    fn transform(&self, body: &Value, ctx: &TransformContext) -> TransformResult {
+        let scripts = self.refresh();
+        if scripts.is_empty() {
+            return TransformResult::Unchanged;
+        }
+
+        let ctx_map = context_map(ctx);
+        let mut current: Option<Value> = None;
+        let mut modifications = Vec::new();
+        let mut errors = Vec::new();
+
+        for (name, ast) in &scripts {
+            let input = current.as_ref().unwrap_or(body);
+            let result = match self.call(ast, input, ctx_map.clone()) {
+                Ok(result) => result,
+                Err(e) => {
+                    tracing::warn!("Script {} failed: {}", name, e);
+                    errors.push(format!("{}: {}", name, e));
+                    continue;
+                }
+            };
+
+            if result.is_unit() {
+                continue;
+            }
+            match result.try_cast::<Outcome>() {
+                Some(Outcome::Modified {
+                    body: new_body,
+                    note,
+                }) => match rhai::serde::from_dynamic::<Value>(&new_body) {
+                    Ok(new_body) if new_body != *input => {
+                        modifications.push(format!(
+                            "{}: {}",
+                            name,
+                            note.as_deref().unwrap_or("modified request")
+                        ));
+                        current = Some(new_body);
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        tracing::warn!("Script {} returned an invalid body: {}", name, e);
+                        errors.push(format!("{}: invalid body", name));
+                    }
+                },
+                Some(Outcome::Block { reason, status }) => {
+                    let status = u16::try_from(status)
+                        .ok()
+                        .and_then(|s| StatusCode::from_u16(s).ok())
+                        .filter(|s| s.is_client_error() || s.is_server_error())
+                        .unwrap_or(StatusCode::FORBIDDEN);
+                    tracing::info!("Script {} blocked request: {}", name, reason);
+                    return TransformResult::Block { reason, status };
+                }
+                None => {
+                    tracing::warn!(
+                        "Script {} returned a value that is not (), modified() or block()",
+                        name
+                    );
+                    errors.push(format!("{}: unexpected return value", name));
+                }
+            }
+        }
+
+        match current {
+            Some(modified) => {
+                let before = estimate_json_tokens(body);
+                let after = estimate_json_tokens(&modified);
+                TransformResult::modified_with_info(modified, before, after, modifications)
+            }
+            None if !errors.is_empty() => {
+                TransformResult::Error(anyhow::anyhow!("{}", errors.join("; ")))
+            }
+            None => TransformResult::Unchanged,
+        }
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Fresh scripts directory under the system temp dir
+    fn script_dir(test: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("aspy-scripts-{}-{}", test, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn scripts(dir: &Path) -> Scripts {
+        let mut scripts = Scripts::new(&ScriptsConfig {
+            enabled: true,
+            dir: dir.to_path_buf(),
+            ..Default::default()
+        });
+        scripts.reload_interval = Duration::ZERO;
+        scripts
+    }
+
+    fn request() -> Value {
+        json!({
+            "model": "claude-sonnet-4",
+            "tools": [{"name": "Read"}, {"name": "WebSearch"}],
+            "messages": [{"role": "user", "content": "hi"}]
+        })
+    }
+
+    #[test]
+    fn test_script_modifies_body_using_context() {
+        let dir = script_dir("modify");
+        std::fs::write(
+            dir.join("10-tools.rhai"),
+            r#"
+            fn transform(body, ctx) {
+                if ctx.client_id != "ci" { return; }
+                body.tools = body.tools.filter(|t| t.name != "WebSearch");
+                modified(body, "removed WebSearch")
+            }
+            "#,
+        )
+        .unwrap();
+        let scripts = scripts(&dir);
+        let body = request();
+
+        let ctx = TransformContext::new(Some("dev-1"), "/v1/messages", None);
+        assert!(matches!(
+            scripts.transform(&body, &ctx),
+            TransformResult::Unchanged
+        ));
+
+        let ctx = TransformContext::new(Some("ci"), "/v1/messages", None);
+        match scripts.transform(&body, &ctx) {
+            TransformResult::Modified {
+                body: out,
+                modifications,
+                ..
+            } => {
+                assert_eq!(out["tools"], json!([{"name": "Read"}]));
+                assert_eq!(out["model"], "claude-sonnet-4");
+                assert_eq!(modifications, vec!["10-tools.rhai: removed WebSearch"]);
+            }
+            other => panic!("Expected Modified, got {:?}", other),
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_script_blocks_request() {
+        let dir = script_dir("block");
+        std::fs::write(
+            dir.join("guard.rhai"),
+            r#"fn transform(body, ctx) { if body.model.contains("opus") { block("no opus here", 402) } }"#,
+        )
+        .unwrap();
+        let scripts = scripts(&dir);
+        let ctx = TransformContext::new(None, "/v1/messages", None);
+
+        let mut body = request();
+        assert!(matches!(
+            scripts.transform(&body, &ctx),
+            TransformResult::Unchanged
+        ));
+        body["model"] = json!("claude-opus-4");
+        match scripts.transform(&body, &ctx) {
+            TransformResult::Block { reason, status } => {
+                assert_eq!(reason, "no opus here");
+                assert_eq!(status, StatusCode::PAYMENT_REQUIRED);
+            }
+            other => panic!("Expected Block, got {:?}", other),
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_sandbox_limits() {
+        let dir = script_dir("sandbox");
+        std::fs::write(
+            dir.join("a-loop.rhai"),
+            "fn transform(body, ctx) { loop { } }",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("b-import.rhai"),
+            r#"fn transform(body, ctx) { import "secrets" as s; modified(body) }"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("c-eval.rhai"),
+            r#"fn transform(body, ctx) { eval("1") }"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("d-ok.rhai"),
+            r#"fn transform(body, ctx) { body.ok = true; modified(body) }"#,
+        )
+        .unwrap();
+        let mut scripts = scripts(&dir);
+        scripts.timeout = Duration::from_millis(20);
+        let ctx = TransformContext::new(None, "/v1/messages", None);
+
+        let start = Instant::now();
+        match scripts.transform(&request(), &ctx) {
+            TransformResult::Modified { body, .. } => assert_eq!(body["ok"], true),
+            other => panic!("Expected Modified, got {:?}", other),
+        }
+        assert!(start.elapsed() < Duration::from_secs(2));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_hot_reload_keeps_last_good_version() {
+        let dir = script_dir("reload");
+        let path = dir.join("tag.rhai");
+        let write = |source: &str| std::fs::write(&path, source).unwrap();
+        write(r#"fn transform(body, ctx) { body.tag = "v1"; modified(body) }"#);
+        let scripts = scripts(&dir);
+        let ctx = TransformContext::new(None, "/v1/messages", None);
+        let tag = |scripts: &Scripts| match scripts.transform(&request(), &ctx) {
+            TransformResult::Modified { body, .. } => body["tag"].as_str().unwrap().to_string(),
+            other => panic!("Expected Modified, got {:?}", other),
+        };
+        assert_eq!(tag(&scripts), "v1");
+
+        write(r#"fn transform(body, ctx) { body.tag = "v2"; modified(body) }"#);
+        assert_eq!(tag(&scripts), "v2");
+
+        // Syntax error: previous version keeps running
+        write(r#"fn transform(body, ctx) { body.tag = "#);
+        assert_eq!(tag(&scripts), "v2");
+        assert_eq!(scripts.script_count(), 1);
+
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(scripts.script_count(), 0);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -10,6 +10,9 @@
 //! - **Prepend**: Add text to the beginning of system prompt
 //! - **Replace**: Find and replace text within system blocks
 //!
+//! Each rule can have an optional `when` condition, using the same condition
+//! language as the tag editor (see [`super::condition`]).
+//!
 //! # Example Config
 //!
 //! ```toml
@@ -24,8 +27,10 @@
 //! type = "replace"
 //! pattern = "Claude Code"
 //! replacement = "Claude Code (Aspy-enhanced)"
+//! when.context_usage = ">80"
 //! ```
 
+use super::condition::WhenCondition;
 use super::{RequestTransformer, TransformContext, TransformResult};
 use regex::Regex;
 use serde::Deserialize;
@@ -39,11 +44,32 @@ use serde_json::Value;
 #[derive(Debug, Clone)]
 pub enum SystemRule {
     /// Append text to the end of the last system block
-    Append { content: String },
+    Append {
+        content: String,
+        when: Option<WhenCondition>,
+    },
     /// Prepend text to the beginning of the first system block
-    Prepend { content: String },
+    Prepend {
+        content: String,
+        when: Option<WhenCondition>,
+    },
     /// Replace matching text in all system blocks
-    Replace { pattern: Regex, replacement: String },
+    Replace {
+        pattern: Regex,
+        replacement: String,
+        when: Option<WhenCondition>,
+    },
+}
+
+impl SystemRule {
+    /// Condition for this rule, if any
+    fn when(&self) -> Option<&WhenCondition> {
+        match self {
+            SystemRule::Append { when, .. }
+            | SystemRule::Prepend { when, .. }
+            | SystemRule::Replace { when, .. } => when.as_ref(),
+        }
+    }
 }
 
 // ============================================================================
@@ -56,13 +82,22 @@ pub enum SystemRule {
 pub enum RuleConfig {
     Append {
         content: String,
+        /// Optional conditions for when this rule applies
+        #[serde(default)]
+        when: Option<WhenCondition>,
     },
     Prepend {
         content: String,
+        /// Optional conditions for when this rule applies
+        #[serde(default)]
+        when: Option<WhenCondition>,
     },
     Replace {
         pattern: String,
         replacement: String,
+        /// Optional conditions for when this rule applies
+        #[serde(default)]
+        when: Option<WhenCondition>,
     },
 }
 
@@ -99,21 +134,24 @@ impl SystemEditor {
 
         for rule_config in &config.rules {
             let rule = match rule_config {
-                RuleConfig::Append { content } => {
+                RuleConfig::Append { content, when } => {
                     tracing::debug!(content_len = content.len(), "Loaded Append rule");
                     SystemRule::Append {
                         content: content.clone(),
+                        when: when.clone(),
                     }
                 }
-                RuleConfig::Prepend { content } => {
+                RuleConfig::Prepend { content, when } => {
                     tracing::debug!(content_len = content.len(), "Loaded Prepend rule");
                     SystemRule::Prepend {
                         content: content.clone(),
+                        when: when.clone(),
                     }
                 }
                 RuleConfig::Replace {
                     pattern,
                     replacement,
+                    when,
                 } => {
                     tracing::debug!(
                         pattern = %pattern,
@@ -123,9 +161,13 @@ impl SystemEditor {
                     SystemRule::Replace {
                         pattern: Regex::new(pattern)?,
                         replacement: replacement.clone(),
+                        when: when.clone(),
                     }
                 }
             };
+            if let Some(cond) = rule.when() {
+                cond.validate()?;
+            }
             rules.push(rule);
         }
 
@@ -138,13 +180,17 @@ impl SystemEditor {
     }
 
     /// Apply all rules to the system array
-    fn apply_rules(&self, system: &mut [Value]) -> (bool, Vec<String>) {
+    fn apply_rules(&self, system: &mut [Value], ctx: &TransformContext) -> (bool, Vec<String>) {
         let mut modified = false;
         let mut modifications = Vec::new();
 
         for rule in &self.rules {
+            if rule.when().is_some_and(|cond| !cond.evaluate(ctx)) {
+                tracing::debug!(rule = ?rule, "SystemEditor rule skipped: condition not met");
+                continue;
+            }
             match rule {
-                SystemRule::Append { content } => {
+                SystemRule::Append { content, .. } => {
                     // Find last text block and append
                     if let Some(block) = system
                         .iter_mut()
@@ -158,7 +204,7 @@ impl SystemEditor {
                         }
                     }
                 }
-                SystemRule::Prepend { content } => {
+                SystemRule::Prepend { content, .. } => {
                     // Find first text block and prepend
                     if let Some(block) = system
                         .iter_mut()
@@ -174,6 +220,7 @@ impl SystemEditor {
                 SystemRule::Replace {
                     pattern,
                     replacement,
+                    ..
                 } => {
                     let mut replace_count = 0;
                     for block in system.iter_mut() {
@@ -212,7 +259,7 @@ impl RequestTransformer for SystemEditor {
         ctx.path.ends_with("/messages") || ctx.path.ends_with("/v1/messages")
     }
 
-    fn transform(&self, body: &Value, _ctx: &TransformContext) -> TransformResult {
+    fn transform(&self, body: &Value, ctx: &TransformContext) -> TransformResult {
         if self.rules.is_empty() {
             return TransformResult::Unchanged;
         }
@@ -233,7 +280,7 @@ impl RequestTransformer for SystemEditor {
 
         // Clone and apply rules
         let mut new_system = system;
-        let (modified, modifications) = self.apply_rules(&mut new_system);
+        let (modified, modifications) = self.apply_rules(&mut new_system, ctx);
 
         if !modified {
             return TransformResult::Unchanged;
@@ -277,13 +324,16 @@ mod tests {
             rules: vec![
                 RuleConfig::Append {
                     content: "Appended text".to_string(),
+                    when: None,
                 },
                 RuleConfig::Prepend {
                     content: "Prepended text".to_string(),
+                    when: None,
                 },
                 RuleConfig::Replace {
                     pattern: "old".to_string(),
                     replacement: "new".to_string(),
+                    when: None,
                 },
             ],
         };
@@ -320,6 +370,7 @@ mod tests {
     fn test_no_system_returns_unchanged() {
         let editor = SystemEditor::new(vec![SystemRule::Append {
             content: "test".to_string(),
+            when: None,
         }]);
         let body = serde_json::json!({
             "model": "claude-3",
@@ -336,6 +387,7 @@ mod tests {
     fn test_append_rule() {
         let editor = SystemEditor::new(vec![SystemRule::Append {
             content: " Augmented by Aspy.".to_string(),
+            when: None,
         }]);
         let body = serde_json::json!({
             "model": "claude-3",
@@ -355,6 +407,7 @@ mod tests {
     fn test_prepend_rule() {
         let editor = SystemEditor::new(vec![SystemRule::Prepend {
             content: "[ENHANCED] ".to_string(),
+            when: None,
         }]);
         let body = serde_json::json!({
             "model": "claude-3",
@@ -375,6 +428,7 @@ mod tests {
         let editor = SystemEditor::new(vec![SystemRule::Replace {
             pattern: Regex::new("Claude Code").unwrap(),
             replacement: "Claude Code (Aspy)".to_string(),
+            when: None,
         }]);
         let body = serde_json::json!({
             "model": "claude-3",
@@ -395,9 +449,11 @@ mod tests {
         let editor = SystemEditor::new(vec![
             SystemRule::Prepend {
                 content: "START: ".to_string(),
+                when: None,
             },
             SystemRule::Append {
                 content: " :END".to_string(),
+                when: None,
             },
         ]);
         let body = serde_json::json!({
@@ -426,6 +482,7 @@ mod tests {
         let editor = SystemEditor::new(vec![SystemRule::Replace {
             pattern: Regex::new("Claude").unwrap(),
             replacement: "Assistant".to_string(),
+            when: None,
         }]);
         let body = serde_json::json!({
             "model": "claude-3",
@@ -456,6 +513,7 @@ mod tests {
         // System can be a string instead of array
         let editor = SystemEditor::new(vec![SystemRule::Append {
             content: " (enhanced)".to_string(),
+            when: None,
         }]);
         let body = serde_json::json!({
             "model": "claude-3",
@@ -477,6 +535,7 @@ mod tests {
         let editor = SystemEditor::new(vec![SystemRule::Replace {
             pattern: Regex::new("nonexistent").unwrap(),
             replacement: "replacement".to_string(),
+            when: None,
         }]);
         let body = serde_json::json!({
             "model": "claude-3",
@@ -14,151 +14,15 @@
 //!
 //! # Conditions
 //!
-//! Rules can have optional `when` conditions that must be met for the rule to apply:
-//! - `turn_number`: Match on conversation turn (e.g., "=1", ">5", "every:3")
-//! - `has_tool_results`: Match on tool result count (e.g., ">0", "=0")
-//! - `client_id`: Match on client ID (e.g., "dev-1", "foundry|local")
+//! Rules can have optional `when` conditions that must be met for the rule to apply
+//! (e.g. `turn_number`, `client_id`, `model`, `prompt`); see [`super::condition`].
 
+use super::condition::WhenCondition;
 use super::{RequestTransformer, TransformContext, TransformResult};
 use regex::Regex;
 use serde::Deserialize;
 use serde_json::Value;
 
-// ============================================================================
-// Condition Types
-// ============================================================================
-
-/// Conditions that must be met for a rule to apply
-///
-/// Multiple conditions in the same WhenCondition are AND'd together.
-/// Pipe-separated values within a condition are OR'd (e.g., "dev-1|foundry").
-#[derive(Debug, Clone, Deserialize, Default)]
-pub struct WhenCondition {
-    /// Turn number condition: "=1", ">5", "<10", "every:3"
-    #[serde(default)]
-    pub turn_number: Option<String>,
-
-    /// Tool results condition: ">0", "=0", ">3"
-    #[serde(default)]
-    pub has_tool_results: Option<String>,
-
-    /// Client ID condition: "dev-1", "foundry|local" (pipe = OR)
-    #[serde(default)]
-    pub client_id: Option<String>,
-}
-
-impl WhenCondition {
-    /// Check if all conditions are met
-    pub fn evaluate(&self, ctx: &TransformContext) -> bool {
-        let turn_ok = self.check_turn_number(ctx);
-        let tools_ok = self.check_tool_results(ctx);
-        let client_ok = self.check_client_id(ctx);
-        let result = turn_ok && tools_ok && client_ok;
-
-        tracing::debug!(
-            turn_cond = ?self.turn_number,
-            actual_turn = ctx.turn_number,
-            turn_ok,
-            tools_cond = ?self.has_tool_results,
-            actual_tools = ctx.tool_result_count,
-            tools_ok,
-            result,
-            "Condition evaluation"
-        );
-
-        result
-    }
-
-    fn check_turn_number(&self, ctx: &TransformContext) -> bool {
-        let Some(ref condition) = self.turn_number else {
-            return true;
-        };
-        let Some(turn) = ctx.turn_number else {
-            return true; // No turn info = pass (permissive)
-        };
-        parse_numeric_condition(condition, turn)
-    }
-
-    fn check_tool_results(&self, ctx: &TransformContext) -> bool {
-        let Some(ref condition) = self.has_tool_results else {
-            return true;
-        };
-        let count = ctx.tool_result_count.unwrap_or(0) as u64;
-        parse_numeric_condition(condition, count)
-    }
-
-    fn check_client_id(&self, ctx: &TransformContext) -> bool {
-        let Some(ref condition) = self.client_id else {
-            return true;
-        };
-        let Some(client) = ctx.client_id else {
-            return true;
-        };
-        // Pipe-separated = OR
-        condition.split('|').any(|c| c.trim() == client)
-    }
-
-    /// Write this condition as TOML dotted keys to the output string.
-    ///
-    /// Used by config serialization to generate valid TOML for array elements.
-    /// Each field becomes a dotted key (e.g., `when.turn_number = "..."`)
-    pub fn write_toml(&self, output: &mut String) {
-        if let Some(ref tn) = self.turn_number {
-            output.push_str(&format!("when.turn_number = \"{}\"\n", tn));
-        }
-        if let Some(ref tr) = self.has_tool_results {
-            output.push_str(&format!("when.has_tool_results = \"{}\"\n", tr));
-        }
-        if let Some(ref ci) = self.client_id {
-            output.push_str(&format!("when.client_id = \"{}\"\n", ci));
-        }
-    }
-}
-
-/// Parse numeric conditions like "=1", ">5", "<10", "every:3"
-/// Supports pipe-separated OR conditions: "=1|every:3" matches 1 OR multiples of 3
-fn parse_numeric_condition(condition: &str, value: u64) -> bool {
-    let condition = condition.trim();
-
-    // Support pipe-separated OR conditions (e.g., "=1|every:3")
-    if condition.contains('|') {
-        return condition
-            .split('|')
-            .any(|c| parse_single_condition(c.trim(), value));
-    }
-
-    parse_single_condition(condition, value)
-}
-
-/// Parse a single numeric condition (no pipe)
-fn parse_single_condition(condition: &str, value: u64) -> bool {
-    if let Some(n) = condition.strip_prefix("every:") {
-        if let Ok(interval) = n.parse::<u64>() {
-            return interval > 0 && value.is_multiple_of(interval);
-        }
-        return true; // Invalid = pass
-    }
-
-    if let Some(n) = condition.strip_prefix(">=") {
-        return n.parse::<u64>().map(|n| value >= n).unwrap_or(true);
-    }
-    if let Some(n) = condition.strip_prefix("<=") {
-        return n.parse::<u64>().map(|n| value <= n).unwrap_or(true);
-    }
-    if let Some(n) = condition.strip_prefix('>') {
-        return n.parse::<u64>().map(|n| value > n).unwrap_or(true);
-    }
-    if let Some(n) = condition.strip_prefix('<') {
-        return n.parse::<u64>().map(|n| value < n).unwrap_or(true);
-    }
-    if let Some(n) = condition.strip_prefix('=') {
-        return n.parse::<u64>().map(|n| value == n).unwrap_or(true);
-    }
-
-    // Plain number = equals
-    condition.parse::<u64>().map(|n| value == n).unwrap_or(true)
-}
-
 // ============================================================================
 // Rule Types
 // ============================================================================
@@ -326,6 +190,13 @@ impl TagEditor {
         let mut rules = Vec::with_capacity(config.rules.len());
 
         for rule_config in &config.rules {
+            let (RuleConfig::Inject { when, .. }
+            | RuleConfig::Remove { when, .. }
+            | RuleConfig::Replace { when, .. }) = rule_config;
+            if let Some(cond) = when {
+                cond.validate()?;
+            }
+
             let rule = match rule_config {
                 RuleConfig::Inject {
                     tag,
@@ -2083,6 +1954,7 @@ Content with keep-me gets removed
                 turn_number: Some(">2".to_string()),
                 has_tool_results: None,
                 client_id: None,
+                ..Default::default()
             }),
         }];
 
@@ -0,0 +1,713 @@
+//! ToolFilter - Remove, rename or re-describe tool definitions per client
+//!
+//! Some clients should never be offered certain tools (a review bot has no
+//! business calling `Bash` or `Write`), and some tools read better under a
+//! different name or description. This transformer edits the request's
+//! `tools` array by tool name or pattern, optionally gated per client with
+//! `when` conditions:
+//!
+//! - **remove**: drop matching definitions
+//! - **rename**: expose matching tools under another name (`$1` expands
+//!   capture groups of the pattern)
+//! - **describe**: replace the description
+//!
+//! The array is edited as raw JSON rather than through
+//! `parser::models::Tool`, so `cache_control` markers and server tool
+//! definitions pass through untouched. A breakpoint on a removed tool moves
+//! to the previous kept tool.
+//!
+//! # History
+//!
+//! Earlier turns are rewritten to match what the model is offered now:
+//! `tool_use` blocks of renamed tools carry the new name, and calls to
+//! removed tools (with their results) become plain text notes, so the model
+//! never sees itself calling a tool it no longer has. `tool_choice` follows
+//! the same edits.
+//!
+//! # Restoration
+//!
+//! Renames are kept per request in `ToolRenames`. When the model calls a
+//! renamed tool, the proxy puts the original name back in the response so
+//! the client runs the tool it knows. Events and logs keep the exposed name.
+//!
+//! # Example Config
+//!
+//! ```toml
+//! [transformers.tool-filter]
+//! enabled = true
+//!
+//! [[transformers.tool-filter.rules]]
+//! type = "remove"
+//! tool = "Bash|Write"             # full-name regex
+//! when.client_id = "review-bot"
+//!
+//! [[transformers.tool-filter.rules]]
+//! type = "rename"
+//! tool = "mcp__github__(.*)"
+//! to = "github_$1"
+//!
+//! [[transformers.tool-filter.rules]]
+//! type = "describe"
+//! tool = "WebFetch"
+//! description = "Fetch a URL from the internal docs site only."
+//! ```
+
+use super::{RequestTransformer, TransformContext, TransformResult, WhenCondition};
+use crate::proxy::sse::{event_end, write_event};
+use crate::tokens::estimate_json_tokens;
+use bytes::Bytes;
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+// ============================================================================
+// Configuration
+// ============================================================================
+
+/// Configuration for the ToolFilter transformer
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ToolFilterConfig {
+    /// Whether the transformer is enabled
+    #[serde(default)]
+    pub enabled: bool,
+    /// Rules applied in order (later rules see earlier renames)
+    #[serde(default)]
+    pub rules: Vec<ToolRuleConfig>,
+}
+
+/// Configuration for a single rule (from TOML)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ToolRuleConfig {
+    Remove {
+        /// Tool name or regex matched against the whole name
+        tool: String,
+        /// Optional conditions for when this rule applies
+        #[serde(default)]
+        when: Option<WhenCondition>,
+    },
+    Rename {
+        /// Tool name or regex matched against the whole name
+        tool: String,
+        /// New name (`$1`, `$name` expand capture groups)
+        to: String,
+        /// Optional conditions for when this rule applies
+        #[serde(default)]
+        when: Option<WhenCondition>,
+    },
+    Describe {
+        /// Tool name or regex matched against the whole name
+        tool: String,
+        /// Replacement description
+        description: String,
+        /// Optional conditions for when this rule applies
+        #[serde(default)]
+        when: Option<WhenCondition>,
+    },
+}
+
+// ============================================================================
+// Per-Request Mapping
+// ============================================================================
+
+/// Tools renamed in one request, keyed by exposed name
+///
+/// Filled by the filter through `TransformContext` and carried to the
+/// response handler for restoration.
+#[derive(Debug, Default)]
+pub struct ToolRenames {
+    originals: Mutex<HashMap<String, String>>,
+}
+
+impl ToolRenames {
+    pub fn is_empty(&self) -> bool {
+        self.originals.lock().map(|o| o.is_empty()).unwrap_or(true)
+    }
+
+    /// Set the mapping (exposed name -> original name)
+    pub(crate) fn replace(&self, originals: HashMap<String, String>) {
+        if let Ok(mut current) = self.originals.lock() {
+            *current = originals;
+        }
+    }
+
+    /// Original name of an exposed tool name, if it was renamed
+    pub fn original(&self, exposed: &str) -> Option<String> {
+        self.originals.lock().ok()?.get(exposed).cloned()
+    }
+
+    /// Restore original names in the `tool_use` blocks of a buffered response
+    ///
+    /// Returns true if anything changed.
+    pub fn restore_response(&self, body: &mut Value) -> bool {
+        let Some(blocks) = body.get_mut("content").and_then(|c| c.as_array_mut()) else {
+            return false;
+        };
+        let mut changed = false;
+        for block in blocks.iter_mut().filter(|b| b["type"] == "tool_use") {
+            if let Some(original) = block["name"].as_str().and_then(|n| self.original(n)) {
+                block["name"] = Value::String(original);
+                changed = true;
+            }
+        }
+        changed
+    }
+}
+
+/// Restores original tool names in a streamed response
+///
+/// Works on whole SSE events (incomplete events wait for the next chunk).
+/// Tool names only appear in `content_block_start`, so every other event
+/// passes through untouched.
+pub struct ToolNameRestorer {
+    renames: Arc<ToolRenames>,
+    /// Bytes of an incomplete SSE event
+    buffer: Vec<u8>,
+}
+
+impl ToolNameRestorer {
+    pub fn new(renames: Arc<ToolRenames>) -> Self {
+        Self {
+            renames,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Feed a chunk of (Anthropic-format) SSE, returning what to forward
+    pub fn push(&mut self, chunk: &[u8]) -> Bytes {
+        self.buffer.extend_from_slice(chunk);
+        let mut output = Vec::with_capacity(self.buffer.len());
+        while let Some(end) = event_end(&self.buffer) {
+            let event: Vec<u8> = self.buffer.drain(..end).collect();
+            self.process_event(&event, &mut output);
+        }
+        Bytes::from(output)
+    }
+
+    /// Flush whatever is left at the end of the stream
+    pub fn finish(&mut self) -> Bytes {
+        Bytes::from(std::mem::take(&mut self.buffer))
+    }
+
+    fn process_event(&self, event: &[u8], output: &mut Vec<u8>) {
+        let start = std::str::from_utf8(event)
+            .ok()
+            .filter(|text| text.contains("content_block_start"))
+            .and_then(|text| text.lines().find_map(|l| l.strip_prefix("data:")))
+            .and_then(|d| serde_json::from_str::<Value>(d.trim()).ok());
+        let original = start.as_ref().and_then(|data| {
+            (data["content_block"]["type"] == "tool_use")
+                .then(|| data["content_block"]["name"].as_str())
+                .flatten()
+                .and_then(|name| self.renames.original(name))
+        });
+        match (start, original) {
+            (Some(mut data), Some(original)) => {
+                data["content_block"]["name"] = Value::String(original);
+                write_event(output, "content_block_start", &data);
+            }
+            _ => output.extend_from_slice(event),
+        }
+    }
+}
+
+// ============================================================================
+// Rules
+// ============================================================================
+
+#[derive(Debug)]
+enum Action {
+    Remove,
+    Rename(String),
+    Describe(String),
+}
+
+/// A compiled rule
+#[derive(Debug)]
+struct ToolRule {
+    tool: Regex,
+    action: Action,
+    when: Option<WhenCondition>,
+}
+
+/// What the rules did to one request's tool definitions
+#[derive(Debug, Default)]
+struct ToolEdits {
+    /// Original names of removed tools
+    removed: HashSet<String>,
+    /// Original name → exposed name
+    renamed: HashMap<String, String>,
+    modifications: Vec<String>,
+}
+
+impl ToolEdits {
+    fn is_empty(&self) -> bool {
+        self.modifications.is_empty()
+    }
+}
+
+/// Filters and rewrites tool definitions
+#[derive(Debug)]
+pub struct ToolFilter {
+    rules: Vec<ToolRule>,
+}
+
+impl ToolFilter {
+    /// Create from configuration
+    pub fn from_config(config: &ToolFilterConfig) -> anyhow::Result<Self> {
+        let rules = config
+            .rules
+            .iter()
+            .map(|rule| {
+                let (tool, action, when) = match rule {
+                    ToolRuleConfig::Remove { tool, when } => (tool, Action::Remove, when),
+                    ToolRuleConfig::Rename { tool, to, when } => {
+                        (tool, Action::Rename(to.clone()), when)
+                    }
+                    ToolRuleConfig::Describe {
+                        tool,
+                        description,
+                        when,
+                    } => (tool, Action::Describe(description.clone()), when),
+                };
+                if let Some(when) = when {
+                    when.validate()?;
+                }
+                Ok(ToolRule {
+                    tool: Regex::new(&format!("^(?:{})$", tool))
+                        .map_err(|e| anyhow::anyhow!("invalid tool pattern '{}': {}", tool, e))?,
+                    action,
+                    when: when.clone(),
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Self { rules })
+    }
+
+    pub fn rule_count(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Apply the rules that pass their conditions to the `tools` array
+    fn edit_tools(&self, tools: &mut Vec<Value>, ctx: &TransformContext) -> ToolEdits {
+        let mut edits = ToolEdits::default();
+        // Original name of each definition still in the array, by position
+        let mut originals: Vec<String> = tools
+            .iter()
+            .map(|t| t["name"].as_str().unwrap_or_default().to_string())
+            .collect();
+
+        for rule in &self.rules {
+            if !rule.when.as_ref().is_none_or(|w| w.evaluate(ctx)) {
+                continue;
+            }
+            let mut index = 0;
+            while index < tools.len() {
+                let name = tools[index]["name"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string();
+                if !rule.tool.is_match(&name) {
+                    index += 1;
+                    continue;
+                }
+                match &rule.action {
+                    Action::Remove => {
+                        let removed = tools.remove(index);
+                        let original = originals.remove(index);
+                        // Keep the prompt cache breakpoint on the tools prefix
+                        if let Some(cache_control) = removed.get("cache_control") {
+                            if let Some(previous) = index.checked_sub(1).map(|i| &mut tools[i]) {
+                                if previous.get("cache_control").is_none() {
+                                    previous["cache_control"] = cache_control.clone();
+                                }
+                            }
+                        }
+                        edits.modifications.push(format!("Removed tool {}", name));
+                        edits.renamed.remove(&original);
+                        edits.removed.insert(original);
+                        continue;
+                    }
+                    Action::Rename(to) => {
+                        let new_name = rule.tool.replace(&name, to.as_str()).into_owned();
+                        if new_name != name {
+                            if tools.iter().any(|t| t["name"] == new_name.as_str()) {
+                                tracing::warn!(
+                                    "tool-filter: not renaming {} to {}, name already taken",
+                                    name,
+                                    new_name
+                                );
+                            } else {
+                                tools[index]["name"] = Value::String(new_name.clone());
+                                edits
+                                    .modifications
+                                    .push(format!("Renamed tool {} → {}", name, new_name));
+                                edits.renamed.insert(originals[index].clone(), new_name);
+                            }
+                        }
+                    }
+                    Action::Describe(description) => {
+                        if tools[index]["description"] != description.as_str() {
+                            tools[index]["description"] = Value::String(description.clone());
+                            edits
+                                .modifications
+                                .push(format!("Rewrote description of tool {}", name));
+                        }
+                    }
+                }
+                index += 1;
+            }
+        }
+
+        // A name renamed back to itself is no rename at all
+        edits
+            .renamed
+            .retain(|original, exposed| original != exposed);
+        edits
+    }
+}
+
+// ============================================================================
+// History Rewriting
+// ============================================================================
+
+/// Text of a tool_result `content` (string or blocks)
+fn result_text(content: &Value) -> String {
+    match content {
+        Value::String(s) => s.clone(),
+        Value::Array(blocks) => blocks
+            .iter()
+            .map(|b| match b["type"].as_str() {
+                Some("text") => b["text"].as_str().unwrap_or_default().to_string(),
+                Some(other) => format!("[{}]", other),
+                None => String::new(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+/// Rewrite earlier tool calls to match the edited definitions
+///
+/// Returns the number of blocks rewritten.
+fn rewrite_history(messages: &mut [Value], edits: &ToolEdits) -> usize {
+    // tool_use id → original name, for calls to removed tools
+    let mut removed_calls: HashMap<String, String> = HashMap::new();
+    let mut rewritten = 0;
+
+    for message in messages.iter_mut() {
+        let Some(blocks) = message["content"].as_array_mut() else {
+            continue;
+        };
+        let mut converted = false;
+        for block in blocks.iter_mut() {
+            match block["type"].as_str() {
+                Some("tool_use") => {
+                    let name = block["name"].as_str().unwrap_or_default().to_string();
+                    if edits.removed.contains(&name) {
+                        let id = block["id"].as_str().unwrap_or_default().to_string();
+                        *block = json!({
+                            "type": "text",
+                            "text": format!(
+                                "[Called {} (no longer available): {}]",
+                                name, block["input"]
+                            ),
+                        });
+                        removed_calls.insert(id, name);
+                        converted = true;
+                        rewritten += 1;
+                    } else if let Some(exposed) = edits.renamed.get(&name) {
+                        block["name"] = Value::String(exposed.clone());
+                        rewritten += 1;
+                    }
+                }
+                Some("tool_result") => {
+                    let id = block["tool_use_id"].as_str().unwrap_or_default();
+                    if let Some(name) = removed_calls.get(id) {
+                        let label = if block["is_error"] == true {
+                            "error"
+                        } else {
+                            "result"
+                        };
+                        *block = json!({
+                            "type": "text",
+                            "text": format!("[{} {}: {}]", name, label, result_text(&block["content"])),
+                        });
+                        converted = true;
+                        rewritten += 1;
+                    }
+                }
+                _ => {}
+            }
+        }
+        // Remaining tool_result blocks must stay ahead of any text
+        if converted {
+            blocks.sort_by_key(|b| b["type"] != "tool_result");
+        }
+    }
+    rewritten
+}
+
+/// Point `tool_choice` at the edited definitions (drop it if its tool is gone)
+fn rewrite_tool_choice(body: &mut Value, edits: &ToolEdits, tools_left: bool) {
+    let Some(object) = body.as_object_mut() else {
+        return;
+    };
+    let forced = object
+        .get("tool_choice")
+        .filter(|c| c["type"] == "tool")
+        .and_then(|c| c["name"].as_str())
+        .map(str::to_string);
+    if !tools_left || forced.as_ref().is_some_and(|n| edits.removed.contains(n)) {
+        object.remove("tool_choice");
+    } else if let Some(exposed) = forced.and_then(|n| edits.renamed.get(&n)) {
+        object["tool_choice"]["name"] = Value::String(exposed.clone());
+    }
+}
+
+impl RequestTransformer for ToolFilter {
+    fn name(&self) -> &'static str {
+        "tool-filter"
+    }
+
+    fn should_apply(&self, ctx: &TransformContext) -> bool {
+        ctx.path.ends_with("/messages") && !self.rules.is_empty()
+    }
+
+    fn transform(&self, body: &Value, ctx: &TransformContext) -> TransformResult {
+        let Some(tools) = body["tools"].as_array() else {
+            return TransformResult::Unchanged;
+        };
+        let mut tools = tools.clone();
+        let mut edits = self.edit_tools(&mut tools, ctx);
+        if edits.is_empty() {
+            return TransformResult::Unchanged;
+        }
+
+        let mut new_body = body.clone();
+        let tools_left = !tools.is_empty();
+        new_body["tools"] = Value::Array(tools);
+        rewrite_tool_choice(&mut new_body, &edits, tools_left);
+        if let Some(messages) = new_body["messages"].as_array_mut() {
+            let rewritten = rewrite_history(messages, &edits);
+            if rewritten > 0 {
+                edits
+                    .modifications
+                    .push(format!("Rewrote {} historical tool block(s)", rewritten));
+            }
+        }
+
+        if let Some(renames) = ctx.tool_renames {
+            renames.replace(
+                edits
+                    .renamed
+                    .into_iter()
+                    .map(|(original, exposed)| (exposed, original))
+                    .collect(),
+            );
+        }
+
+        let tokens_before = estimate_json_tokens(body);
+        let tokens_after = estimate_json_tokens(&new_body);
+        TransformResult::modified_with_info(
+            new_body,
+            tokens_before,
+            tokens_after,
+            edits.modifications,
+        )
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(rules: Vec<ToolRuleConfig>) -> ToolFilter {
+        ToolFilter::from_config(&ToolFilterConfig {
+            enabled: true,
+            rules,
+        })
+        .unwrap()
+    }
+
+    fn tool(name: &str) -> Value {
+        json!({"name": name, "description": format!("The {} tool", name), "input_schema": {"type": "object"}})
+    }
+
+    fn request() -> Value {
+        json!({
+            "model": "claude-sonnet-4-20250514",
+            "tools": [tool("Read"), tool("Bash"), tool("Write"), {
+                "name": "Grep",
+                "description": "Search",
+                "input_schema": {"type": "object"},
+                "cache_control": {"type": "ephemeral"}
+            }],
+            "messages": [
+                {"role": "user", "content": "Look around"},
+                {"role": "assistant", "content": [
+                    {"type": "tool_use", "id": "toolu_1", "name": "Bash", "input": {"command": "ls"}},
+                    {"type": "tool_use", "id": "toolu_2", "name": "Read", "input": {"file_path": "a.rs"}}
+                ]},
+                {"role": "user", "content": [
+                    {"type": "tool_result", "tool_use_id": "toolu_1", "content": "a.rs\nb.rs"},
+                    {"type": "tool_result", "tool_use_id": "toolu_2", "content": "fn main() {}"}
+                ]},
+                {"role": "user", "content": "Now review it"}
+            ]
+        })
+    }
+
+    fn transform(filter: &ToolFilter, body: &Value, client: Option<&str>) -> TransformResult {
+        let ctx = TransformContext::new(client, "/v1/messages", None);
+        filter.transform(body, &ctx)
+    }
+
+    fn names(body: &Value) -> Vec<&str> {
+        body["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["name"].as_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_removes_tools_for_matching_client_only() {
+        let filter = filter(vec![ToolRuleConfig::Remove {
+            tool: "Bash|Write|Grep".to_string(),
+            when: Some(WhenCondition {
+                client_id: Some("review-bot".to_string()),
+                ..Default::default()
+            }),
+        }]);
+
+        assert!(matches!(
+            transform(&filter, &request(), Some("dev-1")),
+            TransformResult::Unchanged
+        ));
+
+        let TransformResult::Modified { body, .. } =
+            transform(&filter, &request(), Some("review-bot"))
+        else {
+            panic!("expected Modified");
+        };
+        assert_eq!(names(&body), vec!["Read"]);
+        // Grep's cache breakpoint moved to the last kept tool
+        assert_eq!(body["tools"][0]["cache_control"]["type"], "ephemeral");
+    }
+
+    #[test]
+    fn test_removed_calls_become_text_in_history() {
+        let filter = filter(vec![ToolRuleConfig::Remove {
+            tool: "Bash".to_string(),
+            when: None,
+        }]);
+        let TransformResult::Modified {
+            body,
+            modifications,
+            ..
+        } = transform(&filter, &request(), None)
+        else {
+            panic!("expected Modified");
+        };
+
+        let assistant = &body["messages"][1]["content"];
+        assert_eq!(assistant[0]["type"], "text");
+        assert!(assistant[0]["text"]
+            .as_str()
+            .unwrap()
+            .contains("Called Bash (no longer available)"));
+        assert_eq!(assistant[1]["name"], "Read");
+
+        // The kept result moves ahead of the converted one
+        let results = &body["messages"][2]["content"];
+        assert_eq!(results[0]["tool_use_id"], "toolu_2");
+        assert_eq!(results[1]["text"], "[Bash result: a.rs\nb.rs]");
+        assert!(modifications.contains(&"Rewrote 2 historical tool block(s)".to_string()));
+    }
+
+    #[test]
+    fn test_renames_tools_history_and_choice() {
+        let filter = filter(vec![
+            ToolRuleConfig::Rename {
+                tool: "(Read|Write)".to_string(),
+                to: "File$1".to_string(),
+                when: None,
+            },
+            ToolRuleConfig::Describe {
+                tool: "FileRead".to_string(),
+                description: "Read a file from the repository".to_string(),
+                when: None,
+            },
+        ]);
+        let mut request = request();
+        request["tool_choice"] = json!({"type": "tool", "name": "Read"});
+        let renames = ToolRenames::default();
+        let mut ctx = TransformContext::new(None, "/v1/messages", None);
+        ctx.tool_renames = Some(&renames);
+
+        let TransformResult::Modified { body, .. } = filter.transform(&request, &ctx) else {
+            panic!("expected Modified");
+        };
+        assert_eq!(names(&body), vec!["FileRead", "Bash", "FileWrite", "Grep"]);
+        assert_eq!(
+            body["tools"][0]["description"],
+            "Read a file from the repository"
+        );
+        assert_eq!(body["messages"][1]["content"][1]["name"], "FileRead");
+        assert_eq!(body["messages"][1]["content"][0]["name"], "Bash");
+        assert_eq!(body["tool_choice"]["name"], "FileRead");
+        assert_eq!(renames.original("FileWrite").as_deref(), Some("Write"));
+    }
+
+    #[test]
+    fn test_restores_original_names_in_responses() {
+        let renames = Arc::new(ToolRenames::default());
+        renames.replace(HashMap::from([(
+            "FileRead".to_string(),
+            "Read".to_string(),
+        )]));
+
+        let mut response = json!({"content": [
+            {"type": "text", "text": "Reading"},
+            {"type": "tool_use", "id": "toolu_9", "name": "FileRead", "input": {}}
+        ]});
+        assert!(renames.restore_response(&mut response));
+        assert_eq!(response["content"][1]["name"], "Read");
+
+        let start = "event: content_block_start\ndata: {\"type\":\"content_block_start\",\"index\":1,\"content_block\":{\"type\":\"tool_use\",\"id\":\"toolu_9\",\"name\":\"FileRead\",\"input\":{}}}\n\n";
+        let delta = "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":1,\"delta\":{\"type\":\"input_json_delta\",\"partial_json\":\"{}\"}}\n\n";
+        let mut restorer = ToolNameRestorer::new(renames);
+        let (head, tail) = start.split_at(60);
+        let mut output = restorer.push(head.as_bytes()).to_vec();
+        assert!(output.is_empty(), "incomplete events wait");
+        output.extend_from_slice(&restorer.push(tail.as_bytes()));
+        output.extend_from_slice(&restorer.push(delta.as_bytes()));
+        output.extend_from_slice(&restorer.finish());
+
+        let text = String::from_utf8(output).unwrap();
+        assert!(text.contains("\"name\":\"Read\""));
+        assert!(!text.contains("FileRead"));
+        assert!(text.ends_with(delta));
+    }
+
+    #[test]
+    fn test_invalid_pattern_is_rejected() {
+        let config = ToolFilterConfig {
+            enabled: true,
+            rules: vec![ToolRuleConfig::Remove {
+                tool: "Bash(".to_string(),
+                when: None,
+            }],
+        };
+        assert!(ToolFilter::from_config(&config).is_err());
+    }
+}
@@ -0,0 +1,532 @@
+//! ToolGovernor - Cap the size of old tool results
+//!
+//! A single full-file `Read` or verbose `Bash` run can add tens of thousands of
+//! tokens to every later request. Once the model has acted on a result it
+//! rarely needs all of it again, so this transformer keeps the head and tail
+//! of oversized results and elides the middle:
+//!
+//! ```text
+//! line 1
+//! ...
+//! [… ~11800 tokens elided by aspy · full result archived as toolu_01AbC …]
+//! ...
+//! last line
+//! ```
+//!
+//! # Scope
+//!
+//! Only results older than the last `keep_recent_turns` turns are touched (a
+//! turn starts at a user message without tool results), so the model always
+//! sees recent output in full. Truncation is deterministic, so an unchanged
+//! history truncates identically on every request and stays cacheable.
+//!
+//! # Archiving
+//!
+//! The original content is reported through `TransformContext::truncations`;
+//! the proxy emits a `ToolResultTruncated` event for it and cortex keeps it,
+//! retrievable at `/api/cortex/tool-result/{tool_use_id}`. Each result is
+//! archived once per process: the proxy marks it archived after sending the
+//! event, so a request blocked later in the pipeline reports it again.
+//!
+//! # Example Config
+//!
+//! ```toml
+//! [transformers.tool-governor]
+//! enabled = true
+//! max_tokens = 4000         # default cap per result
+//! keep_recent_turns = 2
+//!
+//! [transformers.tool-governor.tools]
+//! Read = 8000
+//! Bash = 2000
+//! Task = 0                  # 0 = never truncate
+//! ```
+
+use super::{RequestTransformer, TransformContext, TransformResult};
+use crate::tokens::{estimate_json_tokens, estimate_tokens};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Mutex;
+
+/// Archived result ids remembered before the set is reset
+const MAX_ARCHIVED_IDS: usize = 10_000;
+
+// ============================================================================
+// Configuration
+// ============================================================================
+
+/// Configuration for the ToolGovernor transformer
+#[derive(Debug, Clone, Deserialize)]
+pub struct ToolGovernorConfig {
+    /// Whether the transformer is enabled
+    #[serde(default)]
+    pub enabled: bool,
+    /// Token cap for tools without their own limit
+    #[serde(default = "default_max_tokens")]
+    pub max_tokens: u32,
+    /// Per-tool token caps by tool name (0 = never truncate)
+    #[serde(default)]
+    pub tools: BTreeMap<String, u32>,
+    /// Results from this many most recent turns are left intact
+    #[serde(default = "default_keep_recent_turns")]
+    pub keep_recent_turns: usize,
+}
+
+fn default_max_tokens() -> u32 {
+    4000
+}
+
+fn default_keep_recent_turns() -> usize {
+    2
+}
+
+impl Default for ToolGovernorConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_tokens: default_max_tokens(),
+            tools: BTreeMap::new(),
+            keep_recent_turns: default_keep_recent_turns(),
+        }
+    }
+}
+
+// ============================================================================
+// Archived Originals
+// ============================================================================
+
+/// Original content of a truncated tool result
+#[derive(Debug, Clone)]
+pub struct TruncatedResult {
+    pub tool_use_id: String,
+    pub tool_name: String,
+    pub tokens_before: u32,
+    pub tokens_after: u32,
+    /// The tool_result `content` as the client sent it
+    pub original: Value,
+}
+
+/// Results truncated for the first time in one request
+///
+/// Filled by the governor through `TransformContext` and drained by the
+/// proxy into `ToolResultTruncated` events.
+#[derive(Debug, Default)]
+pub struct Truncations {
+    results: Mutex<Vec<TruncatedResult>>,
+}
+
+impl Truncations {
+    fn push(&self, result: TruncatedResult) {
+        if let Ok(mut results) = self.results.lock() {
+            results.push(result);
+        }
+    }
+
+    pub fn take(&self) -> Vec<TruncatedResult> {
+        self.results
+            .lock()
+            .map(|mut r| std::mem::take(&mut *r))
+            .unwrap_or_default()
+    }
+}
+
+// ============================================================================
+// Truncation
+// ============================================================================
+
+/// Keep the head and tail of `text` within roughly `max_tokens`
+///
+/// Returns the truncated text, or None if it already fits.
+fn truncate_text(text: &str, max_tokens: u32, tool_use_id: &str) -> Option<String> {
+    let tokens = estimate_tokens(text);
+    if tokens <= max_tokens {
+        return None;
+    }
+
+    // Share of characters to keep, split evenly between head and tail
+    let total_chars = text.chars().count();
+    let keep_chars = (total_chars as u64 * max_tokens as u64 / tokens as u64) as usize;
+    let half = keep_chars / 2;
+    let head_end = text.char_indices().nth(half).map_or(text.len(), |(i, _)| i);
+    let tail_start = text
+        .char_indices()
+        .nth(total_chars - half)
+        .map_or(text.len(), |(i, _)| i);
+
+    // Cut on line boundaries when one is close by
+    let head_end = match text[..head_end].rfind('\n') {
+        Some(nl) if nl >= head_end / 2 => nl + 1,
+        _ => head_end,
+    };
+    let tail_start = match text[tail_start..].find('\n') {
+        Some(nl) if nl <= (text.len() - tail_start) / 2 => tail_start + nl + 1,
+        _ => tail_start,
+    };
+
+    let head = &text[..head_end];
+    let tail = &text[tail_start..];
+    let elided = estimate_tokens(&text[head_end..tail_start]);
+    Some(format!(
+        "{}\n[… ~{} tokens elided by aspy · full result archived as {} …]\n\n{}",
+        head.trim_end_matches('\n'),
+        elided,
+        tool_use_id,
+        tail
+    ))
+}
+
+/// Truncate a tool_result `content` (string or block array) in place
+///
+/// Text blocks are measured together; when over the cap they're replaced
+/// by one truncated text block at the first text position. Other blocks
+/// (images) are kept.
+fn truncate_content(content: &mut Value, max_tokens: u32, tool_use_id: &str) -> bool {
+    match content {
+        Value::String(text) => match truncate_text(text, max_tokens, tool_use_id) {
+            Some(truncated) => {
+                *text = truncated;
+                true
+            }
+            None => false,
+        },
+        Value::Array(blocks) => {
+            let is_text = |b: &Value| b["type"] == "text";
+            let joined = blocks
+                .iter()
+                .filter(|b| is_text(b))
+                .filter_map(|b| b["text"].as_str())
+                .collect::<Vec<_>>()
+                .join("\n");
+            let Some(truncated) = truncate_text(&joined, max_tokens, tool_use_id) else {
+                return false;
+            };
+            let Some(first) = blocks.iter().position(is_text) else {
+                return false;
+            };
+            blocks[first] = serde_json::json!({"type": "text", "text": truncated});
+            let mut index = 0;
+            blocks.retain(|b| {
+                let keep = index == first || !is_text(b);
+                index += 1;
+                keep
+            });
+            true
+        }
+        _ => false,
+    }
+}
+
+// ============================================================================
+// Transformer
+// ============================================================================
+
+/// Transformer that truncates oversized tool results from earlier turns
+pub struct ToolGovernor {
+    max_tokens: u32,
+    tools: BTreeMap<String, u32>,
+    keep_recent_turns: usize,
+    /// Results whose originals were sent for archiving
+    archived: Mutex<HashSet<String>>,
+}
+
+impl ToolGovernor {
+    /// Create from configuration
+    pub fn new(config: &ToolGovernorConfig) -> Self {
+        Self {
+            max_tokens: config.max_tokens,
+            tools: config.tools.clone(),
+            keep_recent_turns: config.keep_recent_turns,
+            archived: Mutex::new(HashSet::new()),
+        }
+    }
+
+    fn limit_for(&self, tool_name: &str) -> u32 {
+        self.tools
+            .get(tool_name)
+            .copied()
+            .unwrap_or(self.max_tokens)
+    }
+
+    /// Index of the first message in the protected recent turns
+    fn cutoff(&self, messages: &[Value]) -> usize {
+        let turn_starts: Vec<usize> = messages
+            .iter()
+            .enumerate()
+            .filter(|(_, msg)| msg["role"] == "user")
+            .filter(|(_, msg)| match msg["content"].as_array() {
+                Some(blocks) => !blocks.iter().any(|b| b["type"] == "tool_result"),
+                None => true,
+            })
+            .map(|(i, _)| i)
+            .collect();
+        if self.keep_recent_turns == 0 {
+            return messages.len();
+        }
+        match turn_starts.len().checked_sub(self.keep_recent_turns) {
+            Some(index) => turn_starts[index],
+            None => 0,
+        }
+    }
+
+    fn is_archived(&self, tool_use_id: &str) -> bool {
+        self.archived
+            .lock()
+            .map_or(true, |archived| archived.contains(tool_use_id))
+    }
+
+    /// Remember a result as archived once its `ToolResultTruncated` event is sent
+    pub fn mark_archived(&self, tool_use_id: &str) {
+        let Ok(mut archived) = self.archived.lock() else {
+            return;
+        };
+        if archived.len() >= MAX_ARCHIVED_IDS {
+            archived.clear();
+        }
+        archived.insert(tool_use_id.to_string());
+    }
+}
+
+impl RequestTransformer for ToolGovernor {
+    fn name(&self) -> &'static str {
+        "tool-governor"
+    }
+
+    fn should_apply(&self, ctx: &TransformContext) -> bool {
+        ctx.path.ends_with("/messages")
+    }
+
+    fn transform(&self, body: &Value, ctx: &TransformContext) -> TransformResult {
+        let Some(messages) = body["messages"].as_array() else {
+            return TransformResult::Unchanged;
+        };
+        let cutoff = self.cutoff(messages);
+        if cutoff == 0 {
+            return TransformResult::Unchanged;
+        }
+
+        // tool_use id → tool name (tool_result blocks only carry the id)
+        let tool_names: HashMap<&str, &str> = messages
+            .iter()
+            .filter_map(|msg| msg["content"].as_array())
+            .flatten()
+            .filter(|b| b["type"] == "tool_use")
+            .filter_map(|b| Some((b["id"].as_str()?, b["name"].as_str()?)))
+            .collect();
+
+        let mut new_body = body.clone();
+        let mut modifications = Vec::new();
+        let new_messages = new_body["messages"].as_array_mut().expect("checked above");
+        for message in new_messages.iter_mut().take(cutoff) {
+            let Some(blocks) = message["content"].as_array_mut() else {
+                continue;
+            };
+            for block in blocks.iter_mut().filter(|b| b["type"] == "tool_result") {
+                let id = block["tool_use_id"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string();
+                let tool_name = tool_names.get(id.as_str()).copied().unwrap_or("unknown");
+                let limit = self.limit_for(tool_name);
+                if limit == 0 {
+                    continue;
+                }
+                let original = block["content"].clone();
+                if !truncate_content(&mut block["content"], limit, &id) {
+                    continue;
+                }
+
+                let tokens_before = estimate_json_tokens(&original);
+                let tokens_after = estimate_json_tokens(&block["content"]);
+                modifications.push(format!(
+                    "Truncated {} result {} (~{} → ~{} tokens)",
+                    tool_name, id, tokens_before, tokens_after
+                ));
+                if let Some(truncations) = ctx.truncations {
+                    if !self.is_archived(&id) {
+                        truncations.push(TruncatedResult {
+                            tool_use_id: id.clone(),
+                            tool_name: tool_name.to_string(),
+                            tokens_before,
+                            tokens_after,
+                            original,
+                        });
+                    }
+                }
+            }
+        }
+
+        if modifications.is_empty() {
+            return TransformResult::Unchanged;
+        }
+        let tokens_before = estimate_json_tokens(body);
+        let tokens_after = estimate_json_tokens(&new_body);
+        TransformResult::modified_with_info(new_body, tokens_before, tokens_after, modifications)
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn output(lines: usize) -> String {
+        (1..=lines)
+            .map(|i| format!("line {} of the build output", i))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Conversation with one Bash call per turn, then `extra_turns` plain prompts
+    fn conversation(turns: usize, extra_turns: usize) -> Value {
+        let mut messages = Vec::new();
+        for t in 0..turns {
+            let id = format!("toolu_{}", t);
+            messages.push(json!({"role": "user", "content": format!("prompt {}", t)}));
+            messages.push(json!({"role": "assistant", "content": [
+                {"type": "tool_use", "id": id, "name": "Bash", "input": {"command": "make"}}
+            ]}));
+            messages.push(json!({"role": "user", "content": [
+                {"type": "tool_result", "tool_use_id": id, "content": output(2000)}
+            ]}));
+            messages.push(json!({"role": "assistant", "content": "done"}));
+        }
+        for t in 0..extra_turns {
+            messages.push(json!({"role": "user", "content": format!("follow-up {}", t)}));
+        }
+        json!({"messages": messages})
+    }
+
+    fn governor(config: ToolGovernorConfig) -> ToolGovernor {
+        ToolGovernor::new(&ToolGovernorConfig {
+            enabled: true,
+            ..config
+        })
+    }
+
+    fn result_text(body: &Value, turn: usize) -> &str {
+        body["messages"][turn * 4 + 2]["content"][0]["content"]
+            .as_str()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_truncates_head_and_tail() {
+        let text = output(2000);
+        let truncated = truncate_text(&text, 500, "toolu_x").unwrap();
+
+        assert!(estimate_tokens(&truncated) < 600, "close to the cap");
+        assert!(truncated.starts_with("line 1 of"));
+        assert!(truncated.ends_with("line 2000 of the build output"));
+        assert!(truncated.contains("full result archived as toolu_x"));
+        // Cut on line boundaries
+        let marker_line = truncated.lines().position(|l| l.starts_with("[…")).unwrap();
+        let before = truncated.lines().nth(marker_line - 1).unwrap();
+        assert!(before.ends_with("of the build output"), "{}", before);
+
+        assert!(truncate_text("short", 500, "toolu_x").is_none());
+    }
+
+    #[test]
+    fn test_only_older_turns_truncated() {
+        let governor = governor(ToolGovernorConfig::default());
+        let body = conversation(3, 0);
+        let truncations = Truncations::default();
+        let mut ctx = TransformContext::new(None, "/v1/messages", None);
+        ctx.truncations = Some(&truncations);
+
+        let TransformResult::Modified {
+            body: new_body,
+            tokens,
+            modifications,
+            ..
+        } = governor.transform(&body, &ctx)
+        else {
+            panic!("expected Modified");
+        };
+
+        // Turns 1 and 2 are the two most recent; only turn 0 is truncated
+        assert!(result_text(&new_body, 0).contains("tokens elided"));
+        assert_eq!(result_text(&new_body, 1), output(2000));
+        assert_eq!(result_text(&new_body, 2), output(2000));
+        assert_eq!(modifications.len(), 1);
+        assert!(modifications[0].starts_with("Truncated Bash result toolu_0"));
+        assert!(tokens.unwrap().delta() < -10_000);
+
+        let archived = truncations.take();
+        assert_eq!(archived.len(), 1);
+        assert_eq!(archived[0].original, json!(output(2000)));
+
+        // Not marked yet (e.g. the request was blocked later): reported again
+        governor.transform(&body, &ctx);
+        assert_eq!(truncations.take().len(), 1);
+
+        // Already archived: truncated again, but not reported twice
+        governor.mark_archived(&archived[0].tool_use_id);
+        assert!(matches!(
+            governor.transform(&body, &ctx),
+            TransformResult::Modified { .. }
+        ));
+        assert!(truncations.take().is_empty());
+
+        // Too few turns to have anything old
+        assert!(matches!(
+            governor.transform(&conversation(2, 0), &ctx),
+            TransformResult::Unchanged
+        ));
+    }
+
+    #[test]
+    fn test_per_tool_limits() {
+        let body = conversation(1, 2);
+
+        let exempt = governor(ToolGovernorConfig {
+            tools: [("Bash".to_string(), 0)].into(),
+            ..Default::default()
+        });
+        let ctx = TransformContext::new(None, "/v1/messages", None);
+        assert!(matches!(
+            exempt.transform(&body, &ctx),
+            TransformResult::Unchanged
+        ));
+
+        let generous = governor(ToolGovernorConfig {
+            max_tokens: 100,
+            tools: [("Bash".to_string(), 1_000_000)].into(),
+            ..Default::default()
+        });
+        assert!(matches!(
+            generous.transform(&body, &ctx),
+            TransformResult::Unchanged
+        ));
+
+        let strict = governor(ToolGovernorConfig {
+            tools: [("Bash".to_string(), 200)].into(),
+            ..Default::default()
+        });
+        let TransformResult::Modified { body, .. } = strict.transform(&body, &ctx) else {
+            panic!("expected Modified");
+        };
+        assert!(estimate_tokens(result_text(&body, 0)) < 300);
+    }
+
+    #[test]
+    fn test_block_array_content() {
+        let mut content = json!([
+            {"type": "text", "text": output(1000)},
+            {"type": "image", "source": {"type": "base64", "data": "AAAA"}},
+            {"type": "text", "text": output(1000)}
+        ]);
+        assert!(truncate_content(&mut content, 300, "toolu_x"));
+
+        let blocks = content.as_array().unwrap();
+        assert_eq!(blocks.len(), 2, "text merged, image kept");
+        assert_eq!(blocks[0]["type"], "text");
+        assert!(blocks[0]["text"].as_str().unwrap().contains("elided"));
+        assert_eq!(blocks[1]["type"], "image");
+    }
+}
@@ -21,6 +21,8 @@
 //! let count = estimate_tokens(text);
 //! ```
 
+use serde::{Deserialize, Serialize};
+
 /// Estimate token count for text content
 ///
 /// Uses a multi-factor heuristic:
@@ -116,7 +118,8 @@ impl TokenDelta {
 }
 
 /// Statistics for injection/removal tracking
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct TransformStats {
     /// Total tokens injected by transformers
     pub tokens_injected: u64,
@@ -124,6 +127,33 @@ pub struct TransformStats {
     pub tokens_removed: u64,
     /// Per-transformer breakdown: name -> (injected, removed)
     pub by_transformer: std::collections::HashMap<String, (u64, u64)>,
+    /// Prompt-cache usage of requests the cache optimizer modified
+    pub cache_optimized: CacheUsage,
+    /// Prompt-cache usage of all other requests (the comparison baseline)
+    pub cache_baseline: CacheUsage,
+}
+
+/// Prompt-cache usage for a group of requests
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct CacheUsage {
+    pub requests: u64,
+    /// Uncached input tokens
+    pub input_tokens: u64,
+    pub cache_read_tokens: u64,
+}
+
+impl CacheUsage {
+    /// Share of input served from cache (percent), None with no input yet
+    pub fn hit_rate(&self) -> Option<f64> {
+        let total = self.input_tokens + self.cache_read_tokens;
+        (total > 0).then(|| self.cache_read_tokens as f64 / total as f64 * 100.0)
+    }
+
+    pub fn merge(&mut self, other: &CacheUsage) {
+        self.requests += other.requests;
+        self.input_tokens += other.input_tokens;
+        self.cache_read_tokens += other.cache_read_tokens;
+    }
 }
 
 impl TransformStats {
@@ -146,10 +176,34 @@ impl TransformStats {
     pub fn net_delta(&self) -> i64 {
         self.tokens_injected as i64 - self.tokens_removed as i64
     }
+
+    /// Record a request's cache usage against the optimized or baseline group
+    pub fn record_cache_usage(
+        &mut self,
+        optimized: bool,
+        input_tokens: u32,
+        cache_read_tokens: u32,
+    ) {
+        let group = if optimized {
+            &mut self.cache_optimized
+        } else {
+            &mut self.cache_baseline
+        };
+        group.requests += 1;
+        group.input_tokens += input_tokens as u64;
+        group.cache_read_tokens += cache_read_tokens as u64;
+    }
+
+    /// Cache hit rate of optimized requests minus the baseline (percentage
+    /// points), None until both groups have usage
+    pub fn cache_hit_delta(&self) -> Option<f64> {
+        Some(self.cache_optimized.hit_rate()? - self.cache_baseline.hit_rate()?)
+    }
 }
 
 /// Statistics for augmentation tracking
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AugmentStats {
     /// Total tokens injected by augmenters
     pub tokens_injected: u64,
@@ -234,4 +288,19 @@ mod tests {
         assert_eq!(stats.tokens_injected, 100);
         assert_eq!(stats.net_delta(), 80);
     }
+
+    #[test]
+    fn test_cache_hit_delta() {
+        let mut stats = TransformStats::default();
+        stats.record_cache_usage(false, 1000, 0);
+        assert!(stats.cache_hit_delta().is_none(), "no optimized usage yet");
+
+        stats.record_cache_usage(true, 250, 750);
+        assert_eq!(stats.cache_optimized.requests, 1);
+        assert_eq!(stats.cache_hit_delta(), Some(75.0));
+
+        stats.record_cache_usage(false, 500, 500);
+        assert_eq!(stats.cache_baseline.requests, 2);
+        assert_eq!(stats.cache_hit_delta(), Some(75.0 - 500.0 / 20.0));
+    }
 }
@@ -4,7 +4,7 @@
 // of events, selected item, statistics, and UI state.
 
 use super::components::detail_panel::DetailPanel;
-use super::components::events_panel::EventsPanel;
+use super::components::events_panel::{AgentTree, EventsPanel};
 use super::components::logs_panel::LogsPanel;
 use super::components::settings_panel::SettingsPanel;
 // Re-export SettingsCategory (used in settings_apply_option)
@@ -20,6 +20,7 @@ use super::traits::{Handled, Interactive, Zoomable};
 use crate::config::Config;
 use crate::events::{ProxyEvent, Stats, TrackedEvent};
 use crate::logging::LogBuffer;
+use crate::proxy::breakpoints::{BreakpointDecision, SharedBreakpoints};
 use crate::proxy::sessions::ContextState;
 use crate::theme::{Theme, ThemeConfig};
 use crate::StreamingThinking;
@@ -199,6 +200,9 @@ pub struct App {
     /// Real-time streaming thinking content (shared with proxy)
     pub streaming_thinking: Option<StreamingThinking>,
 
+    /// Request breakpoints (shared with proxy, None when not configured)
+    pub breakpoints: Option<SharedBreakpoints>,
+
     // ─────────────────────────────────────────────────────────────────────────
     // Lifecycle
     // Application lifecycle state
@@ -278,6 +282,7 @@ impl App {
             streaming_session: None,
             animation_frame: 0,
             streaming_thinking: None,
+            breakpoints: None,
             modal: None,
             toast: None,
             preset,
@@ -527,6 +532,9 @@ impl App {
                 self.streaming_sm.on_response();
                 self.streaming_session = None; // Clear on idle
             }
+            ProxyEvent::UpstreamRetry { delay_ms, .. } => {
+                self.stats.record_retry(*delay_ms);
+            }
             ProxyEvent::ToolCall { tool_name, .. } => {
                 self.stats.total_tool_calls += 1;
                 // Track tool calls by name for distribution
@@ -717,6 +725,9 @@ impl App {
             shared.push(event.clone());
         }
 
+        // Track the subagent tree for indentation and folding
+        self.events_panel.agents.observe(&tracked_event);
+
         // Store the full TrackedEvent (includes user_id, session_id for filtering)
         self.events.push(tracked_event);
         // In auto-follow mode (None), we don't need to track selection
@@ -843,16 +854,54 @@ impl App {
     /// Get filtered events for current session
     ///
     /// Returns references to events matching the currently selected session.
-    /// If no session is selected, returns all events.
+    /// If no session is selected, returns all events. Events inside collapsed
+    /// subagents are left out.
     pub fn filtered_events(&self) -> Vec<&TrackedEvent> {
-        match self.effective_session() {
-            Some(session) => self
-                .events
-                .iter()
-                .filter(|e| e.user_id.as_deref() == Some(session))
-                .collect(),
-            None => self.events.iter().collect(), // Show all if no session
+        let session = self.effective_session();
+        self.events
+            .iter()
+            .filter(|e| session.is_none() || e.user_id.as_deref() == session)
+            .filter(|e| !self.events_panel.agents.is_hidden(e))
+            .collect()
+    }
+
+    /// Index into `events` of the selected row (latest row in auto-follow mode)
+    pub fn selected_event_index(&self) -> Option<usize> {
+        let filtered = self.filtered_events();
+        let row = self
+            .events_panel
+            .selected
+            .or_else(|| filtered.len().checked_sub(1))?;
+        let selected = *filtered.get(row)?;
+        self.events.iter().position(|e| std::ptr::eq(e, selected))
+    }
+
+    /// Collapse or expand the subagent at the selected row
+    ///
+    /// On a Task call this folds the subagent it spawned; on an event inside a
+    /// subagent it folds that subagent. Selection follows the Task call row.
+    pub fn toggle_selected_subagent(&mut self) {
+        let Some(tracked) = self.selected_event_index().map(|idx| &self.events[idx]) else {
+            return;
+        };
+        let Some(agent) = AgentTree::spawned_agent(tracked)
+            .or(tracked.agent_id.as_deref())
+            .map(str::to_string)
+        else {
+            return;
+        };
+
+        self.events_panel.agents.toggle(&agent);
+
+        let filtered = self.filtered_events();
+        let task_row = filtered
+            .iter()
+            .position(|e| AgentTree::spawned_agent(e) == Some(agent.as_str()));
+        let count = filtered.len();
+        if task_row.is_some() {
+            self.events_panel.selected = task_row;
         }
+        self.events_panel.sync_events(count);
     }
 
     /// Cycle to next session (wraps around)
@@ -936,6 +985,67 @@ impl App {
         self.settings_panel.handle_key(key)
     }
 
+    // ─────────────────────────────────────────────────────────────
+    // Breakpoints
+    // ─────────────────────────────────────────────────────────────
+
+    /// Open the next held request, or close a modal whose hold timed out
+    ///
+    /// Held requests take priority over other modals - a client is waiting.
+    pub fn sync_breakpoints(&mut self) {
+        let Some(hub) = self.breakpoints.clone() else {
+            return;
+        };
+
+        if let Some(id) = self.modal.as_ref().and_then(|m| m.breakpoint_id()) {
+            if !hub.is_pending(id) {
+                self.detail_panel.reset();
+                self.modal = None;
+                self.show_toast("⏱ Breakpoint timed out - request continued");
+            }
+            return;
+        }
+
+        if let Some(request) = hub.next_pending() {
+            self.detail_panel.reset();
+            // Cached for clipboard copy (y)
+            self.detail_panel.set_content(request.body.clone());
+            self.modal = Some(Modal::breakpoint(request));
+        }
+    }
+
+    /// Send the TUI's decision for a held request and close its modal
+    pub fn resolve_breakpoint(&mut self, id: u64, decision: BreakpointDecision) {
+        let Some(hub) = self.breakpoints.clone() else {
+            return;
+        };
+        let message = match &decision {
+            BreakpointDecision::Continue { body: Some(_) } => "▶ Request continued (edited)",
+            BreakpointDecision::Continue { body: None } => "▶ Request continued",
+            BreakpointDecision::Reject { .. } => "✗ Request rejected",
+        };
+        self.detail_panel.reset();
+        self.modal = None;
+        if hub.resolve(id, decision) {
+            self.show_toast(message);
+        } else {
+            self.show_toast("⏱ Breakpoint already timed out");
+        }
+    }
+
+    /// Arm or disarm breakpoints (no-op when none are configured)
+    pub fn toggle_breakpoints(&mut self) {
+        let Some(hub) = self.breakpoints.clone() else {
+            self.show_toast("No breakpoints configured");
+            return;
+        };
+        if hub.toggle_armed() {
+            self.show_toast("⏸ Breakpoints armed");
+        } else {
+            self.show_toast("Breakpoints disarmed");
+        }
+    }
+
     // ─────────────────────────────────────────────────────────────
     // Toast Notifications
     // ─────────────────────────────────────────────────────────────
@@ -970,7 +1080,8 @@ impl App {
         match self.focused {
             FocusablePanel::Events => {
                 // Delegate to component
-                self.events_panel.copy_text_with_events(&self.events)
+                self.events_panel
+                    .copy_text_with_events(&self.filtered_events())
             }
             FocusablePanel::Thinking => {
                 // Copy current thinking content
@@ -991,7 +1102,8 @@ impl App {
     pub fn copy_current_jsonl(&self) -> Option<String> {
         // JSONL only makes sense for events (modal handles its own copy)
         if self.focused == FocusablePanel::Events {
-            self.events_panel.copy_data_with_events(&self.events)
+            self.events_panel
+                .copy_data_with_events(&self.filtered_events())
         } else {
             None
         }
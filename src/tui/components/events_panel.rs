@@ -11,9 +11,17 @@
 //!
 //! This eliminates the need for explicit "follow latest" toggle - pressing G (scroll to bottom)
 //! naturally returns to auto-follow by setting selected = None.
+//!
+//! # Subagent Tree
+//!
+//! Events from subagent (Task tool) conversations are indented under the Task
+//! call that spawned them. Collapsing a subagent hides its events (and those of
+//! its nested subagents) from the filtered list, so selection indices always
+//! refer to visible rows.
 
 use super::scrollbar::{render_scrollbar_raw, ScrollbarStyle};
 use crate::events::{ProxyEvent, TrackedEvent};
+use crate::proxy::subagents::is_subagent_tool;
 use crate::theme::Theme;
 use crate::tui::scroll::{FocusablePanel, ScrollState};
 use crate::tui::traits::{
@@ -28,6 +36,7 @@ use ratatui::{
     widgets::{Block, Borders, List, ListItem},
     Frame,
 };
+use std::collections::{HashMap, HashSet};
 use unicode_width::UnicodeWidthStr;
 
 /// Events panel component
@@ -45,17 +54,90 @@ pub struct EventsPanel {
     /// Public so App can sync it before delegating operations
     pub event_count: usize,
 
+    /// Subagent hierarchy and collapsed state
+    pub agents: AgentTree,
+
     /// Scroll state (unused for EventsPanel - exists for trait compliance)
     /// EventsPanel uses selection-based scrolling, not ScrollState
     _scroll: ScrollState,
 }
 
+/// Subagent hierarchy seen in the event stream
+///
+/// Built from Task tool calls: the call's id names the subagent and the
+/// call's own `agent_id` is its parent.
+#[derive(Debug, Default)]
+pub struct AgentTree {
+    /// Subagent id → spawning subagent (None = main conversation)
+    parents: HashMap<String, Option<String>>,
+    /// Subagents whose events are hidden
+    collapsed: HashSet<String>,
+}
+
+impl AgentTree {
+    /// Subagent spawned by an event (if it's a Task tool call)
+    pub fn spawned_agent(tracked: &TrackedEvent) -> Option<&str> {
+        match &tracked.event {
+            ProxyEvent::ToolCall { id, tool_name, .. } if is_subagent_tool(tool_name) => {
+                Some(id.as_str())
+            }
+            _ => None,
+        }
+    }
+
+    /// Record subagents spawned by an incoming event
+    pub fn observe(&mut self, tracked: &TrackedEvent) {
+        if let Some(id) = Self::spawned_agent(tracked) {
+            self.parents
+                .insert(id.to_string(), tracked.agent_id.clone());
+        }
+    }
+
+    /// The subagent and its ancestors, innermost first
+    fn lineage<'a>(&'a self, agent: Option<&'a str>) -> Vec<&'a str> {
+        let mut lineage = Vec::new();
+        let mut current = agent;
+        // Bounded so a malformed parent chain can't loop
+        while let Some(id) = current.filter(|_| lineage.len() <= self.parents.len()) {
+            lineage.push(id);
+            current = self.parents.get(id).and_then(|p| p.as_deref());
+        }
+        lineage
+    }
+
+    /// Nesting depth of an event (0 = main conversation)
+    pub fn depth(&self, tracked: &TrackedEvent) -> usize {
+        self.lineage(tracked.agent_id.as_deref()).len()
+    }
+
+    /// Whether an event sits inside a collapsed subagent
+    pub fn is_hidden(&self, tracked: &TrackedEvent) -> bool {
+        !self.collapsed.is_empty()
+            && self
+                .lineage(tracked.agent_id.as_deref())
+                .iter()
+                .any(|id| self.collapsed.contains(*id))
+    }
+
+    pub fn is_collapsed(&self, agent_id: &str) -> bool {
+        self.collapsed.contains(agent_id)
+    }
+
+    /// Collapse or expand a subagent
+    pub fn toggle(&mut self, agent_id: &str) {
+        if !self.collapsed.remove(agent_id) {
+            self.collapsed.insert(agent_id.to_string());
+        }
+    }
+}
+
 impl EventsPanel {
     /// Create a new events panel with auto-follow enabled
     pub fn new() -> Self {
         Self {
             selected: None, // Auto-follow by default
             event_count: 0,
+            agents: AgentTree::default(),
             _scroll: ScrollState::new(), // Unused - for trait compliance
         }
     }
@@ -162,6 +244,7 @@ impl EventsPanel {
                 let is_selected = self.selected == Some(actual_idx);
 
                 let mut line = format_event_line(tracked);
+                self.indent_line(&mut line, tracked);
 
                 // Truncate with ellipsis if line exceeds available width
                 // Use unicode display width (not byte length) for accurate column calculation
@@ -230,6 +313,27 @@ impl EventsPanel {
     }
 }
 
+impl EventsPanel {
+    /// Indent a subagent's events and mark Task calls as expandable
+    ///
+    /// The tree guides go after the `[HH:MM:SS] ` timestamp so times stay aligned.
+    fn indent_line(&self, line: &mut String, tracked: &TrackedEvent) {
+        let depth = self.agents.depth(tracked);
+        let marker = match AgentTree::spawned_agent(tracked) {
+            Some(id) if self.agents.is_collapsed(id) => "▸ ",
+            Some(_) => "▾ ",
+            None => "",
+        };
+        if depth == 0 && marker.is_empty() {
+            return;
+        }
+
+        let prefix = format!("{}{}", "│ ".repeat(depth), marker);
+        let at = line.find("] ").map(|i| i + 2).unwrap_or(0);
+        line.insert_str(at, &prefix);
+    }
+}
+
 impl Default for EventsPanel {
     fn default() -> Self {
         Self::new()
@@ -286,7 +390,7 @@ impl Scrollable for EventsPanel {
             None => {
                 // Enter selection mode at last item, then move up
                 let last = self.event_count.saturating_sub(1);
-                self.selected = Some(last.saturating_sub(1).max(0));
+                self.selected = Some(last.saturating_sub(1));
             }
             Some(idx) if idx > 0 => {
                 self.selected = Some(idx - 1);
@@ -421,7 +525,7 @@ impl Interactive for EventsPanel {
     }
 
     fn focus_hint(&self) -> Option<&'static str> {
-        Some("↑↓:select  g/G:top/end  Enter:detail  y:copy  z:zoom  Esc:follow")
+        Some("↑↓:select  g/G:top/end  Enter:detail  Space:fold  y:copy  z:zoom  Esc:follow")
     }
 }
 
@@ -434,14 +538,14 @@ impl Zoomable for EventsPanel {
 // Helper methods for copy operations that need event data
 impl EventsPanel {
     /// Get formatted text for the selected event (for clipboard)
-    pub fn copy_text_with_events(&self, events: &[TrackedEvent]) -> Option<String> {
+    pub fn copy_text_with_events(&self, events: &[&TrackedEvent]) -> Option<String> {
         self.selected
             .and_then(|idx| events.get(idx))
-            .map(format_event_line)
+            .map(|tracked| format_event_line(tracked))
     }
 
     /// Get JSONL for the selected event (for clipboard)
-    pub fn copy_data_with_events(&self, events: &[TrackedEvent]) -> Option<String> {
+    pub fn copy_data_with_events(&self, events: &[&TrackedEvent]) -> Option<String> {
         self.selected
             .and_then(|idx| events.get(idx))
             .and_then(|tracked| serde_json::to_string(&tracked.event).ok())
@@ -489,9 +593,26 @@ fn event_color_style(event: &ProxyEvent, theme: &Theme) -> Style {
         ProxyEvent::RequestTransformed { .. } => Style::default()
             .fg(theme.api_usage)
             .add_modifier(Modifier::DIM),
+        ProxyEvent::ToolResultTruncated { .. } => Style::default()
+            .fg(theme.api_usage)
+            .add_modifier(Modifier::DIM),
         ProxyEvent::ResponseAugmented { .. } => Style::default()
             .fg(theme.api_usage)
             .add_modifier(Modifier::DIM),
+        ProxyEvent::ModelRouted { .. } => Style::default()
+            .fg(theme.api_usage)
+            .add_modifier(Modifier::DIM),
+        ProxyEvent::ProviderFailover { .. } => Style::default()
+            .fg(theme.rate_limit)
+            .add_modifier(Modifier::BOLD),
+        ProxyEvent::UpstreamRetry { .. } => Style::default().fg(theme.rate_limit),
+        ProxyEvent::ToolPolicyDecision { action, .. } if action == "deny" => {
+            Style::default().fg(theme.error)
+        }
+        ProxyEvent::ToolPolicyDecision { .. } => Style::default().fg(theme.context_bar_warn),
+        ProxyEvent::ShadowComparison { .. } => Style::default()
+            .fg(theme.api_usage)
+            .add_modifier(Modifier::DIM),
         ProxyEvent::PreCompactHook { .. } => Style::default()
             .fg(theme.context_compact)
             .add_modifier(Modifier::BOLD),
@@ -504,6 +625,13 @@ fn event_color_style(event: &ProxyEvent, theme: &Theme) -> Style {
         ProxyEvent::ContextEstimate { .. } => Style::default()
             .fg(theme.context_compact)
             .add_modifier(Modifier::DIM),
+        ProxyEvent::SubagentCompleted { success, .. } => {
+            if *success {
+                Style::default().fg(theme.tool_result_ok)
+            } else {
+                Style::default().fg(theme.tool_result_fail)
+            }
+        }
     }
 }
 
@@ -534,3 +662,61 @@ pub fn render_filtered(
 ) {
     events_panel.render_with_filtered_events(f, area, events, theme, focused);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn task_call(id: &str, agent: Option<&str>) -> TrackedEvent {
+        TrackedEvent::anonymous(ProxyEvent::ToolCall {
+            id: id.to_string(),
+            timestamp: Utc::now(),
+            tool_name: "Task".to_string(),
+            input: serde_json::json!({"prompt": "Look around"}),
+        })
+        .with_agent(agent.map(str::to_string))
+    }
+
+    fn prompt_in(agent: Option<&str>) -> TrackedEvent {
+        TrackedEvent::anonymous(ProxyEvent::UserPrompt {
+            timestamp: Utc::now(),
+            content: "hi".to_string(),
+        })
+        .with_agent(agent.map(str::to_string))
+    }
+
+    #[test]
+    fn test_agent_tree_depth_and_folding() {
+        let mut tree = AgentTree::default();
+        tree.observe(&task_call("outer", None));
+        tree.observe(&task_call("inner", Some("outer")));
+
+        assert_eq!(tree.depth(&prompt_in(None)), 0);
+        assert_eq!(tree.depth(&prompt_in(Some("outer"))), 1);
+        assert_eq!(tree.depth(&prompt_in(Some("inner"))), 2);
+
+        // Folding a subagent hides its events and its nested subagents' events
+        tree.toggle("outer");
+        assert!(tree.is_hidden(&prompt_in(Some("outer"))));
+        assert!(tree.is_hidden(&prompt_in(Some("inner"))));
+        assert!(!tree.is_hidden(&task_call("outer", None)));
+
+        tree.toggle("outer");
+        assert!(!tree.is_hidden(&prompt_in(Some("inner"))));
+    }
+
+    #[test]
+    fn test_indent_keeps_timestamp_first() {
+        let mut panel = EventsPanel::new();
+        panel.agents.observe(&task_call("outer", None));
+
+        let mut line = "[12:00:00] 💬 User: hi".to_string();
+        panel.indent_line(&mut line, &prompt_in(Some("outer")));
+        assert_eq!(line, "[12:00:00] │ 💬 User: hi");
+
+        let mut line = "[12:00:00] 🔧 Tool Call: Task (outer)".to_string();
+        panel.indent_line(&mut line, &task_call("outer", None));
+        assert_eq!(line, "[12:00:00] ▾ 🔧 Tool Call: Task (outer)");
+    }
+}
@@ -18,6 +18,7 @@ pub mod scrollbar;
 pub mod session_gauges_panel;
 pub mod settings_panel;
 pub mod status_bar;
+pub mod text_editor;
 pub mod theme_list_panel;
 pub mod thinking_panel;
 pub mod title_bar;
@@ -0,0 +1,322 @@
+// Text editor component - minimal multi-line editing for modals
+//
+// Owns the text buffer, cursor and viewport offsets. Rendering callers ask
+// for the visible slice after `scroll_into_view` so the cursor stays on
+// screen. Columns are counted in chars, not bytes, so non-ASCII content
+// edits correctly.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// Spaces inserted for Tab (JSON bodies are pretty-printed with 2)
+const TAB_WIDTH: usize = 2;
+
+/// Multi-line text buffer with a cursor
+#[derive(Debug, Clone, Default)]
+pub struct TextEditor {
+    lines: Vec<String>,
+    /// Cursor line
+    row: usize,
+    /// Cursor column (in chars)
+    col: usize,
+    /// First visible line
+    top: usize,
+    /// First visible column
+    left: usize,
+}
+
+impl TextEditor {
+    pub fn new(text: &str) -> Self {
+        let mut lines: Vec<String> = text.lines().map(str::to_string).collect();
+        if lines.is_empty() {
+            lines.push(String::new());
+        }
+        Self {
+            lines,
+            ..Default::default()
+        }
+    }
+
+    /// Current buffer contents
+    pub fn text(&self) -> String {
+        self.lines.join("\n")
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// Cursor position as (line, column)
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.row, self.col)
+    }
+
+    /// First visible line
+    pub fn top(&self) -> usize {
+        self.top
+    }
+
+    /// Handle an editing key, returns true if the buffer changed
+    pub fn handle_key(&mut self, key: &KeyEvent) -> bool {
+        if key
+            .modifiers
+            .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+        {
+            return false;
+        }
+        match key.code {
+            KeyCode::Char(c) => {
+                self.insert_char(c);
+                true
+            }
+            KeyCode::Tab => {
+                for _ in 0..TAB_WIDTH {
+                    self.insert_char(' ');
+                }
+                true
+            }
+            KeyCode::Enter => {
+                self.insert_newline();
+                true
+            }
+            KeyCode::Backspace => self.backspace(),
+            KeyCode::Delete => self.delete(),
+            KeyCode::Left => {
+                self.move_left();
+                false
+            }
+            KeyCode::Right => {
+                self.move_right();
+                false
+            }
+            KeyCode::Up => {
+                self.move_vertical(-1);
+                false
+            }
+            KeyCode::Down => {
+                self.move_vertical(1);
+                false
+            }
+            KeyCode::PageUp => {
+                self.move_vertical(-20);
+                false
+            }
+            KeyCode::PageDown => {
+                self.move_vertical(20);
+                false
+            }
+            KeyCode::Home => {
+                self.col = 0;
+                false
+            }
+            KeyCode::End => {
+                self.col = self.line_len(self.row);
+                false
+            }
+            _ => false,
+        }
+    }
+
+    /// Scroll the viewport without moving the cursor (read-only viewing)
+    pub fn scroll_by(&mut self, delta: isize) {
+        let max_top = self.lines.len().saturating_sub(1);
+        self.top = self.top.saturating_add_signed(delta).min(max_top);
+    }
+
+    /// Jump the viewport to the top or bottom
+    pub fn scroll_to(&mut self, top: usize) {
+        self.top = top.min(self.lines.len().saturating_sub(1));
+    }
+
+    /// Put the cursor at the start of the first visible line
+    pub fn cursor_to_top(&mut self) {
+        self.row = self.top;
+        self.col = 0;
+    }
+
+    /// Adjust viewport offsets so the cursor is visible
+    pub fn scroll_into_view(&mut self, height: usize, width: usize) {
+        if height > 0 {
+            if self.row < self.top {
+                self.top = self.row;
+            } else if self.row >= self.top + height {
+                self.top = self.row + 1 - height;
+            }
+        }
+        if width > 0 {
+            if self.col < self.left {
+                self.left = self.col;
+            } else if self.col >= self.left + width {
+                self.left = self.col + 1 - width;
+            }
+        }
+    }
+
+    /// Visible lines clipped to the viewport
+    pub fn visible_lines(&self, height: usize, width: usize) -> Vec<String> {
+        self.lines
+            .iter()
+            .skip(self.top)
+            .take(height)
+            .map(|line| line.chars().skip(self.left).take(width).collect())
+            .collect()
+    }
+
+    /// Cursor position relative to the viewport (None if off-screen)
+    pub fn viewport_cursor(&self, height: usize, width: usize) -> Option<(u16, u16)> {
+        let row = self.row.checked_sub(self.top).filter(|r| *r < height)?;
+        let col = self.col.checked_sub(self.left).filter(|c| *c < width)?;
+        Some((col as u16, row as u16))
+    }
+
+    fn line_len(&self, row: usize) -> usize {
+        self.lines[row].chars().count()
+    }
+
+    /// Byte offset of a char column in a line
+    fn byte_index(&self, row: usize, col: usize) -> usize {
+        self.lines[row]
+            .char_indices()
+            .nth(col)
+            .map(|(i, _)| i)
+            .unwrap_or(self.lines[row].len())
+    }
+
+    fn insert_char(&mut self, c: char) {
+        let idx = self.byte_index(self.row, self.col);
+        self.lines[self.row].insert(idx, c);
+        self.col += 1;
+    }
+
+    /// Split the line at the cursor, keeping the current indentation
+    fn insert_newline(&mut self) {
+        let idx = self.byte_index(self.row, self.col);
+        let rest = self.lines[self.row].split_off(idx);
+        let indent: String = self.lines[self.row]
+            .chars()
+            .take_while(|c| *c == ' ')
+            .collect();
+        self.col = indent.chars().count();
+        self.row += 1;
+        self.lines.insert(self.row, indent + rest.as_str());
+    }
+
+    fn backspace(&mut self) -> bool {
+        if self.col > 0 {
+            self.col -= 1;
+            let idx = self.byte_index(self.row, self.col);
+            self.lines[self.row].remove(idx);
+            true
+        } else if self.row > 0 {
+            let line = self.lines.remove(self.row);
+            self.row -= 1;
+            self.col = self.line_len(self.row);
+            self.lines[self.row].push_str(&line);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn delete(&mut self) -> bool {
+        if self.col < self.line_len(self.row) {
+            let idx = self.byte_index(self.row, self.col);
+            self.lines[self.row].remove(idx);
+            true
+        } else if self.row + 1 < self.lines.len() {
+            let next = self.lines.remove(self.row + 1);
+            self.lines[self.row].push_str(&next);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn move_left(&mut self) {
+        if self.col > 0 {
+            self.col -= 1;
+        } else if self.row > 0 {
+            self.row -= 1;
+            self.col = self.line_len(self.row);
+        }
+    }
+
+    fn move_right(&mut self) {
+        if self.col < self.line_len(self.row) {
+            self.col += 1;
+        } else if self.row + 1 < self.lines.len() {
+            self.row += 1;
+            self.col = 0;
+        }
+    }
+
+    fn move_vertical(&mut self, delta: isize) {
+        let max_row = self.lines.len() - 1;
+        self.row = self.row.saturating_add_signed(delta).min(max_row);
+        self.col = self.col.min(self.line_len(self.row));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn type_str(editor: &mut TextEditor, s: &str) {
+        for c in s.chars() {
+            editor.handle_key(&key(KeyCode::Char(c)));
+        }
+    }
+
+    #[test]
+    fn test_insert_and_newline_keeps_indent() {
+        let mut editor = TextEditor::new("{\n  \"a\": 1\n}");
+        editor.handle_key(&key(KeyCode::Down));
+        editor.handle_key(&key(KeyCode::End));
+        type_str(&mut editor, ",");
+        editor.handle_key(&key(KeyCode::Enter));
+        type_str(&mut editor, "\"b\": \"é\"");
+
+        assert_eq!(editor.text(), "{\n  \"a\": 1,\n  \"b\": \"é\"\n}");
+        assert_eq!(editor.cursor(), (2, 10));
+    }
+
+    #[test]
+    fn test_backspace_and_delete_join_lines() {
+        let mut editor = TextEditor::new("ab\ncd");
+        editor.handle_key(&key(KeyCode::Down));
+        assert!(editor.handle_key(&key(KeyCode::Backspace)));
+        assert_eq!(editor.text(), "abcd");
+        assert_eq!(editor.cursor(), (0, 2));
+
+        assert!(editor.handle_key(&key(KeyCode::Delete)));
+        assert_eq!(editor.text(), "abd");
+
+        editor.handle_key(&key(KeyCode::Home));
+        assert!(
+            !editor.handle_key(&key(KeyCode::Backspace)),
+            "nothing before"
+        );
+    }
+
+    #[test]
+    fn test_scroll_into_view_follows_cursor() {
+        let text = (0..50)
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let mut editor = TextEditor::new(&text);
+        editor.handle_key(&key(KeyCode::PageDown));
+        editor.scroll_into_view(10, 40);
+        assert_eq!(editor.top(), 11);
+        assert_eq!(editor.visible_lines(10, 40).last().unwrap(), "20");
+        assert_eq!(editor.viewport_cursor(10, 40), Some((0, 9)));
+
+        // Control chords are left to the caller (e.g. Ctrl+S to save)
+        let save = KeyEvent::new(KeyCode::Char('s'), KeyModifiers::CONTROL);
+        assert!(!editor.handle_key(&save));
+        assert_eq!(editor.text(), text);
+    }
+}
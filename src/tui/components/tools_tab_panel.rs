@@ -126,7 +126,7 @@ impl ToolsTabPanel {
             .collect();
 
         // Sort by duration (descending)
-        durations.sort_by(|a, b| b.1.cmp(&a.1));
+        durations.sort_by_key(|d| std::cmp::Reverse(d.1));
 
         // Take top 10
         let top_durations: Vec<_> = durations.iter().take(10).collect();
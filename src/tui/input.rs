@@ -218,6 +218,8 @@ impl InputHandler {
                 KeyCode::Char('Y'),
                 // Help
                 KeyCode::Char('?'),
+                // Breakpoints
+                KeyCode::Char('b'),
             ],
             KeyBehavior::StateChange,
         );
@@ -53,6 +53,7 @@ pub async fn run_tui(
     streaming_thinking: StreamingThinking,
     shared_stats: crate::proxy::api::SharedStats,
     shared_events: crate::proxy::api::SharedEvents,
+    breakpoints: Option<crate::proxy::breakpoints::SharedBreakpoints>,
 ) -> Result<()> {
     // Set up terminal
     enable_raw_mode().context("Failed to enable raw mode")?;
@@ -65,6 +66,7 @@ pub async fn run_tui(
     // Create app state with config (initializes theme, preset from config)
     let mut app = App::with_config(log_buffer, config, shared_stats, shared_events);
     app.streaming_thinking = Some(streaming_thinking);
+    app.breakpoints = breakpoints;
 
     // Run the event loop
     let result = run_event_loop(&mut terminal, &mut app, &mut event_rx).await;
@@ -131,6 +133,9 @@ async fn run_event_loop(
             }
         }
 
+        // Surface requests held at breakpoints
+        app.sync_breakpoints();
+
         // Check if we should quit
         if app.should_quit {
             break;
@@ -190,10 +195,7 @@ fn handle_key_event(app: &mut App, key_event: KeyEvent) {
                                 if app.focused == scroll::FocusablePanel::Events {
                                     // Get index: use selected if in selection mode,
                                     // otherwise use last event (auto-follow mode)
-                                    let idx = app
-                                        .events_panel
-                                        .selected
-                                        .or_else(|| app.events.len().checked_sub(1));
+                                    let idx = app.selected_event_index();
 
                                     if let Some(idx) = idx {
                                         app.detail_panel.reset();
@@ -287,6 +289,17 @@ fn handle_key_event(app: &mut App, key_event: KeyEvent) {
                     }
                     return;
                 }
+                // Space - fold/unfold the selected subagent (Events panel only;
+                // other views handle Space through their focused panel)
+                KeyCode::Char(' ')
+                    if app.view == View::Events
+                        && app.focused == scroll::FocusablePanel::Events =>
+                {
+                    if app.handle_key_press(key) {
+                        app.toggle_selected_subagent();
+                    }
+                    return;
+                }
                 // 'z' - toggle zoom for focused panel (Events view only)
                 KeyCode::Char('z') => {
                     if app.handle_key_press(key) && app.view == View::Events {
@@ -368,7 +381,7 @@ fn handle_modal_input(app: &mut App, key_event: &KeyEvent) -> bool {
         return true; // Modal absorbs other non-press events (Repeat, etc.)
     }
 
-    match modal.handle_input(key_event.code) {
+    match modal.handle_input(key_event) {
         ModalAction::None => {}
         ModalAction::Close => {
             app.detail_panel.reset();
@@ -392,6 +405,9 @@ fn handle_modal_input(app: &mut App, key_event: &KeyEvent) -> bool {
                 }
             }
         }
+        ModalAction::ResolveBreakpoint { id, decision } => {
+            app.resolve_breakpoint(id, decision);
+        }
         ModalAction::CopyJsonl => {
             if let Some(idx) = modal.event_index() {
                 if let Some(event) = app.events.get(idx) {
@@ -485,6 +501,13 @@ fn handle_global_keys(app: &mut App, key_event: &KeyEvent) -> bool {
             }
             true
         }
+        // Arm/disarm request breakpoints
+        KeyCode::Char('b') => {
+            if app.handle_key_press(key) {
+                app.toggle_breakpoints();
+            }
+            true
+        }
         // Session switching: [ = previous, ] = next
         KeyCode::Char('[') => {
             if app.handle_key_press(key) {
@@ -3,7 +3,9 @@
 // Self-contained modal dialogs that handle their own input and return actions.
 // App just holds Option<Modal>, input routing acts on returned ModalAction.
 
-use crossterm::event::KeyCode;
+use crate::proxy::breakpoints::{BreakpointDecision, HeldRequest};
+use crate::tui::components::text_editor::TextEditor;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
 /// Actions returned by modal input handling
 #[derive(Debug, Clone)]
@@ -34,6 +36,11 @@ pub enum ModalAction {
     CopyReadable,
     /// Copy content (JSONL format)
     CopyJsonl,
+    /// Send a decision for a request held at a breakpoint
+    ResolveBreakpoint {
+        id: u64,
+        decision: BreakpointDecision,
+    },
 }
 
 /// Available modal types
@@ -46,6 +53,142 @@ pub enum Modal {
     Detail(usize),
     /// Log entry detail view - content cached in DetailPanel
     LogDetail,
+    /// Request held at a breakpoint - view, edit, approve or reject
+    Breakpoint(Box<BreakpointModal>),
+}
+
+/// What the breakpoint modal is currently doing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakpointMode {
+    /// Read-only body view
+    View,
+    /// Editing the body as JSON
+    Edit,
+    /// Typing a rejection message
+    Reject,
+}
+
+/// State for a request held at a breakpoint
+#[derive(Debug, Clone)]
+pub struct BreakpointModal {
+    pub request: HeldRequest,
+    pub mode: BreakpointMode,
+    /// Request body (edited in place)
+    pub editor: TextEditor,
+    /// Body before the current edit session (restored on Esc)
+    snapshot: String,
+    /// Whether the body differs from what the client sent
+    pub edited: bool,
+    /// Rejection message being typed ("529 message" sets the status)
+    pub reject_input: TextEditor,
+    /// Validation error shown under the body
+    pub error: Option<String>,
+}
+
+impl BreakpointModal {
+    pub fn new(request: HeldRequest) -> Self {
+        let editor = TextEditor::new(&request.body);
+        Self {
+            snapshot: request.body.clone(),
+            request,
+            mode: BreakpointMode::View,
+            editor,
+            edited: false,
+            reject_input: TextEditor::default(),
+            error: None,
+        }
+    }
+
+    fn handle_input(&mut self, key: &KeyEvent) -> ModalAction {
+        match self.mode {
+            BreakpointMode::View => match key.code {
+                KeyCode::Enter | KeyCode::Char('a') => self.approve(),
+                KeyCode::Char('e') => {
+                    self.snapshot = self.editor.text();
+                    self.editor.cursor_to_top();
+                    self.mode = BreakpointMode::Edit;
+                    ModalAction::None
+                }
+                KeyCode::Char('r') => {
+                    self.reject_input = TextEditor::default();
+                    self.mode = BreakpointMode::Reject;
+                    ModalAction::None
+                }
+                KeyCode::Up | KeyCode::Char('k') => self.scroll(-1),
+                KeyCode::Down | KeyCode::Char('j') => self.scroll(1),
+                KeyCode::PageUp => self.scroll(-20),
+                KeyCode::PageDown => self.scroll(20),
+                KeyCode::Home | KeyCode::Char('g') => {
+                    self.editor.scroll_to(0);
+                    ModalAction::None
+                }
+                KeyCode::End | KeyCode::Char('G') => {
+                    self.editor.scroll_to(self.editor.line_count());
+                    ModalAction::None
+                }
+                KeyCode::Char('y') => ModalAction::CopyReadable,
+                _ => ModalAction::None,
+            },
+            BreakpointMode::Edit => {
+                let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+                match key.code {
+                    KeyCode::Char('s') if ctrl => {
+                        match serde_json::from_str::<serde_json::Value>(&self.editor.text()) {
+                            Ok(_) => {
+                                self.edited = self.editor.text() != self.request.body;
+                                self.error = None;
+                                self.mode = BreakpointMode::View;
+                            }
+                            Err(e) => self.error = Some(format!("Invalid JSON: {}", e)),
+                        }
+                    }
+                    KeyCode::Esc => {
+                        self.editor = TextEditor::new(&self.snapshot);
+                        self.error = None;
+                        self.mode = BreakpointMode::View;
+                    }
+                    _ => {
+                        self.editor.handle_key(key);
+                    }
+                }
+                ModalAction::None
+            }
+            BreakpointMode::Reject => match key.code {
+                KeyCode::Enter => ModalAction::ResolveBreakpoint {
+                    id: self.request.id,
+                    decision: BreakpointDecision::reject(&self.reject_input.text()),
+                },
+                KeyCode::Esc => {
+                    self.mode = BreakpointMode::View;
+                    ModalAction::None
+                }
+                _ => {
+                    self.reject_input.handle_key(key);
+                    ModalAction::None
+                }
+            },
+        }
+    }
+
+    /// Continue the request (with the edited body, if any)
+    fn approve(&self) -> ModalAction {
+        let body = self.edited.then(|| {
+            // Re-serialize compactly; the editor holds pretty-printed JSON
+            serde_json::from_str::<serde_json::Value>(&self.editor.text())
+                .map(|v| v.to_string())
+                .unwrap_or_else(|_| self.editor.text())
+                .into_bytes()
+        });
+        ModalAction::ResolveBreakpoint {
+            id: self.request.id,
+            decision: BreakpointDecision::Continue { body },
+        }
+    }
+
+    fn scroll(&mut self, delta: isize) -> ModalAction {
+        self.editor.scroll_by(delta);
+        ModalAction::None
+    }
 }
 
 impl Modal {
@@ -64,8 +207,14 @@ impl Modal {
         Modal::LogDetail
     }
 
+    /// Create a breakpoint modal for a held request
+    pub fn breakpoint(request: HeldRequest) -> Self {
+        Modal::Breakpoint(Box::new(BreakpointModal::new(request)))
+    }
+
     /// Handle keyboard input, return action for caller to execute
-    pub fn handle_input(&mut self, key: KeyCode) -> ModalAction {
+    pub fn handle_input(&mut self, key_event: &KeyEvent) -> ModalAction {
+        let key = key_event.code;
         match self {
             Modal::Help => match key {
                 KeyCode::Esc | KeyCode::Char('?') | KeyCode::Char('q') => ModalAction::Close,
@@ -90,6 +239,15 @@ impl Modal {
                 KeyCode::Char('Y') => ModalAction::CopyJsonl,
                 _ => ModalAction::None,
             },
+            Modal::Breakpoint(breakpoint) => breakpoint.handle_input(key_event),
+        }
+    }
+
+    /// Get the hold id if this is a Breakpoint modal
+    pub fn breakpoint_id(&self) -> Option<u64> {
+        match self {
+            Modal::Breakpoint(breakpoint) => Some(breakpoint.request.id),
+            _ => None,
         }
     }
 
@@ -8,7 +8,7 @@
 // This is the primary view of Aspy, showing all intercepted
 // API traffic in real-time.
 
-use crate::events::{ProxyEvent, TrackedEvent};
+use crate::events::{ProxyEvent, ResponseSample, TrackedEvent};
 use crate::tui::app::App;
 use crate::tui::layout::Breakpoint;
 use crate::tui::preset::{LayoutDirection, Panel};
@@ -397,6 +397,24 @@ pub(crate) fn format_event_line(tracked: &TrackedEvent) -> String {
                 mods_preview
             )
         }
+        ProxyEvent::ToolResultTruncated {
+            timestamp,
+            tool_use_id,
+            tool_name,
+            tokens_before,
+            tokens_after,
+            ..
+        } => {
+            format!(
+                "[{}] {}✂ Truncated {} result {}: ~{} → ~{} tokens",
+                timestamp.format("%H:%M:%S"),
+                user_prefix,
+                tool_name,
+                tool_use_id,
+                tokens_before,
+                tokens_after
+            )
+        }
         ProxyEvent::ResponseAugmented {
             timestamp,
             augmenter,
@@ -410,6 +428,105 @@ pub(crate) fn format_event_line(tracked: &TrackedEvent) -> String {
                 tokens_injected
             )
         }
+        ProxyEvent::ModelRouted {
+            timestamp,
+            rule,
+            provider,
+            model,
+            routed_model,
+            ..
+        } => {
+            let model_label = match (model, routed_model) {
+                (Some(m), Some(r)) if m != r => format!("{} → {}", m, r),
+                (Some(m), _) => m.clone(),
+                _ => "unknown".to_string(),
+            };
+            format!(
+                "[{}] {}🧭 Route [{}]: {} via {}",
+                timestamp.format("%H:%M:%S"),
+                user_prefix,
+                rule,
+                model_label,
+                provider
+            )
+        }
+        ProxyEvent::ProviderFailover {
+            timestamp,
+            from_provider,
+            to_provider,
+            reason,
+            ..
+        } => {
+            format!(
+                "[{}] {}🔀 Failover: {} → {} ({})",
+                timestamp.format("%H:%M:%S"),
+                user_prefix,
+                from_provider,
+                to_provider,
+                reason
+            )
+        }
+        ProxyEvent::ToolPolicyDecision {
+            timestamp,
+            tool_name,
+            action,
+            reason,
+            ..
+        } => {
+            format!(
+                "[{}] {}🛡 Policy {} {}: {}",
+                timestamp.format("%H:%M:%S"),
+                user_prefix,
+                action,
+                tool_name,
+                reason
+            )
+        }
+        ProxyEvent::ShadowComparison {
+            timestamp,
+            shadow_provider,
+            primary,
+            shadow,
+            ..
+        } => {
+            let outcome = |s: &ResponseSample| {
+                s.error
+                    .as_ref()
+                    .map(|_| "error".to_string())
+                    .or_else(|| s.stop_reason.clone())
+                    .unwrap_or_else(|| "?".to_string())
+            };
+            format!(
+                "[{}] {}👥 Shadow {}: {} vs {} · {:.1}s vs {:.1}s",
+                timestamp.format("%H:%M:%S"),
+                user_prefix,
+                shadow_provider,
+                outcome(primary),
+                outcome(shadow),
+                primary.latency_ms as f64 / 1000.0,
+                shadow.latency_ms as f64 / 1000.0
+            )
+        }
+        ProxyEvent::UpstreamRetry {
+            timestamp,
+            attempt,
+            max_attempts,
+            status,
+            delay_ms,
+            delay_source,
+            ..
+        } => {
+            format!(
+                "[{}] {}🔁 Retry {}/{}: HTTP {} → waiting {:.1}s ({})",
+                timestamp.format("%H:%M:%S"),
+                user_prefix,
+                attempt,
+                max_attempts,
+                status,
+                *delay_ms as f64 / 1000.0,
+                delay_source
+            )
+        }
         ProxyEvent::PreCompactHook { timestamp, trigger } => {
             format!(
                 "[{}] {}🔄 PreCompact Hook: {}",
@@ -460,7 +577,85 @@ pub(crate) fn format_event_line(tracked: &TrackedEvent) -> String {
                 estimated_tokens
             )
         }
+        ProxyEvent::SubagentCompleted {
+            timestamp,
+            description,
+            duration,
+            requests,
+            input_tokens,
+            output_tokens,
+            cost_usd,
+            success,
+            ..
+        } => {
+            format!(
+                "[{}] {}🤖 Subagent {}: {} ({} calls, {}in + {}out, ${:.2}, {:.1}s)",
+                timestamp.format("%H:%M:%S"),
+                user_prefix,
+                if *success { "done" } else { "failed" },
+                description,
+                requests,
+                format_number(*input_tokens),
+                format_number(*output_tokens),
+                cost_usd,
+                duration.as_secs_f64()
+            )
+        }
+    }
+}
+
+/// Markdown table rows comparing a primary and shadow response
+fn shadow_comparison_rows(primary: &ResponseSample, shadow: &ResponseSample) -> String {
+    let opt = |v: &Option<String>| v.clone().unwrap_or_else(|| "N/A".to_string());
+    let status = |s: &ResponseSample| {
+        s.status
+            .map(|c| c.to_string())
+            .unwrap_or_else(|| "N/A".to_string())
+    };
+    let tools = |s: &ResponseSample| {
+        if s.tool_uses.is_empty() {
+            "none".to_string()
+        } else {
+            s.tool_uses.join(", ")
+        }
+    };
+    let mut rows = vec![
+        format!("| Status | {} | {} |", status(primary), status(shadow)),
+        format!(
+            "| Model | {} | {} |",
+            opt(&primary.model),
+            opt(&shadow.model)
+        ),
+        format!(
+            "| Stop Reason | {} | {} |",
+            opt(&primary.stop_reason),
+            opt(&shadow.stop_reason)
+        ),
+        format!("| Tool Uses | {} | {} |", tools(primary), tools(shadow)),
+        format!(
+            "| Input Tokens | {} | {} |",
+            format_number(primary.input_tokens),
+            format_number(shadow.input_tokens)
+        ),
+        format!(
+            "| Output Tokens | {} | {} |",
+            format_number(primary.output_tokens),
+            format_number(shadow.output_tokens)
+        ),
+        format!("| TTFB | {}ms | {}ms |", primary.ttfb_ms, shadow.ttfb_ms),
+        format!(
+            "| Latency | {}ms | {}ms |",
+            primary.latency_ms, shadow.latency_ms
+        ),
+    ];
+    if primary.error.is_some() || shadow.error.is_some() {
+        rows.push(format!(
+            "| Error | {} | {} |",
+            opt(&primary.error),
+            opt(&shadow.error)
+        ));
     }
+    rows.join("\n")
 }
 
 /// Format tracking metadata as a small header section
@@ -483,6 +678,10 @@ fn format_tracking_header(tracked: &TrackedEvent) -> String {
         lines.push(format!("*Session: {}*", short));
     }
 
+    if let Some(ref agent_id) = tracked.agent_id {
+        lines.push(format!("*Subagent: {}*", truncate_str(agent_id, 16)));
+    }
+
     if lines.is_empty() {
         String::new()
     } else {
@@ -723,6 +922,7 @@ pub(crate) fn format_event_detail(tracked: &TrackedEvent) -> RenderableContent {
             output_tokens,
             cache_creation_tokens,
             cache_read_tokens,
+            ..
         } => {
             let total =
                 *input_tokens + *output_tokens + *cache_creation_tokens + *cache_read_tokens;
@@ -914,6 +1114,30 @@ pub(crate) fn format_event_detail(tracked: &TrackedEvent) -> RenderableContent {
                 modifications_section
             ))
         }
+        ProxyEvent::ToolResultTruncated {
+            timestamp,
+            tool_use_id,
+            tool_name,
+            tokens_before,
+            tokens_after,
+            original,
+        } => RenderableContent::Markdown(format!(
+            "{}## ✂ Tool Result Truncated\n\n\
+            **Timestamp:** {}  \n\
+            **Tool Use ID:** {}  \n\
+            **Tool:** `{}`  \n\
+            **Tokens:** ~{} → ~{}\n\n\
+            *Later requests carry the truncated result. The original below is archived in cortex.*\n\n\
+            ---\n\n\
+            ```json\n{}\n```",
+            tracking_header,
+            timestamp.to_rfc3339(),
+            tool_use_id,
+            tool_name,
+            tokens_before,
+            tokens_after,
+            serde_json::to_string_pretty(original).unwrap_or_else(|_| "N/A".to_string())
+        )),
         ProxyEvent::ResponseAugmented {
             timestamp,
             augmenter,
@@ -929,6 +1153,137 @@ pub(crate) fn format_event_detail(tracked: &TrackedEvent) -> RenderableContent {
             augmenter,
             tokens_injected
         )),
+        ProxyEvent::ModelRouted {
+            timestamp,
+            request_id,
+            rule,
+            provider,
+            model,
+            routed_model,
+        } => RenderableContent::Markdown(format!(
+            "{}## 🧭 Model Routed\n\n\
+            **Timestamp:** {}  \n\
+            **Request ID:** {}  \n\
+            **Rule:** `{}`  \n\
+            **Provider:** `{}`  \n\
+            **Requested Model:** {}  \n\
+            **Routed Model:** {}\n\n\
+            *Aspy's model router picked the provider for this request.*",
+            tracking_header,
+            timestamp.to_rfc3339(),
+            request_id,
+            rule,
+            provider,
+            model.as_deref().unwrap_or("N/A"),
+            routed_model.as_deref().unwrap_or("N/A")
+        )),
+        ProxyEvent::ProviderFailover {
+            timestamp,
+            request_id,
+            from_provider,
+            to_provider,
+            reason,
+            status,
+        } => RenderableContent::Markdown(format!(
+            "{}## 🔀 Provider Failover\n\n\
+            **Timestamp:** {}  \n\
+            **Request ID:** {}  \n\
+            **From:** `{}`  \n\
+            **To:** `{}`  \n\
+            **Status:** {}  \n\
+            **Reason:** {}\n\n\
+            *Aspy re-routed this request to the next provider in the fallback chain.*",
+            tracking_header,
+            timestamp.to_rfc3339(),
+            request_id,
+            from_provider,
+            to_provider,
+            status
+                .map(|s| s.to_string())
+                .unwrap_or_else(|| "N/A".to_string()),
+            reason
+        )),
+        ProxyEvent::UpstreamRetry {
+            timestamp,
+            request_id,
+            attempt,
+            max_attempts,
+            status,
+            delay_ms,
+            delay_source,
+        } => RenderableContent::Markdown(format!(
+            "{}## 🔁 Upstream Retry\n\n\
+            **Timestamp:** {}  \n\
+            **Request ID:** {}  \n\
+            **Attempt:** {} of {}  \n\
+            **Status:** {}  \n\
+            **Delay:** {}ms  \n\
+            **Delay Source:** `{}`\n\n\
+            *Aspy is holding this request and will re-send it after the delay.*",
+            tracking_header,
+            timestamp.to_rfc3339(),
+            request_id,
+            attempt,
+            max_attempts,
+            status,
+            delay_ms,
+            delay_source
+        )),
+        ProxyEvent::ToolPolicyDecision {
+            timestamp,
+            request_id,
+            tool_use_id,
+            tool_name,
+            action,
+            rule,
+            reason,
+            input,
+        } => RenderableContent::Markdown(format!(
+            "{}## 🛡 Tool Policy: {}\n\n\
+            **Timestamp:** {}  \n\
+            **Request ID:** {}  \n\
+            **Tool:** `{}` ({})  \n\
+            **Rule:** `{}`  \n\
+            **Reason:** {}\n\n\
+            *{}*\n\n\
+            ---\n\n\
+            ```json\n{}\n```",
+            tracking_header,
+            action,
+            timestamp.to_rfc3339(),
+            request_id,
+            tool_name,
+            tool_use_id,
+            rule,
+            reason,
+            if action == "deny" {
+                "The call was replaced with a refusal and never reached the client."
+            } else {
+                "The call went through with a warning shown after it."
+            },
+            serde_json::to_string_pretty(input).unwrap_or_else(|_| "N/A".to_string())
+        )),
+        ProxyEvent::ShadowComparison {
+            timestamp,
+            request_id,
+            shadow_provider,
+            primary,
+            shadow,
+        } => RenderableContent::Markdown(format!(
+            "{}## 👥 Shadow Comparison\n\n\
+            **Timestamp:** {}  \n\
+            **Request ID:** {}  \n\
+            **Shadow Provider:** `{}`\n\n\
+            | | Primary | Shadow |\n\
+            |---|---|---|\n\
+            {}\n\n\
+            *The shadow response was recorded for comparison and never sent to the client.*",
+            tracking_header,
+            timestamp.to_rfc3339(),
+            request_id,
+            shadow_provider,
+            shadow_comparison_rows(primary, shadow)
+        )),
         ProxyEvent::PreCompactHook { timestamp, trigger } => RenderableContent::Markdown(format!(
             "{}## 🔄 PreCompact Hook\n\n\
             **Timestamp:** {}  \n\
@@ -1047,5 +1402,53 @@ pub(crate) fn format_event_detail(tracked: &TrackedEvent) -> RenderableContent {
             timestamp.to_rfc3339(),
             estimated_tokens
         )),
+        ProxyEvent::SubagentCompleted {
+            timestamp,
+            agent_id,
+            parent_id,
+            description,
+            subagent_type,
+            started,
+            duration,
+            requests,
+            input_tokens,
+            output_tokens,
+            cache_read_tokens,
+            cache_creation_tokens,
+            cost_usd,
+            success,
+        } => RenderableContent::Markdown(format!(
+            "{}## 🤖 Subagent {}\n\n\
+            **Task:** {}  \n\
+            **Type:** `{}`  \n\
+            **Agent ID:** {}  \n\
+            **Parent:** {}  \n\
+            **Started:** {}  \n\
+            **Finished:** {}  \n\
+            **Duration:** {:.1}s\n\n\
+            ---\n\n\
+            ### Usage (including nested subagents)\n\n\
+            - **API calls:** {}  \n\
+            - **Input:** {} tokens  \n\
+            - **Output:** {} tokens  \n\
+            - **Cache read:** {} tokens  \n\
+            - **Cache write:** {} tokens  \n\
+            - **Cost:** ${:.4}",
+            tracking_header,
+            if *success { "Completed" } else { "Failed" },
+            description,
+            subagent_type.as_deref().unwrap_or("N/A"),
+            agent_id,
+            parent_id.as_deref().unwrap_or("main conversation"),
+            started.to_rfc3339(),
+            timestamp.to_rfc3339(),
+            duration.as_secs_f64(),
+            requests,
+            format_number(*input_tokens),
+            format_number(*output_tokens),
+            format_number(*cache_read_tokens),
+            format_number(*cache_creation_tokens),
+            cost_usd
+        )),
     }
 }
@@ -89,8 +89,8 @@ pub fn draw(f: &mut Frame, app: &mut App) {
 
     // Render modal overlay (on top of everything)
     // Take modal temporarily to avoid borrow conflict with mutable app
-    if let Some(modal_state) = app.modal.take() {
-        modal::render(f, &modal_state, app);
+    if let Some(mut modal_state) = app.modal.take() {
+        modal::render(f, &mut modal_state, app);
         app.modal = Some(modal_state);
     }
 
@@ -3,14 +3,15 @@
 // Modals are rendered on top of the main content:
 // - Help modal: keyboard shortcuts and current config
 // - Detail modal: event details (full screen overlay)
+// - Breakpoint modal: held request body with approve/edit/reject
 
 use crate::tui::app::App;
 use crate::tui::components::scrollbar::{render_scrollbar_raw, ScrollbarStyle};
 use crate::tui::markdown;
-use crate::tui::modal::Modal;
+use crate::tui::modal::{BreakpointModal, BreakpointMode, Modal};
 use crate::tui::traits::{Copyable, Scrollable};
 use ratatui::{
-    layout::Rect,
+    layout::{Constraint, Direction, Layout, Rect},
     style::{Modifier, Style},
     text::{Line, Span, Text},
     widgets::{Block, Borders, Clear, Paragraph},
@@ -20,11 +21,12 @@ use ratatui::{
 use super::events::RenderableContent;
 
 /// Render a modal dialog as a centered overlay
-pub fn render(f: &mut Frame, modal: &Modal, app: &mut App) {
+pub fn render(f: &mut Frame, modal: &mut Modal, app: &mut App) {
     match modal {
         Modal::Help => render_help(f, app),
         Modal::Detail(event_idx) => render_detail(f, app, *event_idx),
         Modal::LogDetail => render_log_detail(f, app),
+        Modal::Breakpoint(breakpoint) => render_breakpoint(f, app, breakpoint),
     }
 }
 
@@ -81,6 +83,7 @@ fn render_help(f: &mut Frame, app: &App) {
         kb("Y", "Copy to clipboard (JSONL)"),
         Line::raw(""),
         Line::from(Span::styled("  General", header_style)),
+        kb("b", "Arm/disarm breakpoints"),
         kb("?", "Toggle this help"),
         kb("q", "Quit"),
         Line::raw(""),
@@ -101,7 +104,7 @@ fn render_help(f: &mut Frame, app: &App) {
 
     // Calculate modal size
     let width = 44;
-    let height = 34;
+    let height = 35;
     let area = centered_rect(width, height, f.area());
 
     // Clear the area behind the modal
@@ -437,3 +440,129 @@ fn render_log_detail(f: &mut Frame, app: &mut App) {
         ScrollbarStyle::Arrows,
     );
 }
+
+/// Render the breakpoint modal overlay
+///
+/// Header with what was held and why, the body (read-only or editable),
+/// and a footer line for validation errors or the rejection prompt.
+fn render_breakpoint(f: &mut Frame, app: &App, breakpoint: &mut BreakpointModal) {
+    let frame_area = f.area();
+    let width = (frame_area.width * 90 / 100).max(60);
+    let height = (frame_area.height * 85 / 100).max(20);
+    let area = centered_rect(width, height, frame_area);
+    f.render_widget(Clear, area);
+
+    let request = &breakpoint.request;
+    let (mode_label, keys) = match breakpoint.mode {
+        BreakpointMode::View => (
+            "",
+            " Enter/a:continue  e:edit  r:reject  ↑↓:scroll  y:copy ",
+        ),
+        BreakpointMode::Edit => (" [editing]", " Ctrl+S:save  Esc:discard "),
+        BreakpointMode::Reject => (" [reject]", " Enter:send  Esc:back "),
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(app.theme.border_type)
+        .border_style(Style::default().fg(app.theme.rate_limit))
+        .title(format!(
+            " ⏸ Breakpoint #{} — {}{}{} ",
+            request.id,
+            request.path,
+            if breakpoint.edited { " (edited)" } else { "" },
+            mode_label
+        ))
+        .title_bottom(Line::from(keys).centered())
+        .style(
+            Style::default()
+                .fg(app.theme.foreground)
+                .bg(app.theme.background),
+        );
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Min(1),
+            Constraint::Length(1),
+        ])
+        .split(inner);
+
+    // Header: why and what
+    let label = Style::default().fg(app.theme.muted);
+    let value = Style::default().fg(app.theme.tool_call);
+    let held_secs = (chrono::Utc::now() - request.held_at).num_seconds().max(0);
+    let header = Text::from(vec![
+        Line::from(vec![
+            Span::styled(" Rule: ", label),
+            Span::styled(request.reason.as_str(), value),
+            Span::styled("  Request: ", label),
+            Span::styled(request.request_id.as_str(), value),
+        ]),
+        Line::from(vec![
+            Span::styled(" Client: ", label),
+            Span::styled(request.client.as_deref().unwrap_or("-"), value),
+            Span::styled("  Model: ", label),
+            Span::styled(request.model.as_deref().unwrap_or("-"), value),
+            Span::styled("  Held: ", label),
+            Span::styled(format!("{}s", held_secs), value),
+        ]),
+        Line::from(Span::styled(
+            "─".repeat(chunks[0].width as usize),
+            Style::default().fg(app.theme.border),
+        )),
+    ]);
+    f.render_widget(Paragraph::new(header), chunks[0]);
+
+    // Body
+    let body_area = chunks[1];
+    let (body_height, body_width) = (body_area.height as usize, body_area.width as usize);
+    let editing = breakpoint.mode == BreakpointMode::Edit;
+    if editing {
+        breakpoint.editor.scroll_into_view(body_height, body_width);
+    }
+    let body = breakpoint
+        .editor
+        .visible_lines(body_height, body_width)
+        .join("\n");
+    f.render_widget(Paragraph::new(body), body_area);
+    render_scrollbar_raw(
+        f,
+        body_area,
+        breakpoint.editor.line_count(),
+        body_height,
+        breakpoint.editor.top(),
+        ScrollbarStyle::Arrows,
+    );
+    if editing {
+        if let Some((x, y)) = breakpoint.editor.viewport_cursor(body_height, body_width) {
+            f.set_cursor_position((body_area.x + x, body_area.y + y));
+        }
+    }
+
+    // Footer: validation error or rejection prompt
+    let footer_area = chunks[2];
+    match (&breakpoint.error, breakpoint.mode) {
+        (_, BreakpointMode::Reject) => {
+            let prompt = " Reject with [status] message: ";
+            let input = breakpoint.reject_input.text();
+            let line = Line::from(vec![
+                Span::styled(prompt, Style::default().fg(app.theme.error)),
+                Span::raw(input.as_str()),
+            ]);
+            f.render_widget(Paragraph::new(line), footer_area);
+            let (_, col) = breakpoint.reject_input.cursor();
+            let x = footer_area.x + (prompt.chars().count() + col) as u16;
+            f.set_cursor_position((x.min(footer_area.right().saturating_sub(1)), footer_area.y));
+        }
+        (Some(error), _) => {
+            f.render_widget(
+                Paragraph::new(format!(" {}", error)).style(Style::default().fg(app.theme.error)),
+                footer_area,
+            );
+        }
+        (None, _) => {}
+    }
+}
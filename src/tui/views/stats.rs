@@ -220,10 +220,28 @@ fn render_session_summary(f: &mut Frame, area: Rect, app: &App) {
         ]));
     }
 
+    // Add upstream retries if any occurred (time lost to rate limits)
+    if stats.retry_count > 0 {
+        lines.push(Line::from(vec![
+            Span::styled("  Retries:      ", Style::default().fg(muted)),
+            Span::styled(
+                format!("{}", stats.retry_count),
+                Style::default()
+                    .fg(app.theme.rate_limit)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(
+                format!("  ({:.1}s waiting)", stats.retry_delay_ms as f64 / 1000.0),
+                Style::default().fg(muted),
+            ),
+        ]));
+    }
+
     // Add Aspy modification stats if any transformations/augmentations occurred
     let has_modifications = stats.transform_stats.tokens_injected > 0
         || stats.transform_stats.tokens_removed > 0
-        || stats.augment_stats.tokens_injected > 0;
+        || stats.augment_stats.tokens_injected > 0
+        || stats.transform_stats.cache_hit_delta().is_some();
 
     if has_modifications {
         lines.push(Line::from(""));
@@ -254,6 +272,28 @@ fn render_session_summary(f: &mut Frame, area: Rect, app: &App) {
             ]));
         }
 
+        // Cache optimizer effect (optimized vs other requests)
+        if let Some(delta) = stats.transform_stats.cache_hit_delta() {
+            lines.push(Line::from(vec![
+                Span::styled("  Cache opt:    ", Style::default().fg(muted)),
+                Span::styled(
+                    format!("{:+.1}pp", delta),
+                    Style::default().fg(if delta >= 0.0 {
+                        Color::Green
+                    } else {
+                        Color::Red
+                    }),
+                ),
+                Span::styled(
+                    format!(
+                        " hit rate ({} reqs)",
+                        stats.transform_stats.cache_optimized.requests
+                    ),
+                    Style::default().fg(muted),
+                ),
+            ]));
+        }
+
         // Augment stats (response injections)
         if stats.augment_stats.tokens_injected > 0 {
             lines.push(Line::from(vec![